@@ -0,0 +1,250 @@
+// 提示词 A/B 测试：同一张图片分别用两套提示词预设（或两个模型）识别，
+// 以核查阶段的置信度评判胜负，结果保存在 ab_tests.json，并可汇总各组合的胜率。
+
+use crate::data_models::Config;
+use crate::llm_api;
+use crate::{fs_manager, prompt_library, prompts};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+const AB_TESTS_FILENAME: &str = "ab_tests.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AbVariant {
+    /// 展示名称，也用于胜率汇总的分组
+    pub label: String,
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    /// 覆盖 Config.default_engine
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AbVariantResult {
+    pub variant: AbVariant,
+    pub latex: Option<String>,
+    pub confidence_score: u8,
+    pub verification_report: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AbWinner {
+    A,
+    B,
+    Tie,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AbTestRun {
+    pub id: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub source: Option<String>,
+    pub a: AbVariantResult,
+    pub b: AbVariantResult,
+    pub winner: AbWinner,
+    /// 是否由用户手动裁定（覆盖按置信度的自动判定）
+    #[serde(default)]
+    pub manual: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AbPairStats {
+    pub label_a: String,
+    pub label_b: String,
+    pub runs: usize,
+    pub wins_a: usize,
+    pub wins_b: usize,
+    pub ties: usize,
+    pub win_rate_a: f64,
+    pub win_rate_b: f64,
+    pub avg_confidence_a: f64,
+    pub avg_confidence_b: f64,
+}
+
+fn read_runs(app_handle: &AppHandle) -> Result<Vec<AbTestRun>> {
+    let path = fs_manager::get_data_file_path(app_handle, AB_TESTS_FILENAME)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).context("Failed to parse ab_tests.json"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read ab_tests.json")),
+    }
+}
+
+fn write_runs(app_handle: &AppHandle, runs: &[AbTestRun]) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, AB_TESTS_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec_pretty(runs)?).context("Failed to write ab_tests.json")
+}
+
+fn load_png_base64(image_path: Option<&str>, image_base64: Option<String>) -> Result<String, String> {
+    let bytes = match (image_path, image_base64) {
+        (Some(path), _) => std::fs::read(path).map_err(|e| e.to_string())?,
+        (None, Some(b64)) => general_purpose::STANDARD.decode(b64.trim()).map_err(|e| e.to_string())?,
+        (None, None) => return Err("Either imagePath or imageBase64 is required".to_string()),
+    };
+    let img = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
+    let mut png_bytes: Vec<u8> = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(general_purpose::STANDARD.encode(&png_bytes))
+}
+
+async fn run_variant(app_handle: &AppHandle, base: &Config, variant: AbVariant, image_base64: &str) -> AbVariantResult {
+    let failed = |variant: AbVariant, e: String| AbVariantResult {
+        variant,
+        latex: None,
+        confidence_score: 0,
+        verification_report: None,
+        error: Some(e),
+    };
+    let mut config = match prompt_library::apply_preset(app_handle, base.clone(), variant.preset_id.as_deref()) {
+        Ok(c) => c,
+        Err(e) => return failed(variant, e),
+    };
+    if let Some(model) = variant.model.as_ref().filter(|m| !m.trim().is_empty()) {
+        config.default_engine = model.trim().to_string();
+    }
+
+    let client = match llm_api::create_client(config.to_llm_config()) {
+        Ok(c) => c,
+        Err(e) => return failed(variant, format!("{:#}", e)),
+    };
+    let latex_prompt = format!("{}{}", config.stage_prompt(prompts::PromptType::LaTeX), prompts::format_rule_for_latex(&config.default_latex_format));
+    let latex = match client.extract_latex(&latex_prompt, image_base64).await {
+        Ok(l) => l,
+        Err(e) => return failed(variant, e.to_string()),
+    };
+    let verification_prompt = format!(
+        "{}\n\n{}",
+        config.stage_prompt(prompts::PromptType::Verification),
+        prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Verification, &config.language)
+    );
+    match client.get_verification_result_with_image(&verification_prompt, &latex, image_base64).await {
+        Ok(vr) => AbVariantResult {
+            variant,
+            latex: Some(latex),
+            confidence_score: vr.confidence_score,
+            verification_report: Some(vr.verification_report),
+            error: None,
+        },
+        Err(e) => AbVariantResult {
+            variant,
+            latex: Some(latex),
+            confidence_score: 0,
+            verification_report: None,
+            error: Some(format!("Verification failed: {}", e)),
+        },
+    }
+}
+
+fn judge(a: &AbVariantResult, b: &AbVariantResult) -> AbWinner {
+    match (a.latex.is_some(), b.latex.is_some()) {
+        (true, false) => AbWinner::A,
+        (false, true) => AbWinner::B,
+        (false, false) => AbWinner::Tie,
+        (true, true) => match a.confidence_score.cmp(&b.confidence_score) {
+            std::cmp::Ordering::Greater => AbWinner::A,
+            std::cmp::Ordering::Less => AbWinner::B,
+            std::cmp::Ordering::Equal => AbWinner::Tie,
+        },
+    }
+}
+
+// --- Tauri commands ---
+
+/// Runs one image through both variants concurrently and records the comparison
+#[tauri::command]
+pub async fn run_prompt_ab_test(
+    app_handle: AppHandle,
+    image_path: Option<String>,
+    image_base64: Option<String>,
+    variant_a: AbVariant,
+    variant_b: AbVariant,
+) -> Result<AbTestRun, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let png_base64 = load_png_base64(image_path.as_deref(), image_base64)?;
+
+    let (a, b) = tokio::join!(
+        run_variant(&app_handle, &config, variant_a, &png_base64),
+        run_variant(&app_handle, &config, variant_b, &png_base64)
+    );
+    let run = AbTestRun {
+        id: Uuid::new_v4().to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        source: image_path,
+        winner: judge(&a, &b),
+        a,
+        b,
+        manual: false,
+    };
+
+    let mut runs = read_runs(&app_handle).map_err(|e| e.to_string())?;
+    runs.insert(0, run.clone());
+    write_runs(&app_handle, &runs).map_err(|e| e.to_string())?;
+    Ok(run)
+}
+
+#[tauri::command]
+pub fn list_ab_tests(app_handle: AppHandle, limit: Option<usize>) -> Result<Vec<AbTestRun>, String> {
+    let mut runs = read_runs(&app_handle).map_err(|e| e.to_string())?;
+    if let Some(limit) = limit {
+        runs.truncate(limit);
+    }
+    Ok(runs)
+}
+
+/// Overrides the automatic verdict with a human judgement
+#[tauri::command]
+pub fn set_ab_test_winner(app_handle: AppHandle, id: String, winner: AbWinner) -> Result<(), String> {
+    let mut runs = read_runs(&app_handle).map_err(|e| e.to_string())?;
+    let run = runs
+        .iter_mut()
+        .find(|r| r.id == id)
+        .ok_or_else(|| format!("A/B test '{}' not found", id))?;
+    run.winner = winner;
+    run.manual = true;
+    write_runs(&app_handle, &runs).map_err(|e| e.to_string())
+}
+
+/// Aggregates win rates and average confidence per (label A, label B) pair
+#[tauri::command]
+pub fn get_ab_test_stats(app_handle: AppHandle) -> Result<Vec<AbPairStats>, String> {
+    let runs = read_runs(&app_handle).map_err(|e| e.to_string())?;
+    let mut groups: BTreeMap<(String, String), (AbPairStats, u64, u64)> = BTreeMap::new();
+    for run in &runs {
+        let key = (run.a.variant.label.clone(), run.b.variant.label.clone());
+        let (stats, sum_a, sum_b) = groups.entry(key.clone()).or_insert_with(|| {
+            (AbPairStats { label_a: key.0.clone(), label_b: key.1.clone(), ..Default::default() }, 0, 0)
+        });
+        stats.runs += 1;
+        match run.winner {
+            AbWinner::A => stats.wins_a += 1,
+            AbWinner::B => stats.wins_b += 1,
+            AbWinner::Tie => stats.ties += 1,
+        }
+        *sum_a += run.a.confidence_score as u64;
+        *sum_b += run.b.confidence_score as u64;
+    }
+    Ok(groups
+        .into_values()
+        .map(|(mut stats, sum_a, sum_b)| {
+            let n = stats.runs as f64;
+            stats.win_rate_a = stats.wins_a as f64 / n;
+            stats.win_rate_b = stats.wins_b as f64 / n;
+            stats.avg_confidence_a = sum_a as f64 / n;
+            stats.avg_confidence_b = sum_b as f64 / n;
+            stats
+        })
+        .collect())
+}
@@ -0,0 +1,144 @@
+// 多个 API Key 轮换：同一服务商可配置多个密钥（apiKey + additionalApiKeys），
+// 按 keyRotation 轮流使用（roundRobin）或一直使用当前密钥、遇到 429 才切换到下一个（onRateLimit），
+// 以分摊免费额度。每个密钥的请求数与限流次数仅在内存中统计（自应用启动起），按密钥哈希区分，不保存密钥本身。
+
+use crate::data_models::KeyRotation;
+use crate::fs_manager;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+/// 收到 429 后该密钥暂不参与选择的时长
+const RATE_LIMIT_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct KeyState {
+    requests: u64,
+    rate_limited: u64,
+    last_used_at: Option<String>,
+    cooldown_until: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Rotation {
+    /// 下一次优先使用的密钥位置
+    cursor: usize,
+    keys: HashMap<u64, KeyState>,
+}
+
+static ROTATION: Mutex<Option<Rotation>> = Mutex::new(None);
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyUsage {
+    /// 脱敏后的密钥（仅保留首尾几位）
+    pub label: String,
+    pub requests: u64,
+    pub rate_limited: u64,
+    pub last_used_at: Option<String>,
+    /// 当前因 429 暂停使用
+    pub cooling_down: bool,
+}
+
+fn key_hash(key: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Masks a key for display, keeping only a few leading and trailing characters
+fn mask(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 8 {
+        return "*".repeat(chars.len());
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+fn with_rotation<T>(f: impl FnOnce(&mut Rotation) -> T) -> T {
+    let mut guard = ROTATION.lock().unwrap_or_else(|e| e.into_inner());
+    f(guard.get_or_insert_with(Rotation::default))
+}
+
+/// Picks the key for the next request and counts it. Keys cooling down after a 429 are
+/// skipped unless every key is; returns None when no key is configured.
+pub fn next_key(keys: &[String], mode: KeyRotation) -> Option<String> {
+    if keys.is_empty() {
+        return None;
+    }
+    with_rotation(|rotation| {
+        let now = Instant::now();
+        let start = rotation.cursor % keys.len();
+        let index = (0..keys.len())
+            .map(|offset| (start + offset) % keys.len())
+            .find(|&i| {
+                rotation
+                    .keys
+                    .get(&key_hash(&keys[i]))
+                    .and_then(|state| state.cooldown_until)
+                    .map_or(true, |until| until <= now)
+            })
+            .unwrap_or(start);
+        rotation.cursor = match mode {
+            KeyRotation::RoundRobin => index + 1,
+            KeyRotation::OnRateLimit => index,
+        };
+        let state = rotation.keys.entry(key_hash(&keys[index])).or_default();
+        state.requests += 1;
+        state.last_used_at = Some(chrono::Utc::now().to_rfc3339());
+        Some(keys[index].clone())
+    })
+}
+
+/// Records a 429 for `key` and moves the rotation past it.
+/// Returns true when another key that is not cooling down is available.
+pub fn report_rate_limited(keys: &[String], key: &str) -> bool {
+    with_rotation(|rotation| {
+        let now = Instant::now();
+        let state = rotation.keys.entry(key_hash(key)).or_default();
+        state.rate_limited += 1;
+        state.cooldown_until = Some(now + RATE_LIMIT_COOLDOWN);
+        if let Some(index) = keys.iter().position(|k| k == key) {
+            rotation.cursor = index + 1;
+        }
+        keys.iter().any(|k| {
+            k != key
+                && rotation
+                    .keys
+                    .get(&key_hash(k))
+                    .and_then(|state| state.cooldown_until)
+                    .map_or(true, |until| until <= now)
+        })
+    })
+}
+
+// --- Tauri commands ---
+
+/// Per-key usage since the app started, in the configured key order (keys are masked)
+#[tauri::command]
+pub fn get_api_key_usage(app_handle: AppHandle) -> Result<Vec<ApiKeyUsage>, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let now = Instant::now();
+    Ok(with_rotation(|rotation| {
+        config
+            .api_keys()
+            .iter()
+            .map(|key| {
+                let state = rotation.keys.get(&key_hash(key));
+                ApiKeyUsage {
+                    label: mask(key),
+                    requests: state.map_or(0, |s| s.requests),
+                    rate_limited: state.map_or(0, |s| s.rate_limited),
+                    last_used_at: state.and_then(|s| s.last_used_at.clone()),
+                    cooling_down: state.and_then(|s| s.cooldown_until).map_or(false, |until| until > now),
+                }
+            })
+            .collect()
+    }))
+}
@@ -0,0 +1,541 @@
+// S3 兼容存储的备份与恢复
+// 快照布局：
+//   {prefix}/snapshots/{id}/history.json
+//   {prefix}/snapshots/{id}/manifest.json
+//   {prefix}/images/{file_name}        （图片按文件名去重，多个快照共享）
+
+use crate::data_models::{BackupConfig, HistoryItem};
+use crate::{fs_manager, history_schema, pipeline};
+use anyhow::{anyhow, Context, Result};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+use tauri::AppHandle;
+
+const BACKUP_STATE_FILENAME: &str = "backup_state.json";
+const SCHEDULER_TICK_SECS: u64 = 600;
+const SNAPSHOT_ID_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 定时备份与手动备份不能同时进行（上传与快照清理会交错）
+static BACKUP_RUNNING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct BackupState {
+    #[serde(default)]
+    last_backup_at: Option<String>,
+    #[serde(default)]
+    last_snapshot: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotManifest {
+    created_at: String,
+    item_count: usize,
+    images: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSummary {
+    pub snapshot_id: String,
+    pub item_count: usize,
+    pub images_uploaded: usize,
+    pub images_skipped: usize,
+    pub snapshots_pruned: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreSummary {
+    pub snapshot_id: String,
+    pub item_count: usize,
+    pub images_downloaded: usize,
+    /// Conflicts recorded when restoring in merge mode
+    pub conflicts: usize,
+}
+
+// --- Minimal S3 client (SigV4) ---
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex_encode(&Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// RFC 3986 encoding as required by SigV4 (unreserved characters are kept as-is)
+fn uri_encode(input: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(input.len());
+    for b in input.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Extracts every `<tag>value</tag>` occurrence from an S3 XML response
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let Some(end) = after.find(&close) else { break };
+        values.push(
+            after[..end]
+                .replace("&amp;", "&")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'"),
+        );
+        rest = &after[end + close.len()..];
+    }
+    values
+}
+
+struct S3Client {
+    http: Client,
+    cfg: BackupConfig,
+}
+
+impl S3Client {
+    fn new(cfg: &BackupConfig) -> Result<Self> {
+        if !cfg.is_configured() {
+            return Err(anyhow!("Backup target is not configured (endpoint, bucket and credentials are required)"));
+        }
+        let http = Client::builder()
+            .timeout(Duration::from_secs(300))
+            .build()
+            .context("Failed to create HTTP client")?;
+        Ok(Self { http, cfg: cfg.clone() })
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        let prefix = self.cfg.prefix.trim_matches('/');
+        if prefix.is_empty() { key.to_string() } else { format!("{}/{}", prefix, key) }
+    }
+
+    /// Builds the request URL and the canonical URI for `key` ("" addresses the bucket itself)
+    fn object_url(&self, key: &str) -> Result<(Url, String)> {
+        let endpoint = Url::parse(self.cfg.endpoint.trim_end_matches('/'))
+            .with_context(|| format!("Invalid backup endpoint: {}", self.cfg.endpoint))?;
+        let host = endpoint.host_str().ok_or_else(|| anyhow!("Backup endpoint has no host"))?;
+        let port = endpoint.port().map(|p| format!(":{}", p)).unwrap_or_default();
+        let base_path = endpoint.path().trim_end_matches('/');
+        let encoded_key = uri_encode(key, false);
+
+        let (authority, canonical_uri) = if self.cfg.path_style {
+            let uri = format!("{}/{}/{}", base_path, uri_encode(&self.cfg.bucket, true), encoded_key);
+            (format!("{}{}", host, port), uri)
+        } else {
+            (format!("{}.{}{}", self.cfg.bucket, host, port), format!("{}/{}", base_path, encoded_key))
+        };
+        let url = Url::parse(&format!("{}://{}{}", endpoint.scheme(), authority, canonical_uri))
+            .context("Failed to build object URL")?;
+        Ok((url, canonical_uri))
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, String)],
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response> {
+        let (mut url, canonical_uri) = self.object_url(key)?;
+        let mut sorted_query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        sorted_query.sort();
+        let canonical_query = sorted_query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+        if !canonical_query.is_empty() {
+            url.set_query(Some(&canonical_query));
+        }
+
+        let host = match url.port() {
+            Some(p) => format!("{}:{}", url.host_str().unwrap_or_default(), p),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = sha256_hex(&body);
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method.as_str(), canonical_uri, canonical_query, host, payload_hash, amz_date, signed_headers, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.cfg.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date, scope, sha256_hex(canonical_request.as_bytes())
+        );
+        let k_date = hmac_sha256(format!("AWS4{}", self.cfg.secret_access_key).as_bytes(), &date);
+        let k_region = hmac_sha256(&k_date, &self.cfg.region);
+        let k_service = hmac_sha256(&k_region, "s3");
+        let k_signing = hmac_sha256(&k_service, "aws4_request");
+        let signature = hex_encode(&hmac_sha256(&k_signing, &string_to_sign));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.cfg.access_key_id, scope, signed_headers, signature
+        );
+
+        let response = self
+            .http
+            .request(method.clone(), url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("Authorization", authorization)
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send {} request for '{}'", method, key))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("S3 {} '{}' failed with status {}: {}", method, key, status, text));
+        }
+        Ok(response)
+    }
+
+    async fn put_object(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        self.send(Method::PUT, &self.full_key(key), &[], body).await?;
+        Ok(())
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Vec<u8>> {
+        let resp = self.send(Method::GET, &self.full_key(key), &[], Vec::new()).await?;
+        Ok(resp.bytes().await.context("Failed to read object body")?.to_vec())
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<()> {
+        self.send(Method::DELETE, &self.full_key(key), &[], Vec::new()).await?;
+        Ok(())
+    }
+
+    /// Lists keys under `prefix` (relative to the configured key prefix), returned relative as well
+    async fn list_keys(&self, prefix: &str) -> Result<Vec<String>> {
+        let full_prefix = self.full_key(prefix);
+        let strip = self.full_key("");
+        let mut keys = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2".to_string()), ("prefix", full_prefix.clone())];
+            if let Some(t) = &token {
+                query.push(("continuation-token", t.clone()));
+            }
+            let resp = self.send(Method::GET, "", &query, Vec::new()).await?;
+            let xml = resp.text().await.context("Failed to read list response")?;
+            for key in xml_values(&xml, "Key") {
+                keys.push(key.strip_prefix(&strip).unwrap_or(&key).to_string());
+            }
+            let truncated = xml_values(&xml, "IsTruncated").first().map(|v| v == "true").unwrap_or(false);
+            token = xml_values(&xml, "NextContinuationToken").into_iter().next();
+            if !truncated || token.is_none() {
+                break;
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn list_snapshot_ids(&self) -> Result<Vec<String>> {
+        let mut ids: Vec<String> = self
+            .list_keys("snapshots/")
+            .await?
+            .into_iter()
+            .filter_map(|k| {
+                k.strip_prefix("snapshots/")
+                    .and_then(|rest| rest.strip_suffix("/manifest.json"))
+                    .map(|id| id.to_string())
+            })
+            .collect();
+        // 快照 ID 为 UTC 时间戳，字典序即时间序；最新在前
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(ids)
+    }
+
+    async fn read_manifest(&self, snapshot_id: &str) -> Result<SnapshotManifest> {
+        let bytes = self.get_object(&format!("snapshots/{}/manifest.json", snapshot_id)).await?;
+        serde_json::from_slice(&bytes).context("Failed to parse snapshot manifest")
+    }
+}
+
+// --- Backup / restore ---
+
+fn read_state(app_handle: &AppHandle) -> BackupState {
+    fs_manager::get_data_file_path(app_handle, BACKUP_STATE_FILENAME)
+        .ok()
+        .and_then(|p| std::fs::read(p).ok())
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn write_state(app_handle: &AppHandle, state: &BackupState) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, BACKUP_STATE_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec_pretty(state)?).context("Failed to write backup state")
+}
+
+fn image_file_name(app_handle: &AppHandle, item: &HistoryItem) -> Option<String> {
+    let path = fs_manager::resolve_image_path(app_handle, &item.original_image);
+    if !path.is_file() {
+        return None;
+    }
+    path.file_name().map(|n| n.to_string_lossy().to_string())
+}
+
+async fn apply_retention(client: &S3Client, cfg: &BackupConfig) -> Result<usize> {
+    let ids = client.list_snapshot_ids().await?;
+    let cutoff = if cfg.retention_days > 0 {
+        Some(chrono::Utc::now() - chrono::Duration::days(cfg.retention_days as i64))
+    } else {
+        None
+    };
+
+    let mut pruned: Vec<String> = Vec::new();
+    let mut kept: Vec<String> = Vec::new();
+    for (index, id) in ids.into_iter().enumerate() {
+        // 最新快照始终保留
+        let over_count = cfg.retention_count > 0 && index >= cfg.retention_count as usize;
+        let too_old = index > 0
+            && cutoff
+                .zip(chrono::NaiveDateTime::parse_from_str(&id, SNAPSHOT_ID_FORMAT).ok())
+                .map(|(cutoff, ts)| ts.and_utc() < cutoff)
+                .unwrap_or(false);
+        if over_count || too_old {
+            pruned.push(id);
+        } else {
+            kept.push(id);
+        }
+    }
+    if pruned.is_empty() {
+        return Ok(0);
+    }
+
+    for id in &pruned {
+        client.delete_object(&format!("snapshots/{}/history.json", id)).await?;
+        client.delete_object(&format!("snapshots/{}/manifest.json", id)).await?;
+    }
+
+    // 清理不再被任何快照引用的图片
+    let mut referenced: HashSet<String> = HashSet::new();
+    for id in &kept {
+        referenced.extend(client.read_manifest(id).await?.images);
+    }
+    for key in client.list_keys("images/").await? {
+        let name = key.trim_start_matches("images/");
+        if !referenced.contains(name) {
+            client.delete_object(&key).await?;
+        }
+    }
+    Ok(pruned.len())
+}
+
+/// Uploads a new snapshot of the history and any images not yet present in the bucket
+pub async fn run_backup(app_handle: &AppHandle) -> Result<BackupSummary> {
+    let _running = BACKUP_RUNNING.lock().await;
+    let config = fs_manager::read_config(app_handle)?;
+    let cfg = config.backup;
+    let client = S3Client::new(&cfg)?;
+
+    let snapshot_id = chrono::Utc::now().format(SNAPSHOT_ID_FORMAT).to_string();
+    let history = fs_manager::read_history_async(app_handle).await?;
+    let existing: HashSet<String> = client
+        .list_keys("images/")
+        .await?
+        .into_iter()
+        .map(|k| k.trim_start_matches("images/").to_string())
+        .collect();
+
+    let mut images = Vec::new();
+    let mut uploaded = 0;
+    let mut skipped = 0;
+    for item in &history {
+        let Some(name) = image_file_name(app_handle, item) else { continue };
+        if !existing.contains(&name) {
+            let path = fs_manager::resolve_image_path(app_handle, &item.original_image);
+            let bytes = tokio::fs::read(&path)
+                .await
+                .with_context(|| format!("Failed to read image {}", item.original_image))?;
+            client.put_object(&format!("images/{}", name), bytes).await?;
+            uploaded += 1;
+        } else {
+            skipped += 1;
+        }
+        images.push(name);
+    }
+
+    let history_bytes = history_schema::to_json_bytes(&history)?;
+    client.put_object(&format!("snapshots/{}/history.json", snapshot_id), history_bytes).await?;
+    let manifest = SnapshotManifest {
+        created_at: chrono::Utc::now().to_rfc3339(),
+        item_count: history.len(),
+        images,
+    };
+    client
+        .put_object(&format!("snapshots/{}/manifest.json", snapshot_id), serde_json::to_vec_pretty(&manifest)?)
+        .await?;
+
+    let pruned = apply_retention(&client, &cfg).await?;
+
+    write_state(app_handle, &BackupState {
+        last_backup_at: Some(chrono::Utc::now().to_rfc3339()),
+        last_snapshot: Some(snapshot_id.clone()),
+    })?;
+
+    Ok(BackupSummary {
+        snapshot_id,
+        item_count: history.len(),
+        images_uploaded: uploaded,
+        images_skipped: skipped,
+        snapshots_pruned: pruned,
+    })
+}
+
+/// True for a bare file name: no path separators, drive prefix, `.` or `..`
+fn is_plain_file_name(name: &str) -> bool {
+    !name.contains(['/', '\\', ':']) && matches!(Path::new(name).components().next(), Some(std::path::Component::Normal(_)))
+}
+
+/// Restores history and images from a snapshot (latest when `snapshot_id` is None).
+/// The current history is kept as `history.before-restore.json`. With `merge`, the
+/// snapshot is merged into the local history instead of replacing it.
+pub async fn restore(app_handle: &AppHandle, snapshot_id: Option<String>, merge: bool) -> Result<RestoreSummary> {
+    let config = fs_manager::read_config(app_handle)?;
+    let client = S3Client::new(&config.backup)?;
+
+    let snapshot_id = match snapshot_id {
+        Some(id) => id,
+        None => client
+            .list_snapshot_ids()
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No backup snapshots found"))?,
+    };
+    let manifest = client.read_manifest(&snapshot_id).await?;
+    let history_bytes = client.get_object(&format!("snapshots/{}/history.json", snapshot_id)).await?;
+    let mut history = history_schema::parse(&history_bytes, &history_schema::MigrationContext::for_app(app_handle))
+        .context("Failed to parse backed up history")?
+        .items;
+
+    // 清单来自远端存储，图片名只能是 pictures 目录下的单个文件名
+    if let Some(name) = manifest.images.iter().find(|name| !is_plain_file_name(name)) {
+        return Err(anyhow!("Backup manifest contains an invalid image name: {:?}", name));
+    }
+    let pictures_dir = fs_manager::ensure_pictures_dir(app_handle)?;
+    let mut downloaded = 0;
+    for name in &manifest.images {
+        let target = pictures_dir.join(name);
+        if target.exists() {
+            continue;
+        }
+        let bytes = client.get_object(&format!("images/{}", name)).await?;
+        tokio::fs::write(&target, bytes).await.with_context(|| format!("Failed to write {:?}", target))?;
+        downloaded += 1;
+    }
+
+    // 将图片路径改写为本机 pictures 目录
+    let names: HashSet<&String> = manifest.images.iter().collect();
+    for item in history.iter_mut() {
+        let name = Path::new(&item.original_image)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string());
+        if let Some(name) = name.filter(|n| names.contains(n)) {
+            item.original_image = fs_manager::to_stored_image_path(app_handle, &pictures_dir.join(name));
+        }
+    }
+
+    fs_manager::flush_history(app_handle)?;
+    let history_path = fs_manager::get_history_path(app_handle)?;
+    if history_path.exists() {
+        let backup_path = fs_manager::get_data_file_path(app_handle, "history.before-restore.json")?;
+        std::fs::copy(&history_path, backup_path).context("Failed to keep a copy of the current history")?;
+    }
+    let item_count = history.len();
+    let conflicts = if merge {
+        crate::history_merge::merge_into_local(app_handle, history).await?.conflicts
+    } else {
+        // 替换期间不能穿插识别结果的插入，否则新条目的写回会覆盖恢复的历史
+        let _insert = pipeline::HISTORY_INSERT.lock().await;
+        fs_manager::write_history_async(app_handle, history).await?;
+        0
+    };
+
+    Ok(RestoreSummary { snapshot_id, item_count, images_downloaded: downloaded, conflicts })
+}
+
+/// Starts the background loop that runs a backup whenever `interval_hours` has elapsed
+pub fn start_scheduler(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(SCHEDULER_TICK_SECS)).await;
+            let Ok(config) = fs_manager::read_config(&app_handle) else { continue };
+            let cfg = &config.backup;
+            if !cfg.enabled || cfg.interval_hours == 0 || !cfg.is_configured() {
+                continue;
+            }
+            let due = read_state(&app_handle)
+                .last_backup_at
+                .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok())
+                .map(|t| chrono::Utc::now().signed_duration_since(t) >= chrono::Duration::hours(cfg.interval_hours as i64))
+                .unwrap_or(true);
+            if !due {
+                continue;
+            }
+            if let Err(_e) = run_backup(&app_handle).await {
+                #[cfg(debug_assertions)]
+                eprintln!("[Backup] Scheduled backup failed: {:#}", _e);
+            }
+        }
+    });
+}
+
+// --- Tauri commands ---
+
+#[tauri::command]
+pub async fn run_backup_now(app_handle: AppHandle) -> Result<BackupSummary, String> {
+    run_backup(&app_handle).await.map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn list_backups(app_handle: AppHandle) -> Result<Vec<String>, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let client = S3Client::new(&config.backup).map_err(|e| e.to_string())?;
+    client.list_snapshot_ids().await.map_err(|e| format!("{:#}", e))
+}
+
+#[tauri::command]
+pub async fn restore_backup(
+    app_handle: AppHandle,
+    snapshot_id: Option<String>,
+    merge: Option<bool>,
+) -> Result<RestoreSummary, String> {
+    restore(&app_handle, snapshot_id, merge.unwrap_or(false)).await.map_err(|e| format!("{:#}", e))
+}
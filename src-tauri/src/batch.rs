@@ -0,0 +1,136 @@
+// 批量识别：一次提交多个图片文件，每个文件作为一个识别任务加入任务队列（job_queue），
+// 走完整的三阶段识别流水线并写入历史。批量任务不进入单条识别队列，并发数由任务队列限制；
+// 每个文件入队与结束时广播 batch_progress 事件；无法连接 API 的文件与单张识别一样放入离线队列。
+
+use crate::data_models::RecognitionMode;
+use crate::event_stream;
+use crate::job_queue;
+use crate::pipeline::{CaptureImage, RecognitionOptions};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchProgress {
+    pub batch_id: String,
+    /// 文件在 paths 中的位置；批次结束的事件没有
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// queued | done | failed（单个文件），finished（整个批次）
+    pub stage: String,
+    pub completed: usize,
+    pub total: usize,
+    /// 文件对应的任务 id（也是识别进度事件中的 id）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    /// 识别成功时保存的历史条目 id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummary {
+    pub batch_id: String,
+    pub total: usize,
+    /// 新增的历史条目 id（按 paths 的顺序）
+    pub item_ids: Vec<String>,
+    pub failed: Vec<BatchFailure>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFailure {
+    pub path: String,
+    pub error: String,
+}
+
+fn emit_batch_progress(app_handle: &AppHandle, payload: BatchProgress) {
+    event_stream::publish("batch_progress", &payload);
+    let _ = app_handle.emit_all("batch_progress", payload);
+}
+
+/// Reads one file and adds it to the job queue as part of batch `batch_id`
+async fn enqueue_path(app_handle: &AppHandle, path: &str, batch_id: &str, options: &RecognitionOptions) -> Result<(String, job_queue::Completion), String> {
+    let image = CaptureImage::from_file(path)?;
+    job_queue::enqueue(app_handle, image, "file", options, Some(batch_id))
+        .await
+        .map(|(job, completion)| (job.id, completion))
+        .map_err(|e| format!("{:#}", e))
+}
+
+// --- Tauri commands ---
+
+/// Recognizes every file in `paths` through the job queue (a few at a time) and appends the results to history.
+/// Files that fail do not stop the batch; they are listed in the summary.
+#[tauri::command]
+pub async fn recognize_files(app_handle: AppHandle, paths: Vec<String>, mode: Option<RecognitionMode>) -> Result<BatchSummary, String> {
+    let paths: Vec<String> = paths.into_iter().filter(|p| !p.trim().is_empty()).collect();
+    if paths.is_empty() {
+        return Err("No files to recognize".to_string());
+    }
+    let batch_id = Uuid::new_v4().to_string();
+    let options = RecognitionOptions { mode: mode.unwrap_or_default(), ..Default::default() };
+    let total = paths.len();
+    let progress = |stage: &str, index: Option<usize>, completed: usize| BatchProgress {
+        batch_id: batch_id.clone(),
+        index,
+        path: index.map(|i| paths[i].clone()),
+        stage: stage.to_string(),
+        completed,
+        total,
+        job_id: None,
+        item_id: None,
+        error: None,
+    };
+
+    // 先全部入队；无法读取的文件直接记为失败
+    let mut results: Vec<Option<Result<String, String>>> = vec![None; total];
+    let mut completed = 0;
+    let mut tasks = JoinSet::new();
+    for (index, path) in paths.iter().enumerate() {
+        match enqueue_path(&app_handle, path, &batch_id, &options).await {
+            Ok((job_id, completion)) => {
+                emit_batch_progress(&app_handle, BatchProgress { job_id: Some(job_id.clone()), ..progress("queued", Some(index), 0) });
+                tasks.spawn(async move {
+                    let result = completion.await.unwrap_or_else(|_| Err("The recognition job was dropped".to_string()));
+                    (index, job_id, result.map(|item| item.id))
+                });
+            }
+            Err(error) => {
+                completed += 1;
+                emit_batch_progress(&app_handle, BatchProgress { error: Some(error.clone()), ..progress("failed", Some(index), completed) });
+                results[index] = Some(Err(error));
+            }
+        }
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        let (index, job_id, result) = joined.map_err(|e| format!("Batch task failed: {}", e))?;
+        completed += 1;
+        let mut event = BatchProgress { job_id: Some(job_id), ..progress(if result.is_ok() { "done" } else { "failed" }, Some(index), completed) };
+        match &result {
+            Ok(item_id) => event.item_id = Some(item_id.clone()),
+            Err(e) => event.error = Some(e.clone()),
+        }
+        emit_batch_progress(&app_handle, event);
+        results[index] = Some(result);
+    }
+
+    let mut summary = BatchSummary { batch_id: batch_id.clone(), total, ..Default::default() };
+    for (path, result) in paths.iter().zip(results) {
+        match result {
+            Some(Ok(item_id)) => summary.item_ids.push(item_id),
+            Some(Err(error)) => summary.failed.push(BatchFailure { path: path.clone(), error }),
+            None => {}
+        }
+    }
+    emit_batch_progress(&app_handle, progress("finished", None, total));
+    Ok(summary)
+}
@@ -0,0 +1,205 @@
+// 性能测试：对同一张图片重复执行识别流水线（LaTeX 与分析并行、核查在 LaTeX 之后），
+// 统计各阶段耗时的 p50/p95，以及图片预处理和历史记录序列化/解析的开销，为优化提供依据。
+// 结果不写入历史。mock 模式使用本地固定响应，不调用 API，只测量应用自身的开销。
+
+use crate::llm_api::{self, LlmClient};
+use crate::mock_llm::MockClient;
+use crate::{fs_manager, history_schema, prompts};
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::AppHandle;
+
+/// 单次测试的最大迭代次数（真实 API 按次计费）
+const MAX_ITERATIONS: u32 = 100;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StageStats {
+    pub stage: String,
+    pub samples: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkReport {
+    pub iterations: u32,
+    pub mock: bool,
+    pub model_name: String,
+    /// 失败（LaTeX 阶段出错）的迭代次数
+    pub failures: u32,
+    pub errors: Vec<String>,
+    /// 参与序列化测试的历史条目数
+    pub history_items: usize,
+    pub stages: Vec<StageStats>,
+}
+
+fn millis(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn stats(stage: &str, mut samples: Vec<f64>) -> StageStats {
+    samples.sort_by(|a, b| a.total_cmp(b));
+    let mean = if samples.is_empty() { 0.0 } else { samples.iter().sum::<f64>() / samples.len() as f64 };
+    StageStats {
+        stage: stage.to_string(),
+        samples: samples.len(),
+        p50_ms: percentile(&samples, 50.0),
+        p95_ms: percentile(&samples, 95.0),
+        mean_ms: mean,
+        min_ms: samples.first().copied().unwrap_or(0.0),
+        max_ms: samples.last().copied().unwrap_or(0.0),
+    }
+}
+
+#[derive(Default)]
+struct Samples {
+    preprocess: Vec<f64>,
+    latex: Vec<f64>,
+    analysis: Vec<f64>,
+    verification: Vec<f64>,
+    total: Vec<f64>,
+    serialize: Vec<f64>,
+    parse: Vec<f64>,
+}
+
+// --- Tauri commands ---
+
+/// Runs the recognition pipeline `iterations` times on one image (nothing is saved) and reports
+/// p50/p95 timings per stage plus image preprocessing and history (de)serialization overhead.
+#[tauri::command]
+pub async fn run_benchmark(app_handle: AppHandle, image_path: String, iterations: u32, mock: Option<bool>) -> Result<BenchmarkReport, String> {
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+        return Err(format!("Iterations must be between 1 and {}", MAX_ITERATIONS));
+    }
+    let mock = mock.unwrap_or(false);
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let image_data = std::fs::read(&image_path).map_err(|e| format!("Failed to read {}: {}", image_path, e))?;
+    let history = fs_manager::read_history_async(&app_handle).await.map_err(|e| e.to_string())?;
+    let client: Arc<dyn LlmClient> = if mock {
+        Arc::new(MockClient)
+    } else {
+        llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?
+    };
+
+    let latex_prompt = format!(
+        "{}{}",
+        config.stage_prompt(prompts::PromptType::LaTeX),
+        prompts::format_rule_for_latex(&config.default_latex_format)
+    );
+    let analysis_prompt = format!(
+        "{}\n\n{}",
+        config.stage_prompt(prompts::PromptType::Analysis),
+        prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Analysis, &config.language)
+    );
+    let verification_prompt = format!(
+        "{}\n\n{}",
+        config.stage_prompt(prompts::PromptType::Verification),
+        prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Verification, &config.language)
+    );
+
+    let mut samples = Samples::default();
+    let mut failures = 0;
+    let mut errors = Vec::new();
+    for _ in 0..iterations {
+        let started = Instant::now();
+
+        // 与识别命令相同的预处理：解码、统一转为 PNG、base64 编码
+        let t = Instant::now();
+        let dyn_img = image::load_from_memory(&image_data).map_err(|e| e.to_string())?;
+        let mut png_bytes: Vec<u8> = Vec::new();
+        dyn_img
+            .write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+            .map_err(|e| e.to_string())?;
+        let base64_image = general_purpose::STANDARD.encode(&png_bytes);
+        samples.preprocess.push(millis(t));
+
+        let latex_task = {
+            let (c, prompt, img) = (client.clone(), latex_prompt.clone(), base64_image.clone());
+            tokio::spawn(async move {
+                let t = Instant::now();
+                (c.extract_latex(&prompt, &img).await, millis(t))
+            })
+        };
+        let analysis_task = {
+            let (c, prompt, img) = (client.clone(), analysis_prompt.clone(), base64_image.clone());
+            tokio::spawn(async move {
+                let t = Instant::now();
+                (c.generate_analysis(&prompt, &img).await, millis(t))
+            })
+        };
+
+        let latex = match latex_task.await {
+            Ok((Ok(latex), ms)) => {
+                samples.latex.push(ms);
+                latex
+            }
+            Ok((Err(e), _)) => {
+                failures += 1;
+                errors.push(e.to_string());
+                analysis_task.abort();
+                continue;
+            }
+            Err(e) => return Err(format!("LaTeX task failed: {}", e)),
+        };
+
+        let t = Instant::now();
+        let verification = client.get_verification_result_with_image(&verification_prompt, &latex, &base64_image).await;
+        samples.verification.push(millis(t));
+        if let Err(e) = verification {
+            errors.push(format!("verification: {}", e));
+        }
+        match analysis_task.await {
+            Ok((result, ms)) => {
+                samples.analysis.push(ms);
+                if let Err(e) = result {
+                    errors.push(format!("analysis: {}", e));
+                }
+            }
+            Err(e) => errors.push(format!("analysis task failed: {}", e)),
+        }
+        samples.total.push(millis(started));
+
+        // 每次识别都会整体重写 history.json，因此序列化开销随历史条目数增长
+        let t = Instant::now();
+        let bytes = history_schema::to_json_bytes(&history).map_err(|e| e.to_string())?;
+        samples.serialize.push(millis(t));
+        let t = Instant::now();
+        history_schema::parse(&bytes, &history_schema::MigrationContext { data_dir: None }).map_err(|e| e.to_string())?;
+        samples.parse.push(millis(t));
+    }
+    errors.dedup();
+
+    Ok(BenchmarkReport {
+        iterations,
+        mock,
+        model_name: if mock { "mock".to_string() } else { config.default_engine.clone() },
+        failures,
+        errors,
+        history_items: history.len(),
+        stages: vec![
+            stats("preprocess", samples.preprocess),
+            stats("latex", samples.latex),
+            stats("analysis", samples.analysis),
+            stats("verification", samples.verification),
+            stats("total", samples.total),
+            stats("history_serialize", samples.serialize),
+            stats("history_parse", samples.parse),
+        ],
+    })
+}
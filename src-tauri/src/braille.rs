@@ -0,0 +1,499 @@
+// LaTeX → 盲文数学转换，支持 Nemeth 与 UEB（统一英文盲文）技术记法，输出 Unicode 盲文点字，
+// 也可转换为北美 ASCII 盲文（BRF）供盲文打印机/点显器使用。
+// 覆盖常见结构：数字、拉丁/希腊字母、运算与关系符号、括号、分数、根式、上下标、常用函数名；
+// 无法转换的命令以字母拼写输出，并在结果中列出，便于人工校对。
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BrailleCode {
+    #[default]
+    Nemeth,
+    Ueb,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BrailleResult {
+    pub braille: String,
+    /// 未识别、按字母拼写输出的命令
+    pub unsupported: Vec<String>,
+}
+
+/// 北美 ASCII 盲文表，下标为点位掩码（点 1 = bit0 … 点 6 = bit5）
+const BRF_TABLE: &[u8; 64] = b" A1B'K2L@CIF/MSP\"E3H9O6R^DJG>NTQ,*5<-U8V.%[$+X!&;:4\\0Z7(_?W]#Y)=";
+
+const GREEK: &[(&str, char)] = &[
+    ("alpha", 'a'), ("beta", 'b'), ("gamma", 'g'), ("delta", 'd'), ("epsilon", 'e'), ("varepsilon", 'e'),
+    ("zeta", 'z'), ("eta", ':'), ("theta", '?'), ("vartheta", '?'), ("iota", 'i'), ("kappa", 'k'),
+    ("lambda", 'l'), ("mu", 'm'), ("nu", 'n'), ("xi", 'x'), ("pi", 'p'), ("rho", 'r'), ("sigma", 's'),
+    ("tau", 't'), ("upsilon", 'u'), ("phi", 'f'), ("varphi", 'f'), ("chi", '&'), ("psi", 'y'), ("omega", 'w'),
+];
+
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "cot", "sec", "csc", "log", "ln", "exp", "lim", "max", "min", "det", "sinh", "cosh", "tanh",
+];
+
+/// 忽略的排版命令（不影响数学内容）
+const IGNORED: &[&str] = &[
+    "left", "right", "displaystyle", "textstyle", "big", "Big", "bigg", "Bigg", ",", ";", ":", "!", "quad", "qquad",
+    "mathrm", "mathit", "mathbf", "boldsymbol", "operatorname", "limits",
+];
+
+/// Dot pattern of a braille letter/symbol written in the ASCII braille table
+fn dots(ascii: char) -> u8 {
+    let upper = ascii.to_ascii_uppercase() as u8;
+    BRF_TABLE.iter().position(|&c| c == upper).unwrap_or(0) as u8
+}
+
+/// Converts an ASCII-braille string (e.g. "#A") to Unicode braille cells
+fn cells(ascii: &str) -> String {
+    ascii.chars().map(|c| char::from_u32(0x2800 + dots(c) as u32).unwrap_or(' ')).collect()
+}
+
+/// Converts Unicode braille to BRF (ASCII braille); other characters are kept as-is
+pub fn to_brf(braille: &str) -> String {
+    braille
+        .chars()
+        .map(|c| match c as u32 {
+            0x2800..=0x283F => BRF_TABLE[(c as u32 - 0x2800) as usize] as char,
+            _ => c,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Command(String),
+    Group(Vec<Node>),
+    Frac(Vec<Node>, Vec<Node>),
+    Sqrt(Option<Vec<Node>>, Vec<Node>),
+    Sup(Vec<Node>),
+    Sub(Vec<Node>),
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_sequence(&mut self, closing: Option<char>) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        while let Some(&c) = self.chars.get(self.pos) {
+            if Some(c) == closing {
+                self.pos += 1;
+                return nodes;
+            }
+            if c == '}' {
+                // 多余的右花括号直接跳过
+                self.pos += 1;
+                continue;
+            }
+            if let Some(node) = self.parse_atom() {
+                nodes.push(node);
+            }
+        }
+        nodes
+    }
+
+    /// Parses a single argument: a braced group or one token
+    fn parse_argument(&mut self) -> Vec<Node> {
+        while self.chars.get(self.pos).map_or(false, |c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+        match self.chars.get(self.pos) {
+            Some('{') => {
+                self.pos += 1;
+                self.parse_sequence(Some('}'))
+            }
+            Some(_) => self.parse_atom().into_iter().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn parse_atom(&mut self) -> Option<Node> {
+        let c = *self.chars.get(self.pos)?;
+        self.pos += 1;
+        match c {
+            '{' => Some(Node::Group(self.parse_sequence(Some('}')))),
+            '^' => Some(Node::Sup(self.parse_argument())),
+            '_' => Some(Node::Sub(self.parse_argument())),
+            '\\' => {
+                let start = self.pos;
+                while self.chars.get(self.pos).map_or(false, |c| c.is_ascii_alphabetic()) {
+                    self.pos += 1;
+                }
+                if self.pos == start && self.pos < self.chars.len() {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                match name.as_str() {
+                    "frac" | "dfrac" | "tfrac" => {
+                        let num = self.parse_argument();
+                        let den = self.parse_argument();
+                        Some(Node::Frac(num, den))
+                    }
+                    "sqrt" => {
+                        let index = if self.chars.get(self.pos) == Some(&'[') {
+                            self.pos += 1;
+                            Some(self.parse_sequence(Some(']')))
+                        } else {
+                            None
+                        };
+                        Some(Node::Sqrt(index, self.parse_argument()))
+                    }
+                    "text" | "mbox" | "textrm" => {
+                        // 文字内容按普通字符输出
+                        Some(Node::Group(self.parse_argument()))
+                    }
+                    _ => Some(Node::Command(name)),
+                }
+            }
+            c if c.is_whitespace() => None,
+            c => Some(Node::Char(c)),
+        }
+    }
+}
+
+fn parse(latex: &str) -> Vec<Node> {
+    let chars: Vec<char> = latex.chars().collect();
+    Parser { chars: &chars, pos: 0 }.parse_sequence(None)
+}
+
+struct Renderer {
+    code: BrailleCode,
+    out: String,
+    unsupported: Vec<String>,
+    /// Nemeth 当前输出所在的上下标层级（'^' / '_' 序列，空为基线）
+    level: Vec<char>,
+    /// 上一个输出是否为数字（决定是否需要数字符号）
+    in_number: bool,
+}
+
+impl Renderer {
+    fn push(&mut self, ascii: &str) {
+        self.out.push_str(&cells(ascii));
+    }
+
+    /// Nemeth: emits a level indicator when content moves to another script level
+    fn enter_level(&mut self, level: &[char]) {
+        if self.code != BrailleCode::Nemeth || self.level == level {
+            return;
+        }
+        if level.is_empty() {
+            self.push("\"");
+        } else {
+            let indicator: String = level.iter().map(|c| if *c == '^' { '^' } else { ';' }).collect();
+            self.push(&indicator);
+        }
+        self.level = level.to_vec();
+        self.in_number = false;
+    }
+
+    fn space(&mut self) {
+        self.out.push('\u{2800}');
+        self.in_number = false;
+    }
+
+    fn digit(&mut self, d: char, level: &[char]) {
+        self.enter_level(level);
+        match self.code {
+            BrailleCode::Nemeth => {
+                // Nemeth 数字为下位点；在行首或空格后需要数字符号
+                if !self.in_number && (self.out.is_empty() || self.out.ends_with('\u{2800}')) {
+                    self.push("#");
+                }
+                let lower = match d {
+                    '1' => "1", '2' => "2", '3' => "3", '4' => "4", '5' => "5",
+                    '6' => "6", '7' => "7", '8' => "8", '9' => "9", _ => "0",
+                };
+                self.push(lower);
+            }
+            BrailleCode::Ueb => {
+                if !self.in_number {
+                    self.push("#");
+                }
+                let upper = "jabcdefghi".chars().nth(d.to_digit(10).unwrap_or(0) as usize).unwrap_or('j');
+                self.push(&upper.to_string());
+            }
+        }
+        self.in_number = true;
+    }
+
+    fn letter(&mut self, c: char, level: &[char]) {
+        self.enter_level(level);
+        if c.is_ascii_uppercase() {
+            self.push(",");
+        } else if self.code == BrailleCode::Ueb && self.in_number && ('a'..='j').contains(&c) {
+            // UEB：数字后紧跟 a-j 需要一级符号，避免被读成数字
+            self.push(";");
+        }
+        self.push(&c.to_ascii_lowercase().to_string());
+        self.in_number = false;
+    }
+
+    fn symbol(&mut self, nemeth: &str, ueb: &str, spaced: bool, level: &[char]) {
+        // Nemeth 关系符号两侧留空格；空格本身即回到基线，不需要基线符号
+        let spaced = spaced && self.code == BrailleCode::Nemeth;
+        if spaced && level.is_empty() {
+            self.level.clear();
+        }
+        self.enter_level(level);
+        if spaced {
+            self.space();
+        }
+        self.push(if self.code == BrailleCode::Nemeth { nemeth } else { ueb });
+        if spaced {
+            self.space();
+        }
+        self.in_number = false;
+    }
+
+    fn char_node(&mut self, c: char, level: &[char]) {
+        match c {
+            '0'..='9' => self.digit(c, level),
+            'a'..='z' | 'A'..='Z' => self.letter(c, level),
+            '+' => self.symbol("+", "\"6", false, level),
+            '-' => self.symbol("-", "\"-", false, level),
+            '=' => self.symbol(".K", "\"7", true, level),
+            '<' => self.symbol("\"K", "@<", true, level),
+            '>' => self.symbol(".1", "@>", true, level),
+            '(' => self.symbol("(", "\"<", false, level),
+            ')' => self.symbol(")", "\">", false, level),
+            '[' => self.symbol("@(", ".<", false, level),
+            ']' => self.symbol("@)", ".>", false, level),
+            '|' => self.symbol("\\", "_\\", false, level),
+            ',' => self.symbol(",", "1", false, level),
+            '.' => {
+                // 小数点
+                self.enter_level(level);
+                self.push(if self.code == BrailleCode::Nemeth { "." } else { "4" });
+            }
+            '/' => self.symbol("_/", "_/", false, level),
+            '!' => self.symbol("&", "6", false, level),
+            '\'' => self.symbol("'", "7", false, level),
+            _ => {
+                self.enter_level(level);
+                self.out.push(c);
+                self.in_number = false;
+            }
+        }
+    }
+
+    fn command(&mut self, name: &str, level: &[char]) {
+        if IGNORED.contains(&name) {
+            return;
+        }
+        if let Some((_, letter)) = GREEK.iter().find(|(n, _)| *n == name) {
+            self.enter_level(level);
+            self.push(".");
+            self.push(&letter.to_string());
+            self.in_number = false;
+            return;
+        }
+        if let Some((_, letter)) = GREEK.iter().find(|(n, _)| n.eq_ignore_ascii_case(name) && name.starts_with(|c: char| c.is_ascii_uppercase())) {
+            self.enter_level(level);
+            self.push(".,");
+            self.push(&letter.to_string());
+            self.in_number = false;
+            return;
+        }
+        if FUNCTIONS.contains(&name) {
+            for c in name.chars() {
+                self.letter(c, level);
+            }
+            self.space();
+            return;
+        }
+        match name {
+            "times" => self.symbol("@*", "\"8", false, level),
+            "cdot" => self.symbol("*", "\"4", false, level),
+            "div" => self.symbol("./", "\"/", false, level),
+            "pm" => self.symbol("+-", "@+", false, level),
+            "leq" | "le" => self.symbol("\"K:", "_@<", true, level),
+            "geq" | "ge" => self.symbol(".1:", "_@>", true, level),
+            "neq" | "ne" => self.symbol("/.K", "\"7@/", true, level),
+            "approx" => self.symbol("@:@:", "@9", true, level),
+            "to" | "rightarrow" => self.symbol("$33O", "\"O", true, level),
+            "infty" => self.symbol(",=", "#=", false, level),
+            "int" => self.symbol("!", "!", false, level),
+            "sum" => self.symbol(".,S", ".,S", false, level),
+            "prod" => self.symbol(".,P", ".,P", false, level),
+            "partial" => self.symbol("@D", "\"D", false, level),
+            "nabla" => self.symbol("@,D", "@,D", false, level),
+            "in" => self.symbol("`E", "`E", true, level),
+            "cdots" | "ldots" | "dots" => self.symbol("'''", "444", false, level),
+            "{" => self.symbol(".(", "_<", false, level),
+            "}" => self.symbol(".)", "_>", false, level),
+            _ => {
+                // 未知命令：按字母拼写，便于读者识别
+                self.unsupported.push(format!("\\{}", name));
+                self.enter_level(level);
+                self.push("@");
+                for c in name.chars().filter(|c| c.is_ascii_alphabetic()) {
+                    self.letter(c, level);
+                }
+            }
+        }
+    }
+
+    fn render(&mut self, nodes: &[Node], level: &[char]) {
+        let mut prev_letter = false;
+        for node in nodes {
+            match node {
+                Node::Char(c) => {
+                    self.char_node(*c, level);
+                    prev_letter = c.is_ascii_alphabetic();
+                    continue;
+                }
+                Node::Command(name) => self.command(name, level),
+                Node::Group(inner) => self.render(inner, level),
+                Node::Frac(num, den) => {
+                    self.enter_level(level);
+                    match self.code {
+                        BrailleCode::Nemeth => {
+                            self.push("?");
+                            self.in_number = false;
+                            self.render(num, level);
+                            self.push("/");
+                            self.in_number = false;
+                            self.render(den, level);
+                            self.push("#");
+                        }
+                        BrailleCode::Ueb => {
+                            self.push("(");
+                            self.in_number = false;
+                            self.render(num, level);
+                            self.push("./");
+                            self.in_number = false;
+                            self.render(den, level);
+                            self.push(")");
+                        }
+                    }
+                    self.in_number = false;
+                }
+                Node::Sqrt(index, radicand) => {
+                    self.enter_level(level);
+                    match self.code {
+                        BrailleCode::Nemeth => {
+                            if let Some(index) = index {
+                                self.push("$");
+                                let mut index_level = level.to_vec();
+                                index_level.push('^');
+                                self.render(index, &index_level);
+                                self.enter_level(level);
+                            }
+                            self.push(">");
+                            self.in_number = false;
+                            self.render(radicand, level);
+                            self.push("]");
+                        }
+                        BrailleCode::Ueb => {
+                            if let Some(index) = index {
+                                self.push("9");
+                                self.render(index, level);
+                            }
+                            self.push("%");
+                            self.in_number = false;
+                            self.render(radicand, level);
+                            self.push("+");
+                        }
+                    }
+                    self.in_number = false;
+                }
+                Node::Sup(inner) | Node::Sub(inner) => {
+                    let marker = if matches!(node, Node::Sup(_)) { '^' } else { '_' };
+                    match self.code {
+                        BrailleCode::Nemeth => {
+                            // 字母后的纯数字下标（如 x_1）直接写下位数字，不用下标符号
+                            let numeric_subscript = marker == '_'
+                                && prev_letter
+                                && level.is_empty()
+                                && inner.iter().all(|n| matches!(n, Node::Char(c) if c.is_ascii_digit()));
+                            if numeric_subscript {
+                                self.in_number = true;
+                                self.render(inner, level);
+                            } else {
+                                let mut script_level = level.to_vec();
+                                script_level.push(marker);
+                                self.render(inner, &script_level);
+                            }
+                        }
+                        BrailleCode::Ueb => {
+                            self.push(if marker == '^' { "9" } else { "5" });
+                            self.in_number = false;
+                            let single = inner.len() == 1 && matches!(inner[0], Node::Char(_) | Node::Command(_));
+                            let numeric = inner.iter().all(|n| matches!(n, Node::Char(c) if c.is_ascii_digit()));
+                            if single || numeric {
+                                self.render(inner, level);
+                            } else {
+                                self.push("<");
+                                self.render(inner, level);
+                                self.push(">");
+                            }
+                            self.in_number = false;
+                        }
+                    }
+                }
+            }
+            prev_letter = false;
+        }
+    }
+}
+
+/// Converts LaTeX math to Unicode braille in the given code
+pub fn convert(latex: &str, code: BrailleCode) -> BrailleResult {
+    let nodes = parse(latex.trim().trim_matches('$'));
+    let mut renderer = Renderer { code, out: String::new(), unsupported: Vec::new(), level: Vec::new(), in_number: false };
+    renderer.render(&nodes, &[]);
+    renderer.unsupported.dedup();
+    BrailleResult { braille: renderer.out.trim_matches('\u{2800}').to_string(), unsupported: renderer.unsupported }
+}
+
+/// Uncontracted (grade 1) braille for short plain text such as titles
+pub fn text_to_braille(text: &str) -> String {
+    let mut out = String::new();
+    let mut in_number = false;
+    for c in text.chars() {
+        match c {
+            '0'..='9' => {
+                if !in_number {
+                    out.push_str(&cells("#"));
+                }
+                let letter = "jabcdefghi".chars().nth(c.to_digit(10).unwrap_or(0) as usize).unwrap_or('j');
+                out.push_str(&cells(&letter.to_string()));
+                in_number = true;
+                continue;
+            }
+            'A'..='Z' => out.push_str(&cells(&format!(",{}", c))),
+            'a'..='z' => {
+                if in_number && c <= 'j' {
+                    out.push_str(&cells(";"));
+                }
+                out.push_str(&cells(&c.to_string()));
+            }
+            ' ' | '\t' | '\n' => out.push('\u{2800}'),
+            '.' => out.push_str(&cells("4")),
+            ',' => out.push_str(&cells("1")),
+            '-' => out.push_str(&cells("-")),
+            _ => {}
+        }
+        in_number = false;
+    }
+    out
+}
+
+// --- Tauri commands ---
+
+/// Converts a LaTeX formula to braille (Nemeth by default)
+#[tauri::command]
+pub fn latex_to_braille(latex: String, code: Option<BrailleCode>, brf: Option<bool>) -> BrailleResult {
+    let mut result = convert(&latex, code.unwrap_or_default());
+    if brf.unwrap_or(false) {
+        result.braille = to_brf(&result.braille);
+    }
+    result
+}
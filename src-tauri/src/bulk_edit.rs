@@ -0,0 +1,81 @@
+// 批量整理：对一组条目收藏/取消收藏、添加/移除标签，所有修改合并为一次历史写入，
+// 便于批量导入几十页后集中整理，而不必逐条重写 history.json。
+
+use crate::data_models::{self, HistoryItem};
+use crate::{fs_manager, pipeline};
+use std::collections::HashSet;
+use tauri::AppHandle;
+
+/// Applies `change` to every item in `ids` and writes the history once.
+/// Returns how many items actually changed.
+async fn update_items(app_handle: &AppHandle, ids: &[String], mut change: impl FnMut(&mut HistoryItem) -> bool) -> Result<usize, String> {
+    let ids: HashSet<&str> = ids.iter().map(String::as_str).collect();
+    // 读取到写回期间持有插入锁，其间完成的识别结果不会被覆盖
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    let mut history = fs_manager::read_history_async(app_handle).await.map_err(|e| e.to_string())?;
+    let mut matched = 0;
+    let mut changed = 0;
+    for item in history.iter_mut().filter(|item| ids.contains(item.id.as_str())) {
+        matched += 1;
+        if change(item) {
+            changed += 1;
+        }
+    }
+    if matched == 0 {
+        return Err("No matching history items".to_string());
+    }
+    if changed > 0 {
+        fs_manager::write_history_async(app_handle, history).await.map_err(|e| e.to_string())?;
+    }
+    Ok(changed)
+}
+
+// --- Tauri commands ---
+
+/// Favorites or unfavorites the given items; returns how many changed
+#[tauri::command]
+pub async fn set_items_favorite(app_handle: AppHandle, ids: Vec<String>, favorite: bool) -> Result<usize, String> {
+    update_items(&app_handle, &ids, |item| {
+        let changed = item.is_favorite != favorite;
+        item.is_favorite = favorite;
+        changed
+    })
+    .await
+}
+
+/// Adds tags to the given items; returns how many changed
+#[tauri::command]
+pub async fn tag_items(app_handle: AppHandle, ids: Vec<String>, tags: Vec<String>) -> Result<usize, String> {
+    let tags = data_models::normalize_tags(tags);
+    if tags.is_empty() {
+        return Err("No tags given".to_string());
+    }
+    update_items(&app_handle, &ids, |item| {
+        let current = item.tags.get_or_insert_with(Vec::new);
+        let before = current.len();
+        for tag in &tags {
+            if !current.contains(tag) {
+                current.push(tag.clone());
+            }
+        }
+        current.len() != before
+    })
+    .await
+}
+
+/// Removes tags from the given items; returns how many changed
+#[tauri::command]
+pub async fn untag_items(app_handle: AppHandle, ids: Vec<String>, tags: Vec<String>) -> Result<usize, String> {
+    let tags = data_models::normalize_tags(tags);
+    update_items(&app_handle, &ids, |item| {
+        let Some(current) = item.tags.as_mut() else { return false };
+        let before = current.len();
+        current.retain(|tag| !tags.contains(tag));
+        let changed = current.len() != before;
+        if current.is_empty() {
+            item.tags = None;
+        }
+        changed
+    })
+    .await
+}
@@ -0,0 +1,279 @@
+// 历史条目批量重跑：对选中（或全部）条目使用其保存的图片重新执行某个阶段。
+// 同一时间只运行一个批量任务，后提交的任务排队等待；每处理完一条广播 bulk_progress 事件，
+// 结果逐条写回历史，中途失败不影响已完成的条目。
+
+use crate::data_models::HistoryItem;
+use crate::llm_api;
+use crate::{event_stream, fs_manager, latex_lint, pipeline, prompts};
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use std::future::Future;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+/// 批量任务队列：持有锁的任务在运行，其余任务等待
+static BULK_QUEUE: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkProgress {
+    pub job_id: String,
+    pub kind: String,
+    /// queued | running | done
+    pub stage: String,
+    pub completed: usize,
+    pub total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkSummary {
+    pub job_id: String,
+    pub total: usize,
+    pub succeeded: usize,
+    /// 没有可用图片的条目（如选中文本识别生成的条目）
+    pub skipped: Vec<String>,
+    pub failed: Vec<BulkFailure>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkFailure {
+    pub id: String,
+    pub error: String,
+}
+
+fn emit_bulk_progress(app_handle: &AppHandle, payload: BulkProgress) {
+    event_stream::publish("bulk_progress", &payload);
+    let _ = app_handle.emit_all("bulk_progress", payload);
+}
+
+/// Loads the stored image of an item as PNG base64 (None for image-less items)
+pub(crate) fn load_item_image(app_handle: &AppHandle, item: &HistoryItem) -> Result<Option<String>, String> {
+    let source = item.original_image.trim();
+    if source.is_empty() {
+        return Ok(None);
+    }
+    let bytes = if let Some((_, data)) = source.strip_prefix("data:").and_then(|s| s.split_once(',')) {
+        general_purpose::STANDARD.decode(data).map_err(|e| e.to_string())?
+    } else if fs_manager::resolve_image_path(app_handle, source).is_file() {
+        std::fs::read(fs_manager::resolve_image_path(app_handle, source)).map_err(|e| e.to_string())?
+    } else {
+        // 早期版本直接在历史中保存 base64
+        match general_purpose::STANDARD.decode(source) {
+            Ok(bytes) => bytes,
+            Err(_) => return Err(format!("Image not found: {}", source)),
+        }
+    };
+    let img = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
+    let mut png_bytes: Vec<u8> = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(Some(general_purpose::STANDARD.encode(&png_bytes)))
+}
+
+/// Writes one updated item back into history, keeping any concurrent changes to other items
+async fn write_back(app_handle: &AppHandle, updated: HistoryItem) -> Result<(), String> {
+    pipeline::update_history(app_handle, |history| {
+        let slot = history
+            .iter_mut()
+            .find(|i| i.id == updated.id)
+            .ok_or_else(|| format!("Item with ID '{}' not found", updated.id))?;
+        *slot = updated;
+        Ok(())
+    })
+    .await
+}
+
+/// Runs `op` over the selected items one at a time: the given ids, or every item accepted by
+/// `filter` when `ids` is empty. `op` receives the item and its image as PNG base64 and
+/// returns the updated item.
+async fn run_bulk<P, F, Fut>(
+    app_handle: &AppHandle,
+    kind: &str,
+    ids: Vec<String>,
+    filter: P,
+    op: F,
+) -> Result<BulkSummary, String>
+where
+    P: Fn(&HistoryItem) -> bool,
+    F: Fn(HistoryItem, String) -> Fut,
+    Fut: Future<Output = Result<HistoryItem, String>>,
+{
+    let job_id = Uuid::new_v4().to_string();
+    let progress = |stage: &str, completed: usize, total: usize, item_id: Option<String>, error: Option<String>| BulkProgress {
+        job_id: job_id.clone(),
+        kind: kind.to_string(),
+        stage: stage.to_string(),
+        completed,
+        total,
+        item_id,
+        error,
+    };
+
+    emit_bulk_progress(app_handle, progress("queued", 0, 0, None, None));
+    let _guard = BULK_QUEUE.lock().await;
+
+    // 在获得队列锁之后再读取历史，避免使用排队期间已过期的数据
+    let history = crate::get_history(app_handle.clone())?;
+    let items: Vec<HistoryItem> = if ids.is_empty() {
+        history.into_iter().filter(|i| filter(i)).collect()
+    } else {
+        history.into_iter().filter(|i| ids.contains(&i.id)).collect()
+    };
+    if items.is_empty() {
+        return Err("No matching history items".to_string());
+    }
+
+    let total = items.len();
+    let mut summary = BulkSummary { job_id: job_id.clone(), total, ..Default::default() };
+    emit_bulk_progress(app_handle, progress("running", 0, total, None, None));
+
+    for (index, item) in items.into_iter().enumerate() {
+        let id = item.id.clone();
+        let result = match load_item_image(app_handle, &item) {
+            Ok(Some(image)) => match op(item, image).await {
+                Ok(updated) => write_back(app_handle, updated).await.map(|_| true),
+                Err(e) => Err(e),
+            },
+            Ok(None) => Ok(false),
+            Err(e) => Err(e),
+        };
+        let error = match result {
+            Ok(true) => {
+                summary.succeeded += 1;
+                None
+            }
+            Ok(false) => {
+                summary.skipped.push(id.clone());
+                None
+            }
+            Err(e) => {
+                summary.failed.push(BulkFailure { id: id.clone(), error: e.clone() });
+                Some(e)
+            }
+        };
+        emit_bulk_progress(app_handle, progress("running", index + 1, total, Some(id), error));
+    }
+
+    emit_bulk_progress(app_handle, progress("done", total, total, None, None));
+    Ok(summary)
+}
+
+/// 标题仍为分析阶段失败时的占位文本
+fn is_untitled(item: &HistoryItem) -> bool {
+    let title = item.title.trim();
+    title.is_empty() || title == crate::pipeline::default_title_for_lang("zh-CN") || title == crate::pipeline::default_title_for_lang("en")
+}
+
+async fn run_reanalysis<P>(
+    app_handle: &AppHandle,
+    kind: &str,
+    ids: Vec<String>,
+    filter: P,
+    language: Option<String>,
+    keep_titles: bool,
+) -> Result<BulkSummary, String>
+where
+    P: Fn(&HistoryItem) -> bool,
+{
+    let config = fs_manager::read_config(app_handle).map_err(|e| e.to_string())?;
+    if config.stage_prompt(prompts::PromptType::Analysis).trim().is_empty() {
+        return Err("分析提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
+    }
+    let language = language.filter(|l| !l.trim().is_empty()).unwrap_or_else(|| config.language.clone());
+    let analysis_prompt = format!(
+        "{}\n\n{}",
+        config.stage_prompt(prompts::PromptType::Analysis),
+        prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Analysis, &language)
+    );
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
+
+    run_bulk(app_handle, kind, ids, filter, |mut item, image| {
+        let client = &client;
+        let analysis_prompt = &analysis_prompt;
+        let language = &language;
+        async move {
+            let (title, analysis) = client
+                .generate_analysis(analysis_prompt, &image)
+                .await
+                .map_err(|e| e.to_string())?;
+            if !keep_titles {
+                item.title = title;
+            }
+            item.analysis = latex_lint::append(analysis, &item.latex, language);
+            if let Some(used) = item.prompts_used.as_mut() {
+                used.analysis = analysis_prompt.clone();
+            }
+            Ok(item)
+        }
+    })
+    .await
+}
+
+// --- Tauri commands ---
+
+/// Re-runs the analysis stage (title, summary, variables, terms, suggestions) on stored images.
+/// `language` overrides the configured output language; titles are kept when `keepTitles` is set.
+#[tauri::command]
+pub async fn reanalyze_history_items(
+    app_handle: AppHandle,
+    ids: Vec<String>,
+    language: Option<String>,
+    keep_titles: Option<bool>,
+) -> Result<BulkSummary, String> {
+    run_reanalysis(&app_handle, "reanalyze", ids, |_| true, language, keep_titles.unwrap_or(false)).await
+}
+
+/// Regenerates title and summary for items still carrying the placeholder title
+/// ("未命名公式" / "Untitled formula") left by a failed analysis stage
+#[tauri::command]
+pub async fn regenerate_untitled_items(app_handle: AppHandle, language: Option<String>) -> Result<BulkSummary, String> {
+    run_reanalysis(&app_handle, "regenerate_titles", Vec::new(), is_untitled, language, false).await
+}
+
+/// Recomputes verification (score + report) on stored images.
+/// With no ids, only stale items are processed: zero scores from past failures, or items
+/// created with an older prompts version (or before prompts were recorded), unless `all` is set.
+#[tauri::command]
+pub async fn reverify_history_items(
+    app_handle: AppHandle,
+    ids: Vec<String>,
+    all: Option<bool>,
+    language: Option<String>,
+) -> Result<BulkSummary, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    if config.stage_prompt(prompts::PromptType::Verification).trim().is_empty() {
+        return Err("核查提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
+    }
+    let language = language.filter(|l| !l.trim().is_empty()).unwrap_or_else(|| config.language.clone());
+    let verification_prompt = prompts::get_verification_prompt(config.stage_prompt(prompts::PromptType::Verification), &language);
+    let all = all.unwrap_or(false);
+    let prompts_version = config.prompts_version;
+    let is_stale = |item: &HistoryItem| {
+        all || item.confidence_score == 0
+            || item.prompts_used.as_ref().map_or(true, |p| p.prompts_version < prompts_version)
+    };
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
+
+    run_bulk(&app_handle, "reverify", ids, is_stale, |mut item, image| {
+        let client = &client;
+        let verification_prompt = &verification_prompt;
+        async move {
+            let verification = client.verify_latex_against_image(verification_prompt, &item.latex, &image).await.map_err(|e| e.to_string())?;
+            let result = crate::compute_verification_result_from_struct(&verification);
+            item.confidence_score = result.confidence_score;
+            item.verification_report = Some(result.verification_report);
+            item.verification = Some(verification);
+            if let Some(used) = item.prompts_used.as_mut() {
+                used.verification = verification_prompt.clone();
+            }
+            Ok(item)
+        }
+    })
+    .await
+}
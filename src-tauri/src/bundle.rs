@@ -0,0 +1,177 @@
+// 条目包：把若干历史条目连同原图打包成一个 JSON 文件，便于分享给其他用户。
+// 格式：{ "format": "ai-formula-scanner-bundle", "version": 1, "schemaVersion": N,
+//         "items": [...], "images": { "<条目 id>": { "mime": "...", "data": "<base64>" } } }
+// 导入时按 history_schema 迁移并逐条校验，图片写入本机存储，id 与本地冲突的条目分配新 id。
+
+use crate::data_models::HistoryItem;
+use crate::{fs_manager, history_schema, image_storage, pipeline};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use tauri::AppHandle;
+use uuid::Uuid;
+
+const BUNDLE_FORMAT: &str = "ai-formula-scanner-bundle";
+const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct BundleImage {
+    mime: String,
+    data: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleImportSummary {
+    pub imported: usize,
+    /// 因与本地 id 冲突而分配了新 id 的条目数
+    pub reassigned_ids: usize,
+    /// 无法解析而跳过的条目数
+    pub skipped: usize,
+    /// 图片缺失或损坏、导入后没有原图的条目数
+    pub missing_images: usize,
+}
+
+fn extension_for(mime: &str) -> &'static str {
+    match mime {
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "image/bmp" => "bmp",
+        _ => "png",
+    }
+}
+
+fn build_bundle(app_handle: &AppHandle, items: &[HistoryItem]) -> Result<Vec<u8>> {
+    let mut images = HashMap::new();
+    for item in items.iter().filter(|i| !i.original_image.is_empty()) {
+        let path = fs_manager::resolve_image_path(app_handle, &item.original_image);
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                images.insert(
+                    item.id.clone(),
+                    BundleImage { mime: image_storage::mime_type(&bytes).to_string(), data: general_purpose::STANDARD.encode(&bytes) },
+                );
+            }
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("Bundle: image of {} not readable: {}", item.id, _e);
+            }
+        }
+    }
+    // 本机路径、复习进度等个人状态不随条目分享
+    let shared: Vec<HistoryItem> = items
+        .iter()
+        .cloned()
+        .map(|mut item| {
+            item.original_image = String::new();
+            item.duplicate_of = None;
+            item.srs = None;
+            item
+        })
+        .collect();
+    let bundle = json!({
+        "format": BUNDLE_FORMAT,
+        "version": BUNDLE_VERSION,
+        "schemaVersion": history_schema::CURRENT_SCHEMA_VERSION,
+        "items": shared,
+        "images": images,
+    });
+    Ok(serde_json::to_vec_pretty(&bundle)?)
+}
+
+/// Decodes and checks a bundled image; returns the bytes and the file extension to store them under
+fn decode_image(image: &BundleImage) -> Result<(Vec<u8>, &'static str)> {
+    let bytes = general_purpose::STANDARD.decode(image.data.trim()).context("Invalid base64 image data")?;
+    image::load_from_memory(&bytes).context("Bundled image cannot be decoded")?;
+    let extension = extension_for(image_storage::mime_type(&bytes));
+    Ok((bytes, extension))
+}
+
+fn import(app_handle: &AppHandle, bytes: &[u8]) -> Result<BundleImportSummary> {
+    let mut value: serde_json::Value = serde_json::from_slice(bytes).context("Bundle is not valid JSON")?;
+    if value.get("format").and_then(|f| f.as_str()) != Some(BUNDLE_FORMAT) {
+        return Err(anyhow!("Not an AI Formula Scanner bundle"));
+    }
+    let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    if version > BUNDLE_VERSION {
+        return Err(anyhow!("Bundle version {} is newer than supported version {}; please update the app", version, BUNDLE_VERSION));
+    }
+    let images: HashMap<String, BundleImage> = match value.get_mut("images").map(serde_json::Value::take) {
+        Some(images) => serde_json::from_value(images).context("Bundle images are malformed")?,
+        None => HashMap::new(),
+    };
+    // 条目走与 history.json 相同的迁移与逐条校验
+    let envelope = json!({
+        "schemaVersion": value.get("schemaVersion").cloned().unwrap_or(json!(history_schema::CURRENT_SCHEMA_VERSION)),
+        "items": value.get_mut("items").map(serde_json::Value::take).unwrap_or(json!([])),
+    });
+    let parsed = history_schema::parse(&serde_json::to_vec(&envelope)?, &history_schema::MigrationContext { data_dir: None })?;
+
+    let mut summary = BundleImportSummary { skipped: parsed.rejected.len(), ..Default::default() };
+    let history = fs_manager::read_history(app_handle)?;
+    let mut taken: HashSet<String> = history.iter().map(|i| i.id.clone()).collect();
+    let mut imported = Vec::new();
+    for mut item in parsed.items {
+        if item.latex.trim().is_empty() {
+            summary.skipped += 1;
+            continue;
+        }
+        item.original_image = String::new();
+        if let Some(image) = images.get(&item.id) {
+            match decode_image(image).and_then(|(bytes, ext)| fs_manager::store_content_addressed(app_handle, &bytes, ext)) {
+                Ok(path) => item.original_image = fs_manager::to_stored_image_path(app_handle, &path),
+                Err(_e) => {
+                    #[cfg(debug_assertions)]
+                    eprintln!("Bundle: image of {} skipped: {}", item.id, _e);
+                }
+            }
+        }
+        if item.original_image.is_empty() {
+            summary.missing_images += 1;
+        }
+        if !taken.insert(item.id.clone()) {
+            item.id = Uuid::new_v4().to_string();
+            taken.insert(item.id.clone());
+            summary.reassigned_ids += 1;
+        }
+        item.duplicate_of = None;
+        imported.push(item);
+    }
+    if imported.is_empty() {
+        return Err(anyhow!("The bundle contains no importable items"));
+    }
+
+    summary.imported = imported.len();
+    // 导入的条目放在最前（最新），保持包内顺序
+    imported.extend(history);
+    fs_manager::write_history(app_handle, &imported)?;
+    Ok(summary)
+}
+
+// --- Tauri commands ---
+
+/// Writes the selected items and their images to a shareable bundle file. Returns the number of items.
+#[tauri::command]
+pub fn export_bundle(app_handle: AppHandle, ids: Vec<String>, path: String) -> Result<usize, String> {
+    let items = crate::export::select_items(app_handle.clone(), &ids)?;
+    let bytes = build_bundle(&app_handle, &items).map_err(|e| e.to_string())?;
+    std::fs::write(&path, bytes).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(items.len())
+}
+
+/// Imports a bundle: validates every item, copies images into local storage,
+/// assigns new ids on collision and inserts the items at the top of the history
+#[tauri::command]
+pub async fn import_bundle(app_handle: AppHandle, path: String) -> Result<BundleImportSummary, String> {
+    // 读取历史到写回期间持有插入锁，其间完成的识别结果不会被覆盖
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    fs_manager::run_blocking(move || {
+        let bytes = std::fs::read(&path).with_context(|| format!("Failed to read {}", path))?;
+        import(&app_handle, &bytes)
+    })
+    .await
+    .map_err(|e| format!("{:#}", e))
+}
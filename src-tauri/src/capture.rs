@@ -1,8 +1,37 @@
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager};
 use screenshots::Screen;
-use std::path::PathBuf;
-use uuid::Uuid;
+use base64::{engine::general_purpose, Engine as _};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use crate::data_models::{CaptureScale, RecognitionMode};
+
+/// 当前遮罩是否由静默快速截图发起（结果不送往主窗口）
+static QUICK_MODE: AtomicBool = AtomicBool::new(false);
+
+/// 当前遮罩是否由快捷键发起（开启自动粘贴时结果粘贴到前台应用）
+static AUTO_PASTE: AtomicBool = AtomicBool::new(false);
+
+/// 最近一次区域截图是否使用手写模式（“重复上次截图”沿用）
+static LAST_HANDWRITING: AtomicBool = AtomicBool::new(false);
+
+/// 最近一次区域截图的识别模式（“重复上次截图”沿用）
+static LAST_MODE: Mutex<RecognitionMode> = Mutex::new(RecognitionMode::Math);
+
+/// 最近一次区域截图参数，供“重复上次截图”快捷键使用
+static LAST_CAPTURE: OnceLock<Mutex<Option<CaptureArgs>>> = OnceLock::new();
+
+fn last_capture_slot() -> &'static Mutex<Option<CaptureArgs>> {
+    LAST_CAPTURE.get_or_init(|| Mutex::new(None))
+}
+
+/// 已保存但尚未开始识别的区域截图的缩放信息（按截图路径）
+static PENDING_SCALES: OnceLock<Mutex<HashMap<String, CaptureScale>>> = OnceLock::new();
+
+fn pending_scales() -> &'static Mutex<HashMap<String, CaptureScale>> {
+    PENDING_SCALES.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DisplayInfo {
@@ -15,7 +44,7 @@ pub struct DisplayInfo {
     pub scale_factor: f64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct CaptureArgs {
     pub rect: (i32, i32, i32, i32), // 逻辑像素：x,y,w,h（相对 overlay 左上）
     pub scale_factor: f64,          // 该屏缩放
@@ -42,9 +71,113 @@ pub fn get_displays() -> Result<Vec<DisplayInfo>, String> {
     Ok(displays)
 }
 
+/// Index of the display containing the mouse cursor, if it can be determined
+pub fn cursor_display_index() -> Option<usize> {
+    use enigo::{Enigo, Mouse, Settings};
+    let (x, y) = Enigo::new(&Settings::default()).ok()?.location().ok()?;
+    let screens = Screen::all().ok()?;
+    screens.iter().position(|screen| {
+        let info = &screen.display_info;
+        x >= info.x && x < info.x + info.width as i32 && y >= info.y && y < info.y + info.height as i32
+    })
+}
+
+/// 全屏截图：指定的屏 > 设置中选定的屏（已拔出时忽略）> 光标所在的屏 > 第一块屏，返回 PNG 及其缩放信息
+pub fn capture_display_png(display_index: Option<usize>, configured: Option<usize>) -> Result<(Vec<u8>, CaptureScale), String> {
+    let screens = Screen::all().map_err(|e| format!("Failed to get screens: {}", e))?;
+    let index = match display_index {
+        Some(index) if index >= screens.len() => return Err(format!("Display index {} out of range", index)),
+        Some(index) => index,
+        None => configured
+            .filter(|index| *index < screens.len())
+            .or_else(cursor_display_index)
+            .unwrap_or(0),
+    };
+    let screen = screens.get(index).ok_or("No screens found.")?;
+    #[cfg(debug_assertions)] println!("🖥️ 全屏截图使用屏幕 {}", index);
+    let image = screen.capture().map_err(|e| format!("Failed to capture display {}: {}", index, e))?;
+    let png_data = image.to_png(None).map_err(|e| format!("Failed to convert to PNG: {}", e))?;
+    let scale = CaptureScale {
+        scale_factor: screen.display_info.scale_factor as f64,
+        physical_width: image.width(),
+        physical_height: image.height(),
+        logical_width: screen.display_info.width,
+        logical_height: screen.display_info.height,
+        logical_x: 0,
+        logical_y: 0,
+        display_index: Some(index),
+    };
+    Ok((png_data, scale))
+}
+
+/// 拼接所有显示器为一张 PNG：按各屏的逻辑坐标排布，统一缩放到最高的缩放比例，
+/// 使跨屏窗口中的公式保持正确的相对位置；屏幕之间的空隙填充为白色
+pub fn capture_all_displays_png() -> Result<(Vec<u8>, CaptureScale), String> {
+    let screens = Screen::all().map_err(|e| format!("Failed to get screens: {}", e))?;
+    if screens.is_empty() {
+        return Err("No screens found.".to_string());
+    }
+    let scale = screens
+        .iter()
+        .map(|s| s.display_info.scale_factor as f64)
+        .fold(1.0_f64, f64::max);
+    let min_x = screens.iter().map(|s| s.display_info.x).min().unwrap_or(0);
+    let min_y = screens.iter().map(|s| s.display_info.y).min().unwrap_or(0);
+    let max_x = screens.iter().map(|s| s.display_info.x + s.display_info.width as i32).max().unwrap_or(0);
+    let max_y = screens.iter().map(|s| s.display_info.y + s.display_info.height as i32).max().unwrap_or(0);
+    let to_physical = |logical: i32| (logical as f64 * scale).round() as u32;
+
+    let mut canvas = image::RgbaImage::from_pixel(
+        to_physical(max_x - min_x).max(1),
+        to_physical(max_y - min_y).max(1),
+        image::Rgba([255, 255, 255, 255]),
+    );
+    for (index, screen) in screens.iter().enumerate() {
+        let info = &screen.display_info;
+        let shot = screen.capture().map_err(|e| format!("Failed to capture display {}: {}", index, e))?;
+        let mut tile = image::RgbaImage::from_raw(shot.width(), shot.height(), shot.rgba().clone())
+            .ok_or_else(|| format!("Unexpected pixel data from display {}", index))?;
+        let (width, height) = (to_physical(info.width as i32), to_physical(info.height as i32));
+        if tile.dimensions() != (width, height) {
+            tile = image::imageops::resize(&tile, width, height, image::imageops::FilterType::Lanczos3);
+        }
+        image::imageops::overlay(&mut canvas, &tile, to_physical(info.x - min_x) as i64, to_physical(info.y - min_y) as i64);
+        #[cfg(debug_assertions)] println!("🖥️ 拼接屏幕 {} 于 ({}, {})", index, info.x - min_x, info.y - min_y);
+    }
+
+    let mut png_bytes = Vec::new();
+    canvas
+        .write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to convert to PNG: {}", e))?;
+    let capture_scale = CaptureScale {
+        scale_factor: scale,
+        physical_width: canvas.width(),
+        physical_height: canvas.height(),
+        logical_width: (max_x - min_x) as u32,
+        logical_height: (max_y - min_y) as u32,
+        logical_x: min_x,
+        logical_y: min_y,
+        display_index: None,
+    };
+    Ok((png_bytes, capture_scale))
+}
+
+/// 列出所有显示器，供设置中选择全屏识别的屏幕
+#[tauri::command]
+pub fn list_displays() -> Result<Vec<DisplayInfo>, String> {
+    get_displays()
+}
+
 /// 创建所有显示器的遮罩窗口
 #[tauri::command]
 pub async fn open_overlays_for_all_displays(app: AppHandle) -> Result<(), String> {
+    open_overlays(app, false, false).await
+}
+
+/// 打开遮罩窗口；quick 为 true 时截图完成后走静默快速识别，auto_paste 为 true（快捷键发起）时结果自动粘贴
+pub async fn open_overlays(app: AppHandle, quick: bool, auto_paste: bool) -> Result<(), String> {
+    QUICK_MODE.store(quick, Ordering::SeqCst);
+    AUTO_PASTE.store(auto_paste, Ordering::SeqCst);
     let displays = get_displays()?;
     
     for display in displays {
@@ -79,7 +212,7 @@ pub async fn open_overlays_for_all_displays(app: AppHandle) -> Result<(), String
 
 /// 完成区域截图
 #[tauri::command]
-pub async fn complete_capture(args: CaptureArgs) -> Result<String, String> {
+pub async fn complete_capture(app: AppHandle, args: CaptureArgs) -> Result<String, String> {
     #[cfg(debug_assertions)] println!("🔍 开始截图，参数: {:?}", args);
 
     // 获取所有屏幕
@@ -110,36 +243,94 @@ pub async fn complete_capture(args: CaptureArgs) -> Result<String, String> {
     
     // 保存图像
     #[cfg(debug_assertions)] println!("💾 图像尺寸: {}x{}", img.width(), img.height());
-    let save_path = save_screenshot_image(&img)?;
+    let save_path = save_screenshot_image(&app, &img)?;
     #[cfg(debug_assertions)] println!("✅ 截图保存到: {}", save_path);
 
+    let scale = CaptureScale {
+        scale_factor: args.scale_factor,
+        physical_width: img.width(),
+        physical_height: img.height(),
+        logical_width: w.max(0) as u32,
+        logical_height: h.max(0) as u32,
+        logical_x: x,
+        logical_y: y,
+        display_index: Some(args.display_index),
+    };
+    pending_scales().lock().unwrap_or_else(|e| e.into_inner()).insert(save_path.clone(), scale);
+
+    *last_capture_slot().lock().unwrap() = Some(args);
     Ok(save_path)
 }
 
-/// 保存截图图像到本地
-fn save_screenshot_image(img: &screenshots::Image) -> Result<String, String> {
-    // 获取保存目录
-    let save_dir = get_save_directory().map_err(|e| e.to_string())?;
-    std::fs::create_dir_all(&save_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
-    
-    // 生成文件名
-    let filename = format!("region_capture_{}.png", Uuid::new_v4());
-    let file_path = save_dir.join(filename);
-    
-    // 将图像转换为PNG格式并保存
+/// 放大镜默认与最大的取样边长（物理像素）
+const MAGNIFIER_DEFAULT_SIZE: u32 = 20;
+const MAGNIFIER_MAX_SIZE: u32 = 64;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MagnifierPixels {
+    /// base64 编码的 PNG，按物理像素 1:1 取样，由遮罩放大显示
+    pub image: String,
+    /// 取样区域左上角（物理像素，相对该屏）
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// 光标所在像素在取样区域内的位置
+    pub cursor_x: u32,
+    pub cursor_y: u32,
+}
+
+/// 放大镜取样：返回光标（逻辑像素，相对 overlay 左上）周围 size×size 物理像素的截图，
+/// 靠近屏幕边缘时取样区域向内平移而不缩小
+#[tauri::command]
+pub async fn get_magnifier_pixels(display_index: usize, x: f64, y: f64, scale_factor: f64, size: Option<u32>) -> Result<MagnifierPixels, String> {
+    let screens = Screen::all().map_err(|e| format!("Failed to get screens: {}", e))?;
+    let screen = screens.get(display_index)
+        .ok_or_else(|| format!("Display index {} out of range", display_index))?;
+
+    let screen_w = (screen.display_info.width as f64 * screen.display_info.scale_factor as f64) as u32;
+    let screen_h = (screen.display_info.height as f64 * screen.display_info.scale_factor as f64) as u32;
+    let size = size.unwrap_or(MAGNIFIER_DEFAULT_SIZE).clamp(1, MAGNIFIER_MAX_SIZE);
+    let (width, height) = (size.min(screen_w), size.min(screen_h));
+
+    let cursor_x = ((x * scale_factor) as i32).clamp(0, screen_w as i32 - 1);
+    let cursor_y = ((y * scale_factor) as i32).clamp(0, screen_h as i32 - 1);
+    let left = (cursor_x - width as i32 / 2).clamp(0, (screen_w - width) as i32);
+    let top = (cursor_y - height as i32 / 2).clamp(0, (screen_h - height) as i32);
+
+    let img = screen.capture_area(left, top, width, height)
+        .map_err(|e| format!("Failed to capture area: {}", e))?;
     let png_data = img.to_png(None).map_err(|e| format!("Failed to convert to PNG: {}", e))?;
-    std::fs::write(&file_path, png_data).map_err(|e| format!("Failed to write file: {}", e))?;
-    
-    Ok(file_path.to_string_lossy().to_string())
+
+    Ok(MagnifierPixels {
+        image: general_purpose::STANDARD.encode(png_data),
+        x: left,
+        y: top,
+        width: img.width(),
+        height: img.height(),
+        cursor_x: (cursor_x - left) as u32,
+        cursor_y: (cursor_y - top) as u32,
+    })
 }
 
-/// 获取保存目录
-fn get_save_directory() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let pictures_dir = dirs::picture_dir()
-        .or_else(|| dirs::home_dir())
-        .ok_or("Could not find pictures directory")?;
-    
-    Ok(pictures_dir.join("AI Formula Scanner"))
+/// 按上次的区域重新截图并开始识别
+pub async fn repeat_last_capture(app: AppHandle, auto_paste: bool) -> Result<(), String> {
+    let args = last_capture_slot()
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| "No previous region capture to repeat".to_string())?;
+    let image_path = complete_capture(app.clone(), args).await?;
+    let mode = *LAST_MODE.lock().unwrap_or_else(|e| e.into_inner());
+    recognize_capture(app, image_path, false, auto_paste, LAST_HANDWRITING.load(Ordering::SeqCst), mode);
+    Ok(())
+}
+
+/// 保存截图图像到应用数据目录的 pictures 中（与识别结果的图片统一由 fs_manager 管理）
+fn save_screenshot_image(app: &AppHandle, img: &screenshots::Image) -> Result<String, String> {
+    let png_data = img.to_png(None).map_err(|e| format!("Failed to convert to PNG: {}", e))?;
+    let file_path = crate::fs_manager::save_png_to_pictures(app, &png_data).map_err(|e| e.to_string())?;
+    Ok(file_path.to_string_lossy().to_string())
 }
 
 /// 关闭所有遮罩窗口
@@ -157,15 +348,60 @@ pub async fn close_all_overlays(app: AppHandle) -> Result<(), String> {
     Ok(())
 }
 
-/// 开始从区域截图进行识别
+/// 开始从区域截图进行识别；handwriting、mode 为遮罩上手写模式与识别模式开关的状态
 #[tauri::command]
-pub async fn start_recognition_from_region_capture(app: AppHandle, image_path: String) -> Result<(), String> {
-    // 获取主窗口
-    if let Some(main_window) = app.get_window("main") {
-        // 发送事件到主窗口，通知开始识别
-        main_window.emit("region-capture-completed", image_path)
-            .map_err(|e| format!("Failed to emit event: {}", e))?;
-    }
-
+pub async fn start_recognition_from_region_capture(
+    app: AppHandle,
+    image_path: String,
+    handwriting: Option<bool>,
+    mode: Option<RecognitionMode>,
+) -> Result<(), String> {
+    let quick = QUICK_MODE.swap(false, Ordering::SeqCst);
+    let auto_paste = AUTO_PASTE.swap(false, Ordering::SeqCst);
+    let handwriting = handwriting.unwrap_or(false);
+    let mode = mode.unwrap_or_default();
+    LAST_HANDWRITING.store(handwriting, Ordering::SeqCst);
+    *LAST_MODE.lock().unwrap_or_else(|e| e.into_inner()) = mode;
+    recognize_capture(app, image_path, quick, auto_paste, handwriting, mode);
     Ok(())
 }
+
+/// Recognizes a region capture in the background (independent of any window), then removes the
+/// capture file unless the saved history item shares it
+fn recognize_capture(app: AppHandle, image_path: String, quick: bool, auto_paste: bool, handwriting: bool, mode: RecognitionMode) {
+    let capture_scale = pending_scales().lock().unwrap_or_else(|e| e.into_inner()).remove(&image_path);
+    tauri::async_runtime::spawn(async move {
+        let result = if quick {
+            crate::quick_capture::run(app.clone(), image_path.clone(), capture_scale, handwriting, mode, auto_paste).await.map(|_| ())
+        } else {
+            // 通知主窗口进入识别状态，进度与结果通过 recognition_progress 等事件送达
+            if let Some(main_window) = app.get_window("main") {
+                let _ = main_window.emit("region-capture-started", &image_path);
+            }
+            match std::fs::read(&image_path) {
+                Ok(png_bytes) => {
+                    let image = crate::pipeline::CaptureImage::from_bytes(png_bytes).with_capture_scale(capture_scale);
+                    let options = crate::pipeline::RecognitionOptions { handwriting, mode, auto_paste, ..Default::default() };
+                    crate::job_queue::submit(&app, image, "screenshot", options).await.map(|_| ())
+                }
+                Err(e) => Err(e.to_string()),
+            }
+        };
+        if let Err(e) = result {
+            #[cfg(debug_assertions)]
+            eprintln!("Region capture recognition failed: {}", e);
+            if !quick {
+                if let Some(main_window) = app.get_window("main") {
+                    let _ = main_window.emit("region-capture-failed", e);
+                }
+            }
+        }
+        match crate::fs_manager::read_history_async(&app).await {
+            Ok(history) => crate::fs_manager::release_image(&app, &image_path, &history),
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("Keeping capture {} (history unreadable): {}", image_path, _e);
+            }
+        }
+    });
+}
@@ -0,0 +1,53 @@
+// 化学模式：化学式与反应式改用 prompts::get_chemistry_prompt 的提示词集（LaTeX 阶段输出 mhchem 的 \ce{...}），
+// 开启 chemistry.smiles 时在 LaTeX 阶段完成后额外请求各物质的 SMILES，保存在条目的 smiles 上。
+// SMILES 请求失败（模型不支持、输出无法解析）时静默跳过，不影响识别结果。
+
+use crate::data_models::Config;
+use crate::llm_api::LlmClient;
+use crate::prompts::{self, PromptType};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// Config with the chemistry prompts in place of the saved stage prompts;
+/// per-stage overrides (custom prompts, presets) still take precedence
+pub fn with_prompts(config: &Config) -> Config {
+    let mut config = config.clone();
+    config.latex_prompt = prompts::get_chemistry_prompt(PromptType::LaTeX);
+    config.analysis_prompt = prompts::get_chemistry_prompt(PromptType::Analysis);
+    config.verification_prompt = prompts::get_chemistry_prompt(PromptType::Verification);
+    config
+}
+
+/// Starts the SMILES request in the background when enabled in config
+pub fn spawn(
+    config: &Config,
+    client: &Arc<dyn LlmClient>,
+    latex: &str,
+    image_base64: &Arc<str>,
+) -> Option<JoinHandle<anyhow::Result<Vec<String>>>> {
+    if !config.chemistry.smiles || latex.trim().is_empty() {
+        return None;
+    }
+    let client = client.clone();
+    let latex = latex.to_string();
+    let image = image_base64.clone();
+    Some(tokio::spawn(async move { client.extract_smiles(&latex, &image).await }))
+}
+
+/// Waits for the SMILES request; failures yield None
+pub async fn collect(task: Option<JoinHandle<anyhow::Result<Vec<String>>>>) -> Option<Vec<String>> {
+    match task?.await {
+        Ok(Ok(smiles)) if !smiles.is_empty() => Some(smiles),
+        Ok(Ok(_)) => None,
+        Ok(Err(_e)) => {
+            #[cfg(debug_assertions)]
+            eprintln!("SMILES extraction failed: {}", _e);
+            None
+        }
+        Err(_e) => {
+            #[cfg(debug_assertions)]
+            eprintln!("SMILES task failed: {}", _e);
+            None
+        }
+    }
+}
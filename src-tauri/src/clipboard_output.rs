@@ -0,0 +1,103 @@
+// 识别结果自动写入剪贴板，缩短“截图 → 粘贴”流程
+// 通过快捷键发起的截图还可在 LaTeX 写入剪贴板后模拟 Ctrl+V（macOS 为 Cmd+V），
+// 粘贴到截图前处于前台的应用（遮罩窗口关闭后焦点会回到该应用）。
+// 自动粘贴按识别 id 登记，只有快捷键发起的那次识别会被粘贴，其他识别不会误用。
+
+use crate::data_models::Config;
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::api::notification::Notification;
+use tauri::{AppHandle, ClipboardManager};
+
+/// 快捷键截图后等待识别结果的最长时间，超时则不再自动粘贴
+const PASTE_ARM_TTL: Duration = Duration::from_secs(120);
+/// 给目标窗口重新获得焦点留出的时间
+const PASTE_DELAY: Duration = Duration::from_millis(150);
+
+/// 等待自动粘贴的识别：识别 id -> 登记时间
+static PASTE_ARMS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+
+fn paste_arms() -> std::sync::MutexGuard<'static, HashMap<String, Instant>> {
+    PASTE_ARMS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Marks recognition `id` as shortcut-triggered so its result gets pasted
+pub fn arm_auto_paste(config: &Config, id: &str) {
+    if config.auto_paste {
+        let mut arms = paste_arms();
+        arms.retain(|_, armed_at| armed_at.elapsed() <= PASTE_ARM_TTL);
+        arms.insert(id.to_string(), Instant::now());
+    }
+}
+
+fn take_paste_armed(id: &str) -> bool {
+    paste_arms().remove(id).is_some_and(|armed_at| armed_at.elapsed() <= PASTE_ARM_TTL)
+}
+
+/// Sends Ctrl+<key> (Cmd+<key> on macOS) to the foreground application
+fn simulate_shortcut(key: char) -> Result<(), String> {
+    let modifier = if cfg!(target_os = "macos") { Key::Meta } else { Key::Control };
+    let mut enigo = Enigo::new(&Settings::default()).map_err(|e| e.to_string())?;
+    enigo.key(modifier, Direction::Press).map_err(|e| e.to_string())?;
+    let result = enigo.key(Key::Unicode(key), Direction::Click).map_err(|e| e.to_string());
+    // 无论主键是否成功都要释放修饰键，避免其卡在按下状态
+    enigo.key(modifier, Direction::Release).map_err(|e| e.to_string())?;
+    result
+}
+
+fn simulate_paste() -> Result<(), String> {
+    simulate_shortcut('v')
+}
+
+/// Copies the current selection of the foreground application to the clipboard
+pub fn simulate_copy() -> Result<(), String> {
+    simulate_shortcut('c')
+}
+
+fn notify(app_handle: &AppHandle, language: &str, latex: &str) {
+    let title = if language == "zh-CN" { "LaTeX 已复制到剪贴板" } else { "LaTeX copied to clipboard" };
+    let preview: String = latex.chars().take(80).collect();
+    let _ = Notification::new(&app_handle.config().tauri.bundle.identifier)
+        .title(title)
+        .body(preview)
+        .show();
+}
+
+/// Places the LaTeX of recognition `id` on the clipboard when `autoCopyLatex` is enabled (or an
+/// auto-paste is armed for it) and pastes it into the foreground app for shortcut-triggered
+/// captures. Returns true if copied.
+pub fn auto_copy_latex(app_handle: &AppHandle, config: &Config, id: &str, latex: &str) -> bool {
+    deliver_latex(app_handle, config, id, latex, config.auto_copy_latex)
+}
+
+/// Like [`auto_copy_latex`], but `always_copy` forces the copy regardless of the config flag
+pub fn deliver_latex(app_handle: &AppHandle, config: &Config, id: &str, latex: &str, always_copy: bool) -> bool {
+    let latex = latex.trim();
+    let paste = config.auto_paste && take_paste_armed(id);
+    if !(always_copy || paste) || latex.is_empty() {
+        return false;
+    }
+    match app_handle.clipboard_manager().write_text(latex) {
+        Ok(()) => {
+            if paste {
+                std::thread::spawn(|| {
+                    std::thread::sleep(PASTE_DELAY);
+                    if let Err(_e) = simulate_paste() {
+                        #[cfg(debug_assertions)]
+                        eprintln!("[Clipboard] Auto-paste failed: {}", _e);
+                    }
+                });
+            } else {
+                notify(app_handle, &config.language, latex);
+            }
+            true
+        }
+        Err(_e) => {
+            #[cfg(debug_assertions)]
+            eprintln!("[Clipboard] Failed to copy LaTeX: {}", _e);
+            false
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// 公式集合：有序的历史条目列表（如“论文第 3 章”），保存在 collections.json。
+// 集合内的公式按顺序自动编号（起始编号可设），并为每条公式生成稳定的 \label 名，
+// 导出时可生成带 \label 的 equation 环境及 \eqref 引用表，直接放入论文。
+
+use crate::data_models::HistoryItem;
+use crate::fs_manager;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+const COLLECTIONS_FILENAME: &str = "collections.json";
+
+fn default_start_number() -> u32 {
+    1
+}
+
+fn default_label_prefix() -> String {
+    "eq".to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    /// 按编号顺序排列的条目 id
+    #[serde(default)]
+    pub item_ids: Vec<String>,
+    /// 第一条公式的编号
+    #[serde(default = "default_start_number")]
+    pub start_number: u32,
+    /// \label 名前缀，如 "eq" 生成 eq:energy
+    #[serde(default = "default_label_prefix")]
+    pub label_prefix: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Fields accepted when creating or updating a collection
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionInput {
+    pub name: String,
+    #[serde(default)]
+    pub item_ids: Vec<String>,
+    #[serde(default = "default_start_number")]
+    pub start_number: u32,
+    #[serde(default = "default_label_prefix")]
+    pub label_prefix: String,
+}
+
+/// 集合中一条公式的编号与引用名
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NumberedEquation {
+    pub item_id: String,
+    pub number: u32,
+    pub label: String,
+}
+
+pub fn read_collections(app_handle: &AppHandle) -> Result<Vec<Collection>> {
+    let path = fs_manager::get_data_file_path(app_handle, COLLECTIONS_FILENAME)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).context("Failed to parse collections.json"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read collections.json")),
+    }
+}
+
+fn write_collections(app_handle: &AppHandle, collections: &[Collection]) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, COLLECTIONS_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec_pretty(collections)?).context("Failed to write collections.json")
+}
+
+fn validate_input(collections: &[Collection], input: &CollectionInput, except_id: Option<&str>) -> Result<(String, String), String> {
+    let name = input.name.trim();
+    if name.is_empty() {
+        return Err("Collection name must not be empty".to_string());
+    }
+    if collections
+        .iter()
+        .any(|c| Some(c.id.as_str()) != except_id && c.name.eq_ignore_ascii_case(name))
+    {
+        return Err(format!("A collection named '{}' already exists", name));
+    }
+    // \label 名只允许字母、数字和 - _ .
+    let prefix: String = input
+        .label_prefix
+        .trim()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        .collect();
+    let prefix = if prefix.is_empty() { default_label_prefix() } else { prefix };
+    Ok((name.to_string(), prefix))
+}
+
+/// Numbers the collection's items in order; items no longer in history are skipped
+/// without consuming a number. Labels are `<prefix>:<title slug>`, made unique.
+pub fn number_items(collection: &Collection, history: &[HistoryItem]) -> Vec<(NumberedEquation, HistoryItem)> {
+    let mut used = HashSet::new();
+    let mut number = collection.start_number;
+    let mut out = Vec::new();
+    for id in &collection.item_ids {
+        let Some(item) = history.iter().find(|i| &i.id == id) else { continue };
+        let base = format!("{}:{}", collection.label_prefix, crate::export::slugify(&item.title, &item.id));
+        let mut label = base.clone();
+        let mut n = 2;
+        while !used.insert(label.clone()) {
+            label = format!("{}-{}", base, n);
+            n += 1;
+        }
+        out.push((NumberedEquation { item_id: item.id.clone(), number, label }, item.clone()));
+        number += 1;
+    }
+    out
+}
+
+// --- Tauri commands ---
+
+#[tauri::command]
+pub fn list_collections(app_handle: AppHandle) -> Result<Vec<Collection>, String> {
+    read_collections(&app_handle).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_collection(app_handle: AppHandle, collection: CollectionInput) -> Result<Collection, String> {
+    let mut collections = read_collections(&app_handle).map_err(|e| e.to_string())?;
+    let (name, label_prefix) = validate_input(&collections, &collection, None)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let created = Collection {
+        id: Uuid::new_v4().to_string(),
+        name,
+        item_ids: collection.item_ids,
+        start_number: collection.start_number,
+        label_prefix,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    collections.push(created.clone());
+    write_collections(&app_handle, &collections).map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+/// Replaces name, numbering options and item order of a collection
+#[tauri::command]
+pub fn update_collection(app_handle: AppHandle, id: String, collection: CollectionInput) -> Result<Collection, String> {
+    let mut collections = read_collections(&app_handle).map_err(|e| e.to_string())?;
+    let (name, label_prefix) = validate_input(&collections, &collection, Some(&id))?;
+    let existing = collections
+        .iter_mut()
+        .find(|c| c.id == id)
+        .ok_or_else(|| format!("Collection '{}' not found", id))?;
+    existing.name = name;
+    existing.item_ids = collection.item_ids;
+    existing.start_number = collection.start_number;
+    existing.label_prefix = label_prefix;
+    existing.updated_at = chrono::Utc::now().to_rfc3339();
+    let updated = existing.clone();
+    write_collections(&app_handle, &collections).map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn delete_collection(app_handle: AppHandle, id: String) -> Result<(), String> {
+    let mut collections = read_collections(&app_handle).map_err(|e| e.to_string())?;
+    let before = collections.len();
+    collections.retain(|c| c.id != id);
+    if collections.len() == before {
+        return Err(format!("Collection '{}' not found", id));
+    }
+    write_collections(&app_handle, &collections).map_err(|e| e.to_string())
+}
+
+/// Moves items into a collection in one write: they are removed from every other collection
+/// and appended to the target (items already in it keep their position)
+#[tauri::command]
+pub fn move_items_to_collection(app_handle: AppHandle, ids: Vec<String>, collection_id: String) -> Result<Collection, String> {
+    let mut collections = read_collections(&app_handle).map_err(|e| e.to_string())?;
+    let target = collections
+        .iter()
+        .position(|c| c.id == collection_id)
+        .ok_or_else(|| format!("Collection '{}' not found", collection_id))?;
+    let moving: HashSet<&str> = ids.iter().map(String::as_str).collect();
+    let now = chrono::Utc::now().to_rfc3339();
+    for collection in collections.iter_mut() {
+        let before = collection.item_ids.len();
+        if collection.id == collection_id {
+            for id in &ids {
+                if !collection.item_ids.contains(id) {
+                    collection.item_ids.push(id.clone());
+                }
+            }
+        } else {
+            collection.item_ids.retain(|id| !moving.contains(id.as_str()));
+        }
+        if collection.item_ids.len() != before {
+            collection.updated_at = now.clone();
+        }
+    }
+    write_collections(&app_handle, &collections).map_err(|e| e.to_string())?;
+    Ok(collections.swap_remove(target))
+}
+
+/// Equation numbers and labels of a collection, in order
+#[tauri::command]
+pub fn get_collection_numbering(app_handle: AppHandle, id: String) -> Result<Vec<NumberedEquation>, String> {
+    let collection = read_collections(&app_handle)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or_else(|| format!("Collection '{}' not found", id))?;
+    let history = crate::get_history(app_handle)?;
+    Ok(number_items(&collection, &history).into_iter().map(|(n, _)| n).collect())
+}
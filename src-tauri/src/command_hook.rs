@@ -0,0 +1,110 @@
+// 识别完成后执行用户配置的外部命令（比 WASM 插件更轻量的扩展方式）
+//
+// 命令模板支持 {latex}、{image_path}、{id}、{title} 占位符，替换时会按当前平台的 shell 规则加引号；
+// 同样的值也通过环境变量 AIFS_LATEX / AIFS_IMAGE_PATH / AIFS_ID / AIFS_TITLE 传入。
+// 开启 captureCommandOutput 时等待命令结束并把标准输出写入条目备注，否则在后台执行不阻塞识别。
+
+use crate::data_models::{Config, HistoryItem};
+use crate::fs_manager;
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::process::Command;
+
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Quotes a value for `cmd /C`. Inside quotes cmd still expands `%VAR%`, so each `%` is emitted
+/// outside the quotes and caret-escaped (`"^%"`); `^` is literal inside quotes and needs nothing.
+#[cfg(target_os = "windows")]
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\"").replace('%', "\"^%\""))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn render_template(template: &str, item: &HistoryItem, image_path: &str) -> String {
+    template
+        .replace("{latex}", &quote(&item.latex))
+        .replace("{image_path}", &quote(image_path))
+        .replace("{id}", &quote(&item.id))
+        .replace("{title}", &quote(&item.title))
+}
+
+fn build_command(command_line: &str, item: &HistoryItem, image_path: &str) -> Command {
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        let mut c = Command::new("cmd");
+        // 原样传给 cmd，避免再按 C 运行库规则转义一次引号
+        c.arg("/C").raw_arg(command_line);
+        c
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut cmd = {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command_line);
+        c
+    };
+    cmd.env("AIFS_LATEX", &item.latex)
+        .env("AIFS_IMAGE_PATH", image_path)
+        .env("AIFS_ID", &item.id)
+        .env("AIFS_TITLE", &item.title)
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    cmd
+}
+
+async fn run_and_capture(mut cmd: Command) -> Result<String, String> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let child = cmd.spawn().map_err(|e| format!("Failed to start command: {}", e))?;
+    let output = tokio::time::timeout(COMMAND_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| format!("Command timed out after {}s", COMMAND_TIMEOUT.as_secs()))?
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "Command exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Runs the configured post-recognition command for the item.
+/// The item is returned unchanged unless output capture is enabled and the command succeeds.
+pub async fn run(app_handle: &AppHandle, config: &Config, mut item: HistoryItem) -> HistoryItem {
+    let template = config.post_recognition_command.trim();
+    if template.is_empty() {
+        return item;
+    }
+    // 外部命令拿到的是绝对路径
+    let image_path = if item.original_image.is_empty() {
+        String::new()
+    } else {
+        fs_manager::resolve_image_path(app_handle, &item.original_image).to_string_lossy().to_string()
+    };
+    let cmd = build_command(&render_template(template, &item, &image_path), &item, &image_path);
+
+    if !config.capture_command_output {
+        tauri::async_runtime::spawn(async move {
+            if let Err(_e) = run_and_capture(cmd).await {
+                #[cfg(debug_assertions)]
+                eprintln!("[CommandHook] {}", _e);
+            }
+        });
+        return item;
+    }
+
+    match run_and_capture(cmd).await {
+        Ok(stdout) if !stdout.is_empty() => item.notes = Some(stdout),
+        Ok(_) => {}
+        Err(_e) => {
+            #[cfg(debug_assertions)]
+            eprintln!("[CommandHook] {}", _e);
+        }
+    }
+    item
+}
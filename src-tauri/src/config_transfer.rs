@@ -0,0 +1,185 @@
+// 设置导出/导入：把 config.json 包装成 { "format": "ai-formula-scanner-config", "version": 1, "appVersion": "...",
+// "includesSecrets": bool, "config": {...} } 写出，便于迁移到另一台机器或分享给团队。默认不含密钥
+// （API Key、备用服务密钥、S3 凭据、本地 API token）；导入时文件中为空的密钥保留本机现有值，窗口位置也保留本机的。
+// 导入文件的提示词版本落后时与启动迁移一致升级为当前默认提示词（较新的版本原样保留），替换前的提示词都记入提示词历史。
+
+use crate::data_models::{Config, PROMPTS_VERSION_CURRENT};
+use crate::{config_validation, fs_manager, prompt_history, shortcuts};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+const CONFIG_EXPORT_FORMAT: &str = "ai-formula-scanner-config";
+const CONFIG_EXPORT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ConfigExport {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    app_version: String,
+    #[serde(default)]
+    exported_at: String,
+    #[serde(default)]
+    includes_secrets: bool,
+    config: serde_json::Value,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigImportSummary {
+    /// 导入文件的提示词版本较旧，已升级为当前默认提示词
+    pub prompts_migrated: bool,
+    /// 文件不含密钥，沿用了本机的密钥
+    pub kept_local_secrets: bool,
+    /// 注册失败的快捷键等非致命问题
+    pub warnings: Vec<String>,
+}
+
+/// Clears API keys, backup and proxy credentials and the local API token
+fn strip_secrets(config: &mut Config) {
+    config.api_key.clear();
+    config.additional_api_keys.clear();
+    config.failover.api_key.clear();
+    config.backup.access_key_id.clear();
+    config.backup.secret_access_key.clear();
+    config.local_api.token.clear();
+    config.proxy.password.clear();
+    for (name, value) in config.gateway.headers.iter_mut() {
+        if is_secret_header(name) {
+            value.clear();
+        }
+    }
+}
+
+/// Gateway headers that usually carry credentials (Authorization, X-Api-Key, tokens)
+fn is_secret_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    ["auth", "key", "token", "secret"].iter().any(|word| name.contains(word))
+}
+
+/// Fills secrets the imported config leaves empty from the local config.
+/// Returns true if any local secret was kept.
+fn keep_local_secrets(imported: &mut Config, local: &Config) -> bool {
+    let mut kept = false;
+    let mut keep = |target: &mut String, local: &String| {
+        if target.trim().is_empty() && !local.trim().is_empty() {
+            *target = local.clone();
+            kept = true;
+        }
+    };
+    keep(&mut imported.api_key, &local.api_key);
+    keep(&mut imported.failover.api_key, &local.failover.api_key);
+    keep(&mut imported.backup.access_key_id, &local.backup.access_key_id);
+    keep(&mut imported.backup.secret_access_key, &local.backup.secret_access_key);
+    keep(&mut imported.local_api.token, &local.local_api.token);
+    keep(&mut imported.proxy.password, &local.proxy.password);
+    for (name, value) in imported.gateway.headers.iter_mut().filter(|(name, _)| is_secret_header(name)) {
+        if let Some(local_value) = local.gateway.headers.get(name) {
+            keep(value, local_value);
+        }
+    }
+    if imported.additional_api_keys.is_empty() && !local.additional_api_keys.is_empty() {
+        imported.additional_api_keys = local.additional_api_keys.clone();
+        kept = true;
+    }
+    kept
+}
+
+fn build_export(config: &Config, include_secrets: bool) -> Result<Vec<u8>> {
+    let mut config = config.clone();
+    if !include_secrets {
+        strip_secrets(&mut config);
+    }
+    let export = ConfigExport {
+        format: CONFIG_EXPORT_FORMAT.to_string(),
+        version: CONFIG_EXPORT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        includes_secrets: include_secrets,
+        config: serde_json::to_value(&config)?,
+    };
+    Ok(serde_json::to_vec_pretty(&export)?)
+}
+
+/// Parses an exported file; a bare config.json is accepted as well
+fn parse_export(bytes: &[u8]) -> Result<Config> {
+    let value: serde_json::Value = serde_json::from_slice(bytes).context("The file is not valid JSON")?;
+    let config_value = if value.get("format").is_some() {
+        let export: ConfigExport = serde_json::from_value(value).context("Malformed settings file")?;
+        if export.format != CONFIG_EXPORT_FORMAT {
+            return Err(anyhow!("Not a settings file (format '{}')", export.format));
+        }
+        if export.version > CONFIG_EXPORT_VERSION {
+            return Err(anyhow!("The settings file was written by a newer version of the app (format version {})", export.version));
+        }
+        export.config
+    } else {
+        value
+    };
+    serde_json::from_value(config_value).context("The settings in the file are invalid")
+}
+
+fn import(app_handle: &AppHandle, bytes: &[u8]) -> Result<ConfigImportSummary> {
+    let mut imported = parse_export(bytes)?;
+    let local = fs_manager::read_config(app_handle)?;
+    let mut summary = ConfigImportSummary::default();
+
+    // 当前提示词被替换前先记入提示词历史，便于回退
+    if let Err(e) = prompt_history::record_revision(app_handle, &local, "import") {
+        summary.warnings.push(format!("Could not save the current prompts to the prompt history: {}", e));
+    }
+    if imported.prompts_version > PROMPTS_VERSION_CURRENT {
+        // 来自更新版本的提示词原样保留，按当前版本记录
+        summary.warnings.push(format!(
+            "The prompts come from a newer app version (prompts version {}); they were kept as they are",
+            imported.prompts_version
+        ));
+        imported.prompts_version = PROMPTS_VERSION_CURRENT;
+    }
+    let before_migration = imported.clone();
+    imported.migrate_shortcuts();
+    if imported.migrate_prompts() && before_migration.prompts_version != imported.prompts_version {
+        // 文件中的旧版提示词会被默认提示词覆盖，同样先保存
+        let _ = prompt_history::record_revision(app_handle, &before_migration, "import (outdated prompts)");
+        summary.prompts_migrated = true;
+    }
+
+    summary.kept_local_secrets = keep_local_secrets(&mut imported, &local);
+    imported.window_width = local.window_width;
+    imported.window_height = local.window_height;
+    imported.window_x = local.window_x;
+    imported.window_y = local.window_y;
+
+    let validation = config_validation::validate(&imported);
+    if !validation.is_valid() {
+        return Err(anyhow!(validation.error_message()));
+    }
+    summary.warnings.extend(validation.warnings.into_iter().map(|w| format!("{}: {}", w.field, w.message)));
+
+    fs_manager::write_config(app_handle, &imported)?;
+    for (action, error) in shortcuts::register_all(app_handle, &imported) {
+        summary.warnings.push(format!("Shortcut for {:?} could not be registered: {}", action, error));
+    }
+    Ok(summary)
+}
+
+// --- Tauri commands ---
+
+/// Writes the current settings to a file. Secrets (API keys, backup credentials, local API token)
+/// are left out unless `includeSecrets` is set.
+#[tauri::command]
+pub fn export_config(app_handle: AppHandle, path: String, include_secrets: Option<bool>) -> Result<(), String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let bytes = build_export(&config, include_secrets.unwrap_or(false)).map_err(|e| e.to_string())?;
+    std::fs::write(&path, bytes).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Replaces the settings with those in an exported file (or a bare config.json), keeping local
+/// secrets the file does not contain and this machine's window geometry
+#[tauri::command]
+pub fn import_config(app_handle: AppHandle, path: String) -> Result<ConfigImportSummary, String> {
+    let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    import(&app_handle, &bytes).map_err(|e| format!("{:#}", e))
+}
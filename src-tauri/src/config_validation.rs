@@ -0,0 +1,292 @@
+// 设置校验：保存前逐字段检查配置（API 地址、超时、快捷键格式与冲突、备份/备用服务/本地 API 设置等），
+// 返回带字段路径的错误与警告。有错误时 save_config / import_config 拒绝写入，
+// 警告（如未填写 API Key、重试次数过多）不阻止保存，由前端提示。
+
+use crate::data_models::{Config, GatewayConfig, SafetySetting, ShortcutsConfig, StageGeneration};
+use crate::llm_api::Provider;
+use crate::shortcuts::{ShortcutAction, ALL_ACTIONS};
+use serde::Serialize;
+
+/// 与 Gemini 的输出上限大致相当，超出多半是误填
+const MAX_OUTPUT_TOKENS_LIMIT: u32 = 65_536;
+const MAX_TIMEOUT_SECONDS: u64 = 600;
+const MAX_RETRIES_WARNING: u32 = 5;
+const MAX_REQUEST_INTERVAL_MS: u64 = 60_000;
+
+/// 与 llm_api::Provider::from_name 识别的名称一致
+const KNOWN_PROVIDERS: &[&str] = &["gemini", "openai", "openai-compatible", "openai_compatible", "ollama", "mock"];
+
+/// Gemini 接受的安全过滤阈值
+const SAFETY_THRESHOLDS: &[&str] = &[
+    "BLOCK_NONE", "BLOCK_ONLY_HIGH", "BLOCK_MEDIUM_AND_ABOVE", "BLOCK_LOW_AND_ABOVE", "HARM_BLOCK_THRESHOLD_UNSPECIFIED", "OFF",
+];
+
+const MODIFIERS: &[&str] = &[
+    "shift", "control", "ctrl", "alt", "option", "super", "command", "cmd", "meta",
+    "commandorcontrol", "commandorctrl", "cmdorctrl", "cmdorcontrol", "altgr",
+];
+
+const NAMED_KEYS: &[&str] = &[
+    "space", "enter", "return", "tab", "backspace", "delete", "insert", "home", "end", "pageup", "pagedown",
+    "up", "down", "left", "right", "arrowup", "arrowdown", "arrowleft", "arrowright", "escape", "esc",
+    "printscreen", "plus", "minus", "equal", "comma", "period", "slash", "backslash", "semicolon", "quote",
+    "backquote", "bracketleft", "bracketright", "numlock", "scrolllock", "capslock", "pause",
+];
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldIssue {
+    /// 字段路径（与 config.json 中的键一致），如 apiBaseUrl、shortcuts.captureRegion
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigValidation {
+    pub errors: Vec<FieldIssue>,
+    pub warnings: Vec<FieldIssue>,
+}
+
+impl ConfigValidation {
+    fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldIssue { field: field.to_string(), message: message.into() });
+    }
+
+    fn warning(&mut self, field: &str, message: impl Into<String>) {
+        self.warnings.push(FieldIssue { field: field.to_string(), message: message.into() });
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// All errors on one line, for commands that can only return a string
+    pub fn error_message(&self) -> String {
+        let issues: Vec<String> = self.errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+        format!("Invalid settings: {}", issues.join("; "))
+    }
+}
+
+/// Checks an accelerator such as `CommandOrControl+Shift+A`: known modifiers followed by one key
+fn check_accelerator(accelerator: &str) -> Result<(), String> {
+    let parts: Vec<&str> = accelerator.split('+').map(str::trim).collect();
+    if parts.iter().any(|p| p.is_empty()) {
+        return Err(format!("'{}' has an empty part", accelerator));
+    }
+    let (key, modifiers) = parts.split_last().ok_or_else(|| "Empty shortcut".to_string())?;
+    if let Some(unknown) = modifiers.iter().find(|m| !MODIFIERS.contains(&m.to_lowercase().as_str())) {
+        return Err(format!("'{}' is not a modifier key", unknown));
+    }
+    let lower = key.to_lowercase();
+    let is_function_key = lower
+        .strip_prefix('f')
+        .and_then(|n| n.parse::<u8>().ok())
+        .is_some_and(|n| (1..=24).contains(&n));
+    let is_single_char = key.chars().count() == 1 && !key.chars().any(char::is_whitespace);
+    if MODIFIERS.contains(&lower.as_str()) {
+        return Err(format!("'{}' needs a key after the modifiers", accelerator));
+    }
+    if !(is_single_char || is_function_key || NAMED_KEYS.contains(&lower.as_str())) {
+        return Err(format!("'{}' is not a known key", key));
+    }
+    Ok(())
+}
+
+fn action_field(action: ShortcutAction) -> String {
+    format!("shortcuts.{}", action.name())
+}
+
+fn check_shortcuts(shortcuts: &ShortcutsConfig, result: &mut ConfigValidation) {
+    let mut seen: Vec<(String, ShortcutAction)> = Vec::new();
+    for action in ALL_ACTIONS {
+        let accelerator = action.binding(shortcuts).trim();
+        if accelerator.is_empty() {
+            continue;
+        }
+        let field = action_field(action);
+        if let Err(message) = check_accelerator(accelerator) {
+            result.error(&field, message);
+            continue;
+        }
+        let normalized = accelerator.to_lowercase().replace(' ', "");
+        match seen.iter().find(|(a, _)| *a == normalized) {
+            Some((_, other)) => result.error(&field, format!("'{}' is already used by {}", accelerator, action_field(*other))),
+            None => seen.push((normalized, action)),
+        }
+    }
+}
+
+fn check_url(field: &str, url: &str, result: &mut ConfigValidation) {
+    match reqwest::Url::parse(url.trim()) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.host().is_some() => {}
+        Ok(_) => result.error(field, format!("'{}' must be an http(s) URL", url)),
+        Err(e) => result.error(field, format!("'{}' is not a valid URL: {}", url, e)),
+    }
+}
+
+fn check_generation(stage: &str, params: &StageGeneration, result: &mut ConfigValidation) {
+    if !(0.0..=2.0).contains(&params.temperature) {
+        result.error(&format!("generation.{}.temperature", stage), "The temperature must be between 0 and 2");
+    }
+    if params.top_p.is_some_and(|p| !(p > 0.0 && p <= 1.0)) {
+        result.error(&format!("generation.{}.topP", stage), "topP must be greater than 0 and at most 1");
+    }
+    if params.top_k == Some(0) {
+        result.error(&format!("generation.{}.topK", stage), "topK must be at least 1");
+    }
+    match params.max_output_tokens {
+        Some(0) => result.error(&format!("generation.{}.maxOutputTokens", stage), "At least 1 output token is required"),
+        Some(tokens) if tokens > MAX_OUTPUT_TOKENS_LIMIT => result.warning(
+            &format!("generation.{}.maxOutputTokens", stage),
+            format!("Most models accept at most {} output tokens", MAX_OUTPUT_TOKENS_LIMIT),
+        ),
+        _ => {}
+    }
+}
+
+fn check_proxy(config: &Config, result: &mut ConfigValidation) {
+    let url = config.proxy.url.trim();
+    if url.is_empty() {
+        result.error("proxy.url", "The proxy is enabled but no URL is set");
+        return;
+    }
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https" | "socks5" | "socks5h") && parsed.host().is_some() => {}
+        Ok(_) => result.error("proxy.url", format!("'{}' must be an http(s) or socks5 URL", url)),
+        Err(e) => result.error("proxy.url", format!("'{}' is not a valid URL: {}", url, e)),
+    }
+    if config.proxy.username.trim().is_empty() && !config.proxy.password.is_empty() {
+        result.warning("proxy.username", "A proxy password is set without a username and will be ignored");
+    }
+}
+
+fn check_gateway(gateway: &GatewayConfig, result: &mut ConfigValidation) {
+    for (name, value) in &gateway.headers {
+        let field = format!("gateway.headers.{}", name);
+        if reqwest::header::HeaderName::from_bytes(name.trim().as_bytes()).is_err() {
+            result.error(&field, format!("'{}' is not a valid header name", name));
+        } else if reqwest::header::HeaderValue::from_str(value).is_err() {
+            result.error(&field, format!("The value of header '{}' contains invalid characters", name));
+        }
+    }
+    let envelope = gateway.request_envelope.trim();
+    if !envelope.is_empty() {
+        match serde_json::from_str::<serde_json::Value>(envelope) {
+            Ok(_) if !envelope.contains("\"{{body}}\"") => {
+                result.error("gateway.requestEnvelope", "The envelope must contain \"{{body}}\" where the request body goes")
+            }
+            Ok(_) => {}
+            Err(e) => result.error("gateway.requestEnvelope", format!("The envelope is not valid JSON: {}", e)),
+        }
+    }
+    let path = gateway.response_path.trim();
+    if !path.is_empty() && !path.starts_with('/') {
+        result.error("gateway.responsePath", format!("'{}' must be a JSON Pointer starting with '/'", path));
+    }
+}
+
+fn check_safety_settings(config: &Config, settings: &[SafetySetting], result: &mut ConfigValidation) {
+    if !settings.is_empty() && Provider::from_name(&config.provider) != Provider::Gemini {
+        result.warning("safetySettings", "Safety settings only apply to the Gemini provider");
+    }
+    for (i, setting) in settings.iter().enumerate() {
+        let field = format!("safetySettings.{}", i);
+        if !setting.category.starts_with("HARM_CATEGORY_") {
+            result.error(&field, format!("'{}' is not a Gemini harm category", setting.category));
+        }
+        if !SAFETY_THRESHOLDS.contains(&setting.threshold.as_str()) {
+            result.error(&field, format!("'{}' is not a valid threshold (expected one of {})", setting.threshold, SAFETY_THRESHOLDS.join(", ")));
+        }
+    }
+}
+
+/// Validates the whole config; errors block saving, warnings do not
+pub fn validate(config: &Config) -> ConfigValidation {
+    let mut result = ConfigValidation::default();
+
+    check_url("apiBaseUrl", &config.api_base_url, &mut result);
+    if !KNOWN_PROVIDERS.contains(&config.provider.trim().to_ascii_lowercase().as_str()) {
+        result.warning("provider", format!("Unknown provider '{}'; the Gemini protocol will be used", config.provider));
+    }
+    if config.default_engine.trim().is_empty() {
+        result.error("defaultEngine", "A model must be selected");
+    } else if config.default_engine.trim().contains(char::is_whitespace) {
+        result.error("defaultEngine", format!("'{}' is not a valid model name", config.default_engine));
+    }
+    if config.request_timeout_seconds == 0 {
+        result.error("requestTimeoutSeconds", "The timeout must be at least 1 second");
+    } else if config.request_timeout_seconds > MAX_TIMEOUT_SECONDS {
+        result.warning("requestTimeoutSeconds", format!("Timeouts over {} seconds rarely help", MAX_TIMEOUT_SECONDS));
+    }
+    if config.max_output_tokens == 0 {
+        result.error("maxOutputTokens", "At least 1 output token is required");
+    } else if config.max_output_tokens > MAX_OUTPUT_TOKENS_LIMIT {
+        result.warning("maxOutputTokens", format!("Most models accept at most {} output tokens", MAX_OUTPUT_TOKENS_LIMIT));
+    }
+    if config.min_request_interval_ms > MAX_REQUEST_INTERVAL_MS {
+        result.warning("minRequestIntervalMs", "Intervals over a minute make every recognition very slow");
+    }
+    if config.max_concurrent_requests == 1 {
+        result.warning("maxConcurrentRequests", "With a single request at a time the recognition stages run one after another");
+    }
+    if config.max_retries > MAX_RETRIES_WARNING {
+        result.warning("maxRetries", "Many retries with exponential backoff can make failures take minutes");
+    }
+    if config.api_keys().is_empty() && Provider::from_name(&config.provider).requires_api_key() {
+        result.warning("apiKey", "No API key is set; only proxies that add their own key will work");
+    }
+    if config.language.trim().is_empty() {
+        result.error("language", "An output language is required");
+    }
+    if config.duplicate_threshold > 64 {
+        result.error("duplicateThreshold", "The threshold is a Hamming distance between 0 and 64");
+    }
+
+    check_shortcuts(&config.shortcuts, &mut result);
+    check_generation("latex", &config.generation.latex, &mut result);
+    check_generation("analysis", &config.generation.analysis, &mut result);
+    check_generation("verification", &config.generation.verification, &mut result);
+
+    if config.failover.enabled {
+        if config.failover.api_base_url.trim().is_empty() {
+            result.error("failover.apiBaseUrl", "Failover is enabled but no URL is set");
+        } else {
+            check_url("failover.apiBaseUrl", &config.failover.api_base_url, &mut result);
+        }
+    }
+    if config.proxy.enabled {
+        check_proxy(config, &mut result);
+    }
+    check_gateway(&config.gateway, &mut result);
+    check_safety_settings(config, &config.safety_settings, &mut result);
+    if !config.poppler_path.trim().is_empty() && !std::path::Path::new(config.poppler_path.trim()).is_dir() {
+        result.warning("popplerPath", format!("'{}' is not a directory; PDF import will not work", config.poppler_path));
+    }
+    if config.ensemble.enabled {
+        let models = config.ensemble.models.iter().filter(|m| !m.trim().is_empty() && m.trim() != config.default_engine).count();
+        if models == 0 {
+            result.warning("ensemble.models", "Ensemble mode needs at least one model besides the default model");
+        } else if models > crate::ensemble::MAX_EXTRA_MODELS {
+            result.warning("ensemble.models", format!("Only the first {} extra models are used", crate::ensemble::MAX_EXTRA_MODELS));
+        }
+    }
+    if config.backup.enabled && !config.backup.is_configured() {
+        result.error("backup", "Backup is enabled but endpoint, bucket or credentials are missing");
+    }
+    if config.backup.enabled && !config.backup.endpoint.trim().is_empty() {
+        check_url("backup.endpoint", &config.backup.endpoint, &mut result);
+    }
+    if config.local_api.enabled && config.local_api.websocket_enabled && config.local_api.port == config.local_api.websocket_port {
+        result.error("localApi.websocketPort", "The WebSocket port must differ from the HTTP API port");
+    }
+    result
+}
+
+// --- Tauri commands ---
+
+/// Field-level errors and warnings for a config, without saving it
+#[tauri::command]
+pub fn validate_config(config: Config) -> ConfigValidation {
+    validate(&config)
+}
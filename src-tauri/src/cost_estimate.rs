@@ -0,0 +1,183 @@
+// 费用预估：在发送前按图片尺寸估算图片 Token（Gemini 按 258 Token 的图块计费），加上各阶段提示词与
+// 典型输出长度，得出单张图片的 Token 数与按模型价格表计算的费用，便于批量处理数百页前做预算。
+// 结果只是近似值：输出长度取经验值，思考模型的思考 Token 未计入，价格表未收录的模型只给出 Token 数。
+
+use crate::data_models::Config;
+use crate::{fs_manager, prompt_library, prompts};
+use serde::Serialize;
+use tauri::AppHandle;
+
+/// 每个图块（或不超过 384px 的小图）计 258 Token
+const TOKENS_PER_TILE: u64 = 258;
+const SMALL_IMAGE_MAX_SIDE: u32 = 384;
+/// 估算文本 Token 时按每 Token 约 4 个字符计
+const CHARS_PER_TOKEN: u64 = 4;
+
+// 各阶段的典型输出长度（Token）
+const LATEX_OUTPUT_TOKENS: u64 = 300;
+const ANALYSIS_OUTPUT_TOKENS: u64 = 800;
+const VERIFICATION_OUTPUT_TOKENS: u64 = 400;
+const LANGUAGE_OUTPUT_TOKENS: u64 = 5;
+const SYMBOL_BOXES_PROMPT_TOKENS: u64 = 150;
+const SYMBOL_BOXES_OUTPUT_TOKENS: u64 = 1000;
+
+/// (模型名前缀, 输入价格, 输出价格)，美元 / 百万 Token，按最长前缀匹配
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-flash-lite", 0.10, 0.40),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-2.0-flash-lite", 0.075, 0.30),
+    ("gemini-1.5-pro", 1.25, 5.0),
+    ("gemini-1.5-flash", 0.075, 0.30),
+];
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StageEstimate {
+    pub stage: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCost {
+    pub model: String,
+    /// primary 或 failover
+    pub backend: String,
+    /// 价格表未收录该模型时为空
+    pub cost_per_image_usd: Option<f64>,
+    pub total_cost_usd: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CostEstimate {
+    pub width: u32,
+    pub height: u32,
+    pub image_tokens: u64,
+    /// 按相同图片估算的数量
+    pub pages: u32,
+    pub stages: Vec<StageEstimate>,
+    /// 单张图片的合计
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub models: Vec<ModelCost>,
+}
+
+/// Image tokens as Gemini counts them: small images are one tile, larger ones are cut into
+/// square crops of min(width, height) / 1.5 (clamped to 256–768 px)
+fn image_tokens(width: u32, height: u32) -> u64 {
+    if width <= SMALL_IMAGE_MAX_SIDE && height <= SMALL_IMAGE_MAX_SIDE {
+        return TOKENS_PER_TILE;
+    }
+    let unit = ((width.min(height) as f64 / 1.5) as u32).clamp(256, 768);
+    let tiles = width.div_ceil(unit) as u64 * height.div_ceil(unit) as u64;
+    tiles * TOKENS_PER_TILE
+}
+
+fn text_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Input/output price per million tokens for a model, by longest matching prefix
+pub(crate) fn prices_for(model: &str) -> Option<(f64, f64)> {
+    let model = model.trim().trim_start_matches("models/");
+    PRICES
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|(_, input, output)| (*input, *output))
+}
+
+/// The API calls one recognition makes with this config, mirroring the pipeline
+fn stages(config: &Config, image_tokens: u64) -> Vec<StageEstimate> {
+    let cap = |tokens: u64| tokens.min(config.max_output_tokens as u64);
+    // 三个主要阶段可单独设置输出上限
+    let stage_cap = |stage: prompts::PromptType, tokens: u64| {
+        tokens.min(config.generation.for_stage(stage).max_output_tokens.unwrap_or(config.max_output_tokens) as u64)
+    };
+    let latex_prompt = format!(
+        "{}{}",
+        config.stage_prompt(prompts::PromptType::LaTeX),
+        prompts::format_rule_for_latex(&config.default_latex_format)
+    );
+    let analysis_prompt = format!(
+        "{}\n\n{}",
+        config.stage_prompt(prompts::PromptType::Analysis),
+        prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Analysis, &config.language)
+    );
+    let verification_prompt = format!(
+        "{}\n\n{}",
+        config.stage_prompt(prompts::PromptType::Verification),
+        prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Verification, &config.language)
+    );
+    let latex_output = stage_cap(prompts::PromptType::LaTeX, LATEX_OUTPUT_TOKENS);
+
+    let mut stages = Vec::new();
+    if config.auto_detect_language {
+        stages.push(StageEstimate {
+            stage: "language".to_string(),
+            input_tokens: image_tokens + text_tokens(&prompts::get_language_detection_prompt()),
+            output_tokens: cap(LANGUAGE_OUTPUT_TOKENS),
+        });
+    }
+    stages.push(StageEstimate { stage: "latex".to_string(), input_tokens: image_tokens + text_tokens(&latex_prompt), output_tokens: latex_output });
+    stages.push(StageEstimate {
+        stage: "analysis".to_string(),
+        input_tokens: image_tokens + text_tokens(&analysis_prompt),
+        output_tokens: stage_cap(prompts::PromptType::Analysis, ANALYSIS_OUTPUT_TOKENS),
+    });
+    stages.push(StageEstimate {
+        stage: "verification".to_string(),
+        input_tokens: image_tokens + text_tokens(&verification_prompt) + latex_output,
+        output_tokens: stage_cap(prompts::PromptType::Verification, VERIFICATION_OUTPUT_TOKENS),
+    });
+    if config.symbol_boxes {
+        stages.push(StageEstimate {
+            stage: "symbol_boxes".to_string(),
+            input_tokens: image_tokens + SYMBOL_BOXES_PROMPT_TOKENS + latex_output,
+            output_tokens: cap(SYMBOL_BOXES_OUTPUT_TOKENS),
+        });
+    }
+    stages
+}
+
+// --- Tauri commands ---
+
+/// Approximate tokens and price of recognizing the image at `image_path` (optionally with a prompt
+/// preset), for the primary model and the failover model if one is set. `pages` multiplies the
+/// total, for budgeting a batch of similar images.
+#[tauri::command]
+pub fn estimate_cost(app_handle: AppHandle, image_path: String, preset: Option<String>, pages: Option<u32>) -> Result<CostEstimate, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let config = prompt_library::apply_preset(&app_handle, config, preset.as_deref())?;
+    let (width, height) = image::image_dimensions(&image_path).map_err(|e| format!("Failed to read {}: {}", image_path, e))?;
+    let pages = pages.unwrap_or(1).max(1);
+
+    let image_tokens = image_tokens(width, height);
+    let stages = stages(&config, image_tokens);
+    let input_tokens: u64 = stages.iter().map(|s| s.input_tokens).sum();
+    let output_tokens: u64 = stages.iter().map(|s| s.output_tokens).sum();
+
+    let mut models = vec![(config.default_engine.clone(), crate::failover::PRIMARY_BACKEND)];
+    if let Some(failover) = config.to_llm_config().failover {
+        models.push((failover.model_name, crate::failover::FAILOVER_BACKEND));
+    }
+    let models = models
+        .into_iter()
+        .map(|(model, backend)| {
+            let per_image = prices_for(&model)
+                .map(|(input, output)| (input_tokens as f64 * input + output_tokens as f64 * output) / 1_000_000.0);
+            ModelCost {
+                model,
+                backend: backend.to_string(),
+                cost_per_image_usd: per_image,
+                total_cost_usd: per_image.map(|cost| cost * pages as f64),
+            }
+        })
+        .collect();
+
+    Ok(CostEstimate { width, height, image_tokens, pages, stages, input_tokens, output_tokens, models })
+}
@@ -0,0 +1,138 @@
+// 崩溃报告：启动时安装 panic 钩子，崩溃时把错误信息、调用栈、最近的运行记录以及应用/系统版本
+// 写入 {数据目录}/crash_reports/crash-<时间>.txt；下次启动时前端通过 get_crash_reports 取出并提示用户，
+// 方便附在 issue 中。运行记录是内存中的环形缓冲（识别阶段等关键事件），不写入磁盘。
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::AppHandle;
+
+const CRASH_DIR: &str = "crash_reports";
+/// 报告中附带的最近运行记录条数
+const BREADCRUMB_CAPACITY: usize = 100;
+
+static BREADCRUMBS: OnceLock<Mutex<VecDeque<String>>> = OnceLock::new();
+static CRASH_DIR_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+fn breadcrumbs() -> &'static Mutex<VecDeque<String>> {
+    BREADCRUMBS.get_or_init(|| Mutex::new(VecDeque::with_capacity(BREADCRUMB_CAPACITY)))
+}
+
+/// Records a line in the in-memory log that is attached to crash reports
+pub fn breadcrumb(message: impl Into<String>) {
+    // 崩溃发生在持锁期间时锁可能已中毒，此时放弃记录即可
+    let Ok(mut lines) = breadcrumbs().lock() else { return };
+    if lines.len() == BREADCRUMB_CAPACITY {
+        lines.pop_front();
+    }
+    lines.push_back(format!("{} {}", chrono::Utc::now().format("%H:%M:%S%.3f"), message.into()));
+}
+
+fn crash_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(crate::fs_manager::app_data_dir(app_handle).map_err(|e| e.to_string())?.join(CRASH_DIR))
+}
+
+fn render_report(message: &str, location: &str) -> String {
+    let thread = std::thread::current();
+    let log_tail = breadcrumbs()
+        .lock()
+        .map(|lines| lines.iter().cloned().collect::<Vec<_>>().join("\n"))
+        .unwrap_or_default();
+
+    format!(
+        "AI Formula Scanner crash report\n\
+         Time: {}\n\
+         App version: {}\n\
+         OS: {} {} ({})\n\
+         Thread: {}\n\
+         Panic: {}\n\
+         Location: {}\n\n\
+         Backtrace:\n{}\n\n\
+         Recent log:\n{}\n",
+        chrono::Utc::now().to_rfc3339(),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::env::consts::FAMILY,
+        thread.name().unwrap_or("<unnamed>"),
+        message,
+        location,
+        std::backtrace::Backtrace::force_capture(),
+        log_tail
+    )
+}
+
+/// Installs the panic hook; the previous hook (stderr output) still runs afterwards
+pub fn install(app_handle: &AppHandle) {
+    if let Ok(dir) = crash_dir(app_handle) {
+        let _ = CRASH_DIR_PATH.set(dir);
+    }
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(dir) = CRASH_DIR_PATH.get() {
+            let message = info
+                .payload()
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| info.payload().downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "<non-string panic payload>".to_string());
+            let location = info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+                .unwrap_or_else(|| "<unknown>".to_string());
+            let report = render_report(&message, &location);
+            let file = dir.join(format!("crash-{}.txt", chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f")));
+            let _ = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&file, report));
+        }
+        previous(info);
+    }));
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub file_name: String,
+    pub path: String,
+    pub content: String,
+}
+
+// --- Tauri commands ---
+
+/// Crash reports left by previous runs, newest first
+#[tauri::command]
+pub fn get_crash_reports(app_handle: AppHandle) -> Result<Vec<CrashReport>, String> {
+    let dir = crash_dir(&app_handle)?;
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    let mut reports: Vec<CrashReport> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "txt"))
+        .filter_map(|path| {
+            let content = std::fs::read_to_string(&path).ok()?;
+            Some(CrashReport {
+                file_name: path.file_name()?.to_string_lossy().to_string(),
+                path: path.to_string_lossy().to_string(),
+                content,
+            })
+        })
+        .collect();
+    // 文件名含时间戳，按名称倒序即最新在前
+    reports.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+    Ok(reports)
+}
+
+/// Deletes a crash report once the user has dealt with it
+#[tauri::command]
+pub fn dismiss_crash_report(app_handle: AppHandle, file_name: String) -> Result<(), String> {
+    // 只接受文件名，防止删除目录外的文件
+    if file_name.contains(['/', '\\']) || file_name.contains("..") {
+        return Err("Invalid crash report name".to_string());
+    }
+    let path = crash_dir(&app_handle)?.join(&file_name);
+    std::fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", file_name, e))
+}
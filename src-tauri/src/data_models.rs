@@ -13,18 +13,47 @@ fn default_window_width() -> u32 { 1280 }
 fn default_window_height() -> u32 { 800 }
 fn default_remember_window_state() -> bool { true }
 fn default_screenshot_shortcut() -> String { "CommandOrControl+Shift+A".to_string() }
-const PROMPTS_VERSION_CURRENT: u32 = 3;
+pub(crate) const PROMPTS_VERSION_CURRENT: u32 = 3;
 fn current_prompts_version() -> u32 { PROMPTS_VERSION_CURRENT }
 fn default_prompts_version() -> u32 { 0 }
+fn default_true() -> bool { true }
+fn default_backup_region() -> String { "us-east-1".to_string() }
+fn default_backup_prefix() -> String { "ai-formula-scanner".to_string() }
+fn default_backup_interval_hours() -> u32 { 24 }
+fn default_backup_retention_count() -> u32 { 7 }
+fn default_local_api_port() -> u16 { 17321 }
+fn default_websocket_port() -> u16 { 17322 }
+fn default_duplicate_threshold() -> u32 { 6 }
+// 一次识别最多同时发出 LaTeX、分析、核查与位置框 4 个请求
+fn default_max_concurrent_requests() -> u32 { 4 }
+fn default_llm_cache_ttl_hours() -> u64 { 24 * 7 }
+fn default_files_api_threshold_kb() -> u64 { 4096 }
+// LaTeX 与核查要求稳定输出，分析允许更多变化
+fn default_latex_generation() -> StageGeneration { StageGeneration::with_temperature(0.2) }
+fn default_analysis_generation() -> StageGeneration { StageGeneration::with_temperature(0.5) }
+fn default_verification_generation() -> StageGeneration { StageGeneration::with_temperature(0.2) }
+// 本地服务（如 Ollama）默认直连
+fn default_proxy_bypass() -> Vec<String> { vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".to_string()] }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
     pub api_key: String,
+    /// 同一服务商的其他 API Key，与 apiKey 一起轮换使用
+    #[serde(default)]
+    pub additional_api_keys: Vec<String>,
+    /// 多个 API Key 时的轮换方式
+    #[serde(default)]
+    pub key_rotation: KeyRotation,
+    /// 主服务不可用时自动切换到的备用服务
+    #[serde(default)]
+    pub failover: FailoverConfig,
     pub api_base_url: String,
     pub provider: String,
     pub default_engine: String,
-    pub custom_prompt: String,
+    /// 按阶段的自定义提示词覆盖（非空时优先于下方保存的提示词）
+    #[serde(default)]
+    pub custom_prompts: CustomPrompts,
     /// Prompt for LaTeX-only fast extraction
     #[serde(default = "default_latex_prompt")]
     pub latex_prompt: String,
@@ -45,6 +74,9 @@ pub struct Config {
     pub max_output_tokens: u32,
     #[serde(default = "default_language")]
     pub language: String,
+    /// 根据截图中的文字自动识别输出语言（失败时回退到 language）
+    #[serde(default)]
+    pub auto_detect_language: bool,
     /// 窗口默认/记忆尺寸与位置
     #[serde(default = "default_window_width")]
     pub window_width: u32,
@@ -59,19 +91,517 @@ pub struct Config {
     /// 内置提示词版本号，用于触发自动迁移
     #[serde(default = "default_prompts_version")]
     pub prompts_version: u32,
-    /// 截图识别快捷键
+    /// 旧版单一截图快捷键，读取时迁移到 shortcuts.captureRegion
+    #[serde(default, rename = "screenshotShortcut", skip_serializing)]
+    pub legacy_screenshot_shortcut: Option<String>,
+    /// 全局快捷键映射
+    #[serde(default)]
+    pub shortcuts: ShortcutsConfig,
+    /// S3 兼容存储的自动备份设置
+    #[serde(default)]
+    pub backup: BackupConfig,
+    /// 本地 HTTP API（仅监听 127.0.0.1）
+    #[serde(default)]
+    pub local_api: LocalApiConfig,
+    /// 启用 {数据目录}/plugins 下的 WASM 插件
+    #[serde(default)]
+    pub plugins_enabled: bool,
+    /// 每次识别完成后执行的外部命令模板，支持 {latex}、{image_path}、{id}、{title} 占位符；留空则不执行
+    #[serde(default)]
+    pub post_recognition_command: String,
+    /// 将外部命令的标准输出写入条目备注（需等待命令结束）
+    #[serde(default)]
+    pub capture_command_output: bool,
+    /// LaTeX 阶段完成后自动复制到剪贴板并弹出通知
+    #[serde(default)]
+    pub auto_copy_latex: bool,
+    /// 快捷键截图识别完成后自动粘贴到先前的前台窗口
+    #[serde(default)]
+    pub auto_paste: bool,
+    /// 保存前按感知哈希检测重复截图
+    #[serde(default = "default_true")]
+    pub duplicate_detection: bool,
+    /// 视为重复的最大汉明距离（0-64）
+    #[serde(default = "default_duplicate_threshold")]
+    pub duplicate_threshold: u32,
+    /// 按图片哈希复用先前的识别结果
+    #[serde(default = "default_true")]
+    pub result_cache_enabled: bool,
+    /// 历史图片的存储格式与压缩级别
+    #[serde(default)]
+    pub image_storage: ImageStorageConfig,
+    /// LaTeX 阶段后额外请求逐符号的位置框（多一次 API 调用，视模型能力而定）
+    #[serde(default)]
+    pub symbol_boxes: bool,
+    /// 检查更新时使用的发布渠道
+    #[serde(default)]
+    pub update_channel: UpdateChannel,
+    /// 记录本地使用统计（仅保存在本机，不联网）
+    #[serde(default)]
+    pub usage_stats_enabled: bool,
+    /// 全屏识别使用的显示器序号；为空时使用光标所在的显示器
+    #[serde(default)]
+    pub full_screen_display: Option<usize>,
+    /// 全屏识别时拼接所有显示器为一张图片
+    #[serde(default)]
+    pub full_screen_all_displays: bool,
+    /// 访问模型服务使用的代理
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    /// 请求模型按 JSON 模式输出（Gemini responseSchema）；不支持该参数的代理可关闭
+    #[serde(default = "default_true")]
+    pub structured_output: bool,
+    /// 各阶段的采样参数
+    #[serde(default)]
+    pub generation: GenerationSettings,
+    /// 同时进行的模型请求上限（所有识别共享，0 表示不限）
+    #[serde(default = "default_max_concurrent_requests")]
+    pub max_concurrent_requests: u32,
+    /// 相邻两次请求发出的最小间隔（毫秒），用于遵守按分钟计的配额
+    #[serde(default)]
+    pub min_request_interval_ms: u64,
+    /// 将每次模型请求与响应写入本地审计日志（密钥脱敏，图片只记录哈希与大小）
+    #[serde(default)]
+    pub llm_audit_log: bool,
+    /// 相同请求（图片、提示词、模型均相同）复用缓存的模型响应，不再调用 API
+    #[serde(default)]
+    pub llm_cache_enabled: bool,
+    /// 响应缓存的有效期（小时）
+    #[serde(default = "default_llm_cache_ttl_hours")]
+    pub llm_cache_ttl_hours: u64,
+    /// 图片超过该大小（KB）时经 Gemini Files API 上传，请求中只引用文件；0 表示始终内联
+    #[serde(default = "default_files_api_threshold_kb")]
+    pub files_api_threshold_kb: u64,
+    /// 经企业 LLM 网关访问时附加的请求头与请求/响应改写规则（只作用于主服务）
+    #[serde(default)]
+    pub gateway: GatewayConfig,
+    /// LaTeX 阶段同时请求多个模型并取多数一致的结果
+    #[serde(default)]
+    pub ensemble: EnsembleConfig,
+    /// Gemini 安全过滤阈值（如 HARM_CATEGORY_DANGEROUS_CONTENT → BLOCK_ONLY_HIGH），为空时使用服务端默认值
+    #[serde(default)]
+    pub safety_settings: Vec<SafetySetting>,
+    /// poppler 命令行工具（pdftoppm、pdfinfo）所在目录，用于导入 PDF；为空时从 PATH 中查找
+    #[serde(default)]
+    pub poppler_path: String,
+    /// 手写模式发给模型前的图片预处理
+    #[serde(default)]
+    pub handwriting: HandwritingConfig,
+    /// 化学模式（mhchem 输出、SMILES）
+    #[serde(default)]
+    pub chemistry: ChemistryConfig,
+}
+
+/// Per-stage prompt overrides. An empty string falls back to the saved stage prompt.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomPrompts {
+    #[serde(default)]
+    pub latex: String,
+    #[serde(default)]
+    pub analysis: String,
+    #[serde(default)]
+    pub verification: String,
+}
+
+impl CustomPrompts {
+    pub fn is_empty(&self) -> bool {
+        self.latex.trim().is_empty() && self.analysis.trim().is_empty() && self.verification.trim().is_empty()
+    }
+}
+
+/// Sampling parameters of one pipeline stage. Unset values are not sent, so the model's
+/// defaults apply; an unset maxOutputTokens falls back to the global maxOutputTokens.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StageGeneration {
+    pub temperature: f32,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default)]
+    pub top_k: Option<u32>,
+    #[serde(default)]
+    pub max_output_tokens: Option<u32>,
+}
+
+impl StageGeneration {
+    fn with_temperature(temperature: f32) -> Self {
+        Self { temperature, top_p: None, top_k: None, max_output_tokens: None }
+    }
+}
+
+/// Per-stage sampling parameters for the LaTeX, analysis and verification requests
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationSettings {
+    #[serde(default = "default_latex_generation")]
+    pub latex: StageGeneration,
+    #[serde(default = "default_analysis_generation")]
+    pub analysis: StageGeneration,
+    #[serde(default = "default_verification_generation")]
+    pub verification: StageGeneration,
+}
+
+impl Default for GenerationSettings {
+    fn default() -> Self {
+        Self {
+            latex: default_latex_generation(),
+            analysis: default_analysis_generation(),
+            verification: default_verification_generation(),
+        }
+    }
+}
+
+impl GenerationSettings {
+    pub fn for_stage(&self, stage: crate::prompts::PromptType) -> &StageGeneration {
+        use crate::prompts::PromptType;
+        match stage {
+            PromptType::LaTeX => &self.latex,
+            PromptType::Analysis => &self.analysis,
+            PromptType::Verification => &self.verification,
+        }
+    }
+}
+
+/// Global shortcut bindings. An empty string leaves the action unbound.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutsConfig {
     #[serde(default = "default_screenshot_shortcut")]
-    pub screenshot_shortcut: String,
+    pub capture_region: String,
+    #[serde(default)]
+    pub capture_full_screen: String,
+    #[serde(default)]
+    pub recognize_clipboard: String,
+    #[serde(default)]
+    pub toggle_window: String,
+    #[serde(default)]
+    pub repeat_last_capture: String,
+    /// 静默快速截图：仅提取 LaTeX 并复制到剪贴板，不显示主窗口
+    #[serde(default)]
+    pub quick_capture: String,
+    /// 复制前台应用中选中的文本并转换为 LaTeX
+    #[serde(default)]
+    pub recognize_selection: String,
+    /// 把最近一条识别结果的 LaTeX 重新复制到剪贴板
+    #[serde(default)]
+    pub recall_last_result: String,
+}
+
+impl Default for ShortcutsConfig {
+    fn default() -> Self {
+        Self {
+            capture_region: default_screenshot_shortcut(),
+            capture_full_screen: String::new(),
+            recognize_clipboard: String::new(),
+            toggle_window: String::new(),
+            repeat_last_capture: String::new(),
+            quick_capture: String::new(),
+            recognize_selection: String::new(),
+            recall_last_result: String::new(),
+        }
+    }
+}
+
+/// History image storage. PNG at default compression keeps the captured bytes untouched.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageStorageConfig {
+    #[serde(default)]
+    pub format: ImageStorageFormat,
+    /// Only used for PNG
+    #[serde(default)]
+    pub png_compression: PngCompression,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageStorageFormat {
+    #[default]
+    Png,
+    /// Lossless WebP (typically much smaller than PNG for screenshots)
+    Webp,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    /// Also offers GitHub pre-releases
+    Beta,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyRotation {
+    /// Uses the next key for every request
+    RoundRobin,
+    /// Stays on one key and moves on when it is rate limited (429)
+    #[default]
+    OnRateLimit,
+}
+
+/// Config areas that can be reset to their defaults on their own
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigSection {
+    /// Stage prompts, per-stage overrides and prompts version
+    Prompts,
+    /// Endpoint, provider, model, request limits and generation parameters (API keys are kept)
+    Models,
+    /// Window size, position and whether it is remembered
+    Window,
+    Shortcuts,
+}
+
+/// Secondary (Gemini-compatible) backend used while the primary one is failing
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FailoverConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub api_key: String,
+    /// e.g. a second proxy or region; empty disables failover
+    #[serde(default)]
+    pub api_base_url: String,
+    /// 备用服务使用的模型，留空时沿用主模型
+    #[serde(default)]
+    pub model_name: String,
+    /// 备用服务的协议（gemini 或 openai），留空时与主服务相同
+    #[serde(default)]
+    pub provider: String,
+}
+
+/// HTTP(S) or SOCKS5 proxy for requests to the model API
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// e.g. http://proxy.example.edu:8080 or socks5://127.0.0.1:1080
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    /// 不经过代理的主机（域名、IP 或 CIDR，支持 .example.com 匹配子域名）
+    #[serde(default = "default_proxy_bypass")]
+    pub bypass: Vec<String>,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            username: String::new(),
+            password: String::new(),
+            bypass: default_proxy_bypass(),
+        }
+    }
+}
+
+impl ProxyConfig {
+    /// Whether requests should go through the proxy
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.url.trim().is_empty()
+    }
+}
+
+/// Models that extract LaTeX alongside the default model in ensemble mode
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EnsembleConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 默认模型之外的模型（同一服务商，最多两个）
+    #[serde(default)]
+    pub models: Vec<String>,
+}
+
+/// Which prompt set a recognition uses
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RecognitionMode {
+    #[default]
+    Math,
+    /// Chemical formulas and reactions, transcribed with mhchem's \ce{...}
+    Chemistry,
+}
+
+/// Options of the chemistry recognition mode
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChemistryConfig {
+    /// LaTeX 阶段后额外请求各物质的 SMILES
+    #[serde(default = "default_true")]
+    pub smiles: bool,
+}
+
+impl Default for ChemistryConfig {
+    fn default() -> Self {
+        Self { smiles: true }
+    }
+}
+
+/// Image preprocessing applied in handwriting mode before the image is sent to the model
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HandwritingConfig {
+    /// 按 Otsu 阈值二值化，去掉纸张底色、阴影和浅色格线
+    #[serde(default = "default_true")]
+    pub binarize: bool,
+    /// 笔画加粗一个像素（铅笔、细笔迹）
+    #[serde(default)]
+    pub thicken: bool,
+}
+
+impl Default for HandwritingConfig {
+    fn default() -> Self {
+        Self { binarize: true, thicken: false }
+    }
+}
+
+/// Extra headers and body rewrites for LLM gateways that wrap the provider API
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayConfig {
+    /// 每个模型请求附加的请求头；值中的 {apiKey} 替换为当前使用的密钥
+    #[serde(default)]
+    pub headers: std::collections::BTreeMap<String, String>,
+    /// 包装请求体的 JSON 模板：值为 "{{body}}" 的字符串替换为原请求体，"{{model}}" 替换为模型名；为空时不包装
+    #[serde(default)]
+    pub request_envelope: String,
+    /// 服务商响应在网关响应中的位置（JSON Pointer，如 /data/response）；为空时不解包
+    #[serde(default)]
+    pub response_path: String,
+}
+
+/// One Gemini safety filter threshold, sent as-is in the request's safetySettings
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SafetySetting {
+    /// 如 HARM_CATEGORY_HARASSMENT、HARM_CATEGORY_DANGEROUS_CONTENT
+    pub category: String,
+    /// 如 BLOCK_NONE、BLOCK_ONLY_HIGH、BLOCK_MEDIUM_AND_ABOVE
+    pub threshold: String,
+}
+
+/// Optional localhost REST API for editor/script integrations
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalApiConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_local_api_port")]
+    pub port: u16,
+    /// Bearer token required on every request; generated on first start when empty
+    #[serde(default)]
+    pub token: String,
+    /// WebSocket 事件流（与 HTTP API 共用 token）
+    #[serde(default)]
+    pub websocket_enabled: bool,
+    #[serde(default = "default_websocket_port")]
+    pub websocket_port: u16,
+}
+
+impl Default for LocalApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_local_api_port(),
+            token: String::new(),
+            websocket_enabled: false,
+            websocket_port: default_websocket_port(),
+        }
+    }
+}
+
+/// S3-compatible backup target (AWS S3, MinIO, Cloudflare R2, Backblaze B2 ...)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// e.g. https://s3.us-east-1.amazonaws.com or http://127.0.0.1:9000
+    #[serde(default)]
+    pub endpoint: String,
+    #[serde(default = "default_backup_region")]
+    pub region: String,
+    #[serde(default)]
+    pub bucket: String,
+    #[serde(default)]
+    pub access_key_id: String,
+    #[serde(default)]
+    pub secret_access_key: String,
+    /// 对象键前缀，所有快照与图片均存放在该前缀下
+    #[serde(default = "default_backup_prefix")]
+    pub prefix: String,
+    /// 使用 path-style 访问（MinIO 等自建服务通常需要）
+    #[serde(default = "default_true")]
+    pub path_style: bool,
+    /// 自动备份间隔（小时），0 表示仅手动备份
+    #[serde(default = "default_backup_interval_hours")]
+    pub interval_hours: u32,
+    /// 保留最近 N 份快照，0 表示不限
+    #[serde(default = "default_backup_retention_count")]
+    pub retention_count: u32,
+    /// 删除早于 N 天的快照，0 表示不限
+    #[serde(default)]
+    pub retention_days: u32,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: String::new(),
+            region: default_backup_region(),
+            bucket: String::new(),
+            access_key_id: String::new(),
+            secret_access_key: String::new(),
+            prefix: default_backup_prefix(),
+            path_style: true,
+            interval_hours: default_backup_interval_hours(),
+            retention_count: default_backup_retention_count(),
+            retention_days: 0,
+        }
+    }
+}
+
+impl BackupConfig {
+    /// Whether enough fields are filled in to talk to the bucket
+    pub fn is_configured(&self) -> bool {
+        !self.endpoint.trim().is_empty()
+            && !self.bucket.trim().is_empty()
+            && !self.access_key_id.trim().is_empty()
+            && !self.secret_access_key.trim().is_empty()
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             api_key: "".to_string(),
+            additional_api_keys: Vec::new(),
+            key_rotation: KeyRotation::default(),
+            failover: FailoverConfig::default(),
             api_base_url: "https://generativelanguage.googleapis.com/v1beta/models".to_string(),
             provider: "gemini".to_string(),
             default_engine: "gemini-2.5-flash".to_string(),
-            custom_prompt: String::new(),
+            custom_prompts: CustomPrompts::default(),
             latex_prompt: default_latex_prompt(),
             analysis_prompt: default_analysis_prompt(),
             verification_prompt: default_verification_prompt(),
@@ -83,13 +613,46 @@ impl Default for Config {
             max_retries: 2,
             max_output_tokens: default_max_output_tokens(),
             language: default_language(),
+            auto_detect_language: false,
             window_width: default_window_width(),
             window_height: default_window_height(),
             window_x: None,
             window_y: None,
             remember_window_state: default_remember_window_state(),
             prompts_version: current_prompts_version(),
-            screenshot_shortcut: default_screenshot_shortcut(),
+            legacy_screenshot_shortcut: None,
+            shortcuts: ShortcutsConfig::default(),
+            backup: BackupConfig::default(),
+            local_api: LocalApiConfig::default(),
+            plugins_enabled: false,
+            post_recognition_command: String::new(),
+            capture_command_output: false,
+            auto_copy_latex: false,
+            auto_paste: false,
+            duplicate_detection: true,
+            duplicate_threshold: default_duplicate_threshold(),
+            result_cache_enabled: true,
+            image_storage: ImageStorageConfig::default(),
+            symbol_boxes: false,
+            update_channel: UpdateChannel::default(),
+            usage_stats_enabled: false,
+            full_screen_display: None,
+            full_screen_all_displays: false,
+            proxy: ProxyConfig::default(),
+            structured_output: true,
+            generation: GenerationSettings::default(),
+            max_concurrent_requests: default_max_concurrent_requests(),
+            min_request_interval_ms: 0,
+            llm_audit_log: false,
+            llm_cache_enabled: false,
+            llm_cache_ttl_hours: default_llm_cache_ttl_hours(),
+            files_api_threshold_kb: default_files_api_threshold_kb(),
+            gateway: GatewayConfig::default(),
+            ensemble: EnsembleConfig::default(),
+            safety_settings: Vec::new(),
+            poppler_path: String::new(),
+            handwriting: HandwritingConfig::default(),
+            chemistry: ChemistryConfig::default(),
         }
     }
 }
@@ -98,15 +661,127 @@ impl Config {
     /// Convert Config to LlmConfig for the LLM client
     pub fn to_llm_config(&self) -> crate::llm_api::LlmConfig {
         crate::llm_api::LlmConfig {
-            api_key: self.api_key.clone(),
+            provider: crate::llm_api::Provider::from_name(&self.provider),
+            api_keys: self.api_keys(),
+            key_rotation: self.key_rotation,
+            failover: self.failover_llm_config().map(Box::new),
             api_base_url: self.api_base_url.clone(),
             model_name: self.default_engine.clone(),
             request_timeout_seconds: self.request_timeout_seconds,
             max_retries: self.max_retries,
             max_output_tokens: self.max_output_tokens,
+            proxy: self.proxy.clone(),
+            structured_output: self.structured_output,
+            generation: self.generation.clone(),
+            max_concurrent_requests: self.max_concurrent_requests,
+            min_request_interval_ms: self.min_request_interval_ms,
+            audit_log: self.llm_audit_log,
+            cache_ttl: self.llm_cache_ttl(),
+            files_api_threshold_kb: self.files_api_threshold_kb,
+            gateway: self.gateway.clone(),
+            safety_settings: self.safety_settings.clone(),
         }
     }
 
+    /// How long cached model responses stay valid, or None when the cache is off
+    fn llm_cache_ttl(&self) -> Option<std::time::Duration> {
+        (self.llm_cache_enabled && self.llm_cache_ttl_hours > 0).then(|| std::time::Duration::from_secs(self.llm_cache_ttl_hours * 3600))
+    }
+
+    /// LLM settings of the failover backend, when one is enabled
+    fn failover_llm_config(&self) -> Option<crate::llm_api::LlmConfig> {
+        let failover = &self.failover;
+        if !failover.enabled || failover.api_base_url.trim().is_empty() {
+            return None;
+        }
+        let key = failover.api_key.trim();
+        let provider = if failover.provider.trim().is_empty() { &self.provider } else { &failover.provider };
+        Some(crate::llm_api::LlmConfig {
+            provider: crate::llm_api::Provider::from_name(provider),
+            api_keys: if key.is_empty() { Vec::new() } else { vec![key.to_string()] },
+            key_rotation: KeyRotation::default(),
+            failover: None,
+            api_base_url: failover.api_base_url.trim().to_string(),
+            model_name: if failover.model_name.trim().is_empty() {
+                self.default_engine.clone()
+            } else {
+                failover.model_name.trim().to_string()
+            },
+            request_timeout_seconds: self.request_timeout_seconds,
+            max_retries: self.max_retries,
+            max_output_tokens: self.max_output_tokens,
+            proxy: self.proxy.clone(),
+            structured_output: self.structured_output,
+            generation: self.generation.clone(),
+            max_concurrent_requests: self.max_concurrent_requests,
+            min_request_interval_ms: self.min_request_interval_ms,
+            audit_log: self.llm_audit_log,
+            cache_ttl: self.llm_cache_ttl(),
+            files_api_threshold_kb: self.files_api_threshold_kb,
+            // 网关规则针对主服务的地址，备用服务直接访问
+            gateway: GatewayConfig::default(),
+            safety_settings: self.safety_settings.clone(),
+        })
+    }
+
+    /// Resets one area of the config to its defaults, leaving everything else untouched
+    pub fn reset_section(&mut self, section: ConfigSection) {
+        let defaults = Config::default();
+        match section {
+            ConfigSection::Prompts => {
+                self.custom_prompts = defaults.custom_prompts;
+                self.latex_prompt = defaults.latex_prompt;
+                self.analysis_prompt = defaults.analysis_prompt;
+                self.verification_prompt = defaults.verification_prompt;
+                self.prompts_version = defaults.prompts_version;
+            }
+            ConfigSection::Models => {
+                self.api_base_url = defaults.api_base_url;
+                self.provider = defaults.provider;
+                self.default_engine = defaults.default_engine;
+                self.request_timeout_seconds = defaults.request_timeout_seconds;
+                self.max_retries = defaults.max_retries;
+                self.max_output_tokens = defaults.max_output_tokens;
+                self.structured_output = defaults.structured_output;
+                self.generation = defaults.generation;
+                self.max_concurrent_requests = defaults.max_concurrent_requests;
+                self.min_request_interval_ms = defaults.min_request_interval_ms;
+            }
+            ConfigSection::Window => {
+                self.window_width = defaults.window_width;
+                self.window_height = defaults.window_height;
+                self.window_x = defaults.window_x;
+                self.window_y = defaults.window_y;
+                self.remember_window_state = defaults.remember_window_state;
+            }
+            ConfigSection::Shortcuts => self.shortcuts = defaults.shortcuts,
+        }
+    }
+
+    /// All configured API keys, primary first, without blanks or duplicates
+    pub fn api_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::new();
+        for key in std::iter::once(&self.api_key).chain(&self.additional_api_keys) {
+            let key = key.trim();
+            if !key.is_empty() && !keys.iter().any(|k| k == key) {
+                keys.push(key.to_string());
+            }
+        }
+        keys
+    }
+
+    /// Base prompt for a stage, without format/language constraints.
+    /// Precedence: per-stage custom override (a preset writes here) > saved stage prompt.
+    pub fn stage_prompt(&self, stage: crate::prompts::PromptType) -> &str {
+        use crate::prompts::PromptType;
+        let (custom, saved) = match stage {
+            PromptType::LaTeX => (&self.custom_prompts.latex, &self.latex_prompt),
+            PromptType::Analysis => (&self.custom_prompts.analysis, &self.analysis_prompt),
+            PromptType::Verification => (&self.custom_prompts.verification, &self.verification_prompt),
+        };
+        if custom.trim().is_empty() { saved } else { custom }
+    }
+
     /// Returns the current default prompts tuple (latex, analysis, verification)
     pub fn default_prompts_tuple() -> (String, String, String) {
         (default_latex_prompt(), default_analysis_prompt(), default_verification_prompt())
@@ -134,6 +809,20 @@ impl Config {
 
         changed
     }
+
+    /// Moves the legacy `screenshotShortcut` into `shortcuts.captureRegion`
+    /// Returns true if the config was changed
+    pub fn migrate_shortcuts(&mut self) -> bool {
+        match self.legacy_screenshot_shortcut.take() {
+            Some(old) => {
+                if !old.trim().is_empty() {
+                    self.shortcuts.capture_region = old;
+                }
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -154,6 +843,226 @@ pub struct HistoryItem {
     /// 核查报告，描述LaTeX与原图像的对比结果
     #[serde(default)]
     pub verification_report: Option<String>,
+    /// 用户备注（也可由识别后外部命令的输出填充）
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// 生成该结果时实际发送的完整提示词，便于排查提示词回归
+    #[serde(default)]
+    pub prompts_used: Option<PromptsUsed>,
+    /// 截图的感知哈希（dHash，16 位十六进制），用于重复检测
+    #[serde(default)]
+    pub perceptual_hash: Option<String>,
+    /// 疑似重复的已有条目 id（等待用户合并或忽略）
+    #[serde(default)]
+    pub duplicate_of: Option<String>,
+    /// 逐符号位置框（仅在开启 symbolBoxes 且模型返回时存在）
+    #[serde(default)]
+    pub symbol_boxes: Option<Vec<SymbolBox>>,
+    /// LaTeX 的历史版本（应用修正前的内容），最新的在最后
+    #[serde(default)]
+    pub latex_revisions: Option<Vec<LatexRevision>>,
+    /// 间隔重复复习状态（首次复习后才存在）
+    #[serde(default)]
+    pub srs: Option<SrsState>,
+    /// 供屏幕阅读器/TTS 朗读的自然语言描述
+    #[serde(default)]
+    pub spoken_description: Option<String>,
+    /// 公式出处（文献标题、页码、URL、DOI）
+    #[serde(default)]
+    pub source: Option<SourceInfo>,
+    /// 各识别阶段耗时，便于发现变慢的模型或性能回退
+    #[serde(default)]
+    pub stage_timings: Option<StageTimings>,
+    /// 产生结果的服务：primary 或 failover（主服务熔断期间）
+    #[serde(default)]
+    pub backend: Option<String>,
+    /// 用户标签
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    /// 截图来源显示器的缩放与尺寸（仅屏幕截图）
+    #[serde(default)]
+    pub capture_scale: Option<CaptureScale>,
+    /// 用其他模型/预设重新识别时，原条目的 id（便于并排对比）
+    #[serde(default)]
+    pub rerun_of: Option<String>,
+    /// 识别过程中各次请求消耗的 Token 合计（服务商返回用量时才有）
+    #[serde(default)]
+    pub token_usage: Option<TokenUsage>,
+    /// 识别模式：数学公式或化学式/反应式（mhchem）
+    #[serde(default)]
+    pub mode: RecognitionMode,
+    /// 化学模式下各物质的 SMILES（开启 chemistry.smiles 且模型返回时存在）
+    #[serde(default)]
+    pub smiles: Option<Vec<String>>,
+}
+
+/// Partial update of a history item; absent fields are left unchanged
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct HistoryItemPatch {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default, alias = "isFavorite", alias = "favorite")]
+    pub is_favorite: Option<bool>,
+    /// 替换全部标签（空列表清除）
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    /// 空字符串清除备注
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// 修改前的 LaTeX 会记入 latex_revisions
+    #[serde(default)]
+    pub latex: Option<String>,
+}
+
+/// Trims tags and drops blanks and duplicates, keeping the first spelling
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SourceInfo {
+    #[serde(default)]
+    pub document_title: Option<String>,
+    /// 页码，允许 "12"、"xii"、"12-13" 等形式
+    #[serde(default)]
+    pub page: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub doi: Option<String>,
+}
+
+impl SourceInfo {
+    /// Trims fields, drops empty ones and reduces DOI links to the bare DOI; None if nothing is left
+    pub fn normalized(self) -> Option<SourceInfo> {
+        let clean = |v: Option<String>| v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        let doi = clean(self.doi).map(|d| {
+            let lower = d.to_ascii_lowercase();
+            let prefix = ["https://doi.org/", "http://doi.org/", "https://dx.doi.org/", "http://dx.doi.org/", "doi:"]
+                .iter()
+                .find(|p| lower.starts_with(*p))
+                .map_or(0, |p| p.len());
+            d[prefix..].trim().to_string()
+        });
+        let source = SourceInfo { document_title: clean(self.document_title), page: clean(self.page), url: clean(self.url), doi };
+        if source == SourceInfo::default() { None } else { Some(source) }
+    }
+
+    /// One-line citation, e.g. `Attention Is All You Need, p. 4, doi:10.48550/arXiv.1706.03762`
+    pub fn citation(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(title) = &self.document_title {
+            parts.push(title.clone());
+        }
+        if let Some(page) = &self.page {
+            parts.push(format!("p. {}", page));
+        }
+        if let Some(doi) = &self.doi {
+            parts.push(format!("doi:{}", doi));
+        } else if let Some(url) = &self.url {
+            parts.push(url.clone());
+        }
+        parts.join(", ")
+    }
+}
+
+/// 各识别阶段耗时（毫秒）。分析与 LaTeX 同时开始，核查在 LaTeX 完成后开始，因此 total 小于各阶段之和
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StageTimings {
+    #[serde(default)]
+    pub latex_ms: Option<u64>,
+    #[serde(default)]
+    pub analysis_ms: Option<u64>,
+    #[serde(default)]
+    pub verification_ms: Option<u64>,
+    /// 从开始识别到结果生成的总耗时
+    #[serde(default)]
+    pub total_ms: Option<u64>,
+}
+
+/// Token 用量，按服务商返回的计数累加；思考模型的思考 Token 按输出计费，计入 candidate_tokens
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub candidate_tokens: u64,
+    /// 返回了用量的请求数
+    #[serde(default)]
+    pub requests: u32,
+}
+
+impl TokenUsage {
+    pub fn add(&mut self, other: TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.candidate_tokens += other.candidate_tokens;
+        self.requests += other.requests;
+    }
+
+    /// Estimated price in USD at the given input/output prices per million tokens
+    pub fn cost_usd(&self, (input_price, output_price): (f64, f64)) -> f64 {
+        (self.prompt_tokens as f64 * input_price + self.candidate_tokens as f64 * output_price) / 1_000_000.0
+    }
+}
+
+/// 截图的缩放信息，供重新裁剪、重新截图和位置框叠加在 HiDPI 屏幕上换算坐标。
+/// 物理尺寸即保存的图片尺寸，逻辑尺寸 = 物理尺寸 / scale_factor
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CaptureScale {
+    pub scale_factor: f64,
+    pub physical_width: u32,
+    pub physical_height: u32,
+    pub logical_width: u32,
+    pub logical_height: u32,
+    /// 截图区域左上角（逻辑像素，相对来源显示器；拼接所有显示器时相对虚拟桌面）
+    #[serde(default)]
+    pub logical_x: i32,
+    #[serde(default)]
+    pub logical_y: i32,
+    /// 来源显示器序号；拼接所有显示器时为空
+    #[serde(default)]
+    pub display_index: Option<usize>,
+}
+
+/// SM-2 间隔重复状态
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SrsState {
+    /// 下次复习时间（RFC 3339）
+    pub due: String,
+    pub interval_days: u32,
+    /// 难度系数，最低 1.3
+    pub ease: f32,
+    /// 连续答对次数
+    pub repetitions: u32,
+    #[serde(default)]
+    pub lapses: u32,
+    pub last_reviewed: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LatexRevision {
+    pub latex: String,
+    pub created_at: String,
+    /// 产生下一版本的原因，如 "fix: 缺少指数 2"
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PromptsUsed {
+    pub prompts_version: u32,
+    #[serde(default)]
+    pub latex: String,
+    #[serde(default)]
+    pub analysis: String,
+    #[serde(default)]
+    pub verification: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -171,6 +1080,9 @@ pub struct Suggestion {
     #[serde(rename = "type")]
     pub suggestion_type: String,
     pub message: String,
+    /// 本地规则检查产生的建议所对应的规则名（模型给出的建议为空）
+    #[serde(default)]
+    pub rule: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -191,6 +1103,85 @@ pub struct TermInfo {
 pub struct VerificationIssue {
     pub category: String, // missing_term | extra_term | symbol_mismatch | notation_mismatch | layout_mismatch | other
     pub message: String,
+    /// 问题在原图中的大致位置（无法定位时为空）
+    #[serde(default)]
+    pub region: Option<ImageRegion>,
+    /// 问题所指的 LaTeX 片段（模型原样摘录）
+    #[serde(default)]
+    pub latex_fragment: Option<String>,
+    /// 片段在 LaTeX 中的位置，由后端根据 latex_fragment 计算
+    #[serde(default)]
+    pub latex_span: Option<LatexSpan>,
+    /// 模型给出的具体修正（替换 latex_fragment 的 LaTeX），可通过 apply_fix 应用
+    #[serde(default)]
+    pub suggested_fix: Option<String>,
+}
+
+/// LaTeX 源码中的区间 [start, end)，以 UTF-16 码元计（与前端文本框的选区一致）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct LatexSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LatexSpan {
+    /// Finds `fragment` in `latex`, first verbatim, then ignoring whitespace differences
+    pub fn locate(latex: &str, fragment: &str) -> Option<LatexSpan> {
+        let fragment = fragment.trim();
+        if fragment.is_empty() {
+            return None;
+        }
+        let (start, end) = match latex.find(fragment) {
+            Some(start) => (start, start + fragment.len()),
+            None => {
+                // 去掉空白后匹配，再映射回原文的字节位置
+                let compact: Vec<(usize, char)> = latex.char_indices().filter(|(_, c)| !c.is_whitespace()).collect();
+                let haystack: String = compact.iter().map(|(_, c)| *c).collect();
+                let needle: String = fragment.chars().filter(|c| !c.is_whitespace()).collect();
+                let byte_pos = haystack.find(&needle)?;
+                let first = haystack[..byte_pos].chars().count();
+                let last = first + needle.chars().count() - 1;
+                let (end_byte, end_char) = compact[last];
+                (compact[first].0, end_byte + end_char.len_utf8())
+            }
+        };
+        let utf16 = |byte: usize| latex[..byte].encode_utf16().count();
+        Some(LatexSpan { start: utf16(start), end: utf16(end) })
+    }
+}
+
+/// 公式中的单个符号/记号及其在原图中的位置
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SymbolBox {
+    /// LaTeX 记号，如 "\\alpha"、"x"、"^{2}"
+    pub token: String,
+    pub region: ImageRegion,
+}
+
+/// 相对原图尺寸的矩形区域，坐标与宽高均在 0~1 之间，原点为左上角
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct ImageRegion {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ImageRegion {
+    /// Clamps the box into the image; returns None for degenerate or non-finite boxes
+    pub fn normalized(self) -> Option<ImageRegion> {
+        if ![self.x, self.y, self.width, self.height].iter().all(|v| v.is_finite()) {
+            return None;
+        }
+        let x = self.x.clamp(0.0, 1.0);
+        let y = self.y.clamp(0.0, 1.0);
+        let width = self.width.min(1.0 - x);
+        let height = self.height.min(1.0 - y);
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        Some(ImageRegion { x, y, width, height })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -203,7 +1194,7 @@ pub struct VerificationCoverage {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Verification {
-    pub status: String, // error | warning | ok
+    pub status: String, // error | warning | ok | skipped（用户跳过核查）
     #[serde(default)]
     pub issues: Vec<VerificationIssue>,
     #[serde(default)]
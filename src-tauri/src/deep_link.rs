@@ -0,0 +1,304 @@
+// aiformula:// 协议处理
+//   aiformula://recognize?path=<图片路径>   识别指定图片
+//   aiformula://open?id=<历史条目 ID>        在主窗口中打开指定历史条目
+//
+// 系统以命令行参数的形式把 URL 交给新进程；若应用已在运行，新进程通过本地回环端口
+// 把 URL 转发给已有实例后立即退出。转发需附带运行中实例写入数据目录的随机令牌，
+// 其他本地进程无法借此让应用读取任意文件；识别图片前还需用户在对话框中确认，且只接受图片扩展名。
+// 协议注册目前支持 Windows（注册表）与 Linux（.desktop），启动时仅在未注册或程序路径变化时写入。
+
+use crate::{fs_manager, local_api};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+pub const URL_SCHEME: &str = "aiformula";
+const FORWARD_PORT: u16 = 17320;
+const FORWARD_TOKEN_FILENAME: &str = "deep_link.token";
+/// 与 tauri.conf.json 中的 identifier 一致；转发时应用尚未启动，只能自行推算数据目录
+const APP_IDENTIFIER: &str = "com.ai-formula-scanner.app";
+/// 转发连接必须在该时间内发来一整行，避免一个不发送数据的连接阻塞后续转发
+const FORWARD_READ_TIMEOUT: Duration = Duration::from_secs(2);
+/// recognize 链接可以指定的图片类型
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "webp", "bmp", "gif", "tiff"];
+
+static PENDING: OnceLock<Mutex<Option<DeepLinkAction>>> = OnceLock::new();
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum DeepLinkAction {
+    Recognize { path: String },
+    Open { id: String },
+}
+
+fn pending_slot() -> &'static Mutex<Option<DeepLinkAction>> {
+    PENDING.get_or_init(|| Mutex::new(None))
+}
+
+/// Parses an `aiformula://` URL into an action
+pub fn parse(url: &str) -> Result<DeepLinkAction, String> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|e| format!("Invalid deep link '{}': {}", url, e))?;
+    if parsed.scheme() != URL_SCHEME {
+        return Err(format!("Unsupported scheme '{}'", parsed.scheme()));
+    }
+    let param = |name: &str| {
+        parsed
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.to_string())
+            .filter(|v| !v.is_empty())
+    };
+    // 兼容 aiformula://recognize?… 与 aiformula:recognize?… 两种写法
+    let action = parsed
+        .host_str()
+        .map(|h| h.to_string())
+        .unwrap_or_else(|| parsed.path().trim_matches('/').to_string());
+    match action.as_str() {
+        "recognize" => param("path")
+            .map(|path| DeepLinkAction::Recognize { path })
+            .ok_or_else(|| "Missing 'path' parameter".to_string()),
+        "open" => param("id")
+            .map(|id| DeepLinkAction::Open { id })
+            .ok_or_else(|| "Missing 'id' parameter".to_string()),
+        other => Err(format!("Unknown deep link action '{}'", other)),
+    }
+}
+
+/// Returns the first `aiformula://` URL passed on the command line
+pub fn url_from_args() -> Option<String> {
+    std::env::args()
+        .skip(1)
+        .find(|a| a.starts_with(&format!("{}:", URL_SCHEME)))
+}
+
+/// Token file location, resolved without an AppHandle (before the app is built)
+fn forward_token_path() -> Option<PathBuf> {
+    fs_manager::data_dir_override()
+        .map(Path::to_path_buf)
+        .or_else(|| dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER)))
+        .map(|dir| dir.join(FORWARD_TOKEN_FILENAME))
+}
+
+/// Sends the URL to an already running instance. Returns true if it was delivered.
+pub fn forward_to_running_instance(url: &str) -> bool {
+    let Some(token) = forward_token_path().and_then(|path| std::fs::read_to_string(path).ok()) else {
+        return false;
+    };
+    let addr = SocketAddr::from(([127, 0, 0, 1], FORWARD_PORT));
+    match TcpStream::connect_timeout(&addr, Duration::from_millis(300)) {
+        Ok(mut stream) => stream.write_all(format!("{} {}\n", token.trim(), url).as_bytes()).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// True when `path` names a file with an image extension
+fn is_image_path(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Executes a deep link inside the running app
+pub fn handle(app_handle: &AppHandle, url: &str) {
+    let action = match parse(url) {
+        Ok(DeepLinkAction::Recognize { path }) if !is_image_path(&path) => Err(format!("Not an image file: {}", path)),
+        other => other,
+    };
+    let action = match action {
+        Ok(a) => a,
+        Err(_e) => {
+            #[cfg(debug_assertions)]
+            eprintln!("[DeepLink] {}", _e);
+            return;
+        }
+    };
+
+    let window = app_handle.get_window("main");
+    if let Some(win) = &window {
+        let _ = win.unminimize();
+        let _ = win.show();
+        let _ = win.set_focus();
+        let _ = win.emit("deep-link", action.clone());
+    }
+    // 前端可能尚未挂载（冷启动），保留一份供其主动拉取
+    *pending_slot().lock().unwrap() = Some(action.clone());
+
+    if let DeepLinkAction::Recognize { path } = action {
+        // 链接可能来自任意网页或程序，读取文件并发送给模型前先征得用户同意
+        let language = fs_manager::read_config(app_handle).map(|c| c.language).unwrap_or_default();
+        let (title, message) = if language == "zh-CN" {
+            ("识别图片", format!("是否识别并将以下图片发送给模型？\n\n{}", path))
+        } else {
+            ("Recognize image", format!("Recognize this image and send it to the model?\n\n{}", path))
+        };
+        let app = app_handle.clone();
+        tauri::api::dialog::ask(window.as_ref(), title, message, move |confirmed| {
+            if !confirmed {
+                return;
+            }
+            tauri::async_runtime::spawn(async move {
+                if let Err(_e) = crate::recognize_from_file(app, path, None, None, None, None, None).await {
+                    #[cfg(debug_assertions)]
+                    eprintln!("[DeepLink] Recognition failed: {}", _e);
+                }
+            });
+        });
+    }
+}
+
+/// Listens for URLs forwarded by later instances. Each forwarded line must start with the
+/// token this instance writes to the data dir; anything else is dropped.
+pub fn start_listener(app_handle: AppHandle) {
+    let Ok(listener) = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], FORWARD_PORT))) else {
+        return;
+    };
+    let token = Uuid::new_v4().simple().to_string();
+    let written = fs_manager::get_data_file_path(&app_handle, FORWARD_TOKEN_FILENAME)
+        .and_then(|path| std::fs::write(path, &token).map_err(anyhow::Error::from));
+    if let Err(_e) = written {
+        #[cfg(debug_assertions)]
+        eprintln!("[DeepLink] Failed to write forward token: {}", _e);
+        return;
+    }
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if stream.set_read_timeout(Some(FORWARD_READ_TIMEOUT)).is_err() {
+                continue;
+            }
+            let mut line = String::new();
+            if BufReader::new(stream).read_line(&mut line).is_err() {
+                continue;
+            }
+            match line.trim().split_once(' ') {
+                Some((presented, url)) if local_api::tokens_match(presented, &token) && !url.trim().is_empty() => {
+                    handle(&app_handle, url.trim());
+                }
+                _ => {
+                    #[cfg(debug_assertions)]
+                    eprintln!("[DeepLink] Rejected forwarded link without a valid token");
+                }
+            }
+        }
+    });
+}
+
+/// Registers the URL scheme unless it already points at this executable
+pub fn register_url_scheme_if_needed() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    if is_registered(&exe.to_string_lossy()) {
+        return Ok(());
+    }
+    register_url_scheme()
+}
+
+#[cfg(target_os = "windows")]
+fn is_registered(exe: &str) -> bool {
+    let key = format!(r"HKCU\Software\Classes\{}\shell\open\command", URL_SCHEME);
+    std::process::Command::new("reg")
+        .args(["query", &key, "/ve"])
+        .output()
+        .map(|out| out.status.success() && String::from_utf8_lossy(&out.stdout).contains(&windows_command(exe)))
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+fn windows_command(exe: &str) -> String {
+    format!("\"{}\" \"%1\"", exe)
+}
+
+#[cfg(target_os = "linux")]
+const DESKTOP_NAME: &str = "ai-formula-scanner-handler.desktop";
+
+#[cfg(target_os = "linux")]
+fn desktop_entry(exe: &str) -> String {
+    format!(
+        "[Desktop Entry]\nType=Application\nName=AI Formula Scanner\nExec=\"{}\" %u\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+        exe, URL_SCHEME
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn is_registered(exe: &str) -> bool {
+    let entry_matches = dirs::data_dir()
+        .and_then(|dir| std::fs::read_to_string(dir.join("applications").join(DESKTOP_NAME)).ok())
+        .is_some_and(|existing| existing == desktop_entry(exe));
+    entry_matches
+        && std::process::Command::new("xdg-mime")
+            .args(["query", "default", &format!("x-scheme-handler/{}", URL_SCHEME)])
+            .output()
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim() == DESKTOP_NAME)
+            .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn is_registered(_exe: &str) -> bool {
+    // 不支持注册的平台上无需重复尝试
+    true
+}
+
+/// Registers the URL scheme for the current user (idempotent), overwriting any existing registration
+pub fn register_url_scheme() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let exe = exe.to_string_lossy().to_string();
+
+    #[cfg(target_os = "windows")]
+    {
+        let key = format!(r"HKCU\Software\Classes\{}", URL_SCHEME);
+        let command = windows_command(&exe);
+        let entries: [(String, Option<&str>, String); 3] = [
+            (key.clone(), None, "URL:AI Formula Scanner".to_string()),
+            (key.clone(), Some("URL Protocol"), String::new()),
+            (format!(r"{}\shell\open\command", key), None, command),
+        ];
+        for (path, name, value) in entries.iter() {
+            let mut cmd = std::process::Command::new("reg");
+            cmd.arg("add").arg(path);
+            match name {
+                Some(n) => cmd.arg("/v").arg(n),
+                None => cmd.arg("/ve"),
+            };
+            let status = cmd.arg("/d").arg(value).arg("/f").status().map_err(|e| e.to_string())?;
+            if !status.success() {
+                return Err(format!("Failed to write registry key {}", path));
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let apps_dir = dirs::data_dir()
+            .ok_or_else(|| "Could not resolve data directory".to_string())?
+            .join("applications");
+        std::fs::create_dir_all(&apps_dir).map_err(|e| e.to_string())?;
+        std::fs::write(apps_dir.join(DESKTOP_NAME), desktop_entry(&exe)).map_err(|e| e.to_string())?;
+        let _ = std::process::Command::new("xdg-mime")
+            .args(["default", DESKTOP_NAME, &format!("x-scheme-handler/{}", URL_SCHEME)])
+            .status();
+    }
+
+    if cfg!(any(target_os = "windows", target_os = "linux")) {
+        Ok(())
+    } else {
+        let _ = &exe;
+        Err("URL scheme registration is not supported on this platform".to_string())
+    }
+}
+
+// --- Tauri commands ---
+
+/// Returns (and clears) the deep link received before the frontend was ready
+#[tauri::command]
+pub fn take_pending_deep_link() -> Option<DeepLinkAction> {
+    pending_slot().lock().unwrap().take()
+}
+
+#[tauri::command]
+pub fn register_deep_link_scheme() -> Result<(), String> {
+    register_url_scheme()
+}
@@ -0,0 +1,118 @@
+// 重复截图检测：保存前计算截图的感知哈希（dHash，64 位），与已有条目比较汉明距离，
+// 不超过阈值时在条目上标记 duplicateOf 并广播 possible_duplicate 事件，由用户选择合并或保留。
+
+use crate::data_models::{Config, HistoryItem};
+use crate::{event_stream, fs_manager, pipeline};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatePayload {
+    pub id: String,
+    pub duplicate_of: String,
+    pub distance: u32,
+}
+
+/// Difference hash: grayscale 9x8 thumbnail, one bit per horizontally adjacent pixel pair
+pub fn dhash(image_bytes: &[u8]) -> Option<u64> {
+    let img = image::load_from_memory(image_bytes).ok()?;
+    let small = img
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+    Some(hash)
+}
+
+fn parse_hash(hex: &str) -> Option<u64> {
+    u64::from_str_radix(hex, 16).ok()
+}
+
+/// Sets the item's perceptual hash and, when an existing item is within the configured
+/// Hamming distance, flags it as a possible duplicate and notifies the frontend.
+pub fn mark(app_handle: &AppHandle, config: &Config, mut item: HistoryItem, image_bytes: &[u8], history: &[HistoryItem]) -> HistoryItem {
+    let Some(hash) = dhash(image_bytes) else {
+        return item;
+    };
+    item.perceptual_hash = Some(format!("{:016x}", hash));
+    if !config.duplicate_detection {
+        return item;
+    }
+
+    let nearest = history
+        .iter()
+        .filter(|other| other.id != item.id)
+        .filter_map(|other| {
+            let other_hash = parse_hash(other.perceptual_hash.as_deref()?)?;
+            Some((other, (hash ^ other_hash).count_ones()))
+        })
+        .filter(|(_, distance)| *distance <= config.duplicate_threshold)
+        .min_by_key(|(_, distance)| *distance);
+    if let Some((other, distance)) = nearest {
+        item.duplicate_of = Some(other.id.clone());
+        let payload = DuplicatePayload { id: item.id.clone(), duplicate_of: other.id.clone(), distance };
+        event_stream::publish("possible_duplicate", &payload);
+        let _ = app_handle.emit_all("possible_duplicate", payload);
+    }
+    item
+}
+
+// --- Tauri commands ---
+
+/// Merges a flagged item into the item it duplicates: the target keeps its content, takes over
+/// the favorite flag and notes, and the duplicate (with its image file) is removed.
+#[tauri::command]
+pub async fn merge_duplicate(app_handle: AppHandle, id: String, into_id: Option<String>) -> Result<HistoryItem, String> {
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    let mut history = fs_manager::read_history_async(&app_handle).await.map_err(|e| e.to_string())?;
+    let index = history
+        .iter()
+        .position(|i| i.id == id)
+        .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+    let into_id = into_id
+        .or_else(|| history[index].duplicate_of.clone())
+        .ok_or_else(|| format!("Item '{}' is not flagged as a duplicate", id))?;
+    if into_id == id {
+        return Err("Cannot merge an item into itself".to_string());
+    }
+    let duplicate = history.remove(index);
+    let target = history
+        .iter_mut()
+        .find(|i| i.id == into_id)
+        .ok_or_else(|| format!("Item with ID '{}' not found", into_id))?;
+
+    target.is_favorite |= duplicate.is_favorite;
+    if let Some(notes) = duplicate.notes.filter(|n| !n.trim().is_empty()) {
+        target.notes = Some(match target.notes.take().filter(|n| !n.trim().is_empty()) {
+            Some(existing) => format!("{}\n\n{}", existing, notes),
+            None => notes,
+        });
+    }
+    let merged = target.clone();
+    fs_manager::write_history_async(&app_handle, history.clone()).await.map_err(|e| e.to_string())?;
+
+    // 被合并条目的图片若不再被引用则删除
+    fs_manager::release_image(&app_handle, &duplicate.original_image, &history);
+    Ok(merged)
+}
+
+/// Keeps a flagged item as a separate entry
+#[tauri::command]
+pub async fn dismiss_duplicate(app_handle: AppHandle, id: String) -> Result<(), String> {
+    pipeline::update_history(&app_handle, |history| {
+        let item = history
+            .iter_mut()
+            .find(|i| i.id == id)
+            .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+        item.duplicate_of = None;
+        Ok(())
+    })
+    .await
+}
@@ -0,0 +1,145 @@
+// 多模型集成识别（可选）：LaTeX 阶段除默认模型外，同时把图片发给 ensemble.models 中的一到两个模型，
+// 将各自返回的 LaTeX 规范化（去掉空白、间距命令与 \left/\right 等）后比较，取多数一致的结果；
+// 各模型结果不一致时在核查报告中列出分歧。分析与核查仍只使用默认模型。
+
+use crate::data_models::Config;
+use crate::llm_api;
+use tokio::task::JoinHandle;
+
+/// 默认模型之外最多使用的模型数（合计两到三个）
+pub const MAX_EXTRA_MODELS: usize = 2;
+
+/// 规范化时去掉的命令（只影响排版，不影响内容）
+const LAYOUT_COMMANDS: &[&str] = &["left", "right", "displaystyle", "textstyle", "big", "Big", "bigl", "bigr", "Bigl", "Bigr"];
+
+pub type MemberTask = (String, JoinHandle<anyhow::Result<String>>);
+
+/// Starts the LaTeX extraction on the extra ensemble models, if the ensemble is enabled
+pub fn spawn_members(config: &Config, prompt: &str, image_base64: &str) -> Vec<MemberTask> {
+    if !config.ensemble.enabled {
+        return Vec::new();
+    }
+    config
+        .ensemble
+        .models
+        .iter()
+        .map(|model| model.trim())
+        .filter(|model| !model.is_empty() && *model != config.default_engine)
+        .take(MAX_EXTRA_MODELS)
+        .map(|model| {
+            let mut llm_config = config.to_llm_config();
+            llm_config.model_name = model.to_string();
+            let client = llm_api::create_client(llm_config);
+            let (prompt, image) = (prompt.to_string(), image_base64.to_string());
+            (model.to_string(), tokio::spawn(async move { client?.extract_latex(&prompt, &image).await }))
+        })
+        .collect()
+}
+
+/// LaTeX reduced to its content for comparison: no whitespace, spacing commands or
+/// delimiter sizing, and no trailing punctuation
+pub fn normalize(latex: &str) -> String {
+    let latex = latex.trim().trim_matches('$');
+    let mut out = String::with_capacity(latex.len());
+    let mut chars = latex.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '\\' => match chars.peek().copied() {
+                // \, \; \: \! 与 "\ " 只是间距
+                Some(',' | ';' | ':' | '!' | ' ') => {
+                    chars.next();
+                }
+                Some(next) if next.is_ascii_alphabetic() => {
+                    let mut word = String::new();
+                    while let Some(letter) = chars.next_if(|ch| ch.is_ascii_alphabetic()) {
+                        word.push(letter);
+                    }
+                    if !LAYOUT_COMMANDS.contains(&word.as_str()) && !matches!(word.as_str(), "quad" | "qquad") {
+                        out.push('\\');
+                        out.push_str(&word);
+                        // 保留命令与后续字母之间的分隔
+                        if chars.peek().is_some_and(|ch| ch.is_ascii_alphabetic()) {
+                            out.push(' ');
+                        }
+                    }
+                }
+                _ => out.push(c),
+            },
+            _ => out.push(c),
+        }
+    }
+    out.trim_end_matches(['.', ',']).to_string()
+}
+
+/// Result of comparing the models' LaTeX
+#[derive(Debug, Clone)]
+pub struct Consensus {
+    /// The chosen LaTeX (as returned by the first model of the largest group)
+    pub latex: String,
+    /// Model that produced `latex`
+    pub model: String,
+    /// Models agreeing with the chosen LaTeX, and how many answered at all
+    pub agreeing: usize,
+    pub answered: usize,
+    /// Every model's answer, default model first
+    pub candidates: Vec<(String, String)>,
+}
+
+impl Consensus {
+    pub fn is_unanimous(&self) -> bool {
+        self.agreeing == self.answered
+    }
+
+    /// Note for the verification report listing the models' answers, when they disagree
+    pub fn disagreement_report(&self, language: &str) -> Option<String> {
+        if self.is_unanimous() {
+            return None;
+        }
+        let answers: Vec<String> = self.candidates.iter().map(|(model, latex)| format!("- {}: {}", model, latex)).collect();
+        let header = if language == "zh-CN" {
+            format!("多模型识别结果不一致（{}/{} 一致），采用 {} 的结果：", self.agreeing, self.answered, self.model)
+        } else {
+            format!("The ensemble models disagree ({}/{} agree); using the result of {}:", self.agreeing, self.answered, self.model)
+        };
+        Some(format!("{}\n{}", header, answers.join("\n")))
+    }
+}
+
+/// Picks the LaTeX most models agree on; ties go to the group containing the default model
+/// (which comes first in `candidates`)
+pub fn pick(candidates: Vec<(String, String)>) -> Option<Consensus> {
+    let normalized: Vec<String> = candidates.iter().map(|(_, latex)| normalize(latex)).collect();
+    let mut best: Option<(usize, usize)> = None; // (candidate index, group size)
+    for (i, key) in normalized.iter().enumerate() {
+        let size = normalized.iter().filter(|other| *other == key).count();
+        if best.map_or(true, |(_, best_size)| size > best_size) {
+            best = Some((i, size));
+        }
+    }
+    let (index, agreeing) = best?;
+    let (model, latex) = candidates[index].clone();
+    Some(Consensus { latex, model, agreeing, answered: candidates.len(), candidates })
+}
+
+/// Waits for the extra models and picks the consensus with the default model's `latex`.
+/// Models that fail are left out; without any extra answer the default LaTeX is kept.
+pub async fn resolve(default_model: &str, latex: String, members: Vec<MemberTask>) -> Option<Consensus> {
+    if members.is_empty() {
+        return None;
+    }
+    let mut candidates = vec![(default_model.to_string(), latex)];
+    for (model, task) in members {
+        match task.await {
+            Ok(Ok(latex)) if !latex.trim().is_empty() => candidates.push((model, latex)),
+            _result => {
+                #[cfg(debug_assertions)]
+                eprintln!("[Ensemble] {} returned no LaTeX: {:?}", model, _result.map(|r| r.map_err(|e| e.to_string())));
+            }
+        }
+    }
+    if candidates.len() < 2 {
+        return None;
+    }
+    pick(candidates)
+}
@@ -0,0 +1,169 @@
+// 本地 WebSocket 事件流：将 recognition_progress / recognition_completed 等事件实时推送给外部集成
+// （如 OBS 叠加层、编辑器插件），无需轮询 HTTP API。
+// 连接方式：ws://127.0.0.1:{port}/?token=<token>（或 Authorization: Bearer <token>），token 与本地 HTTP API 共用。
+
+use crate::fs_manager;
+use anyhow::{anyhow, Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+use tauri::AppHandle;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Message;
+
+const CHANNEL_CAPACITY: usize = 256;
+
+static HUB: OnceLock<broadcast::Sender<String>> = OnceLock::new();
+
+struct RunningServer {
+    port: u16,
+    /// 通知监听循环与所有已建立的连接退出
+    shutdown: broadcast::Sender<()>,
+}
+
+static SERVER: OnceLock<Mutex<Option<RunningServer>>> = OnceLock::new();
+
+fn hub() -> &'static broadcast::Sender<String> {
+    HUB.get_or_init(|| broadcast::channel(CHANNEL_CAPACITY).0)
+}
+
+fn server_slot() -> &'static Mutex<Option<RunningServer>> {
+    SERVER.get_or_init(|| Mutex::new(None))
+}
+
+/// Publishes an event to all connected WebSocket clients (no-op without subscribers)
+pub fn publish<T: Serialize>(event: &str, payload: &T) {
+    if hub().receiver_count() == 0 {
+        return;
+    }
+    let message = json!({ "event": event, "payload": payload }).to_string();
+    let _ = hub().send(message);
+}
+
+fn token_from_request(req: &Request) -> Option<String> {
+    // 查询参数按 URL 编码解码，令牌中可以包含保留字符
+    let from_query = req
+        .uri()
+        .query()
+        .and_then(|q| reqwest::Url::parse(&format!("http://localhost/?{}", q)).ok())
+        .and_then(|u| u.query_pairs().find(|(k, _)| k == "token").map(|(_, v)| v.to_string()));
+    from_query.or_else(|| {
+        req.headers()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(|t| t.trim().to_string())
+    })
+}
+
+async fn serve_connection(stream: tokio::net::TcpStream, token: String, mut shutdown: broadcast::Receiver<()>) {
+    let check = |req: &Request, resp: Response| -> Result<Response, ErrorResponse> {
+        if token_from_request(req).is_some_and(|presented| crate::local_api::tokens_match(&presented, &token)) {
+            Ok(resp)
+        } else {
+            let mut err = ErrorResponse::new(Some("missing or invalid token".to_string()));
+            *err.status_mut() = StatusCode::UNAUTHORIZED;
+            Err(err)
+        }
+    };
+    let Ok(ws) = tokio_tungstenite::accept_hdr_async(stream, check).await else { return };
+    let (mut sink, mut incoming) = ws.split();
+    let mut events = hub().subscribe();
+
+    loop {
+        tokio::select! {
+            // 服务停止或令牌更换后断开已有连接
+            _ = shutdown.recv() => {
+                let _ = sink.send(Message::Close(None)).await;
+                break;
+            }
+            event = events.recv() => match event {
+                Ok(text) => {
+                    if sink.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                // 客户端过慢导致丢弃部分事件时继续推送后续事件
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            msg = incoming.next() => match msg {
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Stops the running WebSocket server (if any)
+pub fn stop() {
+    if let Some(server) = server_slot().lock().unwrap().take() {
+        let _ = server.shutdown.send(());
+    }
+}
+
+/// Starts the WebSocket server according to the current config.
+/// Must be called from within the async runtime.
+pub async fn start(app_handle: AppHandle) -> Result<u16> {
+    stop();
+    let mut config = fs_manager::read_config(&app_handle)?;
+    if !config.local_api.websocket_enabled {
+        return Err(anyhow!("WebSocket event stream is disabled"));
+    }
+
+    let port = config.local_api.websocket_port;
+    let token = crate::local_api::ensure_token(&app_handle, &mut config)?;
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind WebSocket server on {}", addr))?;
+
+    let (tx, mut rx) = broadcast::channel::<()>(1);
+    let shutdown = tx.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = rx.recv() => break,
+                accepted = listener.accept() => {
+                    if let Ok((stream, _)) = accepted {
+                        tauri::async_runtime::spawn(serve_connection(stream, token.clone(), shutdown.subscribe()));
+                    }
+                }
+            }
+        }
+    });
+    *server_slot().lock().unwrap() = Some(RunningServer { port, shutdown: tx });
+    Ok(port)
+}
+
+#[derive(Serialize)]
+pub struct EventStreamStatus {
+    running: bool,
+    port: Option<u16>,
+    clients: usize,
+}
+
+// --- Tauri commands ---
+
+/// Applies the current WebSocket settings (start, restart on a new port, or stop)
+#[tauri::command]
+pub async fn restart_event_stream(app_handle: AppHandle) -> Result<EventStreamStatus, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    if config.local_api.websocket_enabled {
+        start(app_handle).await.map_err(|e| format!("{:#}", e))?;
+    } else {
+        stop();
+    }
+    Ok(get_event_stream_status())
+}
+
+#[tauri::command]
+pub fn get_event_stream_status() -> EventStreamStatus {
+    let port = server_slot().lock().unwrap().as_ref().map(|s| s.port);
+    EventStreamStatus { running: port.is_some(), port, clients: hub().receiver_count() }
+}
@@ -0,0 +1,582 @@
+// 历史条目导出
+//   espanso：生成 match 文件（trigger = 标题 slug，replace = LaTeX），在任意输入框通过关键词输入常用公式
+//   braille：每个条目输出标题（一级盲文）与公式（Nemeth/UEB），可选 BRF 格式供盲文打印机使用
+//   spoken：标题与朗读文本的纯文本文件，供 TTS 或屏幕阅读器使用（跳过尚未生成朗读文本的条目）
+//   collection：集合内公式按编号输出为 equation 环境（\label 供 \eqref 引用，或 \tag 固定编号），附引用对照表
+//   notebook：Jupyter .ipynb（每个条目一个 markdown 单元格，原图作为单元格附件内嵌）或 Quarto .qmd
+//             （原图复制到 `<文件名>_images/` 目录），用于把扫描的公式整理成讲义
+//   beamer：每个条目一页幻灯片（公式 + 分析摘要与变量说明要点），从扫描的推导快速生成报告
+//   catalogue：收藏公式手册，分页 HTML（原图内嵌、LaTeX、摘要，附目录），打印或另存为 PDF 即成个人公式手册
+// 条目带有出处（source）时，各格式都会附上一行引用信息（盲文除外）
+
+use crate::braille::{self, BrailleCode};
+use crate::collections::{self, Collection};
+use crate::data_models::HistoryItem;
+use crate::{fs_manager, image_storage};
+use base64::{engine::general_purpose, Engine as _};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::AppHandle;
+
+const ESPANSO_TRIGGER_PREFIX: &str = ":";
+
+/// Loads the requested items in history order. An empty id list selects all items.
+pub(crate) fn select_items(app_handle: AppHandle, ids: &[String]) -> Result<Vec<HistoryItem>, String> {
+    let history = crate::get_history(app_handle)?;
+    if ids.is_empty() {
+        return Ok(history);
+    }
+    let items: Vec<HistoryItem> = history.into_iter().filter(|i| ids.contains(&i.id)).collect();
+    if items.is_empty() {
+        return Err("No matching history items".to_string());
+    }
+    Ok(items)
+}
+
+/// Lowercase ASCII slug; falls back to an id-based slug for titles without ASCII letters (e.g. Chinese)
+pub(crate) fn slugify(title: &str, id: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-').to_string();
+    if slug.is_empty() {
+        format!("f{}", id.chars().filter(|c| c.is_ascii_alphanumeric()).take(8).collect::<String>())
+    } else {
+        slug
+    }
+}
+
+/// YAML double-quoted scalar
+fn yaml_quote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            '\t' => out.push_str("\\t"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn render_espanso(items: &[HistoryItem]) -> String {
+    let mut used = HashSet::new();
+    let mut out = String::from("# Generated by AI Formula Scanner\nmatches:\n");
+    for item in items {
+        let base = slugify(&item.title, &item.id);
+        let mut trigger = base.clone();
+        let mut n = 2;
+        while !used.insert(trigger.clone()) {
+            trigger = format!("{}-{}", base, n);
+            n += 1;
+        }
+        out.push_str(&format!("  # {}\n", item.title.replace('\n', " ")));
+        if let Some(source) = &item.source {
+            out.push_str(&format!("  # source: {}\n", source.citation()));
+        }
+        out.push_str(&format!("  - trigger: {}\n", yaml_quote(&format!("{}{}", ESPANSO_TRIGGER_PREFIX, trigger))));
+        out.push_str(&format!("    replace: {}\n", yaml_quote(item.latex.trim())));
+    }
+    out
+}
+
+fn render_braille(items: &[HistoryItem], code: BrailleCode) -> String {
+    let mut out = String::new();
+    for item in items {
+        out.push_str(&braille::text_to_braille(item.title.trim()));
+        out.push('\n');
+        out.push_str(&braille::convert(&item.latex, code).braille);
+        out.push_str("\n\n");
+    }
+    out
+}
+
+fn render_spoken(items: &[HistoryItem]) -> (String, usize) {
+    let mut out = String::new();
+    let mut count = 0;
+    for item in items {
+        let Some(reading) = item.spoken_description.as_deref().filter(|r| !r.trim().is_empty()) else { continue };
+        out.push_str(&format!("{}\n{}\n", item.title.trim(), reading.trim()));
+        if let Some(source) = &item.source {
+            out.push_str(&format!("Source: {}\n", source.citation()));
+        }
+        out.push('\n');
+        count += 1;
+    }
+    (out, count)
+}
+
+/// How equations reference each other in a collection export
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CrossRefMode {
+    /// equation 环境 + \label，由 LaTeX 自动编号，正文用 \eqref 引用
+    #[default]
+    Label,
+    /// equation* + \tag{n}，编号与应用内显示一致
+    Tag,
+}
+
+/// Display-math environments that can carry their own \label/\tag
+const NUMBERED_ENVIRONMENTS: &[&str] = &["equation", "align", "gather", "multline", "flalign", "alignat"];
+
+/// Removes surrounding math delimiters ($$…$$, \[…\], $…$)
+pub(crate) fn strip_math_delimiters(latex: &str) -> &str {
+    let t = latex.trim();
+    for (open, close) in [("$$", "$$"), ("\\[", "\\]"), ("$", "$")] {
+        if let Some(inner) = t.strip_prefix(open).and_then(|s| s.strip_suffix(close)) {
+            return inner.trim();
+        }
+    }
+    t
+}
+
+/// Whether the LaTeX already is a display-math environment and must not be wrapped again
+pub(crate) fn is_display_environment(body: &str) -> bool {
+    NUMBERED_ENVIRONMENTS
+        .iter()
+        .any(|env| body.starts_with(&format!("\\begin{{{}}}", env)) || body.starts_with(&format!("\\begin{{{}*}}", env)))
+}
+
+fn render_collection(collection: &Collection, history: &[HistoryItem], mode: CrossRefMode) -> String {
+    let numbered = collections::number_items(collection, history);
+    let mut out = format!("% Generated by AI Formula Scanner: collection \"{}\"\n", collection.name);
+    out.push_str("% Equation references:\n");
+    for (eq, item) in &numbered {
+        out.push_str(&format!("%   ({}) {}  ->  \\eqref{{{}}}\n", eq.number, item.title.replace('\n', " "), eq.label));
+    }
+    out.push('\n');
+    for (eq, item) in &numbered {
+        let body = strip_math_delimiters(&item.latex);
+        let label = format!("\\label{{{}}}", eq.label);
+        let tagged = format!("\\tag{{{}}}\\label{{{}}}", eq.number, eq.label);
+        let marker = if mode == CrossRefMode::Tag { &tagged } else { &label };
+        out.push_str(&format!("% ({}) {}\n", eq.number, item.title.replace('\n', " ")));
+        if let Some(source) = &item.source {
+            out.push_str(&format!("% source: {}\n", source.citation()));
+        }
+        // 已是显示公式环境的直接在环境开头插入标记，其余包进 equation；
+        // 带星号的环境不自动编号，因此总是用 \tag
+        let begin = NUMBERED_ENVIRONMENTS
+            .iter()
+            .flat_map(|env| [format!("\\begin{{{}}}", env), format!("\\begin{{{}*}}", env)])
+            .find(|begin| body.starts_with(begin.as_str()));
+        match begin {
+            Some(begin) => {
+                let marker = if begin.ends_with("*}") { &tagged } else { marker };
+                out.push_str(&format!("{}{}{}\n", begin, marker, &body[begin.len()..]));
+            }
+            None => {
+                let environment = if mode == CrossRefMode::Tag { "equation*" } else { "equation" };
+                out.push_str(&format!("\\begin{{{}}}{}\n  {}\n\\end{{{}}}\n", environment, marker, body, environment));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Notebook flavour for lecture-note exports
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NotebookFormat {
+    #[default]
+    Ipynb,
+    Qmd,
+}
+
+/// Reads an item's original image; None when the item has no image or the file is gone
+fn read_item_image(app_handle: &AppHandle, item: &HistoryItem) -> Option<Vec<u8>> {
+    if item.original_image.is_empty() {
+        return None;
+    }
+    std::fs::read(fs_manager::resolve_image_path(app_handle, &item.original_image)).ok()
+}
+
+fn image_extension(mime: &str) -> &'static str {
+    match mime {
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        _ => "png",
+    }
+}
+
+/// Markdown shared by both notebook formats: heading, display math, summary, source and image
+fn notebook_markdown(item: &HistoryItem, image_ref: Option<&str>) -> String {
+    let title = item.title.replace('\n', " ");
+    let body = strip_math_delimiters(&item.latex);
+    let mut out = format!("## {}\n\n", title.trim());
+    // 显示公式环境（align 等）不能再包在 $$ 里，MathJax 会直接处理
+    if is_display_environment(body) {
+        out.push_str(&format!("{}\n", body));
+    } else {
+        out.push_str(&format!("$$\n{}\n$$\n", body));
+    }
+    let summary = item.analysis.summary.trim();
+    if !summary.is_empty() {
+        out.push_str(&format!("\n{}\n", summary));
+    }
+    if let Some(source) = &item.source {
+        out.push_str(&format!("\n*Source: {}*\n", source.citation()));
+    }
+    if let Some(image_ref) = image_ref {
+        out.push_str(&format!("\n![{}]({})\n", title.trim().replace(['[', ']'], ""), image_ref));
+    }
+    out
+}
+
+/// Slug per item, made unique within the export (used for image and cell names)
+fn unique_slugs(items: &[HistoryItem]) -> Vec<String> {
+    let mut used = HashSet::new();
+    items
+        .iter()
+        .map(|item| {
+            let base = slugify(&item.title, &item.id);
+            let mut slug = base.clone();
+            let mut n = 2;
+            while !used.insert(slug.clone()) {
+                slug = format!("{}-{}", base, n);
+                n += 1;
+            }
+            slug
+        })
+        .collect()
+}
+
+/// nbformat 4 notebook; images are embedded as markdown cell attachments
+fn render_ipynb(app_handle: &AppHandle, items: &[HistoryItem]) -> String {
+    let cells: Vec<serde_json::Value> = items
+        .iter()
+        .zip(unique_slugs(items))
+        .map(|(item, slug)| {
+            let image = read_item_image(app_handle, item).map(|bytes| {
+                let mime = image_storage::mime_type(&bytes);
+                (format!("{}.{}", slug, image_extension(mime)), mime, general_purpose::STANDARD.encode(&bytes))
+            });
+            let reference = image.as_ref().map(|(name, _, _)| format!("attachment:{}", name));
+            let markdown = notebook_markdown(item, reference.as_deref());
+            let source: Vec<&str> = markdown.split_inclusive('\n').collect();
+            let mut cell = json!({
+                "cell_type": "markdown",
+                "id": slug.chars().take(64).collect::<String>(),
+                "metadata": {},
+                "source": source,
+            });
+            if let Some((name, mime, data)) = image {
+                cell["attachments"] = json!({ name: { mime: data } });
+            }
+            cell
+        })
+        .collect();
+    let notebook = json!({
+        "cells": cells,
+        "metadata": {},
+        "nbformat": 4,
+        "nbformat_minor": 5,
+    });
+    serde_json::to_string_pretty(&notebook).unwrap_or_default()
+}
+
+/// Quarto document; images are copied into `<stem>_images/` next to the .qmd and referenced relatively
+fn render_qmd(app_handle: &AppHandle, items: &[HistoryItem], path: &Path) -> Result<String, String> {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "formulas".to_string());
+    let images_dir_name = format!("{}_images", stem);
+    let images_dir = path.with_file_name(&images_dir_name);
+
+    let mut out = format!("---\ntitle: {}\nformat: html\n---\n", yaml_quote(&stem));
+    for (item, slug) in items.iter().zip(unique_slugs(items)) {
+        let mut reference = None;
+        if let Some(bytes) = read_item_image(app_handle, item) {
+            let file_name = format!("{}.{}", slug, image_extension(image_storage::mime_type(&bytes)));
+            std::fs::create_dir_all(&images_dir).map_err(|e| format!("Failed to create {}: {}", images_dir.display(), e))?;
+            let target = images_dir.join(&file_name);
+            std::fs::write(&target, bytes).map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+            reference = Some(format!("{}/{}", images_dir_name, file_name));
+        }
+        out.push('\n');
+        out.push_str(&notebook_markdown(item, reference.as_deref()));
+    }
+    Ok(out)
+}
+
+/// 每页幻灯片最多列出的变量说明，避免内容溢出
+const BEAMER_MAX_VARIABLES: usize = 6;
+
+/// Escapes text for LaTeX paragraph mode
+pub(crate) fn latex_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            '\n' | '\r' => out.push(' '),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn render_beamer(items: &[HistoryItem], title: &str) -> String {
+    // 标题或说明含中文时需要 ctex，并用 XeLaTeX 编译
+    let needs_cjk = std::iter::once(title.to_string())
+        .chain(items.iter().map(|i| format!("{}{}", i.title, i.analysis.summary)))
+        .any(|text| !text.is_ascii());
+    let mut out = String::new();
+    if needs_cjk {
+        out.push_str("% !TEX program = xelatex\n");
+    }
+    out.push_str("% Generated by AI Formula Scanner\n\\documentclass{beamer}\n\\usepackage{amsmath,amssymb}\n");
+    if needs_cjk {
+        out.push_str("\\usepackage{ctex}\n");
+    }
+    out.push_str(&format!("\\title{{{}}}\n\\date{{}}\n\n\\begin{{document}}\n\n\\begin{{frame}}\n  \\titlepage\n\\end{{frame}}\n", latex_escape(title)));
+
+    for item in items {
+        out.push_str(&format!("\n\\begin{{frame}}{{{}}}\n", latex_escape(item.title.trim())));
+        let body = strip_math_delimiters(&item.latex);
+        if is_display_environment(body) {
+            out.push_str(&format!("  {}\n", body));
+        } else {
+            out.push_str(&format!("  \\[\n    {}\n  \\]\n", body));
+        }
+
+        let mut bullets = Vec::new();
+        let summary = item.analysis.summary.trim();
+        if !summary.is_empty() {
+            bullets.push(latex_escape(summary));
+        }
+        for variable in item.analysis.variables.iter().take(BEAMER_MAX_VARIABLES) {
+            let mut bullet = format!("${}$: {}", strip_math_delimiters(&variable.symbol), latex_escape(variable.description.trim()));
+            if let Some(unit) = variable.unit.as_deref().filter(|u| !u.trim().is_empty()) {
+                bullet.push_str(&format!(" ({})", latex_escape(unit.trim())));
+            }
+            bullets.push(bullet);
+        }
+        if !bullets.is_empty() {
+            out.push_str("  \\begin{itemize}\n");
+            for bullet in &bullets {
+                out.push_str(&format!("    \\item {}\n", bullet));
+            }
+            out.push_str("  \\end{itemize}\n");
+        }
+        if let Some(source) = &item.source {
+            out.push_str(&format!("  \\vfill{{\\footnotesize Source: {}}}\n", latex_escape(&source.citation())));
+        }
+        out.push_str("\\end{frame}\n");
+    }
+    out.push_str("\n\\end{document}\n");
+    out
+}
+
+/// 手册每页默认条目数
+const CATALOGUE_ITEMS_PER_PAGE: usize = 4;
+
+const CATALOGUE_STYLE: &str = "\
+@page { size: A4; margin: 18mm 16mm; }
+body { font-family: sans-serif; color: #222; margin: 0; }
+.page { page-break-after: always; break-after: page; min-height: 250mm; position: relative; }
+.page:last-child { page-break-after: auto; break-after: auto; }
+.toc li { margin: 2px 0; }
+.toc .num { float: right; color: #666; }
+.entry { border-bottom: 1px solid #ddd; padding: 8px 0 12px; break-inside: avoid; }
+.entry h2 { font-size: 15px; margin: 0 0 6px; }
+.entry img { max-width: 100%; max-height: 45mm; display: block; margin: 4px 0; }
+.entry pre { font-size: 11px; background: #f5f5f5; padding: 4px 6px; white-space: pre-wrap; word-break: break-all; }
+.entry .summary { font-size: 12px; }
+.entry .source { font-size: 11px; color: #666; }
+.footer { position: absolute; bottom: 0; width: 100%; text-align: center; font-size: 11px; color: #888; }
+";
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Paginated HTML handbook: a contents page followed by `per_page` entries per page
+fn render_catalogue(app_handle: &AppHandle, items: &[HistoryItem], title: &str, per_page: usize) -> String {
+    let pages: Vec<&[HistoryItem]> = items.chunks(per_page.max(1)).collect();
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}</style>\n\
+         <script src=\"https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-chtml.js\"></script>\n</head>\n<body>\n",
+        html_escape(title),
+        CATALOGUE_STYLE
+    );
+
+    // 目录页（第 1 页），条目从第 2 页开始
+    out.push_str(&format!("<div class=\"page\">\n<h1>{}</h1>\n<ol class=\"toc\">\n", html_escape(title)));
+    for (page_index, page) in pages.iter().enumerate() {
+        for item in page.iter() {
+            out.push_str(&format!(
+                "<li><a href=\"#f-{}\">{}</a><span class=\"num\">{}</span></li>\n",
+                html_escape(&item.id),
+                html_escape(item.title.trim()),
+                page_index + 2
+            ));
+        }
+    }
+    out.push_str("</ol>\n<div class=\"footer\">1</div>\n</div>\n");
+
+    for (page_index, page) in pages.iter().enumerate() {
+        out.push_str("<div class=\"page\">\n");
+        for item in page.iter() {
+            out.push_str(&format!("<div class=\"entry\" id=\"f-{}\">\n<h2>{}</h2>\n", html_escape(&item.id), html_escape(item.title.trim())));
+            if let Some(bytes) = read_item_image(app_handle, item) {
+                out.push_str(&format!(
+                    "<img src=\"data:{};base64,{}\" alt=\"\">\n",
+                    image_storage::mime_type(&bytes),
+                    general_purpose::STANDARD.encode(&bytes)
+                ));
+            }
+            let body = strip_math_delimiters(&item.latex);
+            if is_display_environment(body) {
+                out.push_str(&format!("<div class=\"formula\">{}</div>\n", html_escape(body)));
+            } else {
+                out.push_str(&format!("<div class=\"formula\">\\[{}\\]</div>\n", html_escape(body)));
+            }
+            out.push_str(&format!("<pre>{}</pre>\n", html_escape(item.latex.trim())));
+            let summary = item.analysis.summary.trim();
+            if !summary.is_empty() {
+                out.push_str(&format!("<p class=\"summary\">{}</p>\n", html_escape(summary)));
+            }
+            if let Some(source) = &item.source {
+                out.push_str(&format!("<p class=\"source\">Source: {}</p>\n", html_escape(&source.citation())));
+            }
+            out.push_str("</div>\n");
+        }
+        out.push_str(&format!("<div class=\"footer\">{}</div>\n</div>\n", page_index + 2));
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+// --- Tauri commands ---
+
+/// Writes the selected items (all when `ids` is empty) to an espanso match file. Returns the number of matches.
+#[tauri::command]
+pub fn export_espanso(app_handle: AppHandle, ids: Vec<String>, path: String) -> Result<usize, String> {
+    let items = select_items(app_handle, &ids)?;
+    std::fs::write(&path, render_espanso(&items)).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(items.len())
+}
+
+/// Writes the selected items as braille math (Nemeth by default). With `brf` the file uses
+/// North American ASCII braille, otherwise Unicode braille. Returns the number of items.
+#[tauri::command]
+pub fn export_braille(
+    app_handle: AppHandle,
+    ids: Vec<String>,
+    path: String,
+    code: Option<BrailleCode>,
+    brf: Option<bool>,
+) -> Result<usize, String> {
+    let items = select_items(app_handle, &ids)?;
+    let mut content = render_braille(&items, code.unwrap_or_default());
+    if brf.unwrap_or(false) {
+        content = braille::to_brf(&content);
+    }
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(items.len())
+}
+
+/// Writes the spoken readings of the selected items to a plain-text file. Returns the number
+/// of items written; items without a generated reading are skipped.
+#[tauri::command]
+pub fn export_spoken_descriptions(app_handle: AppHandle, ids: Vec<String>, path: String) -> Result<usize, String> {
+    let items = select_items(app_handle, &ids)?;
+    let (content, count) = render_spoken(&items);
+    if count == 0 {
+        return Err("None of the selected items has a spoken description yet".to_string());
+    }
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(count)
+}
+
+/// Writes a collection as numbered, labelled equations ready to paste into a paper.
+/// Returns the number of equations written.
+#[tauri::command]
+pub fn export_collection_latex(
+    app_handle: AppHandle,
+    collection_id: String,
+    path: String,
+    mode: Option<CrossRefMode>,
+) -> Result<usize, String> {
+    let collection = collections::read_collections(&app_handle)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|c| c.id == collection_id)
+        .ok_or_else(|| format!("Collection '{}' not found", collection_id))?;
+    let history = crate::get_history(app_handle)?;
+    let content = render_collection(&collection, &history, mode.unwrap_or_default());
+    let count = collections::number_items(&collection, &history).len();
+    if count == 0 {
+        return Err("The collection has no equations".to_string());
+    }
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(count)
+}
+
+/// Writes the selected items as a Jupyter notebook (default) or Quarto document for lecture notes.
+/// Returns the number of items written.
+#[tauri::command]
+pub fn export_notebook(
+    app_handle: AppHandle,
+    ids: Vec<String>,
+    path: String,
+    format: Option<NotebookFormat>,
+) -> Result<usize, String> {
+    let items = select_items(app_handle.clone(), &ids)?;
+    let content = match format.unwrap_or_default() {
+        NotebookFormat::Ipynb => render_ipynb(&app_handle, &items),
+        NotebookFormat::Qmd => render_qmd(&app_handle, &items, Path::new(&path))?,
+    };
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(items.len())
+}
+
+/// Writes a Beamer deck with one frame per selected item. `title` defaults to "Formulas".
+/// Returns the number of frames (excluding the title frame).
+#[tauri::command]
+pub fn export_beamer(app_handle: AppHandle, ids: Vec<String>, path: String, title: Option<String>) -> Result<usize, String> {
+    let items = select_items(app_handle, &ids)?;
+    let title = title.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| "Formulas".to_string());
+    std::fs::write(&path, render_beamer(&items, title.trim())).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(items.len())
+}
+
+/// Writes all favorites (oldest first) as a printable, paginated HTML handbook. The frontend opens it
+/// in a window and prints it, which also covers "save as PDF". Returns the number of items.
+#[tauri::command]
+pub fn export_favorites_catalogue(
+    app_handle: AppHandle,
+    path: String,
+    title: Option<String>,
+    items_per_page: Option<usize>,
+) -> Result<usize, String> {
+    let mut items: Vec<HistoryItem> = crate::get_history(app_handle.clone())?.into_iter().filter(|i| i.is_favorite).collect();
+    if items.is_empty() {
+        return Err("There are no favorite items to include".to_string());
+    }
+    items.reverse();
+    let title = title.filter(|t| !t.trim().is_empty()).unwrap_or_else(|| "Formula Handbook".to_string());
+    let content = render_catalogue(&app_handle, &items, title.trim(), items_per_page.unwrap_or(CATALOGUE_ITEMS_PER_PAGE));
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(items.len())
+}
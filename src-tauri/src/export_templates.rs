@@ -0,0 +1,361 @@
+// 自定义导出模板：用 Handlebars 模板生成 Markdown / LaTeX / HTML 导出，保存在 export_templates.json。
+// 内置三个只读的默认模板，用户可复制后按本单位的文档样式修改，无需为每种格式编写新的导出代码。
+// 模板上下文：items（条目列表，字段见 TemplateItem）、count、generatedAt；
+// 辅助函数 latexEscape 用于在 LaTeX 正文中安全输出标题等文本。HTML 模板默认转义输出，其余格式不转义。
+
+use crate::data_models::HistoryItem;
+use crate::{export, fs_manager};
+use anyhow::{Context, Result};
+use handlebars::{handlebars_helper, no_escape, Handlebars};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use uuid::Uuid;
+
+const TEMPLATES_FILENAME: &str = "export_templates.json";
+const BUILTIN_ID_PREFIX: &str = "builtin:";
+
+const BUILTIN_MARKDOWN: &str = r#"# Formulas
+
+{{#each items}}
+## {{title}}
+
+{{#if isEnvironment}}
+{{latexBody}}
+{{else}}
+$$
+{{latexBody}}
+$$
+{{/if}}
+{{#if summary}}
+
+{{summary}}
+{{/if}}
+{{#if source}}
+
+*Source: {{source.citation}}*
+{{/if}}
+
+{{/each}}
+"#;
+
+const BUILTIN_LATEX: &str = r#"\documentclass{article}
+\usepackage{amsmath,amssymb}
+
+\begin{document}
+
+{{#each items}}
+\section*{ {{~latexEscape title~}} }
+{{#if isEnvironment}}
+{{latexBody}}
+{{else}}
+\[
+  {{latexBody}}
+\]
+{{/if}}
+{{#if summary}}
+{{latexEscape summary}}
+{{/if}}
+{{#if source}}
+
+{\footnotesize Source: {{latexEscape source.citation}}}
+{{/if}}
+
+{{/each}}
+\end{document}
+"#;
+
+const BUILTIN_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Formulas</title>
+<script src="https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-chtml.js"></script>
+</head>
+<body>
+{{#each items}}
+<section>
+  <h2>{{title}}</h2>
+  <div class="formula">\[{{latexBody}}\]</div>
+  {{#if summary}}<p>{{summary}}</p>{{/if}}
+  {{#if source}}<p class="source">Source: {{source.citation}}</p>{{/if}}
+</section>
+{{/each}}
+</body>
+</html>
+"#;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateFormat {
+    Markdown,
+    Latex,
+    Html,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportTemplate {
+    pub id: String,
+    pub name: String,
+    pub format: TemplateFormat,
+    pub content: String,
+    /// 内置模板不可修改或删除（不写入文件）
+    #[serde(default)]
+    pub builtin: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Fields accepted when creating or updating a template
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportTemplateInput {
+    pub name: String,
+    pub format: TemplateFormat,
+    pub content: String,
+}
+
+/// Per-item data exposed to templates
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TemplateItem {
+    index: usize,
+    id: String,
+    title: String,
+    latex: String,
+    /// LaTeX without surrounding $$ / \[ \] delimiters
+    latex_body: String,
+    /// latexBody is already a display environment (align, gather, …)
+    is_environment: bool,
+    summary: String,
+    variables: Vec<crate::data_models::VariableInfo>,
+    terms: Vec<crate::data_models::TermInfo>,
+    is_favorite: bool,
+    confidence_score: u8,
+    created_at: String,
+    notes: Option<String>,
+    spoken_description: Option<String>,
+    source: Option<TemplateSource>,
+    /// Absolute path of the original image (empty when the item has none)
+    image_path: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TemplateSource {
+    document_title: Option<String>,
+    page: Option<String>,
+    url: Option<String>,
+    doi: Option<String>,
+    citation: String,
+}
+
+fn builtin_templates() -> Vec<ExportTemplate> {
+    [
+        ("markdown", "Markdown", TemplateFormat::Markdown, BUILTIN_MARKDOWN),
+        ("latex", "LaTeX article", TemplateFormat::Latex, BUILTIN_LATEX),
+        ("html", "HTML (MathJax)", TemplateFormat::Html, BUILTIN_HTML),
+    ]
+    .into_iter()
+    .map(|(key, name, format, content)| ExportTemplate {
+        id: format!("{}{}", BUILTIN_ID_PREFIX, key),
+        name: name.to_string(),
+        format,
+        content: content.to_string(),
+        builtin: true,
+        created_at: String::new(),
+        updated_at: String::new(),
+    })
+    .collect()
+}
+
+fn read_templates(app_handle: &AppHandle) -> Result<Vec<ExportTemplate>> {
+    let path = fs_manager::get_data_file_path(app_handle, TEMPLATES_FILENAME)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).context("Failed to parse export_templates.json"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read export_templates.json")),
+    }
+}
+
+fn write_templates(app_handle: &AppHandle, templates: &[ExportTemplate]) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, TEMPLATES_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec_pretty(templates)?).context("Failed to write export_templates.json")
+}
+
+handlebars_helper!(latex_escape_helper: |text: str| export::latex_escape(text));
+
+fn registry(format: TemplateFormat) -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    if format != TemplateFormat::Html {
+        handlebars.register_escape_fn(no_escape);
+    }
+    handlebars.register_helper("latexEscape", Box::new(latex_escape_helper));
+    handlebars
+}
+
+fn validate_input(templates: &[ExportTemplate], input: &ExportTemplateInput, except_id: Option<&str>) -> Result<String, String> {
+    let name = input.name.trim();
+    if name.is_empty() {
+        return Err("Template name must not be empty".to_string());
+    }
+    if builtin_templates()
+        .iter()
+        .chain(templates)
+        .any(|t| Some(t.id.as_str()) != except_id && t.name.eq_ignore_ascii_case(name))
+    {
+        return Err(format!("A template named '{}' already exists", name));
+    }
+    // 保存前先编译一次，语法错误直接返回给编辑器
+    registry(input.format)
+        .register_template_string("template", &input.content)
+        .map_err(|e| format!("Template syntax error: {}", e))?;
+    Ok(name.to_string())
+}
+
+fn find_template(app_handle: &AppHandle, id: &str) -> Result<ExportTemplate, String> {
+    builtin_templates()
+        .into_iter()
+        .chain(read_templates(app_handle).map_err(|e| e.to_string())?)
+        .find(|t| t.id == id)
+        .ok_or_else(|| format!("Export template '{}' not found", id))
+}
+
+fn template_item(app_handle: &AppHandle, index: usize, item: &HistoryItem) -> TemplateItem {
+    let latex_body = export::strip_math_delimiters(&item.latex).to_string();
+    TemplateItem {
+        index,
+        id: item.id.clone(),
+        title: item.title.clone(),
+        latex: item.latex.clone(),
+        is_environment: export::is_display_environment(&latex_body),
+        latex_body,
+        summary: item.analysis.summary.trim().to_string(),
+        variables: item.analysis.variables.clone(),
+        terms: item.analysis.terms.clone(),
+        is_favorite: item.is_favorite,
+        confidence_score: item.confidence_score,
+        created_at: item.created_at.clone(),
+        notes: item.notes.clone(),
+        spoken_description: item.spoken_description.clone(),
+        source: item.source.as_ref().map(|s| TemplateSource {
+            document_title: s.document_title.clone(),
+            page: s.page.clone(),
+            url: s.url.clone(),
+            doi: s.doi.clone(),
+            citation: s.citation(),
+        }),
+        image_path: if item.original_image.is_empty() {
+            String::new()
+        } else {
+            fs_manager::resolve_image_path(app_handle, &item.original_image).to_string_lossy().to_string()
+        },
+    }
+}
+
+/// Renders `template` over the items; indices in the context are 1-based
+fn render(app_handle: &AppHandle, template: &ExportTemplate, items: &[HistoryItem]) -> Result<String, String> {
+    let items: Vec<TemplateItem> = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| template_item(app_handle, i + 1, item))
+        .collect();
+    let context = serde_json::json!({
+        "count": items.len(),
+        "generatedAt": chrono::Utc::now().to_rfc3339(),
+        "items": items,
+    });
+    registry(template.format)
+        .render_template(&template.content, &context)
+        .map_err(|e| format!("Failed to render template '{}': {}", template.name, e))
+}
+
+// --- Tauri commands ---
+
+/// Built-in templates first, then user templates
+#[tauri::command]
+pub fn list_export_templates(app_handle: AppHandle) -> Result<Vec<ExportTemplate>, String> {
+    let mut templates = builtin_templates();
+    templates.extend(read_templates(&app_handle).map_err(|e| e.to_string())?);
+    Ok(templates)
+}
+
+#[tauri::command]
+pub fn create_export_template(app_handle: AppHandle, template: ExportTemplateInput) -> Result<ExportTemplate, String> {
+    let mut templates = read_templates(&app_handle).map_err(|e| e.to_string())?;
+    let name = validate_input(&templates, &template, None)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let created = ExportTemplate {
+        id: Uuid::new_v4().to_string(),
+        name,
+        format: template.format,
+        content: template.content,
+        builtin: false,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    templates.push(created.clone());
+    write_templates(&app_handle, &templates).map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+#[tauri::command]
+pub fn update_export_template(app_handle: AppHandle, id: String, template: ExportTemplateInput) -> Result<ExportTemplate, String> {
+    if id.starts_with(BUILTIN_ID_PREFIX) {
+        return Err("Built-in templates cannot be modified; create a copy instead".to_string());
+    }
+    let mut templates = read_templates(&app_handle).map_err(|e| e.to_string())?;
+    let name = validate_input(&templates, &template, Some(&id))?;
+    let existing = templates
+        .iter_mut()
+        .find(|t| t.id == id)
+        .ok_or_else(|| format!("Export template '{}' not found", id))?;
+    existing.name = name;
+    existing.format = template.format;
+    existing.content = template.content;
+    existing.updated_at = chrono::Utc::now().to_rfc3339();
+    let updated = existing.clone();
+    write_templates(&app_handle, &templates).map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn delete_export_template(app_handle: AppHandle, id: String) -> Result<(), String> {
+    if id.starts_with(BUILTIN_ID_PREFIX) {
+        return Err("Built-in templates cannot be deleted".to_string());
+    }
+    let mut templates = read_templates(&app_handle).map_err(|e| e.to_string())?;
+    let before = templates.len();
+    templates.retain(|t| t.id != id);
+    if templates.len() == before {
+        return Err(format!("Export template '{}' not found", id));
+    }
+    write_templates(&app_handle, &templates).map_err(|e| e.to_string())
+}
+
+/// Renders unsaved template content over the selected items, for the editor preview
+#[tauri::command]
+pub fn preview_export_template(app_handle: AppHandle, template: ExportTemplateInput, ids: Vec<String>) -> Result<String, String> {
+    let items = export::select_items(app_handle.clone(), &ids)?;
+    let template = ExportTemplate {
+        id: String::new(),
+        name: template.name,
+        format: template.format,
+        content: template.content,
+        builtin: false,
+        created_at: String::new(),
+        updated_at: String::new(),
+    };
+    render(&app_handle, &template, &items)
+}
+
+/// Renders the selected items (all when `ids` is empty) with a saved or built-in template
+/// and writes the result to `path`. Returns the number of items.
+#[tauri::command]
+pub fn export_with_template(app_handle: AppHandle, template_id: String, ids: Vec<String>, path: String) -> Result<usize, String> {
+    let template = find_template(&app_handle, &template_id)?;
+    let items = export::select_items(app_handle.clone(), &ids)?;
+    let content = render(&app_handle, &template, &items)?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(items.len())
+}
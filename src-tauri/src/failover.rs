@@ -0,0 +1,122 @@
+// 主服务故障切换：对主服务的请求连续失败（无法连接或 5xx）达到阈值时熔断器打开，熔断期间的请求改发到
+// 设置中的备用服务（failover）；到期后重新尝试主服务，成功即恢复，仍失败则继续熔断。
+// 熔断状态变化通过 provider_failover 事件通知前端，识别结果的 backend 字段记录由哪个服务产生。
+
+use crate::event_stream;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// 打开熔断器所需的连续失败次数
+const FAILURE_THRESHOLD: u32 = 3;
+/// 熔断持续时长，到期后重新尝试主服务
+const OPEN_DURATION: Duration = Duration::from_secs(120);
+
+pub const PRIMARY_BACKEND: &str = "primary";
+pub const FAILOVER_BACKEND: &str = "failover";
+
+struct Breaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    opened_at: Option<String>,
+    reason: Option<String>,
+    /// 上一次通知前端时的状态
+    reported_active: bool,
+}
+
+static BREAKER: Mutex<Breaker> = Mutex::new(Breaker {
+    consecutive_failures: 0,
+    open_until: None,
+    opened_at: None,
+    reason: None,
+    reported_active: false,
+});
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FailoverStatus {
+    /// 当前是否使用备用服务
+    pub active: bool,
+    pub backend: String,
+    pub since: Option<String>,
+    /// 触发切换的最后一次错误
+    pub reason: Option<String>,
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Breaker> {
+    BREAKER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn is_open_locked(breaker: &Breaker) -> bool {
+    breaker.open_until.map_or(false, |until| until > Instant::now())
+}
+
+/// Whether requests should currently go to the failover backend
+pub fn is_open() -> bool {
+    is_open_locked(&lock())
+}
+
+/// Name of the backend currently serving requests
+pub fn current_backend() -> &'static str {
+    if is_open() { FAILOVER_BACKEND } else { PRIMARY_BACKEND }
+}
+
+/// A request to the primary backend succeeded: closes the circuit
+pub fn record_success() {
+    let mut breaker = lock();
+    breaker.consecutive_failures = 0;
+    breaker.open_until = None;
+    breaker.opened_at = None;
+    breaker.reason = None;
+}
+
+/// A request to the primary backend failed in a way that suggests an outage. Opens the circuit once
+/// the failures reach the threshold, but only when a failover backend is configured.
+pub fn record_failure(reason: &str, can_fail_over: bool) {
+    let mut breaker = lock();
+    breaker.consecutive_failures += 1;
+    if !can_fail_over || breaker.consecutive_failures < FAILURE_THRESHOLD {
+        return;
+    }
+    if breaker.opened_at.is_none() {
+        breaker.opened_at = Some(chrono::Utc::now().to_rfc3339());
+    }
+    breaker.open_until = Some(Instant::now() + OPEN_DURATION);
+    breaker.reason = Some(reason.to_string());
+    #[cfg(debug_assertions)]
+    eprintln!("[LLM] Primary backend failing, using failover for {:?}", OPEN_DURATION);
+}
+
+fn status_locked(breaker: &Breaker) -> FailoverStatus {
+    let active = is_open_locked(breaker);
+    FailoverStatus {
+        active,
+        backend: if active { FAILOVER_BACKEND } else { PRIMARY_BACKEND }.to_string(),
+        since: if active { breaker.opened_at.clone() } else { None },
+        reason: if active { breaker.reason.clone() } else { None },
+    }
+}
+
+/// Emits `provider_failover` if the circuit opened or closed since the last notification
+pub fn notify_change(app_handle: &AppHandle) {
+    let status = {
+        let mut breaker = lock();
+        let status = status_locked(&breaker);
+        if status.active == breaker.reported_active {
+            return;
+        }
+        breaker.reported_active = status.active;
+        status
+    };
+    event_stream::publish("provider_failover", &status);
+    let _ = app_handle.emit_all("provider_failover", status);
+}
+
+// --- Tauri commands ---
+
+/// Which backend is serving requests and, during a failover, since when and why
+#[tauri::command]
+pub fn get_failover_status() -> FailoverStatus {
+    status_locked(&lock())
+}
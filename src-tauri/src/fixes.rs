@@ -0,0 +1,96 @@
+// 应用核查建议：对带有具体修正的核查问题，修补条目的 LaTeX（能直接替换时按规则替换，否则交给模型修改），
+// 把修改前的 LaTeX 记入条目的 latex_revisions，然后用原图重新核查并写回历史。
+
+use crate::data_models::{HistoryItem, LatexRevision};
+use crate::llm_api;
+use crate::{bulk_ops, fs_manager, latex_lint, pipeline, prompts};
+use tauri::AppHandle;
+
+/// 每个条目最多保留的 LaTeX 历史版本数
+const MAX_LATEX_REVISIONS: usize = 20;
+
+/// Records the current LaTeX as a revision before it is replaced
+pub fn push_revision(item: &mut HistoryItem, reason: String) {
+    let revisions = item.latex_revisions.get_or_insert_with(Vec::new);
+    revisions.push(LatexRevision {
+        latex: item.latex.clone(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        reason,
+    });
+    if revisions.len() > MAX_LATEX_REVISIONS {
+        let excess = revisions.len() - MAX_LATEX_REVISIONS;
+        revisions.drain(..excess);
+    }
+}
+
+// --- Tauri commands ---
+
+/// Applies the correction of verification issue `issue_index` to the item's LaTeX,
+/// records the previous LaTeX as a revision and re-verifies against the stored image.
+#[tauri::command]
+pub async fn apply_fix(app_handle: AppHandle, id: String, issue_index: usize) -> Result<HistoryItem, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let history = fs_manager::read_history_async(&app_handle).await.map_err(|e| e.to_string())?;
+    let mut item = history
+        .into_iter()
+        .find(|i| i.id == id)
+        .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+    let issue = item
+        .verification
+        .as_ref()
+        .and_then(|v| v.issues.get(issue_index))
+        .cloned()
+        .ok_or_else(|| format!("Item '{}' has no verification issue #{}", id, issue_index))?;
+    let fragment = issue.latex_fragment.as_deref().filter(|f| !f.trim().is_empty());
+    let suggested_fix = issue.suggested_fix.as_deref();
+    if suggested_fix.is_none() && fragment.is_none() {
+        return Err("This issue does not include a concrete correction".to_string());
+    }
+
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
+    // 片段在 LaTeX 中唯一出现且有修正文本时直接替换，避免模型改动其他部分
+    let patched = match (fragment, suggested_fix) {
+        (Some(fragment), Some(fix)) if item.latex.matches(fragment).count() == 1 => item.latex.replacen(fragment, fix, 1),
+        _ => {
+            let prompt = prompts::get_fix_latex_prompt(&issue.message, fragment, suggested_fix);
+            client.fix_latex(&prompt, &item.latex).await.map_err(|e| e.to_string())?
+        }
+    };
+    if patched.trim().is_empty() || patched == item.latex {
+        return Err("The correction did not change the LaTeX".to_string());
+    }
+
+    push_revision(&mut item, format!("fix: {}", issue.message));
+    item.latex = patched;
+    item.analysis = latex_lint::append(item.analysis, &item.latex, &config.language);
+
+    // 用原图重新核查；没有图片（如文本识别生成的条目）时清除已过期的核查结果
+    match bulk_ops::load_item_image(&app_handle, &item)? {
+        Some(image) => {
+            let verification_prompt = prompts::get_verification_prompt(config.stage_prompt(prompts::PromptType::Verification), &config.language);
+            let (result, verification) = match client.verify_latex_against_image(&verification_prompt, &item.latex, &image).await {
+                Ok(v) => (crate::compute_verification_result_from_struct(&v), Some(v)),
+                Err(_) => (crate::data_models::VerificationResult { confidence_score: 0, verification_report: "验证失败".to_string() }, None),
+            };
+            item.confidence_score = result.confidence_score;
+            item.verification_report = Some(result.verification_report);
+            item.verification = verification;
+        }
+        None => {
+            item.verification = None;
+            item.verification_report = None;
+        }
+    }
+
+    // 重新读取历史再写回，保留等待期间对其他条目的修改
+    pipeline::update_history(&app_handle, |history| {
+        let slot = history
+            .iter_mut()
+            .find(|i| i.id == id)
+            .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+        *slot = item.clone();
+        Ok(())
+    })
+    .await?;
+    Ok(item)
+}
@@ -1,21 +1,95 @@
-use crate::data_models::{Config, HistoryItem};
+use crate::data_models::{Config, HistoryItem, ImageStorageConfig};
+use crate::{history_changes, history_schema, image_storage};
 use anyhow::Context;
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 use tauri::AppHandle;
 
 const CONFIG_FILENAME: &str = "config.json";
 const HISTORY_FILENAME: &str = "history.json";
+const QUARANTINE_FILENAME: &str = "history_quarantine.json";
 const PICTURES_DIRNAME: &str = "pictures";
+const PORTABLE_MARKER_FILENAME: &str = "portable";
+const PORTABLE_DATA_DIRNAME: &str = "data";
+const DATA_DIR_ARG: &str = "--data-dir";
+/// 延迟写入的合并窗口：窗口内的多次修改只写一次 history.json
+const WRITE_BEHIND_DELAY: Duration = Duration::from_millis(1500);
 
-/// Gets the path to the specified data file within the app's data directory.
-/// Ensures the directory exists.
-pub fn get_data_file_path(app_handle: &AppHandle, filename: &str) -> Result<PathBuf, anyhow::Error> {
-    let app_data_dir = app_handle
-        .path_resolver()
-        .app_data_dir()
-        .ok_or_else(|| anyhow::anyhow!("Failed to resolve app data directory."))?;
+static DATA_DIR_OVERRIDE: OnceLock<Option<PathBuf>> = OnceLock::new();
+/// 尚未落盘的历史（延迟写入或正在写入）；存在时 read_history 直接返回它
+static PENDING_HISTORY: Mutex<Option<PendingHistory>> = Mutex::new(None);
+/// 每次更新内存中的历史都分配新的代号，写盘完成后仅在代号未变时清除
+static PENDING_GENERATION: AtomicU64 = AtomicU64::new(0);
+/// 串行化 history.json 的写入，保证延迟写入不会覆盖之后的立即写入
+static HISTORY_WRITE_LOCK: Mutex<()> = Mutex::new(());
+static FLUSH_SCHEDULED: AtomicBool = AtomicBool::new(false);
+
+struct PendingHistory {
+    generation: u64,
+    items: Vec<HistoryItem>,
+}
+
+/// Detects a data directory override, in order of precedence:
+/// 1. `--data-dir <path>` / `--data-dir=<path>` on the command line
+/// 2. a `portable` marker file next to the executable (uses `<exe dir>/data`)
+///
+/// Relative paths are resolved against the executable's directory so a portable
+/// install keeps working regardless of the current working directory.
+/// The result is computed once and cached for the lifetime of the process.
+pub fn data_dir_override() -> Option<&'static Path> {
+    DATA_DIR_OVERRIDE
+        .get_or_init(|| {
+            let exe_dir = std::env::current_exe()
+                .ok()
+                .and_then(|p| p.parent().map(|d| d.to_path_buf()));
+
+            let mut args = std::env::args().skip(1);
+            let mut from_arg: Option<String> = None;
+            while let Some(arg) = args.next() {
+                if arg == DATA_DIR_ARG {
+                    from_arg = args.next();
+                } else if let Some(v) = arg.strip_prefix(&format!("{}=", DATA_DIR_ARG)) {
+                    from_arg = Some(v.to_string());
+                }
+            }
+
+            if let Some(raw) = from_arg.filter(|v| !v.trim().is_empty()) {
+                let path = PathBuf::from(raw.trim());
+                return Some(match (&exe_dir, path.is_relative()) {
+                    (Some(dir), true) => dir.join(path),
+                    _ => path,
+                });
+            }
+
+            let exe_dir = exe_dir?;
+            if exe_dir.join(PORTABLE_MARKER_FILENAME).is_file() {
+                Some(exe_dir.join(PORTABLE_DATA_DIRNAME))
+            } else {
+                None
+            }
+        })
+        .as_deref()
+}
+
+/// Returns true when the app runs in portable mode / with an explicit data dir.
+pub fn is_portable() -> bool {
+    data_dir_override().is_some()
+}
+
+/// Resolves the app data directory (honoring the portable override) and ensures it exists.
+pub fn app_data_dir(app_handle: &AppHandle) -> Result<PathBuf, anyhow::Error> {
+    let app_data_dir = match data_dir_override() {
+        Some(dir) => dir.to_path_buf(),
+        None => app_handle
+            .path_resolver()
+            .app_data_dir()
+            .ok_or_else(|| anyhow::anyhow!("Failed to resolve app data directory."))?,
+    };
 
     if !app_data_dir.exists() {
         fs::create_dir_all(&app_data_dir).context(format!(
@@ -24,22 +98,18 @@ pub fn get_data_file_path(app_handle: &AppHandle, filename: &str) -> Result<Path
         ))?;
     }
 
-    Ok(app_data_dir.join(filename))
+    Ok(app_data_dir)
+}
+
+/// Gets the path to the specified data file within the app's data directory.
+/// Ensures the directory exists.
+pub fn get_data_file_path(app_handle: &AppHandle, filename: &str) -> Result<PathBuf, anyhow::Error> {
+    Ok(app_data_dir(app_handle)?.join(filename))
 }
 
 /// Ensures and returns the pictures directory inside app data dir
 pub fn ensure_pictures_dir(app_handle: &AppHandle) -> Result<PathBuf, anyhow::Error> {
-    let base = app_handle
-        .path_resolver()
-        .app_data_dir()
-        .ok_or_else(|| anyhow::anyhow!("Failed to resolve app data directory."))?;
-
-    if !base.exists() {
-        fs::create_dir_all(&base).context(format!(
-            "Failed to create app data directory at {:?}",
-            base
-        ))?;
-    }
+    let base = app_data_dir(app_handle)?;
 
     let pictures_dir = base.join(PICTURES_DIRNAME);
     if !pictures_dir.exists() {
@@ -51,20 +121,94 @@ pub fn ensure_pictures_dir(app_handle: &AppHandle) -> Result<PathBuf, anyhow::Er
     Ok(pictures_dir)
 }
 
-/// Saves PNG bytes to the pictures directory with the given stem (without extension)
-pub fn save_png_to_pictures(
+/// Saves a capture (PNG, JPEG or WebP) to the pictures directory, re-encoded according to the
+/// configured storage format. Files are content-addressed (`<sha256>.<ext>`), so identical images
+/// are stored once and shared by every history item that references them.
+pub fn save_image_to_pictures(
     app_handle: &AppHandle,
-    file_stem: &str,
-    png_bytes: &[u8],
+    storage: &ImageStorageConfig,
+    image_bytes: &[u8],
 ) -> Result<PathBuf, anyhow::Error> {
+    let (bytes, extension) = image_storage::encode(storage, image_bytes)?;
+    store_content_addressed(app_handle, &bytes, extension)
+}
+
+/// Saves PNG bytes as-is (no re-encoding) to the pictures directory, content-addressed like
+/// [`save_image_to_pictures`]. Used for region captures before they are recognized.
+pub fn save_png_to_pictures(app_handle: &AppHandle, png_bytes: &[u8]) -> Result<PathBuf, anyhow::Error> {
+    store_content_addressed(app_handle, png_bytes, "png")
+}
+
+/// Writes already-encoded image bytes under their content hash (no-op if the file exists)
+pub fn store_content_addressed(app_handle: &AppHandle, bytes: &[u8], extension: &str) -> Result<PathBuf, anyhow::Error> {
     let dir = ensure_pictures_dir(app_handle)?;
-    let path = dir.join(format!("{}.png", file_stem));
-    let file = File::create(&path).context("Failed to create image file")?;
-    let mut writer = BufWriter::new(file);
-    writer.write_all(png_bytes).context("Failed to write image bytes")?;
+    let path = dir.join(format!("{}.{}", image_storage::content_hash(bytes), extension));
+    if path.is_file() {
+        return Ok(path);
+    }
+    // 先写临时文件再改名，避免中断时留下与哈希不符的残缺文件
+    let tmp = path.with_extension(format!("{}.tmp", extension));
+    {
+        let file = File::create(&tmp).context("Failed to create image file")?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(bytes).context("Failed to write image bytes")?;
+        writer.flush().context("Failed to write image bytes")?;
+    }
+    fs::rename(&tmp, &path).context("Failed to move image into place")?;
     Ok(path)
 }
 
+/// Number of history items referencing each image file (by resolved path)
+pub fn image_ref_counts(app_handle: &AppHandle, history: &[HistoryItem]) -> HashMap<PathBuf, usize> {
+    let mut counts = HashMap::new();
+    for item in history.iter().filter(|i| !i.original_image.is_empty()) {
+        *counts.entry(resolve_image_path(app_handle, &item.original_image)).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Deletes a stored image once no item in `history` references it any more.
+/// Only files inside the pictures directory are ever removed.
+pub fn release_image(app_handle: &AppHandle, stored: &str, history: &[HistoryItem]) {
+    if stored.is_empty() {
+        return;
+    }
+    let path = resolve_image_path(app_handle, stored);
+    let Ok(pictures_dir) = ensure_pictures_dir(app_handle) else { return };
+    if !path.starts_with(&pictures_dir) || image_ref_counts(app_handle, history).contains_key(&path) {
+        return;
+    }
+    let _ = fs::remove_file(&path);
+}
+
+/// Converts an image path into the form stored in history: relative to the data dir
+/// (with forward slashes, e.g. `pictures/x.png`) when inside it, otherwise unchanged.
+pub fn to_stored_image_path(app_handle: &AppHandle, path: &Path) -> String {
+    app_data_dir(app_handle)
+        .ok()
+        .and_then(|base| path.strip_prefix(base).ok().map(|rel| rel.to_path_buf()))
+        .map(|rel| {
+            rel.components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
+
+/// Resolves a stored image path: relative paths are joined onto the data dir,
+/// absolute paths (legacy or external files) are returned as-is.
+pub fn resolve_image_path(app_handle: &AppHandle, stored: &str) -> PathBuf {
+    let path = Path::new(stored);
+    if path.is_absolute() || stored.is_empty() {
+        return path.to_path_buf();
+    }
+    match app_data_dir(app_handle) {
+        Ok(base) => base.join(path),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
 /// Reads the application configuration from `config.json`.
 ///
 /// If the file does not exist or cannot be deserialized (e.g., missing new fields),
@@ -77,8 +221,16 @@ pub fn read_config(app_handle: &AppHandle) -> Result<Config, anyhow::Error> {
             let reader = BufReader::new(file);
             match serde_json::from_reader::<_, Config>(reader) {
                 Ok(mut config) => {
-                    // 迁移旧提示词为新版默认（仅在检测到旧文案或为空时）
-                    if config.migrate_prompts() {
+                    // 迁移旧提示词为新版默认（仅在检测到旧文案或为空时），以及旧版单一快捷键
+                    let previous = config.clone();
+                    if config.migrate_prompts() | config.migrate_shortcuts() {
+                        // 版本升级会覆盖用户提示词，覆盖前先保存旧文本
+                        if previous.prompts_version != config.prompts_version {
+                            if let Err(_e) = crate::prompt_history::record_revision(app_handle, &previous, "migration") {
+                                #[cfg(debug_assertions)]
+                                eprintln!("Failed to save prompt revision: {}", _e);
+                            }
+                        }
                         let _ = write_config(app_handle, &config);
                     }
                     Ok(config)
@@ -120,19 +272,33 @@ pub fn write_config(app_handle: &AppHandle, config: &Config) -> Result<(), anyho
 
 /// Reads the recognition history from `history.json`.
 ///
-/// If the file does not exist, it returns an empty vector.
+/// If the file does not exist, it returns an empty vector. Older schema versions are
+/// migrated and written back (the original file is kept as `history.v{N}.bak.json`).
 pub fn read_history(app_handle: &AppHandle) -> Result<Vec<HistoryItem>, anyhow::Error> {
+    if let Some(pending) = PENDING_HISTORY.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return Ok(pending.items.clone());
+    }
     let history_path = get_data_file_path(app_handle, HISTORY_FILENAME)?;
 
-    match File::open(history_path) {
-        Ok(file) => {
-            let reader = BufReader::new(file);
-            let history = serde_json::from_reader(reader)
-                .context("Failed to deserialize history.json. Returning empty list.")?;
-            Ok(history)
+    match fs::read(&history_path) {
+        Ok(bytes) => {
+            let parsed = history_schema::parse(&bytes, &history_schema::MigrationContext::for_app(app_handle)).context("Failed to read history.json")?;
+            if parsed.needs_rewrite() {
+                // 升级前保留原文件，无法解析的条目单独转存
+                let backup_name = format!("history.v{}.bak.json", parsed.from_version);
+                fs::write(get_data_file_path(app_handle, &backup_name)?, &bytes)
+                    .context("Failed to back up history.json before migration")?;
+                if !parsed.rejected.is_empty() {
+                    append_quarantine(app_handle, parsed.rejected)?;
+                }
+                write_history(app_handle, &parsed.items)?;
+            }
+            history_changes::observe(&parsed.items);
+            Ok(parsed.items)
         }
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
             // File doesn't exist, return empty vector
+            history_changes::observe(&[]);
             Ok(Vec::new())
         }
         Err(e) => {
@@ -142,13 +308,95 @@ pub fn read_history(app_handle: &AppHandle) -> Result<Vec<HistoryItem>, anyhow::
     }
 }
 
-/// Writes the recognition history to `history.json`.
+/// Appends unreadable raw history entries to `history_quarantine.json` for manual recovery
+fn append_quarantine(app_handle: &AppHandle, mut rejected: Vec<serde_json::Value>) -> Result<(), anyhow::Error> {
+    let path = get_data_file_path(app_handle, QUARANTINE_FILENAME)?;
+    let mut existing: Vec<serde_json::Value> = match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    existing.append(&mut rejected);
+    fs::write(&path, serde_json::to_vec_pretty(&existing)?).context("Failed to write history_quarantine.json")
+}
+
+/// Writes the recognition history to `history.json` in the current schema version.
+/// Any pending deferred write is superseded by this one.
 pub fn write_history(app_handle: &AppHandle, history: &[HistoryItem]) -> Result<(), anyhow::Error> {
+    let _guard = HISTORY_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    history_changes::record(history);
+    // 写盘期间读者看到的是这次写入的内容，而不是旧文件或之前的延迟写入
+    let generation = set_pending(history.to_vec());
+    let result = write_history_file(app_handle, history);
+    clear_pending(generation);
+    result
+}
+
+/// Replaces the in-memory history and returns its generation
+fn set_pending(items: Vec<HistoryItem>) -> u64 {
+    let generation = PENDING_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    *PENDING_HISTORY.lock().unwrap_or_else(|e| e.into_inner()) = Some(PendingHistory { generation, items });
+    generation
+}
+
+/// Drops the in-memory history once it is on disk, unless a newer update replaced it meanwhile
+fn clear_pending(generation: u64) {
+    let mut pending = PENDING_HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    if pending.as_ref().is_some_and(|p| p.generation == generation) {
+        *pending = None;
+    }
+}
+
+fn write_history_file(app_handle: &AppHandle, history: &[HistoryItem]) -> Result<(), anyhow::Error> {
     let history_path = get_data_file_path(app_handle, HISTORY_FILENAME)?;
-    let file = File::create(history_path).context("Failed to create or truncate history.json")?;
-    let writer = BufWriter::new(file);
-    serde_json::to_writer_pretty(writer, history)
-        .context("Failed to serialize and write history")?;
+    let bytes = history_schema::to_json_bytes(history)?;
+    // 先写临时文件再改名，读者不会看到截断或写了一半的 history.json
+    let tmp = history_path.with_extension("json.tmp");
+    {
+        let file = File::create(&tmp).context("Failed to create temporary history file")?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&bytes).context("Failed to write history")?;
+        writer.flush().context("Failed to write history")?;
+    }
+    fs::rename(&tmp, &history_path).context("Failed to move history.json into place")?;
+    Ok(())
+}
+
+/// Keeps the history in memory and writes it to disk after a short delay, so a burst of small
+/// edits (title changes, favorite toggles) results in a single rewrite of `history.json`.
+/// Reads see the pending state immediately; [`flush_history`] forces the write (e.g. on quit).
+pub fn write_history_deferred(app_handle: &AppHandle, history: Vec<HistoryItem>) {
+    history_changes::record(&history);
+    set_pending(history);
+    if FLUSH_SCHEDULED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(WRITE_BEHIND_DELAY).await;
+        FLUSH_SCHEDULED.store(false, Ordering::SeqCst);
+        let result = tauri::async_runtime::spawn_blocking(move || flush_history(&app_handle)).await;
+        if let Ok(Err(_e)) = result {
+            #[cfg(debug_assertions)]
+            eprintln!("Deferred history write failed: {}", _e);
+        }
+    });
+}
+
+/// Writes a pending deferred history update to disk, if there is one.
+/// The pending copy stays visible to readers until the write has succeeded.
+pub fn flush_history(app_handle: &AppHandle) -> Result<(), anyhow::Error> {
+    let _guard = HISTORY_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let Some((generation, history)) = PENDING_HISTORY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|p| (p.generation, p.items.clone()))
+    else {
+        return Ok(());
+    };
+    // 写入失败时保留内存中的版本，等待下一次写入
+    write_history_file(app_handle, &history)?;
+    clear_pending(generation);
     Ok(())
 }
 
@@ -156,3 +404,37 @@ pub fn write_history(app_handle: &AppHandle, history: &[HistoryItem]) -> Result<
 pub fn get_history_path(app_handle: &AppHandle) -> Result<PathBuf, anyhow::Error> {
     get_data_file_path(app_handle, HISTORY_FILENAME)
 }
+
+// --- 异步版本：在 tokio 的阻塞线程池中执行读写（含序列化与图片编码），
+// 避免慢速磁盘（如网络主目录）上的大文件读写阻塞异步命令所在的运行时 ---
+
+/// Runs blocking file work on the blocking thread pool, propagating both its error and a failed join
+pub(crate) async fn run_blocking<T, F>(work: F) -> Result<T, anyhow::Error>
+where
+    F: FnOnce() -> Result<T, anyhow::Error> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work).await.context("File IO task did not complete")?
+}
+
+/// Async variant of [`read_history`]
+pub async fn read_history_async(app_handle: &AppHandle) -> Result<Vec<HistoryItem>, anyhow::Error> {
+    let app_handle = app_handle.clone();
+    run_blocking(move || read_history(&app_handle)).await
+}
+
+/// Async variant of [`write_history`]; takes the history by value so it can move to the IO thread
+pub async fn write_history_async(app_handle: &AppHandle, history: Vec<HistoryItem>) -> Result<(), anyhow::Error> {
+    let app_handle = app_handle.clone();
+    run_blocking(move || write_history(&app_handle, &history)).await
+}
+
+/// Async variant of [`save_image_to_pictures`]
+pub async fn save_image_to_pictures_async(
+    app_handle: &AppHandle,
+    storage: &ImageStorageConfig,
+    image_bytes: &[u8],
+) -> Result<PathBuf, anyhow::Error> {
+    let (app_handle, storage, image_bytes) = (app_handle.clone(), storage.clone(), image_bytes.to_vec());
+    run_blocking(move || save_image_to_pictures(&app_handle, &storage, &image_bytes)).await
+}
@@ -0,0 +1,82 @@
+// 手写模式：识别手写公式时改用专门的提示词（prompts::get_handwriting_latex_prompt），
+// 并按设置对发给模型的图片做预处理——灰度化后按 Otsu 阈值二值化（去掉纸张底色、阴影和浅色格线），
+// 可选把笔画加粗一个像素。预处理只影响发给模型的图片，历史中保存的仍是原图。
+
+use crate::data_models::HandwritingConfig;
+use anyhow::Result;
+use image::{GrayImage, Luma};
+
+/// Otsu's threshold: the gray level that maximizes the between-class variance of the histogram
+fn otsu_threshold(img: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in img.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+    let total = u64::from(img.width()) * u64::from(img.height());
+    let sum_all: f64 = histogram.iter().enumerate().map(|(level, &count)| level as f64 * count as f64).sum();
+
+    let (mut weight_bg, mut sum_bg) = (0u64, 0f64);
+    let (mut best_level, mut best_variance) = (0u8, 0f64);
+    for (level, &count) in histogram.iter().enumerate() {
+        weight_bg += count;
+        if weight_bg == 0 {
+            continue;
+        }
+        let weight_fg = total - weight_bg;
+        if weight_fg == 0 {
+            break;
+        }
+        sum_bg += level as f64 * count as f64;
+        let mean_bg = sum_bg / weight_bg as f64;
+        let mean_fg = (sum_all - sum_bg) / weight_fg as f64;
+        let variance = weight_bg as f64 * weight_fg as f64 * (mean_bg - mean_fg).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best_level = level as u8;
+        }
+    }
+    best_level
+}
+
+/// Black strokes on a white background: pixels at or below the threshold become black
+fn binarize(img: &GrayImage) -> GrayImage {
+    let threshold = otsu_threshold(img);
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        Luma([if img.get_pixel(x, y)[0] <= threshold { 0 } else { 255 }])
+    })
+}
+
+/// Grows dark strokes by one pixel (3x3 minimum filter)
+fn thicken(img: &GrayImage) -> GrayImage {
+    let (width, height) = img.dimensions();
+    GrayImage::from_fn(width, height, |x, y| {
+        let mut darkest = 255u8;
+        for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                darkest = darkest.min(img.get_pixel(nx, ny)[0]);
+            }
+        }
+        Luma([darkest])
+    })
+}
+
+/// Preprocesses a handwriting image for the model and returns it as PNG,
+/// or `None` when every preprocessing step is turned off
+pub fn preprocess(image_bytes: &[u8], settings: &HandwritingConfig) -> Result<Option<Vec<u8>>> {
+    if !settings.binarize && !settings.thicken {
+        return Ok(None);
+    }
+    let mut gray = image::load_from_memory(image_bytes)?.to_luma8();
+    if gray.width() == 0 || gray.height() == 0 {
+        return Ok(None);
+    }
+    if settings.binarize {
+        gray = binarize(&gray);
+    }
+    if settings.thicken {
+        gray = thicken(&gray);
+    }
+    let mut png = Vec::new();
+    gray.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(Some(png))
+}
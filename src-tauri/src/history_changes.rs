@@ -0,0 +1,154 @@
+// 历史增量：每次写入历史时与上一次的状态逐条比较（按内容指纹），把新增/修改/删除记入内存中的变更日志，
+// 前端通过 get_history_changes(since) 只取自某个时间点以来的变化，不必每次修改后重新拉取整个列表。
+// 日志只保存在内存中：since 早于日志起点（应用刚启动或日志已截断）时返回 fullRefresh，由前端整体刷新。
+
+use crate::data_models::HistoryItem;
+use crate::fs_manager;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+/// 内存中保留的最大变更条数，超出时丢弃最旧的
+const MAX_JOURNAL_ENTRIES: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChangeKind {
+    Added,
+    Updated,
+    Deleted,
+}
+
+struct Change {
+    id: String,
+    kind: ChangeKind,
+    /// 毫秒时间戳，严格递增
+    at: i64,
+}
+
+struct Journal {
+    /// 条目 id -> 内容指纹（上一次已知的历史状态）
+    fingerprints: HashMap<String, u64>,
+    changes: VecDeque<Change>,
+    /// 早于此时间的变更已不可知
+    start: i64,
+    last_at: i64,
+}
+
+static JOURNAL: Mutex<Option<Journal>> = Mutex::new(None);
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryChanges {
+    /// 下次请求时作为 since 传入
+    pub cursor: i64,
+    /// 无法提供增量，需重新获取完整历史
+    pub full_refresh: bool,
+    /// 新增的条目（按历史中的顺序，最新在前）
+    pub added: Vec<HistoryItem>,
+    pub updated: Vec<HistoryItem>,
+    pub deleted: Vec<String>,
+}
+
+fn fingerprint(item: &HistoryItem) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_vec(item).unwrap_or_default().hash(&mut hasher);
+    hasher.finish()
+}
+
+fn fingerprints(history: &[HistoryItem]) -> HashMap<String, u64> {
+    history.iter().map(|item| (item.id.clone(), fingerprint(item))).collect()
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Journal>> {
+    JOURNAL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Establishes the baseline the first time the history is read
+pub fn observe(history: &[HistoryItem]) {
+    let mut journal = lock();
+    if journal.is_none() {
+        let now = chrono::Utc::now().timestamp_millis();
+        *journal = Some(Journal { fingerprints: fingerprints(history), changes: VecDeque::new(), start: now, last_at: now });
+    }
+}
+
+/// Records the differences between the last known history and the one being written
+pub fn record(history: &[HistoryItem]) {
+    let mut guard = lock();
+    let Some(journal) = guard.as_mut() else {
+        drop(guard);
+        observe(history);
+        return;
+    };
+    let current = fingerprints(history);
+    let mut changes: Vec<(String, ChangeKind)> = Vec::new();
+    for item in history {
+        match journal.fingerprints.get(&item.id) {
+            None => changes.push((item.id.clone(), ChangeKind::Added)),
+            Some(previous) if *previous != current[&item.id] => changes.push((item.id.clone(), ChangeKind::Updated)),
+            Some(_) => {}
+        }
+    }
+    for id in journal.fingerprints.keys().filter(|id| !current.contains_key(*id)) {
+        changes.push((id.clone(), ChangeKind::Deleted));
+    }
+    journal.fingerprints = current;
+
+    for (id, kind) in changes {
+        let at = chrono::Utc::now().timestamp_millis().max(journal.last_at + 1);
+        journal.last_at = at;
+        journal.changes.push_back(Change { id, kind, at });
+    }
+    while journal.changes.len() > MAX_JOURNAL_ENTRIES {
+        if let Some(dropped) = journal.changes.pop_front() {
+            journal.start = dropped.at;
+        }
+    }
+}
+
+// --- Tauri commands ---
+
+/// Items added, updated or deleted after `since` (a cursor from a previous call, in milliseconds).
+/// Without `since`, or when the changes are no longer known, `fullRefresh` is set.
+#[tauri::command]
+pub fn get_history_changes(app_handle: AppHandle, since: Option<i64>) -> Result<HistoryChanges, String> {
+    let history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
+    let guard = lock();
+    let Some(journal) = guard.as_ref() else {
+        return Err("History change tracking is not initialized".to_string());
+    };
+    let full = |cursor| HistoryChanges { cursor, full_refresh: true, added: Vec::new(), updated: Vec::new(), deleted: Vec::new() };
+    let Some(since) = since else {
+        return Ok(full(journal.last_at));
+    };
+    if since < journal.start {
+        return Ok(full(journal.last_at));
+    }
+
+    // 同一条目的多次变更合并：最后为删除则视为删除，期间曾新增则视为新增，否则为修改
+    let mut latest: HashMap<&str, ChangeKind> = HashMap::new();
+    let mut added: HashSet<&str> = HashSet::new();
+    for change in journal.changes.iter().filter(|c| c.at > since) {
+        latest.insert(&change.id, change.kind);
+        if change.kind == ChangeKind::Added {
+            added.insert(&change.id);
+        }
+    }
+    let mut changes = HistoryChanges { cursor: journal.last_at, full_refresh: false, added: Vec::new(), updated: Vec::new(), deleted: Vec::new() };
+    for item in &history {
+        match latest.get(item.id.as_str()) {
+            Some(ChangeKind::Deleted) | None => {}
+            Some(_) if added.contains(item.id.as_str()) => changes.added.push(item.clone()),
+            Some(_) => changes.updated.push(item.clone()),
+        }
+    }
+    changes.deleted = latest
+        .iter()
+        .filter(|(_, kind)| **kind == ChangeKind::Deleted)
+        .map(|(id, _)| id.to_string())
+        .collect();
+    Ok(changes)
+}
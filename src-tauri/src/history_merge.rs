@@ -0,0 +1,230 @@
+// 多设备历史合并：同 ID 但 LaTeX/标题不同的条目记为冲突，由用户选择保留哪一方
+
+use crate::data_models::HistoryItem;
+use crate::{fs_manager, history_schema, pipeline};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tauri::AppHandle;
+use uuid::Uuid;
+
+const CONFLICTS_FILENAME: &str = "history_conflicts.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryConflict {
+    pub id: String,
+    pub local: HistoryItem,
+    pub remote: HistoryItem,
+    pub detected_at: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    KeepLocal,
+    KeepRemote,
+    KeepBoth,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeSummary {
+    pub added: usize,
+    pub unchanged: usize,
+    pub conflicts: usize,
+}
+
+/// Result of merging a remote history into the local one
+pub struct MergeOutcome {
+    pub merged: Vec<HistoryItem>,
+    pub conflicts: Vec<HistoryConflict>,
+    pub summary: MergeSummary,
+}
+
+fn is_conflicting(local: &HistoryItem, remote: &HistoryItem) -> bool {
+    local.latex != remote.latex || local.title != remote.title
+}
+
+/// Merges `remote` into `local` without overwriting anything:
+/// - remote-only items are added
+/// - identical items (same latex/title) are kept once, favorites are OR-ed
+/// - items modified on both sides stay local and are reported as conflicts
+pub fn merge_histories(local: Vec<HistoryItem>, remote: Vec<HistoryItem>) -> MergeOutcome {
+    let mut merged = local;
+    let index: HashMap<String, usize> = merged
+        .iter()
+        .enumerate()
+        .map(|(i, item)| (item.id.clone(), i))
+        .collect();
+
+    let detected_at = chrono::Utc::now().to_rfc3339();
+    let mut conflicts = Vec::new();
+    let mut summary = MergeSummary::default();
+
+    for remote_item in remote {
+        match index.get(&remote_item.id) {
+            None => {
+                merged.push(remote_item);
+                summary.added += 1;
+            }
+            Some(&i) => {
+                let local_item = &mut merged[i];
+                if is_conflicting(local_item, &remote_item) {
+                    conflicts.push(HistoryConflict {
+                        id: remote_item.id.clone(),
+                        local: local_item.clone(),
+                        remote: remote_item,
+                        detected_at: detected_at.clone(),
+                    });
+                    summary.conflicts += 1;
+                } else {
+                    local_item.is_favorite |= remote_item.is_favorite;
+                    summary.unchanged += 1;
+                }
+            }
+        }
+    }
+
+    // 保持“最新在前”的顺序
+    merged.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    MergeOutcome { merged, conflicts, summary }
+}
+
+fn read_conflicts(app_handle: &AppHandle) -> Result<Vec<HistoryConflict>> {
+    let path = fs_manager::get_data_file_path(app_handle, CONFLICTS_FILENAME)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).context("Failed to parse history_conflicts.json"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read history_conflicts.json")),
+    }
+}
+
+fn write_conflicts(app_handle: &AppHandle, conflicts: &[HistoryConflict]) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, CONFLICTS_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec_pretty(conflicts)?).context("Failed to write history_conflicts.json")
+}
+
+/// Merges `remote` into the stored history and records any new conflicts
+pub async fn merge_into_local(app_handle: &AppHandle, remote: Vec<HistoryItem>) -> Result<MergeSummary> {
+    // 读取与写回之间不能穿插识别结果的插入，否则新条目会丢失
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    let local = fs_manager::read_history_async(app_handle).await?;
+    let outcome = merge_histories(local, remote);
+    fs_manager::write_history_async(app_handle, outcome.merged).await?;
+
+    if !outcome.conflicts.is_empty() {
+        let mut pending = read_conflicts(app_handle)?;
+        // 同一 ID 的旧冲突以最新一次导入为准
+        pending.retain(|c| !outcome.conflicts.iter().any(|n| n.id == c.id));
+        pending.extend(outcome.conflicts);
+        write_conflicts(app_handle, &pending)?;
+    }
+    Ok(outcome.summary)
+}
+
+/// Resolves a stored relative image path under `base`. Absolute paths and paths that leave
+/// `base` (through `..` or symlinks) are rejected.
+fn file_under(base: &Path, stored: &str) -> Option<PathBuf> {
+    let path = Path::new(stored);
+    // 只接受普通的相对路径，导入文件由他人提供，不能借此读取任意本地文件
+    if path.is_absolute() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    let base = base.canonicalize().ok()?;
+    let resolved = base.join(path).canonicalize().ok()?;
+    (resolved.starts_with(&base) && resolved.is_file()).then_some(resolved)
+}
+
+/// Whether a stored path already points at an image in the local pictures directory
+fn is_local_picture(app_handle: &AppHandle, stored: &str) -> bool {
+    let (Ok(data_dir), Some(pictures_dir)) = (
+        fs_manager::app_data_dir(app_handle),
+        fs_manager::ensure_pictures_dir(app_handle).ok().and_then(|dir| dir.canonicalize().ok()),
+    ) else {
+        return false;
+    };
+    file_under(&data_dir, stored).is_some_and(|path| path.starts_with(pictures_dir))
+}
+
+/// Copies the images of imported items (looked up under the imported file's directory, e.g. a
+/// copied data dir) into the local pictures directory and rewrites their paths; other images are
+/// cleared unless they already are local pictures
+fn localize_images(app_handle: &AppHandle, source_dir: Option<&Path>, items: &mut [HistoryItem]) {
+    for item in items.iter_mut().filter(|item| !item.original_image.is_empty()) {
+        let copied = source_dir
+            .and_then(|dir| file_under(dir, &item.original_image))
+            .and_then(|path| {
+                let bytes = std::fs::read(&path).ok()?;
+                let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_else(|| "png".to_string());
+                fs_manager::store_content_addressed(app_handle, &bytes, &extension).ok()
+            });
+        item.original_image = match copied {
+            Some(stored) => fs_manager::to_stored_image_path(app_handle, &stored),
+            None if is_local_picture(app_handle, &item.original_image) => continue,
+            None => String::new(),
+        };
+    }
+}
+
+// --- Tauri commands ---
+
+/// Imports a history JSON file (e.g. history.json from another device) using conflict-aware merge.
+/// Images referenced by the file are copied into the local pictures directory.
+#[tauri::command]
+pub async fn import_history(app_handle: AppHandle, path: String) -> Result<MergeSummary, String> {
+    let remote = {
+        let app_handle = app_handle.clone();
+        fs_manager::run_blocking(move || {
+            let bytes = std::fs::read(&path)?;
+            let mut remote = history_schema::parse(&bytes, &history_schema::MigrationContext::for_app(&app_handle))
+                .map_err(|e| anyhow::anyhow!("Invalid history file: {}", e))?
+                .items;
+            localize_images(&app_handle, Path::new(&path).parent(), &mut remote);
+            Ok(remote)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    };
+    merge_into_local(&app_handle, remote).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_history_conflicts(app_handle: AppHandle) -> Result<Vec<HistoryConflict>, String> {
+    read_conflicts(&app_handle).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn resolve_history_conflict(
+    app_handle: AppHandle,
+    id: String,
+    resolution: ConflictResolution,
+) -> Result<(), String> {
+    let mut pending = read_conflicts(&app_handle).map_err(|e| e.to_string())?;
+    let pos = pending
+        .iter()
+        .position(|c| c.id == id)
+        .ok_or_else(|| format!("No pending conflict for item '{}'", id))?;
+    let conflict = pending.remove(pos);
+
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    let mut history = fs_manager::read_history_async(&app_handle).await.map_err(|e| e.to_string())?;
+    let local_pos = history.iter().position(|item| item.id == id);
+    match resolution {
+        ConflictResolution::KeepLocal => {}
+        ConflictResolution::KeepRemote => match local_pos {
+            Some(i) => history[i] = conflict.remote,
+            None => history.insert(0, conflict.remote),
+        },
+        ConflictResolution::KeepBoth => {
+            let mut copy = conflict.remote;
+            copy.id = Uuid::new_v4().to_string();
+            let at = local_pos.map(|i| i + 1).unwrap_or(0);
+            history.insert(at, copy);
+        }
+    }
+
+    fs_manager::write_history_async(&app_handle, history).await.map_err(|e| e.to_string())?;
+    write_conflicts(&app_handle, &pending).map_err(|e| e.to_string())
+}
@@ -0,0 +1,146 @@
+// 历史记录存储的版本号与迁移框架。
+// history.json 格式：{ "schemaVersion": N, "items": [...] }；早期版本为裸数组，视为版本 0。
+// 读取时按顺序执行 MIGRATIONS 中高于文件版本的迁移（作用于原始 JSON），再逐条反序列化；
+// 无法解析的单个条目转存到 history_quarantine.json，而不是让整个文件读取失败。
+//
+// 版本记录：
+//   v1 引入带版本号的外层结构
+//   v2 originalImage 改为相对数据目录的路径（如 pictures/xxx.png）
+
+use crate::data_models::HistoryItem;
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// 迁移所需的本机环境信息
+pub struct MigrationContext {
+    /// 本机数据目录；无法确定时跳过与路径相关的迁移
+    pub data_dir: Option<PathBuf>,
+}
+
+impl MigrationContext {
+    pub fn for_app(app_handle: &AppHandle) -> Self {
+        MigrationContext { data_dir: crate::fs_manager::app_data_dir(app_handle).ok() }
+    }
+}
+
+/// 单步迁移：把原始条目数组从 (目标版本 - 1) 升级到目标版本
+type Migration = fn(&mut Vec<Value>, &MigrationContext);
+
+/// (目标版本, 迁移函数)，按版本升序排列
+const MIGRATIONS: &[(u32, Migration)] = &[(1, migrate_v1_envelope), (2, migrate_v2_relative_images)];
+
+/// v1：引入带版本号的外层结构；条目本身不变
+fn migrate_v1_envelope(_items: &mut Vec<Value>, _ctx: &MigrationContext) {}
+
+/// v2：数据目录内的绝对图片路径改为相对路径；来自其他机器/旧数据目录的路径，
+/// 若本机 pictures 目录中存在同名文件，也改为指向本机文件
+fn migrate_v2_relative_images(items: &mut Vec<Value>, ctx: &MigrationContext) {
+    let Some(data_dir) = ctx.data_dir.as_deref() else {
+        return;
+    };
+    for item in items.iter_mut() {
+        let Some(image) = item.get_mut("original_image") else { continue };
+        let Some(current) = image.as_str() else { continue };
+        if let Some(relative) = relativize_image_path(data_dir, current) {
+            *image = Value::String(relative);
+        }
+    }
+}
+
+fn relativize_image_path(data_dir: &Path, stored: &str) -> Option<String> {
+    // 兼容 Windows 路径在其他平台上的解析
+    let normalized = stored.replace('\\', "/");
+    let path = Path::new(&normalized);
+    let looks_absolute = path.is_absolute() || normalized.get(1..3) == Some(":/");
+    if !looks_absolute {
+        return None;
+    }
+    let data_dir_normalized = data_dir.to_string_lossy().replace('\\', "/");
+    if let Some(rest) = normalized.strip_prefix(data_dir_normalized.trim_end_matches('/')) {
+        let rest = rest.trim_start_matches('/');
+        if !rest.is_empty() {
+            return Some(rest.to_string());
+        }
+    }
+    let mut parts = normalized.rsplit('/');
+    let name = parts.next()?;
+    if parts.next() == Some("pictures") && data_dir.join("pictures").join(name).is_file() {
+        return Some(format!("pictures/{}", name));
+    }
+    None
+}
+
+pub struct ParsedHistory {
+    pub items: Vec<HistoryItem>,
+    /// 文件中的原始版本号
+    pub from_version: u32,
+    /// 无法解析的原始条目
+    pub rejected: Vec<Value>,
+}
+
+impl ParsedHistory {
+    /// True when the file needs to be rewritten in the current format
+    pub fn needs_rewrite(&self) -> bool {
+        self.from_version < CURRENT_SCHEMA_VERSION || !self.rejected.is_empty()
+    }
+}
+
+/// Parses a history file in any known schema version and migrates it to the current one
+pub fn parse(bytes: &[u8], ctx: &MigrationContext) -> Result<ParsedHistory> {
+    let value: Value = serde_json::from_slice(bytes).context("Failed to parse history JSON")?;
+    let (from_version, mut raw_items) = match value {
+        Value::Array(items) => (0, items),
+        Value::Object(mut obj) => {
+            let version = obj
+                .get("schemaVersion")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow!("History file is missing schemaVersion"))? as u32;
+            let items = match obj.remove("items") {
+                Some(Value::Array(items)) => items,
+                _ => return Err(anyhow!("History file is missing the items array")),
+            };
+            (version, items)
+        }
+        _ => return Err(anyhow!("Unrecognized history file format")),
+    };
+    if from_version > CURRENT_SCHEMA_VERSION {
+        return Err(anyhow!(
+            "History schema version {} is newer than supported version {}; please update the app",
+            from_version,
+            CURRENT_SCHEMA_VERSION
+        ));
+    }
+
+    for (target, migrate) in MIGRATIONS {
+        if *target > from_version {
+            migrate(&mut raw_items, ctx);
+        }
+    }
+
+    let mut items = Vec::with_capacity(raw_items.len());
+    let mut rejected = Vec::new();
+    for raw in raw_items {
+        match serde_json::from_value::<HistoryItem>(raw.clone()) {
+            Ok(item) => items.push(item),
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("Skipping unreadable history item: {}", _e);
+                rejected.push(raw);
+            }
+        }
+    }
+    Ok(ParsedHistory { items, from_version, rejected })
+}
+
+/// Serializes history in the current schema version
+pub fn to_json_bytes(items: &[HistoryItem]) -> Result<Vec<u8>> {
+    serde_json::to_vec_pretty(&json!({
+        "schemaVersion": CURRENT_SCHEMA_VERSION,
+        "items": items,
+    }))
+    .context("Failed to serialize history")
+}
@@ -0,0 +1,239 @@
+// 历史图片存储格式：按 Config.imageStorage 把截图保存为 PNG（可选压缩级别）或无损 WebP，
+// 文件以内容哈希命名（相同图片只存一份，由引用它的历史条目共享）。
+// 并提供把已有图片批量转换为当前格式、以及按内容去重的命令，用于回收 pictures 目录的空间。
+
+use crate::data_models::{ImageStorageConfig, ImageStorageFormat, PngCompression};
+use crate::{fs_manager, pipeline};
+use anyhow::{Context, Result};
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::ImageEncoder;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+/// Encodes capture bytes for storage; returns the bytes and the file extension.
+/// With the default PNG setting, PNG, JPEG and WebP captures are kept as they are.
+pub fn encode(storage: &ImageStorageConfig, image_bytes: &[u8]) -> Result<(Vec<u8>, &'static str)> {
+    if storage.format == ImageStorageFormat::Png && storage.png_compression == PngCompression::Default {
+        match image::guess_format(image_bytes) {
+            Ok(image::ImageFormat::Png) => return Ok((image_bytes.to_vec(), "png")),
+            Ok(image::ImageFormat::Jpeg) => return Ok((image_bytes.to_vec(), "jpg")),
+            Ok(image::ImageFormat::WebP) => return Ok((image_bytes.to_vec(), "webp")),
+            _ => {}
+        }
+    }
+    let img = image::load_from_memory(image_bytes).context("Failed to decode captured image")?;
+    let rgba = img.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let mut out = Vec::new();
+    match storage.format {
+        ImageStorageFormat::Png => {
+            let compression = match storage.png_compression {
+                PngCompression::Fast => CompressionType::Fast,
+                PngCompression::Default => CompressionType::Default,
+                PngCompression::Best => CompressionType::Best,
+            };
+            PngEncoder::new_with_quality(&mut out, compression, FilterType::Adaptive)
+                .write_image(&rgba, width, height, image::ColorType::Rgba8)
+                .context("Failed to encode PNG")?;
+            Ok((out, "png"))
+        }
+        ImageStorageFormat::Webp => {
+            WebPEncoder::new_lossless(&mut out)
+                .write_image(&rgba, width, height, image::ColorType::Rgba8)
+                .context("Failed to encode WebP")?;
+            Ok((out, "webp"))
+        }
+    }
+}
+
+/// Hex SHA-256 of the stored bytes, used as the file name
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// MIME type of stored image bytes (PNG when unknown)
+pub fn mime_type(bytes: &[u8]) -> &'static str {
+    match image::guess_format(bytes) {
+        Ok(image::ImageFormat::Jpeg) => "image/jpeg",
+        Ok(image::ImageFormat::Gif) => "image/gif",
+        Ok(image::ImageFormat::WebP) => "image/webp",
+        Ok(image::ImageFormat::Bmp) => "image/bmp",
+        _ => "image/png",
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RecompressSummary {
+    pub converted: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+fn recompress_all(app_handle: &AppHandle) -> Result<RecompressSummary> {
+    let config = fs_manager::read_config(app_handle)?;
+    let pictures_dir = fs_manager::ensure_pictures_dir(app_handle)?;
+    let mut history = fs_manager::read_history(app_handle)?;
+    let mut summary = RecompressSummary::default();
+    // 同一文件可能被多个条目引用，只转换一次
+    let mut converted_paths: HashMap<String, String> = HashMap::new();
+    // 旧文件在新的历史写入之后才删除，中途出错时条目不会指向已删除的文件
+    let mut obsolete: Vec<PathBuf> = Vec::new();
+
+    for item in history.iter_mut() {
+        if let Some(new_path) = converted_paths.get(&item.original_image) {
+            item.original_image = new_path.clone();
+            continue;
+        }
+        let path = fs_manager::resolve_image_path(app_handle, &item.original_image);
+        // 只处理本应用 pictures 目录中的图片
+        if item.original_image.is_empty() || !path.starts_with(&pictures_dir) || !path.is_file() {
+            summary.skipped += 1;
+            continue;
+        }
+        let result = (|| -> Result<Option<String>> {
+            let original = std::fs::read(&path)?;
+            let png_bytes = {
+                let img = image::load_from_memory(&original)?;
+                let mut png = Vec::new();
+                img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+                png
+            };
+            let (encoded, extension) = encode(&config.image_storage, &png_bytes)?;
+            if encoded.len() >= original.len() {
+                return Ok(None);
+            }
+            let target = fs_manager::store_content_addressed(app_handle, &encoded, extension)?;
+            if target != path {
+                obsolete.push(path.clone());
+            }
+            summary.bytes_before += original.len() as u64;
+            summary.bytes_after += encoded.len() as u64;
+            Ok(Some(fs_manager::to_stored_image_path(app_handle, &target)))
+        })();
+        match result {
+            Ok(Some(stored)) => {
+                converted_paths.insert(item.original_image.clone(), stored.clone());
+                item.original_image = stored;
+                summary.converted += 1;
+            }
+            Ok(None) => summary.skipped += 1,
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("Failed to recompress {:?}: {}", path, _e);
+                summary.failed += 1;
+            }
+        }
+    }
+
+    fs_manager::write_history(app_handle, &history)?;
+    remove_files(&obsolete);
+    Ok(summary)
+}
+
+/// Deletes image files that no history item references any more
+fn remove_files(paths: &[PathBuf]) {
+    for path in paths {
+        if let Err(_e) = std::fs::remove_file(path) {
+            #[cfg(debug_assertions)]
+            eprintln!("Failed to remove {:?}: {}", path, _e);
+        }
+    }
+}
+
+// --- Tauri commands ---
+
+/// Re-encodes existing history images in the configured storage format.
+/// Images are only replaced when the new encoding is smaller.
+#[tauri::command]
+pub async fn recompress_history_images(app_handle: AppHandle) -> Result<RecompressSummary, String> {
+    // 整个过程中不允许新条目插入，否则写回的历史会覆盖掉它们
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    tauri::async_runtime::spawn_blocking(move || recompress_all(&app_handle))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupeSummary {
+    /// 重命名为内容哈希的文件数
+    pub renamed: usize,
+    /// 因内容重复而删除的文件数
+    pub removed: usize,
+    /// 读取或写入失败而保持原样的文件数
+    pub failed: usize,
+    pub bytes_freed: u64,
+}
+
+fn dedupe_all(app_handle: &AppHandle) -> Result<DedupeSummary> {
+    let pictures_dir = fs_manager::ensure_pictures_dir(app_handle)?;
+    let mut history = fs_manager::read_history(app_handle)?;
+    let mut summary = DedupeSummary::default();
+    let mut moved: HashMap<String, String> = HashMap::new();
+    // 先写入内容哈希命名的副本并更新历史，旧文件最后才删除
+    let mut obsolete: Vec<PathBuf> = Vec::new();
+
+    for item in history.iter_mut() {
+        if let Some(new_path) = moved.get(&item.original_image) {
+            item.original_image = new_path.clone();
+            continue;
+        }
+        let path = fs_manager::resolve_image_path(app_handle, &item.original_image);
+        if item.original_image.is_empty() || !path.starts_with(&pictures_dir) || !path.is_file() {
+            continue;
+        }
+        let result = (|| -> Result<Option<(PathBuf, bool, u64)>> {
+            let bytes = std::fs::read(&path)?;
+            let extension = path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_else(|| "png".to_string());
+            let target = pictures_dir.join(format!("{}.{}", content_hash(&bytes), extension));
+            if target == path {
+                return Ok(None);
+            }
+            let duplicate = target.is_file();
+            let target = fs_manager::store_content_addressed(app_handle, &bytes, &extension)?;
+            Ok(Some((target, duplicate, bytes.len() as u64)))
+        })();
+        match result {
+            Ok(Some((target, duplicate, size))) => {
+                if duplicate {
+                    summary.removed += 1;
+                    summary.bytes_freed += size;
+                } else {
+                    summary.renamed += 1;
+                }
+                obsolete.push(path);
+                let stored = fs_manager::to_stored_image_path(app_handle, &target);
+                moved.insert(item.original_image.clone(), stored.clone());
+                item.original_image = stored;
+            }
+            Ok(None) => {}
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("Failed to dedupe {:?}: {}", path, _e);
+                summary.failed += 1;
+            }
+        }
+    }
+
+    fs_manager::write_history(app_handle, &history)?;
+    remove_files(&obsolete);
+    Ok(summary)
+}
+
+/// Moves existing history images to content-addressed names, removing byte-identical copies
+#[tauri::command]
+pub async fn dedupe_history_images(app_handle: AppHandle) -> Result<DedupeSummary, String> {
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    tauri::async_runtime::spawn_blocking(move || dedupe_all(&app_handle))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
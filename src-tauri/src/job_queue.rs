@@ -0,0 +1,409 @@
+// 识别任务队列：截图与批量识别先作为任务保存到本地（jobs.json + jobs/<id>.img），再由后台按提交顺序执行，
+// 应用重启后未完成的任务（pending / running）会重新排队，不会丢失。任务状态为 pending、running、done、failed、
+// cancelled，可通过命令查看、取消与重试；每次变化广播 jobs_changed 事件（完整列表）。
+// 任务 id 同时作为识别 id：进度事件、cancel_recognition 与生成的历史条目都使用它。
+// 异步流程中的文件读写都在阻塞线程池中执行；jobs.json 损坏时改名隔离后从空队列重新开始。
+
+use crate::data_models::{CaptureScale, HistoryItem, RecognitionMode};
+use crate::pipeline::{self, CaptureImage, RecognitionError, RecognitionOptions};
+use crate::{event_stream, fs_manager};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+use tokio::sync::{oneshot, Notify};
+use uuid::Uuid;
+
+const JOBS_FILENAME: &str = "jobs.json";
+const JOBS_DIRNAME: &str = "jobs";
+/// 同时执行的任务数（普通识别仍在识别队列中逐条运行，批量任务不经过识别队列）
+const JOB_CONCURRENCY: usize = 3;
+/// 保留的已结束任务数，更早的连同图片一起删除
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// 串行化 jobs.json 的读改写
+static JOBS_LOCK: Mutex<()> = Mutex::new(());
+/// 有任务加入或结束时唤醒调度
+static WAKE: OnceLock<Notify> = OnceLock::new();
+/// 等待任务结果的调用方（截图命令、批量识别）；重启后恢复的任务没有等待者
+static WAITERS: OnceLock<Mutex<HashMap<String, Waiter>>> = OnceLock::new();
+
+type Waiter = oneshot::Sender<Result<HistoryItem, String>>;
+pub type Completion = oneshot::Receiver<Result<HistoryItem, String>>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    /// 图片来源（screenshot、file 等）
+    pub source: String,
+    pub status: JobStatus,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    #[serde(default)]
+    pub force_refresh: bool,
+    /// 手写模式
+    #[serde(default)]
+    pub handwriting: bool,
+    #[serde(default)]
+    pub mode: RecognitionMode,
+    /// 由快捷键发起，完成后自动粘贴结果
+    #[serde(default)]
+    pub auto_paste: bool,
+    #[serde(default)]
+    pub capture_scale: Option<CaptureScale>,
+    /// 所属的批量识别（recognize_files）
+    #[serde(default)]
+    pub batch_id: Option<String>,
+    pub created_at: String,
+    #[serde(default)]
+    pub started_at: Option<String>,
+    #[serde(default)]
+    pub finished_at: Option<String>,
+    /// 已开始执行的次数
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// 识别成功时保存的历史条目 id
+    #[serde(default)]
+    pub item_id: Option<String>,
+}
+
+fn wake() -> &'static Notify {
+    WAKE.get_or_init(Notify::new)
+}
+
+fn waiters() -> std::sync::MutexGuard<'static, HashMap<String, Waiter>> {
+    WAITERS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn image_path(app_handle: &AppHandle, id: &str) -> Result<PathBuf> {
+    Ok(fs_manager::app_data_dir(app_handle)?.join(JOBS_DIRNAME).join(format!("{}.img", id)))
+}
+
+fn remove_image(app_handle: &AppHandle, id: &str) {
+    if let Ok(path) = image_path(app_handle, id) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn read_jobs(app_handle: &AppHandle) -> Result<Vec<Job>> {
+    let path = fs_manager::get_data_file_path(app_handle, JOBS_FILENAME)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => match serde_json::from_slice(&bytes) {
+            Ok(jobs) => Ok(jobs),
+            Err(_e) => {
+                // 损坏的 jobs.json 会让之后的每次截图都失败；改名保留以便排查，然后从空队列开始
+                let quarantined = path.with_file_name(format!("jobs.corrupt-{}.json", chrono::Utc::now().format("%Y%m%d%H%M%S")));
+                std::fs::rename(&path, &quarantined).context("Failed to move aside corrupt jobs.json")?;
+                #[cfg(debug_assertions)]
+                eprintln!("jobs.json could not be parsed ({}); moved it to {:?}", _e, quarantined);
+                Ok(Vec::new())
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read jobs.json")),
+    }
+}
+
+fn write_jobs(app_handle: &AppHandle, jobs: &[Job]) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, JOBS_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec_pretty(jobs)?).context("Failed to write jobs.json")?;
+    event_stream::publish("jobs_changed", &jobs);
+    let _ = app_handle.emit_all("jobs_changed", jobs.to_vec());
+    Ok(())
+}
+
+/// Applies `change` to the stored jobs under the lock, then drops the oldest finished jobs
+fn update_jobs<T>(app_handle: &AppHandle, change: impl FnOnce(&mut Vec<Job>) -> T) -> Result<T> {
+    let _guard = JOBS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut jobs = read_jobs(app_handle)?;
+    let result = change(&mut jobs);
+    let finished = jobs.iter().filter(|j| j.status.is_finished()).count();
+    if finished > MAX_FINISHED_JOBS {
+        let mut excess = finished - MAX_FINISHED_JOBS;
+        jobs.retain(|job| {
+            if excess > 0 && job.status.is_finished() {
+                excess -= 1;
+                remove_image(app_handle, &job.id);
+                return false;
+            }
+            true
+        });
+    }
+    write_jobs(app_handle, &jobs)?;
+    Ok(result)
+}
+
+/// Saves the image and adds a pending job to the end of the queue. The returned receiver gets
+/// the job's result (it can be dropped when nobody waits for it).
+pub async fn enqueue(
+    app_handle: &AppHandle,
+    image: CaptureImage,
+    source: &str,
+    options: &RecognitionOptions,
+    batch_id: Option<&str>,
+) -> Result<(Job, Completion)> {
+    let job = Job {
+        id: Uuid::new_v4().to_string(),
+        source: source.to_string(),
+        status: JobStatus::Pending,
+        language: options.language.clone(),
+        preset_id: options.preset_id.clone(),
+        force_refresh: options.force_refresh,
+        handwriting: options.handwriting,
+        mode: options.mode,
+        auto_paste: options.auto_paste,
+        capture_scale: image.capture_scale().cloned(),
+        batch_id: batch_id.map(str::to_string),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        started_at: None,
+        finished_at: None,
+        attempts: 0,
+        error: None,
+        item_id: None,
+    };
+    let (sender, receiver) = oneshot::channel();
+    waiters().insert(job.id.clone(), sender);
+    let saved = {
+        let (app_handle, job) = (app_handle.clone(), job.clone());
+        fs_manager::run_blocking(move || {
+            let path = image_path(&app_handle, &job.id)?;
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).context("Failed to create jobs directory")?;
+            }
+            std::fs::write(&path, image.bytes()).context("Failed to save job image")?;
+            update_jobs(&app_handle, |jobs| jobs.push(job))
+        })
+        .await
+    };
+    if let Err(e) = saved {
+        waiters().remove(&job.id);
+        return Err(e);
+    }
+    wake().notify_one();
+    Ok((job, receiver))
+}
+
+/// Runs the image through the queue and waits for its history item
+pub async fn submit(app_handle: &AppHandle, image: CaptureImage, source: &str, options: RecognitionOptions) -> Result<HistoryItem, String> {
+    let (_, completion) = enqueue(app_handle, image, source, &options, None).await.map_err(|e| format!("{:#}", e))?;
+    completion.await.map_err(|_| "The recognition job was dropped".to_string())?
+}
+
+/// Marks job `id` finished (unless it was cancelled meanwhile) and hands the result to its waiter
+async fn finish(app_handle: &AppHandle, id: &str, result: Result<HistoryItem, RecognitionError>) {
+    let (status, item_id, error) = match &result {
+        Ok(item) => (JobStatus::Done, Some(item.id.clone()), None),
+        Err(RecognitionError::Cancelled) => (JobStatus::Cancelled, None, None),
+        Err(e) => (JobStatus::Failed, None, Some(e.to_string())),
+    };
+    let updated = {
+        let (app_handle, id) = (app_handle.clone(), id.to_string());
+        fs_manager::run_blocking(move || {
+            let updated = update_jobs(&app_handle, |jobs| match jobs.iter_mut().find(|j| j.id == id && j.status == JobStatus::Running) {
+                Some(job) => {
+                    job.status = status;
+                    job.finished_at = Some(chrono::Utc::now().to_rfc3339());
+                    job.item_id = item_id;
+                    job.error = error;
+                    true
+                }
+                None => false,
+            });
+            // 结果已写入历史，图片不再需要；失败与取消的任务保留图片以便重试
+            if status == JobStatus::Done {
+                remove_image(&app_handle, &id);
+            }
+            updated
+        })
+        .await
+    };
+    if let Err(_e) = &updated {
+        #[cfg(debug_assertions)]
+        eprintln!("Failed to update job {}: {:#}", id, _e);
+    }
+    if let Some(waiter) = waiters().remove(id) {
+        let _ = waiter.send(result.map_err(String::from));
+    }
+    wake().notify_one();
+}
+
+async fn run_job(app_handle: AppHandle, job: Job) {
+    let bytes = {
+        let (app_handle, id) = (app_handle.clone(), job.id.clone());
+        fs_manager::run_blocking(move || image_path(&app_handle, &id).and_then(|p| std::fs::read(p).context("The job's image is missing"))).await
+    };
+    let result = match bytes {
+        Ok(bytes) => {
+            let image = CaptureImage::from_bytes(bytes).with_capture_scale(job.capture_scale.clone());
+            let options = RecognitionOptions {
+                language: job.language.clone(),
+                preset_id: job.preset_id.clone(),
+                force_refresh: job.force_refresh,
+                handwriting: job.handwriting,
+                mode: job.mode,
+                auto_paste: job.auto_paste,
+                id: Some(job.id.clone()),
+            };
+            pipeline::recognize_or_queue(&app_handle, &image, &job.source, &options).await
+        }
+        Err(e) => Err(RecognitionError::Failed(format!("{:#}", e))),
+    };
+    finish(&app_handle, &job.id, result).await;
+}
+
+/// Starts pending jobs, oldest first, until `JOB_CONCURRENCY` are running
+async fn dispatch(app_handle: &AppHandle) -> Result<()> {
+    let handle = app_handle.clone();
+    let started = fs_manager::run_blocking(move || update_jobs(&handle, |jobs| {
+        let running = jobs.iter().filter(|j| j.status == JobStatus::Running).count();
+        let mut started = Vec::new();
+        for job in jobs.iter_mut().filter(|j| j.status == JobStatus::Pending).take(JOB_CONCURRENCY.saturating_sub(running)) {
+            job.status = JobStatus::Running;
+            job.started_at = Some(chrono::Utc::now().to_rfc3339());
+            job.attempts += 1;
+            started.push(job.clone());
+        }
+        started
+    }))
+    .await?;
+    for job in started {
+        tauri::async_runtime::spawn(run_job(app_handle.clone(), job));
+    }
+    Ok(())
+}
+
+/// Background scheduler: requeues jobs interrupted by the last shutdown, then runs pending jobs
+/// whenever one is added or finishes
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let handle = app_handle.clone();
+        let restored = fs_manager::run_blocking(move || {
+            update_jobs(&handle, |jobs| {
+                for job in jobs.iter_mut() {
+                    if job.status == JobStatus::Running {
+                        job.status = JobStatus::Pending;
+                    }
+                    // 重启后前台应用早已不同，不再自动粘贴
+                    job.auto_paste = false;
+                }
+            })
+        })
+        .await;
+        if let Err(_e) = restored {
+            #[cfg(debug_assertions)]
+            eprintln!("Failed to restore recognition jobs: {:#}", _e);
+        }
+        loop {
+            if let Err(_e) = dispatch(&app_handle).await {
+                #[cfg(debug_assertions)]
+                eprintln!("Failed to start recognition jobs: {:#}", _e);
+            }
+            wake().notified().await;
+        }
+    });
+}
+
+// --- Tauri commands ---
+
+/// Adds an image file to the queue and returns the pending job (results arrive through
+/// jobs_changed and the usual recognition events)
+#[tauri::command]
+pub async fn enqueue_job(
+    app_handle: AppHandle,
+    file_path: String,
+    language: Option<String>,
+    preset_id: Option<String>,
+    force_refresh: Option<bool>,
+    handwriting: Option<bool>,
+    mode: Option<RecognitionMode>,
+) -> Result<Job, String> {
+    let image = CaptureImage::from_file(&file_path)?;
+    let options = RecognitionOptions {
+        language,
+        preset_id,
+        force_refresh: force_refresh.unwrap_or(false),
+        handwriting: handwriting.unwrap_or(false),
+        mode: mode.unwrap_or_default(),
+        ..Default::default()
+    };
+    enqueue(&app_handle, image, "file", &options, None).await.map(|(job, _)| job).map_err(|e| format!("{:#}", e))
+}
+
+/// All jobs in submission order (finished jobs are kept up to a limit)
+#[tauri::command]
+pub fn list_jobs(app_handle: AppHandle) -> Result<Vec<Job>, String> {
+    read_jobs(&app_handle).map_err(|e| e.to_string())
+}
+
+/// Cancels a pending or running job; a running recognition is stopped without saving anything
+#[tauri::command]
+pub fn cancel_job(app_handle: AppHandle, id: String) -> Result<(), String> {
+    let status = read_jobs(&app_handle)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|j| j.id == id)
+        .map(|j| j.status)
+        .ok_or_else(|| format!("Job '{}' not found", id))?;
+    match status {
+        // 运行中的识别被取消后，run_job 以 Cancelled 结束任务
+        JobStatus::Running => {
+            pipeline::cancel_recognition(id);
+        }
+        JobStatus::Pending => {
+            update_jobs(&app_handle, |jobs| {
+                if let Some(job) = jobs.iter_mut().find(|j| j.id == id && j.status == JobStatus::Pending) {
+                    job.status = JobStatus::Cancelled;
+                    job.finished_at = Some(chrono::Utc::now().to_rfc3339());
+                }
+            })
+            .map_err(|e| e.to_string())?;
+            if let Some(waiter) = waiters().remove(&id) {
+                let _ = waiter.send(Err(RecognitionError::Cancelled.to_string()));
+            }
+        }
+        _ => return Err(format!("Job '{}' has already finished", id)),
+    }
+    Ok(())
+}
+
+/// Puts a failed or cancelled job back at the end of the queue
+#[tauri::command]
+pub fn retry_job(app_handle: AppHandle, id: String) -> Result<Job, String> {
+    let retried = update_jobs(&app_handle, |jobs| {
+        let index = jobs.iter().position(|j| j.id == id && matches!(j.status, JobStatus::Failed | JobStatus::Cancelled))?;
+        let mut job = jobs.remove(index);
+        job.status = JobStatus::Pending;
+        job.error = None;
+        job.started_at = None;
+        job.finished_at = None;
+        jobs.push(job.clone());
+        Some(job)
+    })
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Job '{}' is not failed or cancelled", id))?;
+    wake().notify_one();
+    Ok(retried)
+}
@@ -0,0 +1,41 @@
+// 输出语言自动识别：开启 autoDetectLanguage 时，先用一次轻量 LLM 调用判断截图所在文档的语言，
+// 分析与核查结果即使用该语言输出；无法识别时回退到全局设置的 language。
+// 识别命令也可通过 language 参数为单次请求指定语言，此时跳过自动识别。
+
+use crate::data_models::Config;
+use crate::llm_api::LlmClient;
+use crate::prompts;
+
+/// Loose BCP-47 shape check: 2-3 letter primary subtag followed by alphanumeric subtags
+fn is_plausible_tag(tag: &str) -> bool {
+    let mut parts = tag.split('-');
+    let primary_ok = parts
+        .next()
+        .map(|p| (2..=3).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphabetic()))
+        .unwrap_or(false);
+    primary_ok && parts.all(|p| (1..=8).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Returns the language the analysis/verification output should use for this capture.
+/// An explicit per-request language wins over detection and the global setting.
+pub async fn resolve_output_language(
+    config: &Config,
+    requested: Option<&str>,
+    client: &dyn LlmClient,
+    image_base64: &str,
+) -> String {
+    if let Some(lang) = requested.map(str::trim).filter(|l| !l.is_empty()) {
+        return lang.to_string();
+    }
+    if !config.auto_detect_language {
+        return config.language.clone();
+    }
+    match client.detect_language(&prompts::get_language_detection_prompt(), image_base64).await {
+        Ok(tag) if tag != "und" && is_plausible_tag(&tag) => {
+            #[cfg(debug_assertions)]
+            eprintln!("[Language] Detected output language: {}", tag);
+            tag
+        }
+        _ => config.language.clone(),
+    }
+}
@@ -0,0 +1,245 @@
+// 本地 LaTeX 规则检查（不调用 API）：\left/\right 不配对、\frac 缺参数、重复上/下标、
+// 用 .. 代替省略号、裸 Unicode 数学符号。结果以带 rule 的 Suggestion 追加到 analysis.suggestions，
+// 重新检查时先移除旧的规则建议，因此可在 LaTeX 变化后反复调用。
+
+use crate::data_models::{Analysis, Suggestion};
+use crate::fs_manager;
+use tauri::AppHandle;
+
+/// 常见 Unicode 数学符号及对应的 LaTeX 命令
+const UNICODE_SYMBOLS: &[(char, &str)] = &[
+    ('α', "\\alpha"), ('β', "\\beta"), ('γ', "\\gamma"), ('δ', "\\delta"), ('ε', "\\epsilon"),
+    ('θ', "\\theta"), ('λ', "\\lambda"), ('μ', "\\mu"), ('π', "\\pi"), ('ρ', "\\rho"),
+    ('σ', "\\sigma"), ('τ', "\\tau"), ('φ', "\\phi"), ('ω', "\\omega"), ('Δ', "\\Delta"),
+    ('Σ', "\\Sigma"), ('Ω', "\\Omega"), ('×', "\\times"), ('·', "\\cdot"), ('÷', "\\div"),
+    ('±', "\\pm"), ('≤', "\\leq"), ('≥', "\\geq"), ('≠', "\\neq"), ('≈', "\\approx"),
+    ('∞', "\\infty"), ('→', "\\to"), ('∑', "\\sum"), ('∫', "\\int"), ('√', "\\sqrt{}"),
+    ('∂', "\\partial"), ('∈', "\\in"), ('∇', "\\nabla"), ('…', "\\ldots"), ('−', "-"),
+];
+
+fn message(language: &str, zh: String, en: String) -> String {
+    if language == "zh-CN" { zh } else { en }
+}
+
+fn suggestion(kind: &str, rule: &str, message: String) -> Suggestion {
+    Suggestion { suggestion_type: kind.to_string(), message, rule: Some(rule.to_string()) }
+}
+
+/// Reads a command name starting at `i` (just after the backslash)
+fn command_at(chars: &[char], i: usize) -> String {
+    chars[i..].iter().take_while(|c| c.is_ascii_alphabetic()).collect()
+}
+
+fn skip_whitespace(chars: &[char], mut i: usize) -> usize {
+    while i < chars.len() && chars[i].is_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// Parses one macro argument at `i` (a braced group or a single token); returns the index after it
+fn parse_argument(chars: &[char], i: usize) -> Option<usize> {
+    let i = skip_whitespace(chars, i);
+    match chars.get(i)? {
+        '{' => {
+            let mut depth = 0usize;
+            let mut j = i;
+            while j < chars.len() {
+                match chars[j] {
+                    '\\' => j += 1,
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(j + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                j += 1;
+            }
+            None
+        }
+        '}' | '^' | '_' | '&' => None,
+        '\\' => {
+            let name = command_at(chars, i + 1);
+            Some(i + 1 + name.len().max(1))
+        }
+        _ => Some(i + 1),
+    }
+}
+
+/// Removes \text{...}-style groups, whose content is prose rather than math, and mhchem's
+/// \ce{...}/\pu{...}, whose content follows mhchem syntax (e.g. SO4^2-)
+fn strip_text_groups(latex: &str) -> Vec<char> {
+    let chars: Vec<char> = latex.chars().collect();
+    let mut out = Vec::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            let name = command_at(&chars, i + 1);
+            if matches!(name.as_str(), "text" | "textrm" | "mbox" | "mathrm" | "operatorname" | "ce" | "pu") {
+                if let Some(end) = parse_argument(&chars, i + 1 + name.len()) {
+                    out.push(' ');
+                    i = end;
+                    continue;
+                }
+            }
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Checks LaTeX against the local rule set
+pub fn lint(latex: &str, language: &str) -> Vec<Suggestion> {
+    let chars = strip_text_groups(latex);
+    let mut found = Vec::new();
+
+    // \left / \right 配对
+    let (mut lefts, mut rights) = (0usize, 0usize);
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            let name = command_at(&chars, i + 1);
+            match name.as_str() {
+                "left" => lefts += 1,
+                "right" => rights += 1,
+                _ => {}
+            }
+            i += 1 + name.len().max(1);
+            continue;
+        }
+        i += 1;
+    }
+    if lefts != rights {
+        found.push(suggestion(
+            "error",
+            "unbalanced_left_right",
+            message(
+                language,
+                format!("\\left 与 \\right 数量不一致（{} 个 \\left，{} 个 \\right）", lefts, rights),
+                format!("Unbalanced \\left/\\right ({} \\left vs {} \\right)", lefts, rights),
+            ),
+        ));
+    }
+
+    // \frac / \dfrac / \tfrac 需要两个参数
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            let name = command_at(&chars, i + 1);
+            let after = i + 1 + name.len().max(1);
+            if matches!(name.as_str(), "frac" | "dfrac" | "tfrac") {
+                let complete = parse_argument(&chars, after).and_then(|next| parse_argument(&chars, next)).is_some();
+                if !complete {
+                    found.push(suggestion(
+                        "error",
+                        "frac_missing_argument",
+                        message(language, format!("\\{} 缺少分子或分母参数", name), format!("\\{} is missing its numerator or denominator", name)),
+                    ));
+                }
+            }
+            i = after;
+            continue;
+        }
+        i += 1;
+    }
+
+    // 同一底数上重复的上标或下标（如 x^a^b），LaTeX 会报 Double superscript
+    let mut i = 0;
+    let (mut double_sup, mut double_sub) = (false, false);
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                i += 1 + command_at(&chars, i + 1).len().max(1);
+            }
+            '^' | '_' => {
+                let (mut sup, mut sub) = (false, false);
+                let mut j = i;
+                while j < chars.len() && (chars[j] == '^' || chars[j] == '_') {
+                    let slot = if chars[j] == '^' { &mut sup } else { &mut sub };
+                    if *slot {
+                        if chars[j] == '^' { double_sup = true } else { double_sub = true }
+                    }
+                    *slot = true;
+                    match parse_argument(&chars, j + 1) {
+                        Some(next) => j = skip_whitespace(&chars, next),
+                        None => {
+                            j += 1;
+                            break;
+                        }
+                    }
+                }
+                i = j.max(i + 1);
+            }
+            _ => i += 1,
+        }
+    }
+    if double_sup {
+        found.push(suggestion(
+            "error",
+            "double_superscript",
+            message(language, "同一底数上有多个上标，请用花括号分组（如 {x^a}^b）".to_string(), "Double superscript; group with braces (e.g. {x^a}^b)".to_string()),
+        ));
+    }
+    if double_sub {
+        found.push(suggestion(
+            "error",
+            "double_subscript",
+            message(language, "同一底数上有多个下标，请用花括号分组".to_string(), "Double subscript; group with braces".to_string()),
+        ));
+    }
+
+    // 用句点代替省略号
+    let text: String = chars.iter().collect();
+    if text.contains("..") {
+        found.push(suggestion(
+            "warning",
+            "ellipsis_dots",
+            message(
+                language,
+                "使用了 .. 或 ...，建议改为 \\cdots（居中）或 \\ldots（底线）".to_string(),
+                "Use \\cdots (centered) or \\ldots (baseline) instead of .. or ...".to_string(),
+            ),
+        ));
+    }
+
+    // 裸 Unicode 符号
+    let mut seen: Vec<char> = Vec::new();
+    for c in chars.iter().copied().filter(|c| !c.is_ascii() && !c.is_whitespace()) {
+        if seen.contains(&c) {
+            continue;
+        }
+        seen.push(c);
+        let replacement = UNICODE_SYMBOLS.iter().find(|(u, _)| *u == c).map(|(_, cmd)| *cmd);
+        let (zh, en) = match replacement {
+            Some(cmd) => (format!("裸 Unicode 符号 “{}”，建议改为 {}", c, cmd), format!("Bare Unicode symbol '{}'; use {} instead", c, cmd)),
+            None => (format!("裸 Unicode 字符 “{}” 在部分引擎中无法渲染", c), format!("Bare Unicode character '{}' may not render in all engines", c)),
+        };
+        found.push(suggestion("warning", "unicode_symbol", message(language, zh, en)));
+    }
+
+    found
+}
+
+/// Replaces previous rule-based suggestions in `analysis` with a fresh check of `latex`
+pub fn append(mut analysis: Analysis, latex: &str, language: &str) -> Analysis {
+    analysis.suggestions.retain(|s| s.rule.is_none());
+    analysis.suggestions.extend(lint(latex, language));
+    analysis
+}
+
+// --- Tauri commands ---
+
+/// Runs the local rule set on a LaTeX string (e.g. while editing); messages use the
+/// configured language unless `language` is given
+#[tauri::command]
+pub fn lint_latex(app_handle: AppHandle, latex: String, language: Option<String>) -> Result<Vec<Suggestion>, String> {
+    let language = match language.filter(|l| !l.trim().is_empty()) {
+        Some(language) => language,
+        None => fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?.language,
+    };
+    Ok(lint(&latex, &language))
+}
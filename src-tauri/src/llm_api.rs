@@ -1,23 +1,50 @@
 // Handles all communication with the LLM API
 
-use crate::data_models::Analysis;
+use crate::data_models::{Analysis, TokenUsage};
+use crate::prompts::PromptType;
+use crate::response_schema;
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
-use std::time::Duration;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
 use tokio::time::sleep;
 
 /// Configuration for LLM service
 #[derive(Debug, Clone)]
 pub struct LlmConfig {
-    pub api_key: String,
+    pub provider: Provider,
+    /// 可轮换使用的 API Key（可为空，如本地代理不需要密钥）
+    pub api_keys: Vec<String>,
+    pub key_rotation: crate::data_models::KeyRotation,
+    /// 主服务熔断期间使用的备用服务
+    pub failover: Option<Box<LlmConfig>>,
     pub api_base_url: String,
     pub model_name: String,
     pub request_timeout_seconds: u64,
     pub max_retries: u32,
     pub max_output_tokens: u32,
+    pub proxy: crate::data_models::ProxyConfig,
+    /// 请求按响应模式返回 JSON（Gemini 与 Ollama）
+    pub structured_output: bool,
+    pub generation: crate::data_models::GenerationSettings,
+    pub max_concurrent_requests: u32,
+    pub min_request_interval_ms: u64,
+    /// 写入请求/响应审计日志
+    pub audit_log: bool,
+    /// 响应缓存的有效期；为空时不使用缓存
+    pub cache_ttl: Option<Duration>,
+    /// 超过该大小（KB）的图片经 Gemini Files API 上传后引用，0 表示始终内联
+    pub files_api_threshold_kb: u64,
+    pub gateway: crate::data_models::GatewayConfig,
+    /// Gemini 安全过滤阈值；为空时使用服务端默认值
+    pub safety_settings: Vec<crate::data_models::SafetySetting>,
 }
 
 /// Generic LLM client trait for different providers
@@ -32,12 +59,13 @@ pub trait LlmClient: Send + Sync {
         latex: &str,
     ) -> Result<crate::data_models::VerificationResult, anyhow::Error>;
 
-    /// Verifies latex vs image and returns structured verification
+    /// Verifies latex vs image with the verification prompt (see `prompts::get_verification_prompt`)
+    /// and returns structured verification
     async fn verify_latex_against_image(
         &self,
+        prompt: &str,
         latex: &str,
         image_base64: &str,
-        language: &str,
     ) -> Result<crate::data_models::Verification, anyhow::Error>;
 
     /// Extracts only LaTeX from the given image
@@ -47,6 +75,52 @@ pub trait LlmClient: Send + Sync {
         image_base64: &str,
     ) -> Result<String, anyhow::Error>;
 
+    /// Like `extract_latex`, but sends the text received so far to `partial` while the response
+    /// streams in. Clients without streaming only return the final result.
+    async fn extract_latex_streaming(
+        &self,
+        prompt: &str,
+        image_base64: &str,
+        _partial: UnboundedSender<String>,
+    ) -> Result<String, anyhow::Error> {
+        self.extract_latex(prompt, image_base64).await
+    }
+
+    /// Detects the document language (BCP-47 tag, "und" if unknown) from the image
+    async fn detect_language(
+        &self,
+        prompt: &str,
+        image_base64: &str,
+    ) -> Result<String, anyhow::Error>;
+
+    /// Converts plain-text pseudo-math (e.g. "x^2/(2*sigma^2)") into LaTeX
+    async fn convert_text_to_latex(
+        &self,
+        prompt: &str,
+        text: &str,
+    ) -> Result<String, anyhow::Error>;
+
+    /// Locates each symbol of the LaTeX in the image (relative bounding boxes)
+    async fn locate_symbols(
+        &self,
+        latex: &str,
+        image_base64: &str,
+    ) -> Result<Vec<crate::data_models::SymbolBox>, anyhow::Error>;
+
+    /// Gives the SMILES of each chemical species in the (mhchem) LaTeX, using the image for structure
+    async fn extract_smiles(
+        &self,
+        latex: &str,
+        image_base64: &str,
+    ) -> Result<Vec<String>, anyhow::Error>;
+
+    /// Applies a described correction to existing LaTeX and returns the patched LaTeX
+    async fn fix_latex(
+        &self,
+        prompt: &str,
+        latex: &str,
+    ) -> Result<String, anyhow::Error>;
+
     /// Generates analysis (title, summary, variables, terms, suggestions)
     async fn generate_analysis(
         &self,
@@ -66,29 +140,411 @@ pub trait LlmClient: Send + Sync {
 
     /// Generic content generation method
     async fn generate_content(&self, prompt: &str) -> Result<String, anyhow::Error>;
+
+    /// Tokens used by all requests this client has made so far
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage::default()
+    }
 }
 
+/// True when a request never reached the API (no connection, DNS failure or timeout),
+/// as opposed to the API answering with an error
+pub fn is_unreachable(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_connect() || e.is_timeout())
+}
+
+/// Retry-After 超过该时长时不再等待，直接报错
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// A response with a non-success HTTP status
 #[derive(Debug)]
+pub struct HttpError {
+    pub status: reqwest::StatusCode,
+    pub body: String,
+    /// 服务端要求的等待时间（Retry-After 头或 Gemini 错误详情中的 retryDelay）
+    pub retry_after: Option<Duration>,
+}
+
+impl HttpError {
+    fn new(status: reqwest::StatusCode, headers: &reqwest::header::HeaderMap, body: String) -> Self {
+        let retry_after = Self::parse_retry_after(headers, &body);
+        Self { status, body, retry_after }
+    }
+
+    /// Retry-After as seconds or an HTTP date, else the retryDelay (e.g. "12s") of a
+    /// google.rpc.RetryInfo error detail
+    fn parse_retry_after(headers: &reqwest::header::HeaderMap, body: &str) -> Option<Duration> {
+        if let Some(value) = headers.get(reqwest::header::RETRY_AFTER).and_then(|v| v.to_str().ok()).map(str::trim) {
+            if let Ok(secs) = value.parse::<u64>() {
+                return Some(Duration::from_secs(secs));
+            }
+            if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value) {
+                let ms = (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_milliseconds().max(0);
+                return Some(Duration::from_millis(ms as u64));
+            }
+        }
+        let body: serde_json::Value = serde_json::from_str(body).ok()?;
+        body["error"]["details"]
+            .as_array()?
+            .iter()
+            .find_map(|detail| detail["retryDelay"].as_str())
+            .and_then(|delay| delay.strip_suffix('s')?.parse::<f64>().ok())
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map(Duration::from_secs_f64)
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        self.status == reqwest::StatusCode::TOO_MANY_REQUESTS
+    }
+
+    /// 5xx statuses that usually pass (bad gateway, unavailable, gateway timeout, internal error)
+    pub fn is_server_error(&self) -> bool {
+        matches!(self.status.as_u16(), 500 | 502 | 503 | 504)
+    }
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "API request failed with status {}: {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// The HTTP error behind `error`, if the request got a response
+pub fn http_error(error: &anyhow::Error) -> Option<&HttpError> {
+    error.chain().find_map(|cause| cause.downcast_ref::<HttpError>())
+}
+
+/// 视为没有产出可用结果的结束原因（Gemini 的 finishReason / blockReason 名称）
+const STOPPING_REASONS: &[&str] = &["SAFETY", "IMAGE_SAFETY", "PROHIBITED_CONTENT", "BLOCKLIST", "SPII", "RECITATION", "MAX_TOKENS"];
+
+/// The model stopped without usable output: blocked by safety filters, cut off at the output
+/// limit, or the prompt itself was blocked
+#[derive(Debug, Clone, Serialize)]
+pub struct FinishReasonError {
+    pub stage: String,
+    /// Gemini finishReason or blockReason; OpenAI and Ollama reasons are mapped to the same names
+    pub finish_reason: String,
+    /// 触发拦截的安全类别（如 HARM_CATEGORY_DANGEROUS_CONTENT），仅 Gemini 提供
+    pub categories: Vec<String>,
+}
+
+impl FinishReasonError {
+    /// Blocked by a safety, blocklist or recitation filter (changing the image or safety settings may help)
+    pub fn is_blocked(&self) -> bool {
+        !self.is_max_tokens()
+    }
+
+    pub fn is_max_tokens(&self) -> bool {
+        self.finish_reason == "MAX_TOKENS"
+    }
+}
+
+impl std::fmt::Display for FinishReasonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The model stopped without a usable result for {} (finishReason: {})", self.stage, self.finish_reason)?;
+        if !self.categories.is_empty() {
+            write!(f, " [{}]", self.categories.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FinishReasonError {}
+
+/// The finish-reason error behind `error`, if the model stopped early
+pub fn finish_reason_error(error: &anyhow::Error) -> Option<&FinishReasonError> {
+    error.chain().find_map(|cause| cause.downcast_ref::<FinishReasonError>())
+}
+
+/// 即将重试一次请求，供前端显示“已限流，12 秒后重试”
+#[derive(Serialize, Debug, Clone)]
+pub struct RetryNotice {
+    /// 请求所属阶段（latex extraction、analysis 等）
+    pub stage: String,
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub delay_ms: u64,
+    /// rate_limited、server_error 或 network
+    pub reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+}
+
+/// Called before each retry's wait
+pub type RetryListener = Arc<dyn Fn(RetryNotice) + Send + Sync>;
+
+/// 请求在并发上限（maxConcurrentRequests）处排队，供前端显示排队位置与预计等待时间
+#[derive(Debug, Clone, Copy)]
+pub struct QueueNotice {
+    /// 排在前面、同样在等待名额的请求数
+    pub requests_ahead: usize,
+    /// 预计等到名额的时间（毫秒）
+    pub eta_ms: u64,
+}
+
+/// Called whenever a request waiting for a slot moves up
+pub type QueueListener = Arc<dyn Fn(QueueNotice) + Send + Sync>;
+
+/// 服务商协议。请求统一按 Gemini 的结构构建，发送时转换为对应服务商的请求体，响应再还原为模型输出的文本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Provider {
+    #[default]
+    Gemini,
+    /// OpenAI 的 chat/completions 接口及兼容服务（OpenRouter、vLLM、LM Studio 等）
+    OpenAi,
+    /// 本地 Ollama（/api/chat），配合 llava、qwen2.5vl 等多模态模型离线使用
+    Ollama,
+    /// 不发送请求，返回固定数据（见 mock_llm），用于开发调试
+    Mock,
+}
+
+impl Provider {
+    /// Maps `Config.provider` to a protocol; unknown names use Gemini
+    pub fn from_name(name: &str) -> Self {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "openai" | "openai-compatible" | "openai_compatible" => Provider::OpenAi,
+            "ollama" => Provider::Ollama,
+            "mock" => Provider::Mock,
+            _ => Provider::Gemini,
+        }
+    }
+
+    /// Whether requests need an API key; a local Ollama server and the mock run without one
+    pub fn requires_api_key(self) -> bool {
+        !matches!(self, Provider::Ollama | Provider::Mock)
+    }
+}
+
+/// The client for `config`: the offline mock for provider "mock", otherwise the HTTP client.
+/// Fails when the configured proxy cannot be used, rather than connecting around it.
+pub fn create_client(config: LlmConfig) -> Result<Arc<dyn LlmClient>> {
+    Ok(match config.provider {
+        Provider::Mock => Arc::new(crate::mock_llm::MockClient),
+        _ => Arc::new(ApiClient::new(config)?),
+    })
+}
+
+/// The value of the "latex" field decoded from a JSON response that may still be incomplete,
+/// e.g. `{"latex": "\\frac{a}{` gives `\frac{a}{`
+pub fn partial_latex(text: &str) -> Option<String> {
+    let key = "\"latex\"";
+    let rest = &text[text.find(key)? + key.len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+    let mut latex = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => latex.push('\n'),
+                Some('t') => latex.push('\t'),
+                Some('r') => latex.push('\r'),
+                Some('b') => latex.push('\u{8}'),
+                Some('f') => latex.push('\u{c}'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        Some(c) if hex.len() == 4 => latex.push(c),
+                        // 转义序列尚未接收完整
+                        _ => break,
+                    }
+                }
+                Some(c) => latex.push(c),
+                None => break,
+            },
+            c => latex.push(c),
+        }
+    }
+    Some(latex)
+}
+
+/// Routes `builder` through the configured proxy, if one is active. SOCKS credentials go
+/// into the proxy URL, HTTP(S) credentials into the Proxy-Authorization header.
+pub fn with_proxy(builder: reqwest::ClientBuilder, proxy: &crate::data_models::ProxyConfig) -> Result<reqwest::ClientBuilder> {
+    if !proxy.is_active() {
+        return Ok(builder);
+    }
+    let mut url = reqwest::Url::parse(proxy.url.trim()).with_context(|| format!("Invalid proxy URL '{}'", proxy.url))?;
+    let username = proxy.username.trim();
+    let is_socks = url.scheme().starts_with("socks");
+    if is_socks && !username.is_empty() {
+        url.set_username(username).map_err(|_| anyhow!("Invalid proxy URL '{}'", proxy.url))?;
+        url.set_password(Some(&proxy.password)).map_err(|_| anyhow!("Invalid proxy URL '{}'", proxy.url))?;
+    }
+    let mut reqwest_proxy = reqwest::Proxy::all(url.as_str()).with_context(|| format!("Unsupported proxy URL '{}'", proxy.url))?;
+    if !is_socks && !username.is_empty() {
+        reqwest_proxy = reqwest_proxy.basic_auth(username, &proxy.password);
+    }
+    let bypass: Vec<&str> = proxy.bypass.iter().map(|h| h.trim()).filter(|h| !h.is_empty()).collect();
+    if !bypass.is_empty() {
+        reqwest_proxy = reqwest_proxy.no_proxy(reqwest::NoProxy::from_string(&bypass.join(",")));
+    }
+    Ok(builder.proxy(reqwest_proxy))
+}
+
+// --- Request limiter ---
+
+/// 所有客户端共享的并发上限；上限改变时换用新的信号量，已发出的请求不受影响
+static REQUEST_SLOTS: OnceLock<Mutex<Option<(u32, Arc<Semaphore>)>>> = OnceLock::new();
+/// 下一次请求最早的发出时间
+static NEXT_REQUEST_AT: OnceLock<Mutex<Option<Instant>>> = OnceLock::new();
+/// 正在等待名额的请求（按到达顺序；信号量按同样的顺序发放名额）
+static SLOT_WAITERS: Mutex<VecDeque<u64>> = Mutex::new(VecDeque::new());
+static NEXT_SLOT_WAITER: AtomicU64 = AtomicU64::new(0);
+static SLOT_WAITERS_CHANGED: OnceLock<Notify> = OnceLock::new();
+/// 尚无数据时假定的单次请求耗时
+const DEFAULT_REQUEST_MS: u64 = 5_000;
+/// 最近请求占用名额时长的指数滑动平均（毫秒），0 表示尚无数据
+static AVERAGE_REQUEST_MS: AtomicU64 = AtomicU64::new(0);
+
+fn slot_waiters_changed() -> &'static Notify {
+    SLOT_WAITERS_CHANGED.get_or_init(Notify::new)
+}
+
+fn record_request_duration(ms: u64) {
+    let average = AVERAGE_REQUEST_MS.load(Ordering::Relaxed);
+    let updated = if average == 0 { ms } else { (average * 4 + ms) / 5 };
+    AVERAGE_REQUEST_MS.store(updated.max(1), Ordering::Relaxed);
+}
+
+/// Rough wait until a slot frees up: requests are served `limit` at a time
+fn slot_eta_ms(requests_ahead: usize, limit: u32) -> u64 {
+    let average = match AVERAGE_REQUEST_MS.load(Ordering::Relaxed) {
+        0 => DEFAULT_REQUEST_MS,
+        average => average,
+    };
+    average * (requests_ahead as u64 / limit.max(1) as u64 + 1)
+}
+
+/// A request waiting for a slot; leaving the line (with or without a permit) moves the others up
+struct SlotWaiter(u64);
+
+impl SlotWaiter {
+    fn join() -> Self {
+        let waiter = NEXT_SLOT_WAITER.fetch_add(1, Ordering::SeqCst);
+        SLOT_WAITERS.lock().unwrap_or_else(|e| e.into_inner()).push_back(waiter);
+        SlotWaiter(waiter)
+    }
+
+    fn ahead(&self) -> usize {
+        let waiters = SLOT_WAITERS.lock().unwrap_or_else(|e| e.into_inner());
+        waiters.iter().position(|w| *w == self.0).unwrap_or(0)
+    }
+}
+
+impl Drop for SlotWaiter {
+    fn drop(&mut self) {
+        SLOT_WAITERS.lock().unwrap_or_else(|e| e.into_inner()).retain(|w| *w != self.0);
+        slot_waiters_changed().notify_waiters();
+    }
+}
+
+/// Waits in line for a permit of the full `semaphore`, reporting the position to `listener` whenever it changes
+async fn wait_for_slot(semaphore: Arc<Semaphore>, limit: u32, listener: Option<&QueueListener>) -> Option<OwnedSemaphorePermit> {
+    let waiter = SlotWaiter::join();
+    let acquire = semaphore.acquire_owned();
+    tokio::pin!(acquire);
+    let mut announced = None;
+    loop {
+        let changed = slot_waiters_changed().notified();
+        let ahead = waiter.ahead();
+        if let Some(listener) = listener.filter(|_| announced != Some(ahead)) {
+            announced = Some(ahead);
+            listener(QueueNotice { requests_ahead: ahead, eta_ms: slot_eta_ms(ahead, limit) });
+        }
+        tokio::select! {
+            permit = &mut acquire => return permit.ok(),
+            _ = changed => {}
+        }
+    }
+}
+
+/// Waits for a free request slot (`max_concurrent` 0 means unlimited) and for the minimum
+/// interval since the previous request. The returned permit frees the slot when dropped.
+async fn acquire_request_slot(max_concurrent: u32, min_interval_ms: u64, listener: Option<&QueueListener>) -> Option<OwnedSemaphorePermit> {
+    let permit = match max_concurrent {
+        0 => None,
+        limit => {
+            let semaphore = {
+                let mut slots = REQUEST_SLOTS.get_or_init(|| Mutex::new(None)).lock().unwrap_or_else(|e| e.into_inner());
+                match slots.as_ref() {
+                    Some((current, semaphore)) if *current == limit => semaphore.clone(),
+                    _ => {
+                        let semaphore = Arc::new(Semaphore::new(limit as usize));
+                        *slots = Some((limit, semaphore.clone()));
+                        semaphore
+                    }
+                }
+            };
+            match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                // 名额已满：排队等待，位置变化时通知
+                Err(_) => wait_for_slot(semaphore, limit, listener).await,
+            }
+        }
+    };
+    if min_interval_ms > 0 {
+        // 预约发出时间后再等待，排队的请求按顺序依次错开
+        let wait = {
+            let mut next = NEXT_REQUEST_AT.get_or_init(|| Mutex::new(None)).lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let start = next.map_or(now, |next| next.max(now));
+            *next = Some(start + Duration::from_millis(min_interval_ms));
+            start - now
+        };
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+    permit
+}
+
+/// Gemini 保留上传文件 48 小时，略早于此不再复用
+const UPLOADED_FILE_TTL: Duration = Duration::from_secs(47 * 3600);
+
+/// 通过 Files API 上传过的图片：地址|密钥|图片 SHA-256 -> (文件 URI, 上传时间)
+static UPLOADED_FILES: OnceLock<tokio::sync::Mutex<HashMap<String, (String, Instant)>>> = OnceLock::new();
+
+/// MIME type of a base64-encoded image, sniffed from its first bytes (PNG when unknown)
+fn inline_mime_type(image_base64: &str) -> &'static str {
+    use base64::{engine::general_purpose, Engine as _};
+    // 16 个 base64 字符解码为 12 字节，足以识别文件头
+    let head = image_base64.get(..16).unwrap_or(image_base64);
+    general_purpose::STANDARD
+        .decode(head)
+        .map(|bytes| crate::image_storage::mime_type(&bytes))
+        .unwrap_or("image/png")
+}
+
 pub struct ApiClient {
     client: Client,
     config: LlmConfig,
+    /// 本客户端各次请求的用量合计（每次识别使用新的客户端，即为该条目的用量）
+    usage: std::sync::Mutex<TokenUsage>,
+    retry_listener: Option<RetryListener>,
+    queue_listener: Option<QueueListener>,
 }
 
 // --- Gemini API Request Structures ---
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct GeminiRequest {
     contents: Vec<GeminiContent>,
     #[serde(rename = "generationConfig")]
     generation_config: GeminiGenerationConfig,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct GeminiContent {
     parts: Vec<GeminiPart>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(untagged)]
 enum GeminiPart {
     Text { text: String },
@@ -96,20 +552,42 @@ enum GeminiPart {
         #[serde(rename = "inlineData")]
         inline_data: GeminiInlineData
     },
+    /// 通过 Files API 上传的图片（仅 Gemini）
+    FileData {
+        #[serde(rename = "fileData")]
+        file_data: GeminiFileData
+    },
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct GeminiInlineData {
     #[serde(rename = "mimeType")]
     mime_type: String,
     data: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
+struct GeminiFileData {
+    #[serde(rename = "mimeType")]
+    mime_type: String,
+    #[serde(rename = "fileUri")]
+    file_uri: String,
+}
+
+#[derive(Serialize, Default, Clone)]
 struct GeminiGenerationConfig {
     temperature: f32,
     #[serde(rename = "maxOutputTokens")]
     max_output_tokens: u32,
+    #[serde(rename = "topP", skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(rename = "topK", skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(rename = "responseMimeType", skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<&'static str>,
+    /// 结构化输出的模式（见 response_schema）
+    #[serde(rename = "responseSchema", skip_serializing_if = "Option::is_none")]
+    response_schema: Option<serde_json::Value>,
 }
 
 // --- Gemini API Response Structures ---
@@ -136,6 +614,43 @@ struct GeminiResponsePart {
     text: String,
 }
 
+// --- OpenAI-compatible Response Structures ---
+
+#[derive(Deserialize, Debug)]
+struct OpenAiResponse {
+    #[serde(default)]
+    choices: Vec<OpenAiChoice>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAiChoice {
+    message: OpenAiMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAiMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+// --- Ollama Response Structures ---
+
+#[derive(Deserialize, Debug)]
+struct OllamaResponse {
+    #[serde(default)]
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaMessage {
+    #[serde(default)]
+    content: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct RecognitionContent {
     latex: String,
@@ -159,6 +674,17 @@ struct LatexOnlyContent {
     latex: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+struct LanguageOnlyContent {
+    language: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SmilesOnlyContent {
+    #[serde(default)]
+    smiles: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct AnalysisOnlyContent {
     title: String,
@@ -167,56 +693,160 @@ struct AnalysisOnlyContent {
 
 impl ApiClient {
     /// Creates a new ApiClient instance with configuration.
-    pub fn new(config: LlmConfig) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(config.request_timeout_seconds))
-            .build()
-            .expect("Failed to create HTTP client");
+    /// Fails when the configured proxy is invalid: requests never fall back to a direct connection
+    pub fn new(config: LlmConfig) -> Result<Self> {
+        let builder = Client::builder().timeout(Duration::from_secs(config.request_timeout_seconds));
+        let builder = with_proxy(builder, &config.proxy)
+            .context("Cannot use the configured proxy; fix the proxy settings or turn the proxy off")?;
+        let client = builder.build().context("Failed to create HTTP client")?;
+
+        Ok(Self { client, config, usage: std::sync::Mutex::new(TokenUsage::default()), retry_listener: None, queue_listener: None })
+    }
 
-        Self { client, config }
+    /// Reports every upcoming retry to `listener`
+    pub fn with_retry_listener(mut self, listener: RetryListener) -> Self {
+        self.retry_listener = Some(listener);
+        self
+    }
+
+    /// Reports the position of requests waiting for a slot of the concurrency limit to `listener`
+    pub fn with_queue_listener(mut self, listener: QueueListener) -> Self {
+        self.queue_listener = Some(listener);
+        self
     }
 
     #[cfg(test)]
     #[allow(dead_code)]
-    fn new_with_config(mut config: LlmConfig, base_url: String) -> Self {
+    fn new_with_config(mut config: LlmConfig, base_url: String) -> Result<Self> {
         config.api_base_url = base_url;
         Self::new(config)
     }
 
-    /// Helper method to send request with retry logic
-    async fn send_request_with_retry(&self, request_body: &GeminiRequest) -> Result<String> {
+    /// Sends the request with retry logic and returns the text the model produced.
+    /// `stage` names the request in error messages.
+    async fn send_request_with_retry(&self, request_body: &GeminiRequest, stage: &str) -> Result<String> {
+        self.send_with_retry(request_body, stage, None).await
+    }
+
+    /// `send_request_with_retry`, streaming the response when `partial` is given: the text
+    /// received so far is sent to it after every chunk and starts over on a retry
+    async fn send_with_retry(&self, request_body: &GeminiRequest, stage: &str, partial: Option<&UnboundedSender<String>>) -> Result<String> {
         let mut attempts = 0;
+        // 429 时换用其他密钥立即重试，不计入重试次数；每次请求最多换一轮
+        let mut key_switches = 0;
+        // 相同请求在缓存有效期内直接返回上次的文本
+        let cache = self.config.cache_ttl.and_then(|ttl| Some((crate::llm_cache::key(&self.config, request_body)?, ttl)));
+        if let Some(text) = cache.as_ref().and_then(|(key, ttl)| crate::llm_cache::lookup(key, *ttl)) {
+            if let Some(partial) = partial {
+                let _ = partial.send(text.clone());
+            }
+            return Ok(text);
+        }
         loop {
-            match self.send_request(request_body).await {
-                Ok(result) => return Ok(result),
+            // 主服务熔断期间改用备用服务（只有一个密钥，不参与轮换）
+            let failover = self.config.failover.as_deref().filter(|_| crate::failover::is_open());
+            let (target, api_key) = match failover {
+                Some(target) => (target, target.api_keys.first().cloned()),
+                None => (&self.config, crate::api_keys::next_key(&self.config.api_keys, self.config.key_rotation)),
+            };
+            // 较大的图片先经 Files API 上传，请求中只引用文件
+            let uploaded = self.upload_large_images(target, api_key.as_deref(), request_body).await;
+            let body = uploaded.as_ref().unwrap_or(request_body);
+            // 限流只作用于请求本身，重试前的退避等待不占用名额
+            let mut slot = acquire_request_slot(self.config.max_concurrent_requests, self.config.min_request_interval_ms, self.queue_listener.as_ref()).await;
+            let started = Instant::now();
+            let sent = match partial {
+                Some(partial) => self.send_streaming_request(target, body, api_key.as_deref(), stage, partial).await,
+                None => self.send_request(target, body, api_key.as_deref()).await.map(|raw| {
+                    let usage = serde_json::from_str(&raw).ok().and_then(|v| Self::usage_from(target.provider, &v));
+                    (raw, usage)
+                }),
+            };
+            if slot.take().is_some() {
+                record_request_duration(started.elapsed().as_millis() as u64);
+            }
+            if self.config.audit_log {
+                self.audit(target, stage, body, partial.is_some(), started, &sent);
+            }
+            match sent {
+                Ok((response_text, usage)) => {
+                    if failover.is_none() {
+                        crate::failover::record_success();
+                    }
+                    if let Some(usage) = usage {
+                        self.record_usage(&target.model_name, usage);
+                    }
+                    let result = match partial {
+                        Some(_) if response_text.trim().is_empty() => Err(anyhow!("The model returned no text for {}", stage)),
+                        Some(_) => Ok(response_text),
+                        None => Self::response_text(target.provider, &response_text, stage),
+                    };
+                    if let (Ok(text), Some((key, _))) = (&result, &cache) {
+                        crate::llm_cache::store(key, &target.model_name, text);
+                    }
+                    return result;
+                }
                 Err(e) => {
                     let msg = e.to_string();
-                    let is_retryable_http = msg.contains("status 429")
-                        || msg.contains("status 500")
-                        || msg.contains("status 502")
-                        || msg.contains("status 503")
-                        || msg.contains("status 504");
-                    let is_retryable_transport = msg.contains("Failed to send request")
-                        || msg.to_lowercase().contains("timeout")
-                        || msg.to_lowercase().contains("timed out")
-                        || msg.to_lowercase().contains("connection reset")
-                        || msg.to_lowercase().contains("temporarily unavailable");
-                    let is_context_canceled = msg.to_lowercase().contains("context canceled")
-                        || msg.contains("status 499");
-
-                    let should_retry = (is_retryable_http || is_retryable_transport) && !is_context_canceled;
+                    let http = http_error(&e);
+                    let rate_limited = http.is_some_and(HttpError::is_rate_limited);
+                    let server_error = http.is_some_and(HttpError::is_server_error);
+                    if failover.is_none() && (is_unreachable(&e) || server_error) {
+                        crate::failover::record_failure(&msg, self.config.failover.is_some());
+                    }
+                    if let Some(key) = api_key.as_deref().filter(|_| failover.is_none()) {
+                        if rate_limited
+                            && crate::api_keys::report_rate_limited(&self.config.api_keys, key)
+                            && key_switches + 1 < self.config.api_keys.len()
+                        {
+                            key_switches += 1;
+                            #[cfg(debug_assertions)]
+                            eprintln!("[LLM] Key rate limited, switching to the next key");
+                            continue;
+                        }
+                    }
+                    // 没有拿到响应时按错误信息判断是否为可恢复的网络问题
+                    let lower = msg.to_lowercase();
+                    let is_retryable_transport = http.is_none()
+                        && (is_unreachable(&e)
+                            || msg.contains("Failed to send request")
+                            || msg.contains("Failed to read response")
+                            || lower.contains("timed out")
+                            || lower.contains("connection reset")
+                            || lower.contains("temporarily unavailable"))
+                        && !lower.contains("context canceled");
+                    let retry_after = http.and_then(|h| h.retry_after);
+                    // 要求等待过久时直接报错，避免识别长时间无响应
+                    let waits_too_long = retry_after.is_some_and(|d| d > MAX_RETRY_AFTER);
+                    let should_retry = (rate_limited || server_error || is_retryable_transport) && !waits_too_long;
 
                     if should_retry && attempts < self.config.max_retries {
                         attempts += 1;
-                        // Exponential backoff with small pseudo-jitter without extra deps
-                        let base_secs = 2u64.pow(attempts);
-                        let jitter_ms = (attempts as u64 * 137) % 1000;
-                        let delay = Duration::from_secs(base_secs) + Duration::from_millis(jitter_ms);
+                        // 服务端给出等待时间时照做，否则指数退避加少量伪随机抖动
+                        let delay = retry_after.unwrap_or_else(|| {
+                            let jitter_ms = (attempts as u64 * 137) % 1000;
+                            Duration::from_secs(2u64.pow(attempts)) + Duration::from_millis(jitter_ms)
+                        });
                         #[cfg(debug_assertions)]
                         eprintln!(
                             "[LLM] Retry #{}, reason='{}', waiting {:?}",
                             attempts, msg, delay
                         );
+                        if let Some(listener) = &self.retry_listener {
+                            let reason = match (rate_limited, server_error) {
+                                (true, _) => "rate_limited",
+                                (_, true) => "server_error",
+                                _ => "network",
+                            };
+                            listener(RetryNotice {
+                                stage: stage.to_string(),
+                                attempt: attempts,
+                                max_retries: self.config.max_retries,
+                                delay_ms: delay.as_millis() as u64,
+                                reason: reason.to_string(),
+                                status: http.map(|h| h.status.as_u16()),
+                            });
+                        }
                         sleep(delay).await;
                         continue;
                     }
@@ -236,15 +866,77 @@ impl ApiClient {
             .to_string()
     }
 
-    fn build_verification_prompt(latex: &str, language: &str) -> String {
-        let lang_note = if language == "zh-CN" {
-            "Output language: Simplified Chinese for 'issues[*].message'. Keys remain English.".to_string()
-        } else {
-            "Output language: English for 'issues[*].message'. Keys remain English.".to_string()
-        };
+    /// Sampling parameters configured for a pipeline stage
+    fn stage_generation(&self, stage: PromptType) -> GeminiGenerationConfig {
+        let params = self.config.generation.for_stage(stage);
+        GeminiGenerationConfig {
+            temperature: params.temperature,
+            max_output_tokens: params.max_output_tokens.unwrap_or(self.config.max_output_tokens),
+            top_p: params.top_p,
+            top_k: params.top_k,
+            ..Default::default()
+        }
+    }
+
+    /// Asks for JSON matching `schema` when structured output is enabled
+    fn structured(&self, mut request_body: GeminiRequest, schema: serde_json::Value) -> GeminiRequest {
+        if self.config.structured_output {
+            request_body.generation_config.response_mime_type = Some("application/json");
+            request_body.generation_config.response_schema = Some(schema);
+        }
+        request_body
+    }
+
+    fn build_symbol_boxes_prompt(latex: &str) -> String {
         format!(
-            "You are a strict verifier. Compare the provided LaTeX with the image. Do NOT fix the LaTeX; only point out mismatches. Return a strict JSON: {{\n  \"status\": \"error|warning|ok\",\n  \"issues\": [{{\"category\": \"missing_term|extra_term|symbol_mismatch|notation_mismatch|layout_mismatch|other\", \"message\": \"...\"}}],\n  \"coverage\": {{\"symbols_matched\": n, \"symbols_total\": n, \"terms_matched\": n, \"terms_total\": n}}\n}}.\nRules:\n- status=error if ANY mismatch that changes math meaning (missing/extra term, wrong symbol, wrong power/subscript, different operator).\n- status=warning for layout/formatting-only differences (line breaks, spacing) that do not change math.\n- status=ok only if visually and semantically equivalent.\n- Be concise but precise.\n{}\nLaTeX to verify:\n{}",
-            lang_note, latex)
+            "Locate every symbol of the given LaTeX formula in the image. Return a strict JSON array, in reading order: [{{\"token\": \"<LaTeX token, e.g. \\\\alpha, x, 2, \\\\frac>\", \"region\": {{\"x\": 0.0, \"y\": 0.0, \"width\": 0.0, \"height\": 0.0}}}}].\nRules:\n- Coordinates are fractions of the image width/height (0-1, origin top-left).\n- One entry per visible symbol; omit symbols you cannot locate.\n- No explanations, JSON only.\nLaTeX:\n{}",
+            latex
+        )
+    }
+
+    async fn internal_locate_symbols(
+        &self,
+        latex: &str,
+        image_base64: &str,
+    ) -> Result<Vec<crate::data_models::SymbolBox>, anyhow::Error> {
+        let request_body = GeminiRequest {
+            contents: vec![GeminiContent { parts: vec![
+                GeminiPart::Text { text: Self::build_symbol_boxes_prompt(latex) },
+                GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: inline_mime_type(image_base64).to_string(), data: image_base64.to_string() }},
+            ]}],
+            generation_config: GeminiGenerationConfig { temperature: 0.0, max_output_tokens: self.config.max_output_tokens, ..Default::default() },
+        };
+        let content_str = self.send_request_with_retry(&request_body, "symbol boxes").await?;
+        let clean = self.clean_response(&content_str);
+        let boxes: Vec<crate::data_models::SymbolBox> = serde_json::from_str(&clean).with_context(|| format!("Failed to parse symbol boxes: {}", clean))?;
+        // 丢弃越界或退化的框
+        Ok(boxes
+            .into_iter()
+            .filter_map(|b| b.region.normalized().map(|region| crate::data_models::SymbolBox { token: b.token, region }))
+            .collect())
+    }
+
+    async fn internal_extract_smiles(
+        &self,
+        latex: &str,
+        image_base64: &str,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        let request_body = GeminiRequest {
+            contents: vec![GeminiContent { parts: vec![
+                GeminiPart::Text { text: crate::prompts::get_smiles_prompt(latex) },
+                GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: inline_mime_type(image_base64).to_string(), data: image_base64.to_string() }},
+            ]}],
+            generation_config: GeminiGenerationConfig { temperature: 0.0, max_output_tokens: self.config.max_output_tokens, ..Default::default() },
+        };
+        let content_str = self.send_request_with_retry(&request_body, "smiles").await?;
+        let clean = self.clean_response(&content_str);
+        let parsed: SmilesOnlyContent = serde_json::from_str(&clean).with_context(|| format!("Failed to parse SMILES: {}", clean))?;
+        Ok(parsed
+            .smiles
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect())
     }
 
     // 已删除 internal_perform_recognition 方法
@@ -253,32 +945,19 @@ impl ApiClient {
         &self,
         prompt: &str,
         image_base64: &str,
+        partial: Option<&UnboundedSender<String>>,
     ) -> Result<String, anyhow::Error> {
-        let request_body = GeminiRequest {
+        let request_body = self.structured(GeminiRequest {
             contents: vec![GeminiContent {
                 parts: vec![
                     GeminiPart::Text { text: prompt.to_string() },
-                    GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: "image/png".to_string(), data: image_base64.to_string() }},
+                    GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: inline_mime_type(image_base64).to_string(), data: image_base64.to_string() }},
                 ],
             }],
-            generation_config: GeminiGenerationConfig {
-                temperature: 0.2,
-                max_output_tokens: self.config.max_output_tokens,
-            },
-        };
+            generation_config: self.stage_generation(PromptType::LaTeX),
+        }, response_schema::latex());
 
-        let response_text = self.send_request_with_retry(&request_body).await?;
-        let content_str = match serde_json::from_str::<GeminiResponse>(&response_text) {
-            Ok(api_response) => {
-                api_response
-                    .candidates
-                    .get(0)
-                    .and_then(|c| c.content.parts.get(0))
-                    .map(|p| p.text.clone())
-                    .ok_or_else(|| anyhow!("Gemini returned no text for latex extraction"))?
-            }
-            Err(_) => return Err(anyhow!("Failed to parse Gemini response for latex extraction")),
-        };
+        let content_str = self.send_with_retry(&request_body, "latex extraction", partial).await?;
         let clean = self.clean_response(&content_str);
         // 首选严格 JSON 解析
         match serde_json::from_str::<LatexOnlyContent>(&clean) {
@@ -293,35 +972,85 @@ impl ApiClient {
         }
     }
 
-    async fn internal_generate_analysis(
+    async fn internal_detect_language(
         &self,
         prompt: &str,
         image_base64: &str,
-    ) -> Result<(String, Analysis), anyhow::Error> {
+    ) -> Result<String, anyhow::Error> {
         let request_body = GeminiRequest {
             contents: vec![GeminiContent {
                 parts: vec![
                     GeminiPart::Text { text: prompt.to_string() },
-                    GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: "image/png".to_string(), data: image_base64.to_string() }},
+                    GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: inline_mime_type(image_base64).to_string(), data: image_base64.to_string() }},
                 ],
             }],
             generation_config: GeminiGenerationConfig {
-                temperature: 0.5,
+                temperature: 0.0,
                 max_output_tokens: self.config.max_output_tokens,
+                ..Default::default()
             },
         };
-        let response_text = self.send_request_with_retry(&request_body).await?;
-        let content_str = match serde_json::from_str::<GeminiResponse>(&response_text) {
-            Ok(api_response) => {
-                api_response
-                    .candidates
-                    .get(0)
-                    .and_then(|c| c.content.parts.get(0))
-                    .map(|p| p.text.clone())
-                    .ok_or_else(|| anyhow!("Gemini returned no text for analysis"))?
-            }
-            Err(_) => return Err(anyhow!("Failed to parse Gemini response for analysis")),
-        };
+
+        let content_str = self.send_request_with_retry(&request_body, "language detection").await?;
+        let clean = self.clean_response(&content_str);
+        let detected: LanguageOnlyContent = serde_json::from_str(&clean)
+            .with_context(|| format!("Failed to parse language detection content: {}", clean))?;
+        Ok(detected.language.trim().to_string())
+    }
+
+    async fn internal_convert_text_to_latex(
+        &self,
+        prompt: &str,
+        text: &str,
+    ) -> Result<String, anyhow::Error> {
+        self.internal_text_to_latex(format!("{}\n\nExpression to convert:\n{}", prompt, text), "text conversion").await
+    }
+
+    async fn internal_fix_latex(
+        &self,
+        prompt: &str,
+        latex: &str,
+    ) -> Result<String, anyhow::Error> {
+        self.internal_text_to_latex(format!("{}\n\nLaTeX to fix:\n{}", prompt, latex), "latex fix").await
+    }
+
+    /// Text-only request whose answer is a {"latex": ...} object
+    async fn internal_text_to_latex(
+        &self,
+        text: String,
+        stage: &str,
+    ) -> Result<String, anyhow::Error> {
+        let request_body = self.structured(GeminiRequest {
+            contents: vec![GeminiContent {
+                parts: vec![GeminiPart::Text { text }],
+            }],
+            generation_config: self.stage_generation(PromptType::LaTeX),
+        }, response_schema::latex());
+
+        let content_str = self.send_request_with_retry(&request_body, stage).await?;
+        let clean = self.clean_response(&content_str);
+        match serde_json::from_str::<LatexOnlyContent>(&clean) {
+            Ok(v) => Ok(v.latex),
+            Err(_) => Self::try_relaxed_extract_latex(&clean)
+                .ok_or_else(|| anyhow!("Failed to parse latex-only content: {}", clean)),
+        }
+    }
+
+    async fn internal_generate_analysis(
+        &self,
+        prompt: &str,
+        image_base64: &str,
+    ) -> Result<(String, Analysis), anyhow::Error> {
+        let request_body = self.structured(GeminiRequest {
+            contents: vec![GeminiContent {
+                parts: vec![
+                    GeminiPart::Text { text: prompt.to_string() },
+                    GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: inline_mime_type(image_base64).to_string(), data: image_base64.to_string() }},
+                ],
+            }],
+            generation_config: self.stage_generation(PromptType::Analysis),
+        }, response_schema::analysis());
+        let content_str = self.send_request_with_retry(&request_body, "analysis").await?;
         let clean = self.clean_response(&content_str);
         // 容错：有些模型会误返回 {"latex": "..."} 到分析提示，尝试兜底
         if clean.contains("\"latex\"") && !clean.contains("\"analysis\"") {
@@ -380,7 +1109,7 @@ impl ApiClient {
         prompt: &str,
         latex: &str,
     ) -> Result<crate::data_models::VerificationResult, anyhow::Error> {
-        let request_body = GeminiRequest {
+        let request_body = self.structured(GeminiRequest {
             contents: vec![GeminiContent {
                 parts: vec![
                     GeminiPart::Text {
@@ -388,29 +1117,10 @@ impl ApiClient {
                     },
                 ],
             }],
-            generation_config: GeminiGenerationConfig {
-                temperature: 0.2,
-                max_output_tokens: self.config.max_output_tokens,
-            },
-        };
+            generation_config: self.stage_generation(PromptType::Verification),
+        }, response_schema::verification_result());
 
-        let response_text = self.send_request_with_retry(&request_body).await?;
-
-        let content_str = match serde_json::from_str::<GeminiResponse>(&response_text) {
-            Ok(api_response) => {
-                let maybe_text = api_response
-                    .candidates
-                    .get(0)
-                    .and_then(|c| c.content.parts.get(0))
-                    .map(|p| p.text.clone());
-                if let Some(text) = maybe_text {
-                    text
-                } else {
-                    return Err(anyhow!("Gemini returned no text for verification"));
-                }
-            }
-            Err(_) => return Err(anyhow!("Failed to parse Gemini response for verification")),
-        };
+        let content_str = self.send_request_with_retry(&request_body, "verification").await?;
 
         let clean_content = self.clean_response(&content_str);
         let verification_content: VerificationResultContent = serde_json::from_str(&clean_content)
@@ -424,25 +1134,28 @@ impl ApiClient {
 
     async fn internal_verify_latex_against_image(
         &self,
+        prompt: &str,
         latex: &str,
         image_base64: &str,
-        language: &str,
     ) -> Result<crate::data_models::Verification, anyhow::Error> {
-        let prompt = Self::build_verification_prompt(latex, language);
-        let request_body = GeminiRequest {
+        let request_body = self.structured(GeminiRequest {
             contents: vec![GeminiContent { parts: vec![
-                GeminiPart::Text { text: prompt },
-                GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: "image/png".into(), data: image_base64.to_string() }},
+                GeminiPart::Text { text: format!("{}\n\nLaTeX to verify:\n{}", prompt, latex) },
+                GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: inline_mime_type(image_base64).to_string(), data: image_base64.to_string() }},
             ]}],
-            generation_config: GeminiGenerationConfig { temperature: 0.2, max_output_tokens: self.config.max_output_tokens },
-        };
-        let response_text = self.send_request_with_retry(&request_body).await?;
-        let content_str = match serde_json::from_str::<GeminiResponse>(&response_text) {
-            Ok(api_response) => api_response.candidates.get(0).and_then(|c| c.content.parts.get(0)).map(|p| p.text.clone()).ok_or_else(|| anyhow!("Gemini returned no text for verification"))?,
-            Err(_) => return Err(anyhow!("Failed to parse Gemini response for verification")),
-        };
+            generation_config: self.stage_generation(PromptType::Verification),
+        }, response_schema::verification());
+        let content_str = self.send_request_with_retry(&request_body, "verification").await?;
         let clean = self.clean_response(&content_str);
-        let v: crate::data_models::Verification = serde_json::from_str(&clean).with_context(|| format!("Failed to parse verification: {}", clean))?;
+        let mut v: crate::data_models::Verification = serde_json::from_str(&clean).with_context(|| format!("Failed to parse verification: {}", clean))?;
+        // 模型给出的坐标可能越界，统一裁剪到图片范围内；片段位置由本地匹配得出，不信任模型给出的偏移
+        for issue in v.issues.iter_mut() {
+            issue.region = issue.region.and_then(|r| r.normalized());
+            issue.latex_span = issue
+                .latex_fragment
+                .as_deref()
+                .and_then(|fragment| crate::data_models::LatexSpan::locate(latex, fragment));
+        }
         Ok(v)
     }
 
@@ -455,31 +1168,17 @@ impl ApiClient {
         latex: &str,
         image_base64: &str,
     ) -> Result<crate::data_models::VerificationResult, anyhow::Error> {
-        let request_body = GeminiRequest {
+        let request_body = self.structured(GeminiRequest {
             contents: vec![GeminiContent {
                 parts: vec![
                     GeminiPart::Text { text: format!("{}\n\nLaTeX to evaluate: {}", prompt, latex) },
-                    GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: "image/png".to_string(), data: image_base64.to_string() }},
+                    GeminiPart::InlineData { inline_data: GeminiInlineData { mime_type: inline_mime_type(image_base64).to_string(), data: image_base64.to_string() }},
                 ],
             }],
-            generation_config: GeminiGenerationConfig {
-                temperature: 0.2,
-                max_output_tokens: self.config.max_output_tokens,
-            },
-        };
+            generation_config: self.stage_generation(PromptType::Verification),
+        }, response_schema::verification_result());
 
-        let response_text = self.send_request_with_retry(&request_body).await?;
-        let content_str = match serde_json::from_str::<GeminiResponse>(&response_text) {
-            Ok(api_response) => {
-                api_response
-                    .candidates
-                    .get(0)
-                    .and_then(|c| c.content.parts.get(0))
-                    .map(|p| p.text.clone())
-                    .ok_or_else(|| anyhow!("Gemini returned no text for verification with image"))?
-            }
-            Err(_) => return Err(anyhow!("Failed to parse Gemini response for verification with image")),
-        };
+        let content_str = self.send_request_with_retry(&request_body, "verification with image").await?;
 
         let clean_content = self.clean_response(&content_str);
         let verification_content: VerificationResultContent = serde_json::from_str(&clean_content)
@@ -491,8 +1190,116 @@ impl ApiClient {
         })
     }
 
-    fn canonical_models_base(&self) -> String {
-        let b = self.config.api_base_url.trim_end_matches('/');
+    /// Files API upload endpoint for the target's base URL,
+    /// e.g. https://host/v1beta/models -> https://host/upload/v1beta/files
+    fn gemini_upload_url(target: &LlmConfig) -> String {
+        let base = Self::canonical_models_base(target);
+        let versioned = base.trim_end_matches("/models");
+        let path_start = versioned.find("://").and_then(|i| versioned[i + 3..].find('/').map(|j| i + 3 + j));
+        match path_start {
+            Some(path_start) => format!("{}/upload{}/files", &versioned[..path_start], &versioned[path_start..]),
+            None => format!("{}/upload/v1beta/files", versioned),
+        }
+    }
+
+    /// Uploads an image with the resumable Files API protocol and returns its file URI
+    async fn upload_gemini_file(&self, target: &LlmConfig, api_key: Option<&str>, mime_type: &str, bytes: Vec<u8>) -> Result<String> {
+        let mut url = Self::gemini_upload_url(target);
+        if let Some(key) = api_key {
+            url.push_str(&format!("?key={}", key));
+        }
+        let start = Self::with_gateway_headers(self.client.post(url), target, api_key)
+            .header("X-Goog-Upload-Protocol", "resumable")
+            .header("X-Goog-Upload-Command", "start")
+            .header("X-Goog-Upload-Header-Content-Length", bytes.len().to_string())
+            .header("X-Goog-Upload-Header-Content-Type", mime_type)
+            .json(&serde_json::json!({ "file": { "display_name": "formula" } }))
+            .send()
+            .await
+            .context("Failed to start file upload")?;
+        if !start.status().is_success() {
+            let (status, headers) = (start.status(), start.headers().clone());
+            let text = start.text().await.context("Failed to read response text")?;
+            return Err(HttpError::new(status, &headers, text).into());
+        }
+        let upload_url = start
+            .headers()
+            .get("x-goog-upload-url")
+            .and_then(|v| v.to_str().ok())
+            .context("The file upload response has no upload URL")?
+            .to_string();
+
+        let response = Self::with_gateway_headers(self.client.post(upload_url), target, api_key)
+            .header("X-Goog-Upload-Offset", "0")
+            .header("X-Goog-Upload-Command", "upload, finalize")
+            .body(bytes)
+            .send()
+            .await
+            .context("Failed to upload file")?;
+        let (status, headers) = (response.status(), response.headers().clone());
+        let text = response.text().await.context("Failed to read response text")?;
+        if !status.is_success() {
+            return Err(HttpError::new(status, &headers, text).into());
+        }
+        let file: serde_json::Value = serde_json::from_str(&text).context("Failed to parse file upload response")?;
+        file["file"]["uri"].as_str().map(str::to_string).context("The file upload response has no file URI")
+    }
+
+    /// URI of `image` on the target's Files API, uploading it unless it was uploaded recently
+    async fn uploaded_file_uri(&self, target: &LlmConfig, api_key: Option<&str>, image: &GeminiInlineData) -> Result<String> {
+        use base64::{engine::general_purpose, Engine as _};
+        let bytes = general_purpose::STANDARD.decode(&image.data).context("Invalid base64 image")?;
+        // 文件归属于密钥所在的项目，键中包含地址与密钥
+        let key = format!("{}|{}|{}", target.api_base_url, api_key.unwrap_or_default(), crate::result_cache::image_key(&bytes));
+        // 同一图片的并发请求（LaTeX 与分析）等待第一次上传完成后复用
+        let mut uploads = UPLOADED_FILES.get_or_init(Default::default).lock().await;
+        uploads.retain(|_, (_, uploaded_at)| uploaded_at.elapsed() < UPLOADED_FILE_TTL);
+        if let Some((uri, _)) = uploads.get(&key) {
+            return Ok(uri.clone());
+        }
+        let uri = self.upload_gemini_file(target, api_key, &image.mime_type, bytes).await?;
+        #[cfg(debug_assertions)]
+        eprintln!("[LLM] Uploaded {} bytes of image data as {}", image.data.len(), uri);
+        uploads.insert(key, (uri.clone(), Instant::now()));
+        Ok(uri)
+    }
+
+    /// For Gemini targets, a copy of the request whose inline images larger than
+    /// `files_api_threshold_kb` reference Files API uploads instead; None when nothing changes.
+    /// Images whose upload fails stay inline.
+    async fn upload_large_images(&self, target: &LlmConfig, api_key: Option<&str>, request_body: &GeminiRequest) -> Option<GeminiRequest> {
+        let threshold = target.files_api_threshold_kb.saturating_mul(1024);
+        // base64 每 4 个字符编码 3 字节
+        let is_large = |image: &GeminiInlineData| image.data.len() as u64 / 4 * 3 > threshold;
+        let has_large_image = request_body
+            .contents
+            .iter()
+            .flat_map(|c| c.parts.iter())
+            .any(|part| matches!(part, GeminiPart::InlineData { inline_data } if is_large(inline_data)));
+        if target.provider != Provider::Gemini || threshold == 0 || !has_large_image {
+            return None;
+        }
+        let mut body = request_body.clone();
+        for part in body.contents.iter_mut().flat_map(|c| c.parts.iter_mut()) {
+            let GeminiPart::InlineData { inline_data } = part else { continue };
+            if !is_large(inline_data) {
+                continue;
+            }
+            let replacement = match self.uploaded_file_uri(target, api_key, inline_data).await {
+                Ok(file_uri) => GeminiPart::FileData { file_data: GeminiFileData { mime_type: inline_data.mime_type.clone(), file_uri } },
+                Err(_e) => {
+                    #[cfg(debug_assertions)]
+                    eprintln!("[LLM] File upload failed, sending the image inline: {:#}", _e);
+                    continue;
+                }
+            };
+            *part = replacement;
+        }
+        Some(body)
+    }
+
+    fn canonical_models_base(target: &LlmConfig) -> String {
+        let b = target.api_base_url.trim_end_matches('/');
         if b.contains("/models") {
             b.to_string()
         } else if b.contains("/v1beta") || b.contains("/v1") {
@@ -502,15 +1309,451 @@ impl ApiClient {
         }
     }
 
-    /// Generic function to send a request to the Gemini API.
-    async fn send_request(&self, request_body: &GeminiRequest) -> Result<String> {
-        // 自动补全代理前缀缺失的版本与 models 段，提高兼容性
-        let base = self.canonical_models_base();
-        let mut url = format!("{}/{}:generateContent", base, self.config.model_name);
-        if !self.config.api_key.is_empty() {
-            url.push_str(&format!("?key={}", self.config.api_key));
+    /// chat/completions endpoint of an OpenAI-compatible base URL (adds a missing /v1)
+    fn openai_chat_url(target: &LlmConfig) -> String {
+        let b = target.api_base_url.trim_end_matches('/');
+        if b.ends_with("/chat/completions") {
+            b.to_string()
+        } else if b.contains("/v1") {
+            format!("{}/chat/completions", b)
+        } else {
+            format!("{}/v1/chat/completions", b)
+        }
+    }
+
+    /// The request as an OpenAI chat/completions body: one user message whose parts keep
+    /// their order, images as data URLs. The response schema is not sent, since support for
+    /// `response_format` varies too much between compatible services.
+    fn openai_body(target: &LlmConfig, request_body: &GeminiRequest) -> serde_json::Value {
+        let content: Vec<serde_json::Value> = request_body
+            .contents
+            .iter()
+            .flat_map(|c| c.parts.iter())
+            .map(|part| match part {
+                GeminiPart::Text { text } => serde_json::json!({ "type": "text", "text": text }),
+                GeminiPart::InlineData { inline_data } => serde_json::json!({
+                    "type": "image_url",
+                    "image_url": { "url": format!("data:{};base64,{}", inline_data.mime_type, inline_data.data) }
+                }),
+                GeminiPart::FileData { file_data } => serde_json::json!({
+                    "type": "image_url",
+                    "image_url": { "url": file_data.file_uri }
+                }),
+            })
+            .collect();
+        let mut body = serde_json::json!({
+            "model": target.model_name,
+            "messages": [{ "role": "user", "content": content }],
+            "temperature": request_body.generation_config.temperature,
+            "max_tokens": request_body.generation_config.max_output_tokens,
+        });
+        // OpenAI 接口没有 top_k
+        if let Some(top_p) = request_body.generation_config.top_p {
+            body["top_p"] = serde_json::json!(top_p);
+        }
+        body
+    }
+
+    /// /api/chat endpoint of an Ollama server, e.g. http://localhost:11434
+    fn ollama_chat_url(target: &LlmConfig) -> String {
+        let b = target.api_base_url.trim_end_matches('/');
+        if b.ends_with("/api/chat") {
+            b.to_string()
+        } else if b.ends_with("/api") {
+            format!("{}/chat", b)
+        } else {
+            format!("{}/api/chat", b)
+        }
+    }
+
+    /// The request as an Ollama /api/chat body: text parts joined into the message, images
+    /// as raw base64 in `images`, no streaming
+    fn ollama_body(target: &LlmConfig, request_body: &GeminiRequest) -> serde_json::Value {
+        let mut texts: Vec<&str> = Vec::new();
+        let mut images: Vec<&str> = Vec::new();
+        for part in request_body.contents.iter().flat_map(|c| c.parts.iter()) {
+            match part {
+                GeminiPart::Text { text } => texts.push(text),
+                GeminiPart::InlineData { inline_data } => images.push(&inline_data.data),
+                // 只有 Gemini 请求会引用上传的文件
+                GeminiPart::FileData { .. } => {}
+            }
+        }
+        let mut message = serde_json::json!({ "role": "user", "content": texts.join("\n\n") });
+        if !images.is_empty() {
+            message["images"] = serde_json::json!(images);
+        }
+        let mut body = serde_json::json!({
+            "model": target.model_name,
+            "messages": [message],
+            "stream": false,
+            "options": {
+                "temperature": request_body.generation_config.temperature,
+                "num_predict": request_body.generation_config.max_output_tokens,
+            },
+        });
+        if let Some(top_p) = request_body.generation_config.top_p {
+            body["options"]["top_p"] = serde_json::json!(top_p);
+        }
+        if let Some(top_k) = request_body.generation_config.top_k {
+            body["options"]["top_k"] = serde_json::json!(top_k);
+        }
+        if let Some(schema) = &request_body.generation_config.response_schema {
+            body["format"] = response_schema::to_json_schema(schema);
+        }
+        body
+    }
+
+    /// Extracts the model's text from a successful response of `provider`
+    fn response_text(provider: Provider, response_text: &str, stage: &str) -> Result<String> {
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(response_text) {
+            if let Some(error) = Self::stop_error(provider, &value, stage) {
+                return Err(error.into());
+            }
+        }
+        match provider {
+            Provider::Gemini => {
+                let api_response: GeminiResponse = serde_json::from_str(response_text)
+                    .map_err(|_| anyhow!("Failed to parse Gemini response for {}", stage))?;
+                let candidate = api_response.candidates.first();
+                candidate.and_then(|c| c.content.parts.first()).map(|p| p.text.clone()).ok_or_else(|| {
+                    let finish_reason = candidate.and_then(|c| c.finish_reason.as_deref()).unwrap_or("unknown");
+                    anyhow!("Gemini returned no text for {} (finishReason: {})", stage, finish_reason)
+                })
+            }
+            Provider::OpenAi => {
+                let api_response: OpenAiResponse = serde_json::from_str(response_text)
+                    .map_err(|_| anyhow!("Failed to parse OpenAI-compatible response for {}", stage))?;
+                let choice = api_response.choices.first();
+                choice
+                    .and_then(|c| c.message.content.clone())
+                    .filter(|text| !text.trim().is_empty())
+                    .ok_or_else(|| {
+                        let finish_reason = choice.and_then(|c| c.finish_reason.as_deref()).unwrap_or("unknown");
+                        anyhow!("The model returned no text for {} (finish_reason: {})", stage, finish_reason)
+                    })
+            }
+            Provider::Ollama => {
+                let api_response: OllamaResponse = serde_json::from_str(response_text)
+                    .map_err(|_| anyhow!("Failed to parse Ollama response for {}", stage))?;
+                api_response
+                    .message
+                    .map(|m| m.content)
+                    .filter(|text| !text.trim().is_empty())
+                    .ok_or_else(|| {
+                        let done_reason = api_response.done_reason.as_deref().unwrap_or("unknown");
+                        anyhow!("Ollama returned no text for {} (done_reason: {})", stage, done_reason)
+                    })
+            }
+            Provider::Mock => Err(anyhow!("The mock provider does not send requests")),
+        }
+    }
+
+    /// Builds the HTTP request for the target's provider; `stream` asks for a streamed response
+    /// (SSE for Gemini and OpenAI-compatible services, NDJSON for Ollama)
+    fn build_request(&self, target: &LlmConfig, request_body: &GeminiRequest, api_key: Option<&str>, stream: bool) -> Result<reqwest::Request> {
+        // Gemini 的密钥放在地址中，其他服务商按 Bearer 发送
+        let (url, body, bearer) = match target.provider {
+            Provider::Gemini => {
+                // 自动补全代理前缀缺失的版本与 models 段，提高兼容性
+                let base = Self::canonical_models_base(target);
+                let mut url = match stream {
+                    true => format!("{}/{}:streamGenerateContent?alt=sse", base, target.model_name),
+                    false => format!("{}/{}:generateContent", base, target.model_name),
+                };
+                if let Some(key) = api_key {
+                    url.push_str(&format!("{}key={}", if stream { '&' } else { '?' }, key));
+                }
+                let mut body = serde_json::to_value(request_body)?;
+                if !target.safety_settings.is_empty() {
+                    body["safetySettings"] = serde_json::to_value(&target.safety_settings)?;
+                }
+                (url, body, false)
+            }
+            Provider::OpenAi => {
+                let mut body = Self::openai_body(target, request_body);
+                body["stream"] = serde_json::json!(stream);
+                if stream {
+                    // 否则流式响应不返回用量
+                    body["stream_options"] = serde_json::json!({ "include_usage": true });
+                }
+                (Self::openai_chat_url(target), body, true)
+            }
+            Provider::Ollama => {
+                let mut body = Self::ollama_body(target, request_body);
+                body["stream"] = serde_json::json!(stream);
+                // Ollama 本身不需要密钥；填写了密钥时按 Bearer 发送，便于经过鉴权代理
+                (Self::ollama_chat_url(target), body, true)
+            }
+            // create_client 为 mock 返回 MockClient，不会走到这里
+            Provider::Mock => return Err(anyhow!("The mock provider does not send requests")),
+        };
+        let mut request = self.client.post(url).json(&Self::wrap_gateway_request(target, body)?);
+        if let Some(key) = api_key.filter(|_| bearer) {
+            request = request.bearer_auth(key);
+        }
+        Self::with_gateway_headers(request, target, api_key)
+            .header("Content-Type", "application/json")
+            .build()
+            .context("Failed to build request")
+    }
+
+    /// Adds the gateway's extra headers, with `{apiKey}` replaced by the current key
+    fn with_gateway_headers(mut request: reqwest::RequestBuilder, target: &LlmConfig, api_key: Option<&str>) -> reqwest::RequestBuilder {
+        for (name, value) in &target.gateway.headers {
+            request = request.header(name.trim(), value.replace("{apiKey}", api_key.unwrap_or_default()));
+        }
+        request
+    }
+
+    /// The provider body inside the gateway's request envelope; unchanged without an envelope
+    fn wrap_gateway_request(target: &LlmConfig, body: serde_json::Value) -> Result<serde_json::Value> {
+        let template = target.gateway.request_envelope.trim();
+        if template.is_empty() {
+            return Ok(body);
+        }
+        let mut envelope: serde_json::Value = serde_json::from_str(template).context("gateway.requestEnvelope is not valid JSON")?;
+        fn fill(value: &mut serde_json::Value, body: &serde_json::Value, model: &str) {
+            match value {
+                serde_json::Value::String(s) if s == "{{body}}" => *value = body.clone(),
+                serde_json::Value::String(s) if s.contains("{{model}}") => *s = s.replace("{{model}}", model),
+                serde_json::Value::Array(items) => items.iter_mut().for_each(|item| fill(item, body, model)),
+                serde_json::Value::Object(map) => map.values_mut().for_each(|item| fill(item, body, model)),
+                _ => {}
+            }
+        }
+        fill(&mut envelope, &body, &target.model_name);
+        Ok(envelope)
+    }
+
+    /// The provider response taken out of the gateway's reply at `gateway.responsePath`;
+    /// unchanged without a path
+    fn unwrap_gateway_response(target: &LlmConfig, text: String) -> Result<String> {
+        let path = target.gateway.response_path.trim();
+        if path.is_empty() {
+            return Ok(text);
+        }
+        let reply: serde_json::Value = serde_json::from_str(&text).context("Failed to parse gateway response")?;
+        match reply.pointer(path) {
+            // 网关可能把服务商响应作为 JSON 字符串返回
+            Some(serde_json::Value::String(inner)) => Ok(inner.clone()),
+            Some(inner) => Ok(inner.to_string()),
+            None => Err(anyhow!("Gateway response has nothing at '{}': {}", path, text)),
+        }
+    }
+
+    /// Like `unwrap_gateway_response`, for one parsed chunk of a streamed reply
+    fn unwrap_gateway_chunk(target: &LlmConfig, chunk: serde_json::Value) -> Result<serde_json::Value> {
+        let path = target.gateway.response_path.trim();
+        if path.is_empty() {
+            return Ok(chunk);
+        }
+        match chunk.pointer(path) {
+            Some(serde_json::Value::String(inner)) => serde_json::from_str(inner).context("Failed to parse gateway stream chunk"),
+            Some(inner) => Ok(inner.clone()),
+            None => Err(anyhow!("Gateway stream chunk has nothing at '{}': {}", path, chunk)),
+        }
+    }
+
+    /// The reason a response (or stream chunk) says generation ended, in Gemini's names
+    fn finish_reason(provider: Provider, value: &serde_json::Value) -> Option<String> {
+        let reason = match provider {
+            Provider::Gemini => value["promptFeedback"]["blockReason"].as_str().or_else(|| value["candidates"][0]["finishReason"].as_str()),
+            Provider::OpenAi => match value["choices"][0]["finish_reason"].as_str()? {
+                "length" => Some("MAX_TOKENS"),
+                "content_filter" => Some("SAFETY"),
+                other => Some(other),
+            },
+            Provider::Ollama => match value["done_reason"].as_str()? {
+                "length" => Some("MAX_TOKENS"),
+                other => Some(other),
+            },
+            Provider::Mock => None,
+        };
+        reason.map(str::to_string)
+    }
+
+    /// An error when the response ended for a reason that leaves no usable output
+    fn stop_error(provider: Provider, value: &serde_json::Value, stage: &str) -> Option<FinishReasonError> {
+        let finish_reason = Self::finish_reason(provider, value).filter(|r| STOPPING_REASONS.contains(&r.as_str()))?;
+        // 被拦截的安全类别：blocked 为 true 或概率为 HIGH 的评级
+        let ratings = value["promptFeedback"]["safetyRatings"].as_array().or_else(|| value["candidates"][0]["safetyRatings"].as_array());
+        let categories = ratings
+            .into_iter()
+            .flatten()
+            .filter(|r| r["blocked"].as_bool().unwrap_or(false) || r["probability"].as_str() == Some("HIGH"))
+            .filter_map(|r| r["category"].as_str().map(str::to_string))
+            .collect();
+        Some(FinishReasonError { stage: stage.to_string(), finish_reason, categories })
+    }
+
+    /// Token counts reported in a response (or the final chunk of a stream)
+    fn usage_from(provider: Provider, value: &serde_json::Value) -> Option<TokenUsage> {
+        let count = |v: &serde_json::Value| v.as_u64().unwrap_or(0);
+        let (prompt_tokens, candidate_tokens) = match provider {
+            Provider::Gemini => {
+                let meta = value.get("usageMetadata")?;
+                (count(&meta["promptTokenCount"]), count(&meta["candidatesTokenCount"]) + count(&meta["thoughtsTokenCount"]))
+            }
+            Provider::OpenAi => {
+                let usage = value.get("usage").filter(|u| u.is_object())?;
+                (count(&usage["prompt_tokens"]), count(&usage["completion_tokens"]))
+            }
+            Provider::Ollama => (value.get("prompt_eval_count")?.as_u64()?, count(&value["eval_count"])),
+            Provider::Mock => return None,
+        };
+        Some(TokenUsage { prompt_tokens, candidate_tokens, requests: 1 })
+    }
+
+    /// Writes one request and its outcome to the audit log, without keys or image data
+    fn audit(
+        &self,
+        target: &LlmConfig,
+        stage: &str,
+        request_body: &GeminiRequest,
+        streamed: bool,
+        started: Instant,
+        sent: &Result<(String, Option<TokenUsage>)>,
+    ) {
+        let mut request = serde_json::to_value(request_body).unwrap_or_default();
+        crate::llm_log::redact_images(&mut request);
+        let redact = |text: &str| crate::llm_log::redact_keys(text, &target.api_keys);
+        let (status, response, error) = match sent {
+            Ok((text, _)) => (Some(200), Some(redact(text)), None),
+            Err(e) => {
+                let http = http_error(e);
+                (http.map(|h| h.status.as_u16()), http.map(|h| redact(&h.body)), Some(redact(&format!("{:#}", e))))
+            }
+        };
+        crate::llm_log::record(&crate::llm_log::LlmLogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            stage: stage.to_string(),
+            provider: format!("{:?}", target.provider),
+            model: target.model_name.clone(),
+            api_base_url: redact(&target.api_base_url),
+            streamed,
+            duration_ms: started.elapsed().as_millis() as u64,
+            status,
+            request,
+            response,
+            error,
+        });
+    }
+
+    /// Adds one request's usage to this client's total and the usage log
+    fn record_usage(&self, model: &str, usage: TokenUsage) {
+        self.usage.lock().unwrap_or_else(|e| e.into_inner()).add(usage);
+        crate::token_usage::record(model, usage);
+    }
+
+    /// Parsed chunk from one line of a streamed response; `None` for lines without data
+    /// (SSE comments, keep-alives, the final `[DONE]`)
+    fn stream_chunk(provider: Provider, line: &str) -> Result<Option<serde_json::Value>> {
+        let data = match provider {
+            Provider::Ollama | Provider::Mock => line,
+            Provider::Gemini | Provider::OpenAi => match line.strip_prefix("data:") {
+                Some(data) => data.trim(),
+                None => return Ok(None),
+            },
+        };
+        if data.is_empty() || data == "[DONE]" {
+            return Ok(None);
+        }
+        let chunk: serde_json::Value = serde_json::from_str(data).with_context(|| format!("Failed to parse stream chunk: {}", data))?;
+        if let Some(error) = chunk.get("error") {
+            return Err(anyhow!("API stream failed: {}", error));
+        }
+        Ok(Some(chunk))
+    }
+
+    /// Text a stream chunk adds to the response
+    fn chunk_text(provider: Provider, chunk: &serde_json::Value) -> Option<String> {
+        let text = match provider {
+            Provider::Gemini => chunk["candidates"][0]["content"]["parts"]
+                .as_array()
+                .map(|parts| parts.iter().filter_map(|p| p["text"].as_str()).collect::<String>()),
+            Provider::OpenAi => chunk["choices"][0]["delta"]["content"].as_str().map(str::to_string),
+            Provider::Ollama => chunk["message"]["content"].as_str().map(str::to_string),
+            Provider::Mock => None,
+        };
+        text.filter(|t| !t.is_empty())
+    }
+
+    /// Sends one streaming request and returns the model's full text with the reported usage,
+    /// sending the text received so far to `partial` after every chunk
+    async fn send_streaming_request(
+        &self,
+        target: &LlmConfig,
+        request_body: &GeminiRequest,
+        api_key: Option<&str>,
+        stage: &str,
+        partial: &UnboundedSender<String>,
+    ) -> Result<(String, Option<TokenUsage>)> {
+        let request = self.build_request(target, request_body, api_key, true)?;
+        let mut response = self
+            .client
+            .execute(request)
+            .await
+            .with_context(|| format!("Failed to send request to {:?} API", target.provider))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let headers = response.headers().clone();
+            let text = response.text().await.context("Failed to read response text")?;
+            return Err(HttpError::new(status, &headers, text).into());
         }
 
+        let mut pending: Vec<u8> = Vec::new();
+        let mut text = String::new();
+        let mut usage = None;
+        let mut stopped = None;
+        let mut finished = false;
+        while !finished {
+            match response.chunk().await.context("Failed to read response stream")? {
+                Some(chunk) => pending.extend_from_slice(&chunk),
+                // 流结束时补一个换行，处理最后不完整的一行
+                None => {
+                    pending.push(b'\n');
+                    finished = true;
+                }
+            }
+            // 只处理完整的行，半行留到下一块
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                let Some(chunk) = Self::stream_chunk(target.provider, String::from_utf8_lossy(&line).trim())? else { continue };
+                let chunk = Self::unwrap_gateway_chunk(target, chunk)?;
+                // 用量通常只在最后一块中出现（Gemini 每块都带累计值）
+                if let Some(chunk_usage) = Self::usage_from(target.provider, &chunk) {
+                    usage = Some(chunk_usage);
+                }
+                if let Some(error) = Self::stop_error(target.provider, &chunk, stage) {
+                    stopped = Some(error);
+                }
+                if let Some(delta) = Self::chunk_text(target.provider, &chunk) {
+                    text.push_str(&delta);
+                    let _ = partial.send(text.clone());
+                }
+            }
+        }
+
+        #[cfg(debug_assertions)]
+        eprintln!("[LLM] Stream <- provider={:?} status={} len={}", target.provider, status.as_u16(), text.len());
+
+        if let Some(error) = stopped {
+            // 截断或被拦截的输出不可用，但已消耗的用量照常记录
+            if let Some(usage) = usage {
+                self.record_usage(&target.model_name, usage);
+            }
+            return Err(error.into());
+        }
+        Ok((text, usage))
+    }
+
+    /// Sends one request to the target's provider and returns the raw response body
+    async fn send_request(&self, target: &LlmConfig, request_body: &GeminiRequest, api_key: Option<&str>) -> Result<String> {
+        let request = self.build_request(target, request_body, api_key, false)?;
+        let url = request.url().to_string();
+
         // 打印请求摘要（不泄露密钥，不输出图片原始数据）
         #[cfg(debug_assertions)]
         {
@@ -523,6 +1766,7 @@ impl ApiClient {
                         GeminiPart::InlineData { inline_data } => {
                             parts_desc.push(format!("image({} bytes)", inline_data.data.len()))
                         }
+                        GeminiPart::FileData { file_data } => parts_desc.push(format!("file({})", file_data.file_uri)),
                     }
                 }
             }
@@ -537,14 +1781,12 @@ impl ApiClient {
 
         let response = self
             .client
-            .post(&url)
-            .header("Content-Type", "application/json")
-            .json(request_body)
-            .send()
+            .execute(request)
             .await
-            .context("Failed to send request to Gemini API")?;
+            .with_context(|| format!("Failed to send request to {:?} API", target.provider))?;
 
         let status = response.status();
+        let headers = response.headers().clone();
         let text = response
             .text()
             .await
@@ -564,14 +1806,10 @@ impl ApiClient {
         }
 
         if !status.is_success() {
-            return Err(anyhow!(
-                "API request failed with status {}: {}",
-                status,
-                text
-            ));
+            return Err(HttpError::new(status, &headers, text).into());
         }
 
-        Ok(text)
+        Self::unwrap_gateway_response(target, text)
     }
 }
 
@@ -590,11 +1828,11 @@ impl LlmClient for ApiClient {
 
     async fn verify_latex_against_image(
         &self,
+        prompt: &str,
         latex: &str,
         image_base64: &str,
-        language: &str,
     ) -> Result<crate::data_models::Verification, anyhow::Error> {
-        self.internal_verify_latex_against_image(latex, image_base64, language).await
+        self.internal_verify_latex_against_image(prompt, latex, image_base64).await
     }
 
     async fn extract_latex(
@@ -602,7 +1840,56 @@ impl LlmClient for ApiClient {
         prompt: &str,
         image_base64: &str,
     ) -> Result<String, anyhow::Error> {
-        self.internal_extract_latex(prompt, image_base64).await
+        self.internal_extract_latex(prompt, image_base64, None).await
+    }
+
+    async fn extract_latex_streaming(
+        &self,
+        prompt: &str,
+        image_base64: &str,
+        partial: UnboundedSender<String>,
+    ) -> Result<String, anyhow::Error> {
+        self.internal_extract_latex(prompt, image_base64, Some(&partial)).await
+    }
+
+    async fn detect_language(
+        &self,
+        prompt: &str,
+        image_base64: &str,
+    ) -> Result<String, anyhow::Error> {
+        self.internal_detect_language(prompt, image_base64).await
+    }
+
+    async fn convert_text_to_latex(
+        &self,
+        prompt: &str,
+        text: &str,
+    ) -> Result<String, anyhow::Error> {
+        self.internal_convert_text_to_latex(prompt, text).await
+    }
+
+    async fn locate_symbols(
+        &self,
+        latex: &str,
+        image_base64: &str,
+    ) -> Result<Vec<crate::data_models::SymbolBox>, anyhow::Error> {
+        self.internal_locate_symbols(latex, image_base64).await
+    }
+
+    async fn extract_smiles(
+        &self,
+        latex: &str,
+        image_base64: &str,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        self.internal_extract_smiles(latex, image_base64).await
+    }
+
+    async fn fix_latex(
+        &self,
+        prompt: &str,
+        latex: &str,
+    ) -> Result<String, anyhow::Error> {
+        self.internal_fix_latex(prompt, latex).await
     }
 
     async fn generate_analysis(
@@ -634,55 +1921,17 @@ impl LlmClient for ApiClient {
             generation_config: GeminiGenerationConfig {
                 temperature: 0.7,
                 max_output_tokens: self.config.max_output_tokens,
+                ..Default::default()
             },
         };
 
-        let response_text = self.send_request_with_retry(&request_body).await?;
-
-        let content = match serde_json::from_str::<GeminiResponse>(&response_text) {
-            Ok(api_response) => {
-                let maybe_text = api_response
-                    .candidates
-                    .get(0)
-                    .and_then(|c| c.content.parts.get(0))
-                    .map(|p| p.text.clone());
-                if let Some(text) = maybe_text {
-                    text
-                } else {
-                    let v: serde_json::Value = serde_json::from_str(&response_text)
-                        .with_context(|| format!("Failed to parse Gemini API response JSON: {}", response_text))?;
-                    let finish_reason = v
-                        .get("candidates")
-                        .and_then(|c| c.get(0))
-                        .and_then(|c0| c0.get("finishReason"))
-                        .and_then(|fr| fr.as_str())
-                        .unwrap_or("unknown");
-                    return Err(anyhow!(
-                        "Gemini returned no text (finishReason: {}). Raw: {}",
-                        finish_reason,
-                        response_text
-                    ));
-                }
-            }
-            Err(_) => {
-                let v: serde_json::Value = serde_json::from_str(&response_text)
-                    .with_context(|| format!("Failed to parse Gemini API response JSON: {}", response_text))?;
-                let finish_reason = v
-                    .get("candidates")
-                    .and_then(|c| c.get(0))
-                    .and_then(|c0| c0.get("finishReason"))
-                    .and_then(|fr| fr.as_str())
-                    .unwrap_or("unknown");
-                return Err(anyhow!(
-                    "Gemini returned no text (finishReason: {}). Raw: {}",
-                    finish_reason,
-                    response_text
-                ));
-            }
-        };
-
+        let content = self.send_request_with_retry(&request_body, "content generation").await?;
         Ok(self.clean_response(&content))
     }
+
+    fn token_usage(&self) -> TokenUsage {
+        *self.usage.lock().unwrap_or_else(|e| e.into_inner())
+    }
 }
 
 // 测试已移除，因为相关方法已重构
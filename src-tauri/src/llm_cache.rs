@@ -0,0 +1,97 @@
+// 模型响应缓存（可选）：以请求内容（图片、提示词、生成参数）与服务商、模型的 SHA-256 为键，
+// 将模型返回的文本保存在 llm_cache 目录下（每个请求一个文件）。相同请求在有效期内直接复用，不再计费。
+// 与 result_cache 不同，这里按单次请求缓存，提示词或模型变化后自然失效。
+
+use crate::fs_manager;
+use crate::llm_api::LlmConfig;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::AppHandle;
+
+const CACHE_DIRNAME: &str = "llm_cache";
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct CachedResponse {
+    cached_at: chrono::DateTime<chrono::Utc>,
+    model: String,
+    text: String,
+}
+
+/// Remembers the cache directory so API clients can use it without an AppHandle
+pub fn install(app_handle: &AppHandle) {
+    if let Ok(dir) = fs_manager::app_data_dir(app_handle) {
+        let _ = CACHE_DIR.set(dir.join(CACHE_DIRNAME));
+    }
+}
+
+/// Cache key: hex SHA-256 of the provider, endpoint, model and serialized request body
+pub fn key<T: Serialize>(config: &LlmConfig, request_body: &T) -> Option<String> {
+    let body = serde_json::to_vec(request_body).ok()?;
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{:?}\n{}\n{}\n", config.provider, config.api_base_url.trim(), config.model_name).as_bytes());
+    hasher.update(&body);
+    Some(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn entry_path(key: &str) -> Option<PathBuf> {
+    Some(CACHE_DIR.get()?.join(format!("{}.json", key)))
+}
+
+/// The cached text for `key` if it is younger than `ttl`; expired entries are deleted
+pub fn lookup(key: &str, ttl: Duration) -> Option<String> {
+    let path = entry_path(key)?;
+    let entry: CachedResponse = serde_json::from_slice(&std::fs::read(&path).ok()?).ok()?;
+    let age = (chrono::Utc::now() - entry.cached_at).to_std().unwrap_or_default();
+    if age > ttl {
+        let _ = std::fs::remove_file(&path);
+        return None;
+    }
+    #[cfg(debug_assertions)]
+    eprintln!("[LLM] Cache hit {} ({})", key, entry.model);
+    Some(entry.text)
+}
+
+/// Saves the model's text for `key`
+pub fn store(key: &str, model: &str, text: &str) {
+    let result = (|| -> Result<()> {
+        let path = entry_path(key).context("LLM cache is not initialized")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let entry = CachedResponse { cached_at: chrono::Utc::now(), model: model.to_string(), text: text.to_string() };
+        std::fs::write(&path, serde_json::to_vec(&entry)?).context("Failed to write LLM cache entry")
+    })();
+    if let Err(_e) = result {
+        #[cfg(debug_assertions)]
+        eprintln!("Failed to cache LLM response: {}", _e);
+    }
+}
+
+// --- Tauri commands ---
+
+/// Deletes every cached model response and returns how many were removed
+#[tauri::command]
+pub fn clear_llm_cache() -> Result<usize, String> {
+    let Some(dir) = CACHE_DIR.get() else { return Ok(0) };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
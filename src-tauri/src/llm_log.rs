@@ -0,0 +1,140 @@
+// 模型请求审计日志（可选）：开启 llmAuditLog 后，每次发给模型的请求与收到的响应按行写入 llm_log.jsonl，
+// 便于排查识别结果异常的原因。密钥不写入日志（地址中的 key 参数与响应中出现的密钥都会替换），
+// 图片数据只记录 SHA-256（与结果缓存的键相同）和字节数。日志超过上限时轮换到 llm_log.1.jsonl。
+
+use crate::fs_manager;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::AppHandle;
+
+const LOG_FILENAME: &str = "llm_log.jsonl";
+const ROTATED_LOG_FILENAME: &str = "llm_log.1.jsonl";
+/// 单个日志文件的大小上限，超过后轮换
+const MAX_LOG_BYTES: u64 = 8 * 1024 * 1024;
+/// get_llm_logs 默认返回的条数
+const DEFAULT_LIMIT: usize = 200;
+
+static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+/// 串行化写入与轮换
+static LOG_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmLogEntry {
+    pub timestamp: String,
+    /// 请求所属阶段（latex extraction、analysis 等）
+    pub stage: String,
+    pub provider: String,
+    pub model: String,
+    pub api_base_url: String,
+    pub streamed: bool,
+    pub duration_ms: u64,
+    /// 没有收到响应（网络错误）时为空
+    pub status: Option<u16>,
+    /// 请求体（统一的 Gemini 结构，其他服务商的请求由它转换而来），图片替换为 {sha256, bytes}
+    pub request: Value,
+    /// 原始响应体；流式请求为拼接后的模型输出
+    pub response: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Remembers where the log lives so API clients can write without an AppHandle
+pub fn install(app_handle: &AppHandle) {
+    if let Ok(path) = fs_manager::get_data_file_path(app_handle, LOG_FILENAME) {
+        let _ = LOG_PATH.set(path);
+    }
+}
+
+fn rotated_path(path: &PathBuf) -> PathBuf {
+    path.with_file_name(ROTATED_LOG_FILENAME)
+}
+
+/// Replaces inline image data with its hash and size, recursively
+pub fn redact_images(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let is_image = map.contains_key("mimeType") || map.contains_key("mime_type");
+            for (key, field) in map.iter_mut() {
+                match (key.as_str(), &*field) {
+                    ("data", Value::String(data)) if is_image => *field = image_summary(data),
+                    _ => redact_images(field),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_images),
+        _ => {}
+    }
+}
+
+fn image_summary(base64_data: &str) -> Value {
+    match general_purpose::STANDARD.decode(base64_data) {
+        Ok(bytes) => serde_json::json!({ "sha256": crate::result_cache::image_key(&bytes), "bytes": bytes.len() }),
+        Err(_) => serde_json::json!({ "base64Chars": base64_data.len() }),
+    }
+}
+
+/// Removes every API key from `text`
+pub fn redact_keys(text: &str, keys: &[String]) -> String {
+    keys.iter()
+        .filter(|key| key.len() >= 8)
+        .fold(text.to_string(), |text, key| text.replace(key.as_str(), "***"))
+}
+
+/// Appends one entry to the log, rotating it when it grows past the size limit
+pub fn record(entry: &LlmLogEntry) {
+    let Some(path) = LOG_PATH.get() else { return };
+    let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let result = (|| -> anyhow::Result<()> {
+        if std::fs::metadata(path).map(|m| m.len() > MAX_LOG_BYTES).unwrap_or(false) {
+            std::fs::rename(path, rotated_path(path))?;
+        }
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    })();
+    if let Err(_e) = result {
+        #[cfg(debug_assertions)]
+        eprintln!("Failed to write LLM audit log: {}", _e);
+    }
+}
+
+fn read_entries(path: &PathBuf) -> Vec<LlmLogEntry> {
+    std::fs::read_to_string(path)
+        .map(|text| text.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+        .unwrap_or_default()
+}
+
+// --- Tauri commands ---
+
+/// Most recent audit log entries, newest first (`limit` defaults to 200)
+#[tauri::command]
+pub fn get_llm_logs(limit: Option<usize>) -> Result<Vec<LlmLogEntry>, String> {
+    let Some(path) = LOG_PATH.get() else { return Ok(Vec::new()) };
+    let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries = read_entries(&rotated_path(path));
+    entries.extend(read_entries(path));
+    entries.reverse();
+    entries.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(entries)
+}
+
+/// Deletes the audit log and its rotated copy
+#[tauri::command]
+pub fn clear_llm_logs() -> Result<(), String> {
+    let Some(path) = LOG_PATH.get() else { return Ok(()) };
+    let _guard = LOG_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    for file in [path.clone(), rotated_path(path)] {
+        match std::fs::remove_file(&file) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Ok(())
+}
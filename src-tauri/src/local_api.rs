@@ -0,0 +1,230 @@
+// 本地 HTTP API：供编辑器/脚本（VS Code、Emacs、AutoHotkey 等）与正在运行的应用集成
+// 仅监听 127.0.0.1，所有请求需携带 `Authorization: Bearer <token>`（或 `X-Api-Token`）
+//
+//   GET  /health
+//   GET  /history?limit=N&favorites=true
+//   GET  /item/{id}
+//   POST /recognize   {"path": "..."} | {"imageBase64": "..."}，可选 "language"、"presetId"
+
+use crate::data_models::{Config, RecognitionMode};
+use crate::fs_manager;
+use anyhow::{anyhow, Context, Result};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::AppHandle;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+struct RunningServer {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
+}
+
+static SERVER: OnceLock<Mutex<Option<RunningServer>>> = OnceLock::new();
+
+fn server_slot() -> &'static Mutex<Option<RunningServer>> {
+    SERVER.get_or_init(|| Mutex::new(None))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecognizeRequest {
+    #[serde(default)]
+    path: Option<String>,
+    #[serde(default)]
+    image_base64: Option<String>,
+    /// 本次请求的输出语言（BCP-47），缺省使用设置中的语言
+    #[serde(default)]
+    language: Option<String>,
+    /// 提示词预设 ID
+    #[serde(default)]
+    preset_id: Option<String>,
+    /// 跳过结果缓存，强制重新识别
+    #[serde(default)]
+    force_refresh: Option<bool>,
+    /// 手写模式（手写提示词 + 图片预处理）
+    #[serde(default)]
+    handwriting: Option<bool>,
+    /// 识别模式：math（默认）或 chemistry
+    #[serde(default)]
+    mode: Option<RecognitionMode>,
+}
+
+#[derive(Serialize)]
+pub struct LocalApiStatus {
+    running: bool,
+    port: Option<u16>,
+}
+
+type ApiError = (StatusCode, String);
+
+fn json_response(status: StatusCode, value: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json; charset=utf-8")
+        .body(Body::from(value.to_string()))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+fn is_authorized(req: &Request<Body>, token: &str) -> bool {
+    let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok()).map(|v| v.trim().to_string());
+    let bearer = header("authorization").and_then(|v| v.strip_prefix("Bearer ").map(|t| t.trim().to_string()));
+    bearer.or_else(|| header("x-api-token")).map(|t| tokens_match(&t, token)).unwrap_or(false)
+}
+
+/// Compares a presented token with the expected one in constant time (for equal lengths),
+/// so response timing does not reveal how many leading characters were right
+pub(crate) fn tokens_match(presented: &str, expected: &str) -> bool {
+    let (a, b) = (presented.as_bytes(), expected.as_bytes());
+    if a.len() != b.len() || b.is_empty() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+async fn route(app: AppHandle, token: &str, req: Request<Body>) -> Result<Response<Body>, ApiError> {
+    let path = req.uri().path().trim_end_matches('/').to_string();
+    if req.method() == Method::GET && path == "/health" {
+        return Ok(json_response(StatusCode::OK, json!({ "status": "ok" })));
+    }
+    if !is_authorized(&req, token) {
+        return Err((StatusCode::UNAUTHORIZED, "missing or invalid token".into()));
+    }
+
+    let query: Vec<(String, String)> = req
+        .uri()
+        .query()
+        .map(|q| reqwest::Url::parse(&format!("http://localhost/?{}", q)))
+        .and_then(|r| r.ok())
+        .map(|u| u.query_pairs().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+        .unwrap_or_default();
+    let param = |name: &str| query.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+
+    match (req.method().clone(), path.as_str()) {
+        (Method::GET, "/history") => {
+            let mut items = crate::get_history(app).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+            if param("favorites").as_deref() == Some("true") {
+                items.retain(|i| i.is_favorite);
+            }
+            if let Some(limit) = param("limit").and_then(|l| l.parse::<usize>().ok()) {
+                items.truncate(limit);
+            }
+            Ok(json_response(StatusCode::OK, json!(items)))
+        }
+        (Method::GET, p) if p.starts_with("/item/") => {
+            let id = &p["/item/".len()..];
+            let items = crate::get_history(app).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+            items
+                .into_iter()
+                .find(|i| i.id == id)
+                .map(|item| json_response(StatusCode::OK, json!(item)))
+                .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Item with ID '{}' not found", id)))
+        }
+        (Method::POST, "/recognize") => {
+            let bytes = hyper::body::to_bytes(req.into_body())
+                .await
+                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+            let body: RecognizeRequest = serde_json::from_slice(&bytes)
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid JSON body: {}", e)))?;
+            let result = match (body.path, body.image_base64) {
+                (Some(path), _) => crate::recognize_from_file(app, path, body.language, body.preset_id, body.force_refresh, body.handwriting, body.mode).await,
+                (None, Some(image)) => crate::recognize_from_image_base64(app, image, body.language, body.preset_id, body.force_refresh, body.handwriting, body.mode).await,
+                (None, None) => return Err((StatusCode::BAD_REQUEST, "Either 'path' or 'imageBase64' is required".into())),
+            };
+            result
+                .map(|item| json_response(StatusCode::OK, json!(item)))
+                .map_err(|e| (StatusCode::BAD_GATEWAY, e))
+        }
+        _ => Err((StatusCode::NOT_FOUND, "Not found".into())),
+    }
+}
+
+/// Stops the running server (if any)
+pub fn stop() {
+    if let Some(server) = server_slot().lock().unwrap().take() {
+        let _ = server.shutdown.send(());
+    }
+}
+
+/// Returns the shared local API token, generating and persisting one when missing
+pub fn ensure_token(app_handle: &AppHandle, config: &mut Config) -> Result<String> {
+    if config.local_api.token.trim().is_empty() {
+        config.local_api.token = Uuid::new_v4().simple().to_string();
+        fs_manager::write_config(app_handle, config)?;
+    }
+    Ok(config.local_api.token.clone())
+}
+
+/// Starts the server according to the current config. Generates a token on first start.
+/// Must be called from within the async runtime.
+pub async fn start(app_handle: AppHandle) -> Result<u16> {
+    stop();
+    let mut config = fs_manager::read_config(&app_handle)?;
+    if !config.local_api.enabled {
+        return Err(anyhow!("Local API is disabled"));
+    }
+
+    let port = config.local_api.port;
+    let token = Arc::new(ensure_token(&app_handle, &mut config)?);
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let builder = Server::try_bind(&addr).with_context(|| format!("Failed to bind local API on {}", addr))?;
+
+    let make_svc = make_service_fn(move |_conn| {
+        let app = app_handle.clone();
+        let token = token.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let app = app.clone();
+                let token = token.clone();
+                async move {
+                    let resp = route(app, &token, req)
+                        .await
+                        .unwrap_or_else(|(status, msg)| json_response(status, json!({ "error": msg })));
+                    Ok::<_, Infallible>(resp)
+                }
+            }))
+        }
+    });
+
+    let (tx, rx) = oneshot::channel::<()>();
+    let server = builder.serve(make_svc).with_graceful_shutdown(async {
+        let _ = rx.await;
+    });
+    tauri::async_runtime::spawn(async move {
+        if let Err(_e) = server.await {
+            #[cfg(debug_assertions)]
+            eprintln!("[LocalAPI] Server error: {}", _e);
+        }
+    });
+    *server_slot().lock().unwrap() = Some(RunningServer { port, shutdown: tx });
+    Ok(port)
+}
+
+fn status() -> LocalApiStatus {
+    let port = server_slot().lock().unwrap().as_ref().map(|s| s.port);
+    LocalApiStatus { running: port.is_some(), port }
+}
+
+// --- Tauri commands ---
+
+/// Applies the current local API settings (start, restart on a new port, or stop)
+#[tauri::command]
+pub async fn restart_local_api(app_handle: AppHandle) -> Result<LocalApiStatus, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    if config.local_api.enabled {
+        start(app_handle).await.map_err(|e| format!("{:#}", e))?;
+    } else {
+        stop();
+    }
+    Ok(status())
+}
+
+#[tauri::command]
+pub fn get_local_api_status() -> LocalApiStatus {
+    status()
+}
@@ -7,17 +7,68 @@ mod fs_manager;
 mod llm_api;
 mod prompts;
 mod capture;
+mod backup;
+mod history_merge;
+mod local_api;
+mod event_stream;
+mod deep_link;
+mod plugins;
+mod command_hook;
+mod export;
+mod clipboard_output;
+mod shortcuts;
+mod quick_capture;
+mod text_recognition;
+mod language_detect;
+mod prompt_library;
+mod ab_testing;
+mod prompt_history;
+mod bulk_ops;
+mod duplicates;
+mod result_cache;
+mod history_schema;
+mod image_storage;
+mod symbol_boxes;
+mod fixes;
+mod latex_lint;
+mod srs;
+mod braille;
+mod spoken;
+mod collections;
+mod export_templates;
+mod bundle;
+mod updates;
+mod crash_report;
+mod usage_stats;
+mod benchmark;
+mod pipeline;
+mod history_changes;
+mod offline_queue;
+mod api_keys;
+mod failover;
+mod cost_estimate;
+mod bulk_edit;
+mod config_transfer;
+mod config_validation;
+mod rerun;
+mod onboarding;
+mod token_usage;
+mod response_schema;
+mod llm_log;
+mod llm_cache;
+mod mock_llm;
+mod ensemble;
+mod batch;
+mod job_queue;
+mod pdf_import;
+mod handwriting;
+mod chemistry;
 
 use arboard::Clipboard;
-use base64::{engine::general_purpose, Engine as _};
-use data_models::{Config, HistoryItem};
-use llm_api::{ApiClient, LlmClient};
-use screenshots::Screen;
-use tauri::{AppHandle, Manager, GlobalShortcutManager};
+use base64::Engine as _;
+use data_models::{Config, HistoryItem, RecognitionMode};
+use tauri::{AppHandle, Manager};
 use serde::Serialize;
-#[cfg(debug_assertions)]
-use serde_json::json;
-use uuid::Uuid;
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::SystemTime;
 
@@ -25,37 +76,6 @@ use std::time::SystemTime;
 
 // 旧的提示词构建函数已移至 prompts.rs 模块
 
-fn default_title_for_lang(language: &str) -> String {
-    if language == "zh-CN" { "未命名公式".to_string() } else { "Untitled formula".to_string() }
-}
-
-fn default_summary_for_lang(language: &str) -> String {
-    if language == "zh-CN" { "分析暂不可用，请稍后重试。".to_string() } else { "Analysis is temporarily unavailable. Please try again.".to_string() }
-}
-
-#[derive(Serialize, Clone)]
-struct RecognitionProgressPayload {
-    id: String,
-    stage: String, // "latex" | "analysis" | "confidence"
-    latex: Option<String>,
-    title: Option<String>,
-    analysis: Option<data_models::Analysis>,
-    confidence_score: Option<u8>,
-    created_at: Option<String>,
-    original_image: Option<String>,
-    model_name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    verification: Option<data_models::Verification>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    prompt_version: Option<String>, // "default" | "custom" | "full"
-    #[serde(skip_serializing_if = "Option::is_none")]
-    verification_report: Option<String>,
-}
-
-fn emit_progress(app_handle: &AppHandle, payload: RecognitionProgressPayload) {
-    let _ = app_handle.emit_all("recognition_progress", payload);
-}
-
 fn compute_verification_result_from_struct(
     verification: &data_models::Verification,
 ) -> data_models::VerificationResult {
@@ -110,29 +130,12 @@ fn compute_verification_result_from_struct(
     data_models::VerificationResult { confidence_score: score, verification_report: report }
 }
 
-fn determine_prompt_version(config: &crate::data_models::Config) -> String {
-    // 检查实际使用的提示词类型
-    // 根据代码逻辑：如果latex_prompt不为空，使用后端默认提示词；否则使用custom_prompt
-
-    // 如果latex_prompt不为空，说明使用的是后端默认提示词（含语言约束的完整版）
-    if !config.latex_prompt.is_empty() {
-        return "full".to_string();
-    }
-
-    // 如果latex_prompt为空但custom_prompt不为空，说明使用自定义提示词
-    if !config.custom_prompt.is_empty() {
-        return "custom".to_string();
-    }
-
-    // 兜底情况
-    "default".to_string()
-}
 
 #[tauri::command]
 async fn test_connection(app_handle: AppHandle) -> Result<String, String> {
     // 每次读取最新配置，避免旧配置缓存
     let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
-    let client = ApiClient::new(config.to_llm_config());
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
     client
         .generate_content("ping")
         .await
@@ -142,10 +145,7 @@ async fn test_connection(app_handle: AppHandle) -> Result<String, String> {
 
 #[tauri::command]
 fn open_config_dir(app_handle: AppHandle) -> Result<(), String> {
-    let dir = app_handle
-        .path_resolver()
-        .app_data_dir()
-        .ok_or_else(|| "Failed to resolve app data dir".to_string())?;
+    let dir = fs_manager::app_data_dir(&app_handle).map_err(|e| e.to_string())?;
 
     #[cfg(target_os = "windows")]
     {
@@ -174,6 +174,18 @@ fn open_config_dir(app_handle: AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+#[derive(Serialize)]
+struct DataDirInfo {
+    path: String,
+    portable: bool,
+}
+
+#[tauri::command]
+fn get_data_dir_info(app_handle: AppHandle) -> Result<DataDirInfo, String> {
+    let dir = fs_manager::app_data_dir(&app_handle).map_err(|e| e.to_string())?;
+    Ok(DataDirInfo { path: dir.to_string_lossy().to_string(), portable: fs_manager::is_portable() })
+}
+
 #[derive(Serialize)]
 struct DefaultPromptsResponse {
     latex_prompt: String,
@@ -218,6 +230,21 @@ fn get_full_prompts_with_language(language: String) -> FullPromptsResponse {
     FullPromptsResponse { latex_prompt: latex_base, analysis_prompt, verification_prompt, latex_language: None, analysis_language, verification_language }
 }
 
+#[derive(Serialize)]
+struct LanguageOption {
+    code: String,
+    name: String,
+}
+
+/// 可选输出语言（任意 BCP-47 标签均可使用，此处仅为常用列表）
+#[tauri::command]
+fn get_supported_languages() -> Vec<LanguageOption> {
+    prompts::known_languages()
+        .into_iter()
+        .map(|(code, name)| LanguageOption { code, name })
+        .collect()
+}
+
 #[derive(Serialize)]
 struct PromptParts {
     base: String,
@@ -258,194 +285,59 @@ fn get_prompt_parts(language: String, default_format: String) -> PromptPartsResp
     }
 }
 
+/// Recognizes a full-screen capture of `display_index`, or when omitted the display chosen in
+/// settings, falling back to the display under the cursor. `all_displays` (or the matching
+/// setting, when no display is given) stitches every monitor into one image instead.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn recognize_from_screenshot(
     app_handle: AppHandle,
+    display_index: Option<usize>,
+    all_displays: Option<bool>,
+    language: Option<String>,
+    preset_id: Option<String>,
+    force_refresh: Option<bool>,
+    handwriting: Option<bool>,
+    mode: Option<RecognitionMode>,
 ) -> Result<HistoryItem, String> {
-    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
-
-    let screens = Screen::all().map_err(|e| e.to_string())?;
-    if let Some(screen) = screens.first() {
-        let image = screen.capture().map_err(|e| e.to_string())?;
-        let png_bytes = image
-            .to_png(None)
-            .map_err(|e| e.to_string())?;
-        let base64_image = general_purpose::STANDARD.encode(&png_bytes);
-
-        let id = Uuid::new_v4().to_string();
-        let created_at = chrono::Utc::now().to_rfc3339();
-        let model_name = Some(config.default_engine.clone());
-
-        let client = std::sync::Arc::new(ApiClient::new(config.to_llm_config()));
-
-        // 运行期仅使用用户在前端保存的提示词；若为空则直接报错，提示用户去设置页恢复默认或保存
-        if config.latex_prompt.trim().is_empty() {
-            return Err("LaTeX 提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
-        }
-        if config.analysis_prompt.trim().is_empty() {
-            return Err("分析提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
-        }
-        if config.verification_prompt.trim().is_empty() {
-            return Err("核查提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
-        }
-
-        let latex_prompt = {
-            let mut p = config.latex_prompt.clone();
-            p.push_str(&prompts::format_rule_for_latex(&config.default_latex_format));
-            p
-        };
-        let analysis_prompt = {
-            let mut p = config.analysis_prompt.clone();
-            let lang = prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Analysis, &config.language);
-            p.push_str(&format!("\n\n{}", lang));
-            p
-        };
-        // 第1次和第2次调用同时发出（都只输入图片）
-        let latex_task = {
-            let c = client.clone();
-            let latex_prompt = latex_prompt.clone();
-            let img = base64_image.clone();
-            tokio::spawn(async move { c.extract_latex(&latex_prompt, &img).await })
-        };
-
-        let analysis_task = {
-            let c = client.clone();
-            let analysis_prompt = analysis_prompt.clone();
-            let img = base64_image.clone();
-            tokio::spawn(async move { c.generate_analysis(&analysis_prompt, &img).await })
-        };
-
-        // 等待第1次调用（LaTeX识别）完成
-        let latex = match latex_task.await {
-            Ok(Ok(latex)) => latex,
-            Ok(Err(e)) => return Err(e.to_string()),
-            Err(e) => return Err(format!("LaTeX task failed: {}", e)),
-        };
-        // 打印第1次返回（LaTeX 提取结果）
-        #[cfg(debug_assertions)]
-        {
-            let payload = json!({ "latex": &latex });
-            eprintln!("[LLM][Result][latex][{}] {}", id, payload.to_string());
-        }
-        let prompt_version = determine_prompt_version(&config);
-        emit_progress(&app_handle, RecognitionProgressPayload {
-            id: id.clone(), stage: "latex".into(), latex: Some(latex.clone()),
-            title: None, analysis: None, confidence_score: None,
-            created_at: Some(created_at.clone()),
-            original_image: Some(format!("data:image/png;base64,{}", base64_image.clone())),
-            model_name: model_name.clone(),
-            verification: None,
-            prompt_version: Some(prompt_version.clone()),
-            verification_report: None,
-        });
-
-        // 第3阶段：仅使用用户保存的核查提示词（图像+LaTeX）计算置信度与报告
-        let verification_prompt = {
-            let mut p = config.verification_prompt.clone();
-            let lang = prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Verification, &config.language);
-            p.push_str(&format!("\n\n{}", lang));
-            p
-        };
-        let verification_task = {
-            let c = client.clone();
-            let latex = latex.clone();
-            let img = base64_image.clone();
-            let verification_prompt = verification_prompt.clone();
-            tokio::spawn(async move {
-                let vr = c.get_verification_result_with_image(&verification_prompt, &latex, &img)
-                    .await
-                    .unwrap_or(crate::data_models::VerificationResult { confidence_score: 0, verification_report: "验证失败".to_string() });
-                (vr, None)
-            })
-        };
-
-        // 等待第2次调用（分析）结果
-        let (title, analysis) = match analysis_task.await {
-            Ok(Ok(v)) => v,
-            _ => (
-                default_title_for_lang(&config.language),
-                crate::data_models::Analysis { summary: default_summary_for_lang(&config.language), variables: Vec::new(), terms: Vec::new(), suggestions: Vec::new() }
-            )
-        };
-        // 打印第2次返回（分析：标题/简介/变量/项/建议）
-        #[cfg(debug_assertions)]
-        {
-            let payload = json!({ "title": &title, "analysis": &analysis });
-            eprintln!("[LLM][Result][analysis][{}] {}", id, payload.to_string());
-        }
-        emit_progress(&app_handle, RecognitionProgressPayload {
-            id: id.clone(), stage: "analysis".into(), latex: None,
-            title: Some(title.clone()), analysis: Some(analysis.clone()), confidence_score: None,
-            created_at: None, original_image: None, model_name: model_name.clone(),
-            verification: None,
-            prompt_version: Some(prompt_version.clone()),
-            verification_report: None,
-        });
-
-        // 等待第3次调用（验证）结果
-        let (verification_result, verification) = match verification_task.await {
-            Ok(result) => result,
-            Err(e) => {
-                eprintln!("Verification task failed: {}", e);
-                (crate::data_models::VerificationResult {
-                    confidence_score: 0,
-                    verification_report: "验证失败".to_string(),
-                }, None)
-            }
-        };
-        // 打印第3次返回（置信度 + 核查）
-        #[cfg(debug_assertions)]
-        {
-            let payload = json!({ "confidence_score": verification_result.confidence_score, "verification_report": &verification_result.verification_report, "verification": &verification });
-            eprintln!("[LLM][Result][confidence+verify][{}] {}", id, payload.to_string());
-        }
-        emit_progress(&app_handle, RecognitionProgressPayload {
-            id: id.clone(), stage: "confidence".into(), latex: None,
-            title: None, analysis: None, confidence_score: Some(verification_result.confidence_score),
-            created_at: None, original_image: None, model_name: model_name.clone(),
-            verification: verification.clone(),
-            prompt_version: Some(prompt_version.clone()),
-            verification_report: Some(verification_result.verification_report.clone()),
-        });
-
-        let mut history_item = HistoryItem {
-            id: id.clone(),
-            latex,
-            title,
-            analysis,
-            is_favorite: false,
-            created_at: created_at.clone(),
-            confidence_score: verification_result.confidence_score,
-            original_image: base64_image.to_string(),
-            model_name: model_name.clone(),
-            verification,
-            verification_report: Some(verification_result.verification_report),
-        };
-
-        // 将图片保存为文件（日期前缀），并用文件路径替换原始图片字段
-        let date_str = chrono::DateTime::parse_from_rfc3339(&history_item.created_at)
-            .map(|dt| dt.format("%Y%m%d_%H%M%S").to_string())
-            .unwrap_or_else(|_| chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string());
-        let stem = format!("{}_{}", date_str, history_item.id);
-        let img_path = fs_manager::save_png_to_pictures(&app_handle, &stem, &png_bytes)
-            .map_err(|e| e.to_string())?;
-        history_item.original_image = img_path.to_string_lossy().to_string();
-
-        // 持久化保存历史，防止前端页面切换导致结果丢失
-        let mut history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
-        history.insert(0, history_item.clone());
-        fs_manager::write_history(&app_handle, &history).map_err(|e| e.to_string())?;
+    let options = pipeline::RecognitionOptions {
+        language,
+        preset_id,
+        force_refresh: force_refresh.unwrap_or(false),
+        handwriting: handwriting.unwrap_or(false),
+        mode: mode.unwrap_or_default(),
+        ..Default::default()
+    };
+    screenshot_with_options(app_handle, display_index, all_displays, options).await
+}
 
-        Ok(history_item)
+/// [`recognize_from_screenshot`] with prepared options (used by the shortcuts)
+pub(crate) async fn screenshot_with_options(
+    app_handle: AppHandle,
+    display_index: Option<usize>,
+    all_displays: Option<bool>,
+    options: pipeline::RecognitionOptions,
+) -> Result<HistoryItem, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let stitch = all_displays.unwrap_or(display_index.is_none() && config.full_screen_all_displays);
+    let (png_bytes, capture_scale) = if stitch {
+        capture::capture_all_displays_png()?
     } else {
-        Err("No screens found.".to_string())
-    }
+        capture::capture_display_png(display_index, config.full_screen_display)?
+    };
+    let image = pipeline::CaptureImage::from_bytes(png_bytes).with_capture_scale(Some(capture_scale));
+    job_queue::submit(&app_handle, image, "screenshot", options).await
 }
 
 #[tauri::command]
 async fn recognize_from_file(
     app_handle: AppHandle,
     file_path: String,
+    language: Option<String>,
+    preset_id: Option<String>,
+    force_refresh: Option<bool>,
+    handwriting: Option<bool>,
+    mode: Option<RecognitionMode>,
 ) -> Result<HistoryItem, String> {
     #[cfg(debug_assertions)]
     {
@@ -453,158 +345,40 @@ async fn recognize_from_file(
         eprintln!("🔥 [DEBUG] This function should only be called once per recognition");
     }
 
-    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
-    let image_data = std::fs::read(&file_path).map_err(|e| e.to_string())?;
-    // 统一转换为 PNG 字节
-    let dyn_img = image::load_from_memory(&image_data).map_err(|e| e.to_string())?;
-    let mut png_bytes: Vec<u8> = Vec::new();
-    {
-        let mut cursor = std::io::Cursor::new(&mut png_bytes);
-        dyn_img
-            .write_to(&mut cursor, image::ImageFormat::Png)
-            .map_err(|e| e.to_string())?;
-    }
-    let base64_image = general_purpose::STANDARD.encode(&png_bytes);
-
-    let id = Uuid::new_v4().to_string();
-    let created_at = chrono::Utc::now().to_rfc3339();
-    let model_name = Some(config.default_engine.clone());
-
-        let client = std::sync::Arc::new(ApiClient::new(config.to_llm_config()));
-
-    if config.latex_prompt.trim().is_empty() {
-        return Err("LaTeX 提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
-    }
-    if config.analysis_prompt.trim().is_empty() {
-        return Err("分析提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
-    }
-    if config.verification_prompt.trim().is_empty() {
-        return Err("核查提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
-    }
-    let latex_prompt = {
-        let mut p = config.latex_prompt.clone();
-        p.push_str(&prompts::format_rule_for_latex(&config.default_latex_format));
-        p
+    let image = pipeline::CaptureImage::from_file(&file_path)?;
+    let options = pipeline::RecognitionOptions {
+        language,
+        preset_id,
+        force_refresh: force_refresh.unwrap_or(false),
+        handwriting: handwriting.unwrap_or(false),
+        mode: mode.unwrap_or_default(),
+        ..Default::default()
     };
-        let analysis_prompt = {
-            let mut p = config.analysis_prompt.clone();
-            let lang = prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Analysis, &config.language);
-            p.push_str(&format!("\n\n{}", lang));
-            p
-        };
-    // 第1次和第2次调用同时发出（都只输入图片）
-    let latex_task = {
-        let c = client.clone();
-        let latex_prompt = latex_prompt.clone();
-        let img = base64_image.clone();
-        tokio::spawn(async move { c.extract_latex(&latex_prompt, &img).await })
-    };
-
-    let analysis_task = {
-        let c = client.clone();
-        let analysis_prompt = analysis_prompt.clone();
-        let img = base64_image.clone();
-        tokio::spawn(async move { c.generate_analysis(&analysis_prompt, &img).await })
-    };
-
-    // 等待第1次调用（LaTeX识别）完成
-    let latex = match latex_task.await {
-        Ok(Ok(latex)) => latex,
-        Ok(Err(e)) => return Err(e.to_string()),
-        Err(e) => return Err(format!("LaTeX task failed: {}", e)),
-    };
-    #[cfg(debug_assertions)]
-    {
-        let payload = json!({ "latex": &latex });
-        eprintln!("[LLM][Result][latex][{}] {}", id, payload.to_string());
-    }
-    let prompt_version = determine_prompt_version(&config);
-    emit_progress(&app_handle, RecognitionProgressPayload { id: id.clone(), stage: "latex".into(), latex: Some(latex.clone()), title: None, analysis: None, confidence_score: None, created_at: Some(created_at.clone()), original_image: Some(format!("data:image/png;base64,{}", base64_image.clone())), model_name: model_name.clone(), verification: None, prompt_version: Some(prompt_version.clone()), verification_report: None });
-
-    // 第3次调用：在第1次完成后发出（输入图片+LaTeX）
-    let verification_prompt = {
-        let mut p = config.verification_prompt.clone();
-        let lang = prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Verification, &config.language);
-        p.push_str(&format!("\n\n{}", lang));
-        p
-    };
-    let verification_task = {
-        let c = client.clone();
-        let latex = latex.clone();
-        let img = base64_image.clone();
-            let verification_prompt = verification_prompt.clone();
-        tokio::spawn(async move {
-                let vr = c.get_verification_result_with_image(&verification_prompt, &latex, &img)
-                    .await
-                    .unwrap_or(crate::data_models::VerificationResult { confidence_score: 0, verification_report: "验证失败".to_string() });
-                (vr, None)
-        })
-    };
-    // 等待第2次调用（分析）结果
-    let (title, analysis) = match analysis_task.await { Ok(Ok(v)) => v, _ => (default_title_for_lang(&config.language), crate::data_models::Analysis { summary: default_summary_for_lang(&config.language), variables: Vec::new(), terms: Vec::new(), suggestions: Vec::new() }) };
-    #[cfg(debug_assertions)]
-    {
-        let payload = json!({ "title": &title, "analysis": &analysis });
-        eprintln!("[LLM][Result][analysis][{}] {}", id, payload.to_string());
-    }
-    emit_progress(&app_handle, RecognitionProgressPayload { id: id.clone(), stage: "analysis".into(), latex: None, title: Some(title.clone()), analysis: Some(analysis.clone()), confidence_score: None, created_at: None, original_image: None, model_name: model_name.clone(), verification: None, prompt_version: Some(prompt_version.clone()), verification_report: None });
-
-    // 等待第3次调用（验证）结果
-    let (verification_result, verification) = match verification_task.await {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("Verification task failed: {}", e);
-            (crate::data_models::VerificationResult {
-                confidence_score: 0,
-                verification_report: "验证失败".to_string(),
-            }, None)
-        }
-    };
-    // 若有细粒度核查，则以其计算的分数/报告为准，否则使用回退评分
-        let final_verification_result = verification_result.clone();
-    #[cfg(debug_assertions)]
-    {
-        let payload = json!({ "confidence_score": final_verification_result.confidence_score, "verification_report": &final_verification_result.verification_report, "verification": &verification });
-        eprintln!("[LLM][Result][confidence+verify][{}] {}", id, payload.to_string());
-    }
-    emit_progress(&app_handle, RecognitionProgressPayload { id: id.clone(), stage: "confidence".into(), latex: None, title: None, analysis: None, confidence_score: Some(final_verification_result.confidence_score), created_at: None, original_image: None, model_name: model_name.clone(), verification: verification.clone(), prompt_version: Some(prompt_version.clone()), verification_report: Some(final_verification_result.verification_report.clone()) });
-
-    let mut history_item = HistoryItem {
-        id: id.clone(),
-        latex,
-        title,
-        analysis,
-        is_favorite: false,
-        created_at: created_at.clone(),
-        confidence_score: final_verification_result.confidence_score,
-        original_image: base64_image.to_string(),
-        model_name: model_name.clone(),
-            verification: None,
-        verification_report: Some(final_verification_result.verification_report),
-    };
-
-    // 将图片保存为文件（日期前缀），并用文件路径替换原始图片字段
-    let date_str = chrono::DateTime::parse_from_rfc3339(&history_item.created_at)
-        .map(|dt| dt.format("%Y%m%d_%H%M%S").to_string())
-        .unwrap_or_else(|_| chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string());
-    let stem = format!("{}_{}", date_str, history_item.id);
-    let img_path = fs_manager::save_png_to_pictures(&app_handle, &stem, &png_bytes)
-        .map_err(|e| e.to_string())?;
-    history_item.original_image = img_path.to_string_lossy().to_string();
-
-    // 持久化保存历史
-    let mut history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
-    history.insert(0, history_item.clone());
-    fs_manager::write_history(&app_handle, &history).map_err(|e| e.to_string())?;
-
-    Ok(history_item)
+    pipeline::run(&app_handle, image, "file", options).await
 }
 
 #[tauri::command]
 async fn recognize_from_clipboard(
     app_handle: AppHandle,
+    language: Option<String>,
+    preset_id: Option<String>,
+    force_refresh: Option<bool>,
+    handwriting: Option<bool>,
+    mode: Option<RecognitionMode>,
 ) -> Result<HistoryItem, String> {
-    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let options = pipeline::RecognitionOptions {
+        language,
+        preset_id,
+        force_refresh: force_refresh.unwrap_or(false),
+        handwriting: handwriting.unwrap_or(false),
+        mode: mode.unwrap_or_default(),
+        ..Default::default()
+    };
+    clipboard_with_options(app_handle, options).await
+}
+
+/// [`recognize_from_clipboard`] with prepared options (used by the shortcuts)
+pub(crate) async fn clipboard_with_options(app_handle: AppHandle, options: pipeline::RecognitionOptions) -> Result<HistoryItem, String> {
     let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
 
     let image = clipboard.get_image().map_err(|e| e.to_string())?;
@@ -619,264 +393,42 @@ async fn recognize_from_clipboard(
     
     let dynamic_img = image::DynamicImage::ImageRgba8(img_buffer);
 
-    // Encode to PNG and then to base64
+    // Encode to PNG
     let mut png_bytes = Vec::new();
     let mut cursor = std::io::Cursor::new(&mut png_bytes);
     dynamic_img
         .write_to(&mut cursor, image::ImageFormat::Png)
         .map_err(|e| format!("Failed to encode clipboard image: {}", e))?;
-    let base64_image = general_purpose::STANDARD.encode(&png_bytes);
-
-    let id = Uuid::new_v4().to_string();
-    let created_at = chrono::Utc::now().to_rfc3339();
-    let model_name = Some(config.default_engine.clone());
-
-    let client = std::sync::Arc::new(ApiClient::new(config.to_llm_config()));
-
-    if config.latex_prompt.trim().is_empty() {
-        return Err("LaTeX 提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
-    }
-    if config.analysis_prompt.trim().is_empty() {
-        return Err("分析提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
-    }
-    if config.verification_prompt.trim().is_empty() {
-        return Err("核查提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
-    }
-    let latex_prompt = {
-        let mut p = config.latex_prompt.clone();
-        p.push_str(&prompts::format_rule_for_latex(&config.default_latex_format));
-        p
-    };
-    let analysis_prompt = {
-        let mut p = config.analysis_prompt.clone();
-        let lang = prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Analysis, &config.language);
-        p.push_str(&format!("\n\n{}", lang));
-        p
-    };
-    // 第1次和第2次调用同时发出（都只输入图片）
-    let latex_task = {
-        let c = client.clone();
-        let latex_prompt = latex_prompt.clone();
-        let img = base64_image.clone();
-        tokio::spawn(async move { c.extract_latex(&latex_prompt, &img).await })
-    };
-
-    let analysis_task = {
-        let c = client.clone();
-        let analysis_prompt = analysis_prompt.clone();
-        let img = base64_image.clone();
-        tokio::spawn(async move { c.generate_analysis(&analysis_prompt, &img).await })
-    };
-
-    // 等待第1次调用（LaTeX识别）完成
-    let latex = match latex_task.await {
-        Ok(Ok(latex)) => latex,
-        Ok(Err(e)) => return Err(e.to_string()),
-        Err(e) => return Err(format!("LaTeX task failed: {}", e)),
-    };
-    let prompt_version = determine_prompt_version(&config);
-    emit_progress(&app_handle, RecognitionProgressPayload { id: id.clone(), stage: "latex".into(), latex: Some(latex.clone()), title: None, analysis: None, confidence_score: None, created_at: Some(created_at.clone()), original_image: Some(format!("data:image/png;base64,{}", base64_image.clone())), model_name: model_name.clone(), verification: None, prompt_version: Some(prompt_version.clone()), verification_report: None });
-
-    // 第3次调用：在第1次完成后发出（输入图片+LaTeX）
-    let verification_prompt = config.verification_prompt.clone();
-    let verification_task = {
-        let c = client.clone();
-        let latex = latex.clone();
-        let img = base64_image.clone();
-            let verification_prompt = verification_prompt.clone();
-        tokio::spawn(async move {
-                let vr = c.get_verification_result_with_image(&verification_prompt, &latex, &img)
-                    .await
-                    .unwrap_or(crate::data_models::VerificationResult { confidence_score: 0, verification_report: "验证失败".to_string() });
-                (vr, None)
-        })
-    };
-
-    // 等待第2次调用（分析）结果
-    let (title, analysis) = match analysis_task.await { Ok(Ok(v)) => v, _ => (default_title_for_lang(&config.language), crate::data_models::Analysis { summary: default_summary_for_lang(&config.language), variables: Vec::new(), terms: Vec::new(), suggestions: Vec::new() }) };
-    emit_progress(&app_handle, RecognitionProgressPayload { id: id.clone(), stage: "analysis".into(), latex: None, title: Some(title.clone()), analysis: Some(analysis.clone()), confidence_score: None, created_at: None, original_image: None, model_name: model_name.clone(), verification: None, prompt_version: Some(prompt_version.clone()), verification_report: None });
-
-    // 等待第3次调用（验证）结果
-    let (verification_result, verification) = match verification_task.await {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("Verification task failed: {}", e);
-            (crate::data_models::VerificationResult {
-                confidence_score: 0,
-                verification_report: "验证失败".to_string(),
-            }, None)
-        }
-    };
-    emit_progress(&app_handle, RecognitionProgressPayload { id: id.clone(), stage: "confidence".into(), latex: None, title: None, analysis: None, confidence_score: Some(verification_result.confidence_score), created_at: None, original_image: None, model_name: model_name.clone(), verification: verification.clone(), prompt_version: Some(prompt_version.clone()), verification_report: Some(verification_result.verification_report.clone()) });
-
-    let mut history_item = HistoryItem {
-        id: id.clone(),
-        latex,
-        title,
-        analysis,
-        is_favorite: false,
-        created_at: created_at.clone(),
-        confidence_score: verification_result.confidence_score,
-        original_image: base64_image.to_string(),
-        model_name: model_name.clone(),
-        verification,
-        verification_report: Some(verification_result.verification_report),
-    };
-
-    // 将图片保存为文件（日期前缀），并用文件路径替换原始图片字段
-    let date_str = chrono::DateTime::parse_from_rfc3339(&history_item.created_at)
-        .map(|dt| dt.format("%Y%m%d_%H%M%S").to_string())
-        .unwrap_or_else(|_| chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string());
-    let stem = format!("{}_{}", date_str, history_item.id);
-    let img_path = fs_manager::save_png_to_pictures(&app_handle, &stem, &png_bytes)
-        .map_err(|e| e.to_string())?;
-    history_item.original_image = img_path.to_string_lossy().to_string();
-
-    // 持久化保存历史
-    let mut history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
-    history.insert(0, history_item.clone());
-    fs_manager::write_history(&app_handle, &history).map_err(|e| e.to_string())?;
-
-    Ok(history_item)
+    pipeline::run(&app_handle, pipeline::CaptureImage::from_bytes(png_bytes), "clipboard", options).await
 }
 
 #[tauri::command]
 async fn recognize_from_image_base64(
     app_handle: AppHandle,
     image_base64: String,
+    language: Option<String>,
+    preset_id: Option<String>,
+    force_refresh: Option<bool>,
+    handwriting: Option<bool>,
+    mode: Option<RecognitionMode>,
 ) -> Result<HistoryItem, String> {
-    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
-
-    // 输入已是 base64 的 PNG 数据
-    let base64_image = image_base64;
-    let png_bytes = match base64::engine::general_purpose::STANDARD.decode(&base64_image) {
-        Ok(bytes) => bytes,
-        Err(e) => return Err(format!("Failed to decode base64 image: {}", e)),
+    // 输入已是 base64 的图片数据（PNG/JPEG/WebP）
+    let image = pipeline::CaptureImage::from_base64(image_base64)?;
+    let options = pipeline::RecognitionOptions {
+        language,
+        preset_id,
+        force_refresh: force_refresh.unwrap_or(false),
+        handwriting: handwriting.unwrap_or(false),
+        mode: mode.unwrap_or_default(),
+        ..Default::default()
     };
-
-    let id = Uuid::new_v4().to_string();
-    let created_at = chrono::Utc::now().to_rfc3339();
-    let model_name = Some(config.default_engine.clone());
-
-    let client = std::sync::Arc::new(ApiClient::new(config.to_llm_config()));
-
-    let latex_prompt = if !config.latex_prompt.is_empty() {
-        let mut p = config.latex_prompt.clone();
-        p.push_str(&prompts::format_rule_for_latex(&config.default_latex_format));
-        p
-    } else {
-        config.custom_prompt.clone()
-    };
-    let analysis_prompt = if !config.analysis_prompt.is_empty() {
-        let mut p = config.analysis_prompt.clone();
-        let lang = prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Analysis, &config.language);
-        p.push_str(&format!("\n\n{}", lang));
-        p
-    } else {
-        config.custom_prompt.clone()
-    };
-
-    // 第1次和第2次调用同时发出（都只输入图片）
-    let latex_task = {
-        let c = client.clone();
-        let latex_prompt = latex_prompt.clone();
-        let img = base64_image.clone();
-        tokio::spawn(async move { c.extract_latex(&latex_prompt, &img).await })
-    };
-
-    let analysis_task = {
-        let c = client.clone();
-        let analysis_prompt = analysis_prompt.clone();
-        let img = base64_image.clone();
-        tokio::spawn(async move { c.generate_analysis(&analysis_prompt, &img).await })
-    };
-
-    // 等待第1次调用（LaTeX识别）完成
-    let latex = match latex_task.await {
-        Ok(Ok(latex)) => latex,
-        Ok(Err(e)) => return Err(e.to_string()),
-        Err(e) => return Err(format!("LaTeX task failed: {}", e)),
-    };
-    let prompt_version = determine_prompt_version(&config);
-    emit_progress(&app_handle, RecognitionProgressPayload { id: id.clone(), stage: "latex".into(), latex: Some(latex.clone()), title: None, analysis: None, confidence_score: None, created_at: Some(created_at.clone()), original_image: Some(format!("data:image/png;base64,{}", base64_image.clone())), model_name: model_name.clone(), verification: None, prompt_version: Some(prompt_version.clone()), verification_report: None });
-
-    // 第3次调用：在第1次完成后发出（输入图片+LaTeX），优先细粒度核查
-    let verification_prompt = {
-        let mut p = config.verification_prompt.clone();
-        let lang = prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Verification, &config.language);
-        p.push_str(&format!("\n\n{}", lang));
-        p
-    };
-    let verification_task = {
-        let c = client.clone();
-        let latex = latex.clone();
-        let img = base64_image.clone();
-            let verification_prompt = verification_prompt.clone();
-        tokio::spawn(async move {
-                let vr = c.get_verification_result_with_image(&verification_prompt, &latex, &img)
-                    .await
-                    .unwrap_or(crate::data_models::VerificationResult { confidence_score: 0, verification_report: "验证失败".to_string() });
-                (vr, None)
-        })
-    };
-
-    // 等待第2次调用（分析）结果
-    let (title, analysis) = match analysis_task.await {
-        Ok(Ok(v)) => v,
-        _ => (
-            default_title_for_lang(&config.language),
-            crate::data_models::Analysis { summary: default_summary_for_lang(&config.language), variables: Vec::new(), terms: Vec::new(), suggestions: Vec::new() }
-        )
-    };
-    emit_progress(&app_handle, RecognitionProgressPayload { id: id.clone(), stage: "analysis".into(), latex: None, title: Some(title.clone()), analysis: Some(analysis.clone()), confidence_score: None, created_at: None, original_image: None, model_name: model_name.clone(), verification: None, prompt_version: Some(prompt_version.clone()), verification_report: None });
-
-    // 等待第3次调用（验证）结果
-    let (verification_result, verification) = match verification_task.await {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("Verification task failed: {}", e);
-            (crate::data_models::VerificationResult {
-                confidence_score: 0,
-                verification_report: "验证失败".to_string(),
-            }, None)
-        }
-    };
-    emit_progress(&app_handle, RecognitionProgressPayload { id: id.clone(), stage: "confidence".into(), latex: None, title: None, analysis: None, confidence_score: Some(verification_result.confidence_score), created_at: None, original_image: None, model_name: model_name.clone(), verification: verification.clone(), prompt_version: Some(prompt_version.clone()), verification_report: Some(verification_result.verification_report.clone()) });
-
-    let mut history_item = HistoryItem {
-        id: id.clone(),
-        latex,
-        title,
-        analysis,
-        is_favorite: false,
-        created_at: created_at.clone(),
-        confidence_score: verification_result.confidence_score,
-        original_image: base64_image.to_string(),
-        model_name: model_name.clone(),
-        verification,
-        verification_report: Some(verification_result.verification_report),
-    };
-
-    // 将图片保存为文件，并替换为路径
-    let date_str = chrono::DateTime::parse_from_rfc3339(&history_item.created_at)
-        .map(|dt| dt.format("%Y%m%d_%H%M%S").to_string())
-        .unwrap_or_else(|_| chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string());
-    let stem = format!("{}_{}", date_str, history_item.id);
-    let img_path = fs_manager::save_png_to_pictures(&app_handle, &stem, &png_bytes)
-        .map_err(|e| e.to_string())?;
-    history_item.original_image = img_path.to_string_lossy().to_string();
-
-    // 持久化保存历史
-    let mut history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
-    history.insert(0, history_item.clone());
-    fs_manager::write_history(&app_handle, &history).map_err(|e| e.to_string())?;
-
-    Ok(history_item)
+    pipeline::run(&app_handle, image, "image", options).await
 }
+
 #[tauri::command]
-fn copy_image_to_clipboard(image_path: String) -> Result<(), String> {
+fn copy_image_to_clipboard(app_handle: AppHandle, image_path: String) -> Result<(), String> {
     // 读取图片并复制到系统剪贴板
-    let bytes = std::fs::read(&image_path).map_err(|e| e.to_string())?;
+    let bytes = std::fs::read(fs_manager::resolve_image_path(&app_handle, &image_path)).map_err(|e| e.to_string())?;
     let dyn_img = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
     let rgba = dyn_img.to_rgba8();
     let (w, h) = rgba.dimensions();
@@ -890,18 +442,10 @@ fn copy_image_to_clipboard(image_path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn read_image_as_data_url(image_path: String) -> Result<String, String> {
-    let bytes = std::fs::read(&image_path).map_err(|e| e.to_string())?;
-    let mime = if image_path.to_ascii_lowercase().ends_with(".jpg")
-        || image_path.to_ascii_lowercase().ends_with(".jpeg")
-    {
-        "image/jpeg"
-    } else if image_path.to_ascii_lowercase().ends_with(".gif") {
-        "image/gif"
-    } else {
-        // default to png
-        "image/png"
-    };
+fn read_image_as_data_url(app_handle: AppHandle, image_path: String) -> Result<String, String> {
+    let bytes = std::fs::read(fs_manager::resolve_image_path(&app_handle, &image_path)).map_err(|e| e.to_string())?;
+    // 按文件内容识别格式（历史图片可能以 PNG 或 WebP 存储）
+    let mime = image_storage::mime_type(&bytes);
     let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
     Ok(format!("data:{};base64,{}", mime, encoded))
 }
@@ -950,11 +494,35 @@ fn get_history(app_handle: AppHandle) -> Result<Vec<HistoryItem>, String> {
     Ok(data)
 }
 
+/// The most recent history item, if any. With `copy` its LaTeX is also put on the clipboard.
 #[tauri::command]
-fn save_to_history(app_handle: AppHandle, item: HistoryItem) -> Result<(), String> {
-    let mut history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
+fn get_last_item(app_handle: AppHandle, copy: Option<bool>) -> Result<Option<HistoryItem>, String> {
+    recall_last_item(app_handle, copy.unwrap_or(false), false)
+}
+
+/// Like [`get_last_item`]; with `paste` (shortcut-triggered) the copied LaTeX is also pasted into
+/// the foreground app when auto-paste is on
+pub(crate) fn recall_last_item(app_handle: AppHandle, copy: bool, paste: bool) -> Result<Option<HistoryItem>, String> {
+    let history = get_history(app_handle.clone())?;
+    let last = history.into_iter().max_by(|a, b| a.created_at.cmp(&b.created_at));
+    if let (Some(item), true) = (&last, copy) {
+        let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+        if paste {
+            clipboard_output::arm_auto_paste(&config, &item.id);
+        }
+        if !clipboard_output::deliver_latex(&app_handle, &config, &item.id, &item.latex, true) {
+            return Err("Failed to copy the LaTeX to the clipboard".to_string());
+        }
+    }
+    Ok(last)
+}
+
+#[tauri::command]
+async fn save_to_history(app_handle: AppHandle, item: HistoryItem) -> Result<(), String> {
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    let mut history = fs_manager::read_history_async(&app_handle).await.map_err(|e| e.to_string())?;
     history.insert(0, item);
-    fs_manager::write_history(&app_handle, &history).map_err(|e| e.to_string())?;
+    fs_manager::write_history_async(&app_handle, history.clone()).await.map_err(|e| e.to_string())?;
     // 更新缓存
     let cache = init_cache_if_needed();
     let mut cache_guard = cache.lock().unwrap();
@@ -966,14 +534,17 @@ fn save_to_history(app_handle: AppHandle, item: HistoryItem) -> Result<(), Strin
 }
 
 #[tauri::command]
-fn delete_history_item(app_handle: AppHandle, id: String) -> Result<(), String> {
-    let mut history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
-    let before_len = history.len();
-    history.retain(|item| item.id != id);
-    if history.len() == before_len {
-        return Err(format!("Item with ID '{}' not found", id));
-    }
-    fs_manager::write_history(&app_handle, &history).map_err(|e| e.to_string())?;
+async fn delete_history_item(app_handle: AppHandle, id: String) -> Result<(), String> {
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    let mut history = fs_manager::read_history_async(&app_handle).await.map_err(|e| e.to_string())?;
+    let index = history
+        .iter()
+        .position(|item| item.id == id)
+        .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+    let removed = history.remove(index);
+    fs_manager::write_history_async(&app_handle, history.clone()).await.map_err(|e| e.to_string())?;
+    // 图片可能被其他条目共享，仅在无引用时删除
+    fs_manager::release_image(&app_handle, &removed.original_image, &history);
     let cache = init_cache_if_needed();
     let mut cache_guard = cache.lock().unwrap();
     cache_guard.data = history;
@@ -983,54 +554,64 @@ fn delete_history_item(app_handle: AppHandle, id: String) -> Result<(), String>
     Ok(())
 }
 
+/// Applies a partial update (title, favorite, tags, notes, LaTeX) to one item in a single
+/// history write and returns the updated item. A LaTeX change keeps the old LaTeX as a revision.
 #[tauri::command]
-fn update_history_title(
-    app_handle: AppHandle,
-    id: String,
-    title: String,
-) -> Result<(), String> {
-    let mut history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
-    if let Some(item) = history.iter_mut().find(|item| item.id == id) {
+async fn update_history_item(app_handle: AppHandle, id: String, patch: data_models::HistoryItemPatch) -> Result<HistoryItem, String> {
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    let mut history = fs_manager::read_history_async(&app_handle).await.map_err(|e| e.to_string())?;
+    let item = history
+        .iter_mut()
+        .find(|item| item.id == id)
+        .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+    if let Some(title) = patch.title {
         item.title = title;
-        fs_manager::write_history(&app_handle, &history).map_err(|e| e.to_string())?;
-        // 更新缓存
-        let cache = init_cache_if_needed();
-        let mut cache_guard = cache.lock().unwrap();
-        cache_guard.data = history;
-        cache_guard.last_mtime = std::fs::metadata(
-            &fs_manager::get_history_path(&app_handle).map_err(|e| e.to_string())?
-        ).and_then(|m| m.modified()).ok();
-        Ok(())
-    } else {
-        Err(format!("Item with ID '{}' not found", id))
     }
+    if let Some(is_favorite) = patch.is_favorite {
+        item.is_favorite = is_favorite;
+    }
+    if let Some(tags) = patch.tags {
+        let tags = data_models::normalize_tags(tags);
+        item.tags = if tags.is_empty() { None } else { Some(tags) };
+    }
+    if let Some(notes) = patch.notes {
+        item.notes = if notes.trim().is_empty() { None } else { Some(notes) };
+    }
+    if let Some(latex) = patch.latex.filter(|latex| *latex != item.latex) {
+        fixes::push_revision(item, "edit".to_string());
+        item.latex = latex;
+    }
+    let updated = item.clone();
+    // 频繁的小修改走延迟写入，合并为一次磁盘写入
+    fs_manager::write_history_deferred(&app_handle, history.clone());
+    let cache = init_cache_if_needed();
+    let mut cache_guard = cache.lock().unwrap();
+    cache_guard.data = history;
+    // 文件尚未写入，不能用它的 mtime；清空后下次 get_history 重新读取（会拿到内存中的待写版本）
+    cache_guard.last_mtime = None;
+    Ok(updated)
 }
 
+/// Sets (or clears, with an empty/absent source) where the given items came from
 #[tauri::command]
-fn update_favorite_status(
+async fn set_item_source(
     app_handle: AppHandle,
-    id: String,
-    // 兼容前端传参：同时支持 snake_case 与 camelCase
-    #[allow(non_snake_case)]
-    is_favorite: Option<bool>,
-    #[allow(non_snake_case)]
-    isFavorite: Option<bool>,
-) -> Result<(), String> {
-    let is_favorite = is_favorite.or(isFavorite).ok_or_else(|| "missing is_favorite/isFavorite".to_string())?;
-    let mut history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
-    if let Some(item) = history.iter_mut().find(|item| item.id == id) {
-        item.is_favorite = is_favorite;
-        fs_manager::write_history(&app_handle, &history).map_err(|e| e.to_string())?;
-        let cache = init_cache_if_needed();
-        let mut cache_guard = cache.lock().unwrap();
-        cache_guard.data = history;
-        cache_guard.last_mtime = std::fs::metadata(
-            &fs_manager::get_history_path(&app_handle).map_err(|e| e.to_string())?
-        ).and_then(|m| m.modified()).ok();
-        Ok(())
-    } else {
-        Err(format!("Item with ID '{}' not found", id))
+    ids: Vec<String>,
+    source: Option<data_models::SourceInfo>,
+) -> Result<usize, String> {
+    let source = source.and_then(|s| s.normalized());
+    let _insert = pipeline::HISTORY_INSERT.lock().await;
+    let mut history = fs_manager::read_history_async(&app_handle).await.map_err(|e| e.to_string())?;
+    let mut updated = 0;
+    for item in history.iter_mut().filter(|item| ids.contains(&item.id)) {
+        item.source = source.clone();
+        updated += 1;
     }
+    if updated == 0 {
+        return Err("No matching history items".to_string());
+    }
+    fs_manager::write_history_async(&app_handle, history).await.map_err(|e| e.to_string())?;
+    Ok(updated)
 }
 
 #[tauri::command]
@@ -1038,29 +619,35 @@ fn get_config(app_handle: AppHandle) -> Result<Config, String> {
     fs_manager::read_config(&app_handle).map_err(|e| e.to_string())
 }
 
+/// Validates and saves the config. Fails with the field-level errors when invalid;
+/// otherwise returns the warnings (if any) alongside.
 #[tauri::command]
-fn save_config(app_handle: AppHandle, config: Config) -> Result<(), String> {
-    fs_manager::write_config(&app_handle, &config).map_err(|e| e.to_string())
+fn save_config(app_handle: AppHandle, config: Config) -> Result<config_validation::ConfigValidation, String> {
+    let validation = config_validation::validate(&config);
+    if !validation.is_valid() {
+        return Err(validation.error_message());
+    }
+    fs_manager::write_config(&app_handle, &config).map_err(|e| e.to_string())?;
+    Ok(validation)
 }
 
+/// Resets one config section (prompts / models / window / shortcuts) to its defaults and
+/// returns the updated config. Replaced prompts are kept in the prompt history.
 #[tauri::command]
-fn register_global_shortcut(app_handle: AppHandle, shortcut: String) -> Result<(), String> {
-    // 先取消注册所有现有的快捷键
-    app_handle.global_shortcut_manager().unregister_all().map_err(|e| e.to_string())?;
-
-    // 注册新的快捷键
-    let app_handle_for_shortcut = app_handle.clone();
-    app_handle.global_shortcut_manager().register(&shortcut, move || {
-        let app_handle = app_handle_for_shortcut.clone();
-        tauri::async_runtime::spawn(async move {
-            if let Err(_e) = capture::open_overlays_for_all_displays(app_handle).await {
-                #[cfg(debug_assertions)]
-                eprintln!("Failed to open overlays from shortcut: {}", _e);
-            }
-        });
-    }).map_err(|e| e.to_string())?;
-
-    Ok(())
+fn reset_config_section(app_handle: AppHandle, section: data_models::ConfigSection) -> Result<Config, String> {
+    let mut config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    if section == data_models::ConfigSection::Prompts {
+        prompt_history::record_revision(&app_handle, &config, "reset").map_err(|e| e.to_string())?;
+    }
+    config.reset_section(section);
+    fs_manager::write_config(&app_handle, &config).map_err(|e| e.to_string())?;
+    if section == data_models::ConfigSection::Shortcuts {
+        for (_action, _e) in shortcuts::register_all(&app_handle, &config) {
+            #[cfg(debug_assertions)]
+            eprintln!("Failed to register shortcut {:?}: {}", _action, _e);
+        }
+    }
+    Ok(config)
 }
 
 #[tauri::command]
@@ -1069,8 +656,12 @@ async fn get_confidence_score(
     latex: String,
 ) -> Result<u8, String> {
     let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
-    let client = ApiClient::new(config.to_llm_config());
-    let verification_prompt = prompts::get_verification_prompt(&config.language);
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
+    let verification_prompt = format!(
+        "{}\n\n{}",
+        config.stage_prompt(prompts::PromptType::Verification),
+        prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Verification, &config.language)
+    );
     let verification_result = client
         .get_verification_result(&verification_prompt, &latex)
         .await
@@ -1082,14 +673,16 @@ async fn get_confidence_score(
 async fn retry_analysis_phase(
     app_handle: AppHandle,
     image_base64: String,
+    language: Option<String>,
 ) -> Result<(String, crate::data_models::Analysis), String> {
     let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
-    let client = ApiClient::new(config.to_llm_config());
-    let analysis_prompt = if !config.analysis_prompt.is_empty() {
-        prompts::get_analysis_prompt(&config.language)
-    } else {
-        config.custom_prompt.clone()
-    };
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
+    let language = language.filter(|l| !l.trim().is_empty()).unwrap_or_else(|| config.language.clone());
+    let analysis_prompt = format!(
+        "{}\n\n{}",
+        config.stage_prompt(prompts::PromptType::Analysis),
+        prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Analysis, &language)
+    );
 
     let result = client
         .generate_analysis(&analysis_prompt, &image_base64)
@@ -1104,46 +697,48 @@ async fn retry_verification_phase(
     app_handle: AppHandle,
     latex: String,
     image_base64: String,
+    language: Option<String>,
 ) -> Result<(crate::data_models::VerificationResult, Option<crate::data_models::Verification>), String> {
     let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
-    let client = ApiClient::new(config.to_llm_config());
-    let verification_prompt = prompts::get_verification_prompt(&config.language);
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
+    let language = language.filter(|l| !l.trim().is_empty()).unwrap_or_else(|| config.language.clone());
+    let verification_prompt = prompts::get_verification_prompt(config.stage_prompt(prompts::PromptType::Verification), &language);
 
-    match client.verify_latex_against_image(&latex, &image_base64, &config.language).await {
+    match client.verify_latex_against_image(&verification_prompt, &latex, &image_base64).await {
         Ok(v) => {
             let vr = compute_verification_result_from_struct(&v);
             Ok((vr, Some(v)))
         }
-        Err(_) => {
-            let fallback = client
-                .get_verification_result_with_image(&verification_prompt, &latex, &image_base64)
-                .await
-                .unwrap_or(crate::data_models::VerificationResult { confidence_score: 0, verification_report: "验证失败".to_string() });
-            Ok((fallback, None))
-        }
+        Err(_) => Ok((crate::data_models::VerificationResult { confidence_score: 0, verification_report: "验证失败".to_string() }, None)),
     }
 }
 
 fn main() {
+    // 通过 aiformula:// 启动时，若已有实例在运行则转发后退出
+    let deep_link_url = deep_link::url_from_args();
+    if let Some(url) = &deep_link_url {
+        if deep_link::forward_to_running_instance(url) {
+            return;
+        }
+    }
+
     tauri::Builder::default()
-        .setup(|app| {
+        .setup(move |app| {
             // 读取配置并应用窗口大小/位置
             let app_handle = app.handle();
+            // 尽早安装崩溃钩子，以便记录后续初始化中的 panic
+            crash_report::install(&app_handle);
+            token_usage::install(&app_handle);
+            llm_log::install(&app_handle);
+            llm_cache::install(&app_handle);
+            mock_llm::install(&app_handle);
+            crash_report::breadcrumb(format!("app started, version {}", env!("CARGO_PKG_VERSION")));
             let cfg = fs_manager::read_config(&app_handle).unwrap_or_default();
 
             // 注册全局快捷键
-            let shortcut = cfg.screenshot_shortcut.clone();
-            let app_handle_for_shortcut = app_handle.clone();
-            if let Err(_e) = app.global_shortcut_manager().register(&shortcut, move || {
-                let app_handle = app_handle_for_shortcut.clone();
-                tauri::async_runtime::spawn(async move {
-                    if let Err(e) = capture::open_overlays_for_all_displays(app_handle).await {
-                        eprintln!("Failed to open overlays from shortcut: {}", e);
-                    }
-                });
-            }) {
+            for (_action, _e) in shortcuts::register_all(&app_handle, &cfg) {
                 #[cfg(debug_assertions)]
-                eprintln!("Failed to register global shortcut '{}': {}", shortcut, _e);
+                eprintln!("Failed to register global shortcut for {:?}: {}", _action, _e);
             }
             if let Some(win) = app.get_window("main") {
                 // 设置窗口图标为自定义 ICO（Windows 任务栏与标题栏图标）
@@ -1164,6 +759,43 @@ fn main() {
                 }
             }
 
+            // aiformula:// 协议：注册、监听后续实例转发、处理本次启动携带的链接
+            deep_link::start_listener(app_handle.clone());
+            std::thread::spawn(|| {
+                if let Err(_e) = deep_link::register_url_scheme_if_needed() {
+                    #[cfg(debug_assertions)]
+                    eprintln!("Failed to register URL scheme: {}", _e);
+                }
+            });
+            if let Some(url) = &deep_link_url {
+                deep_link::handle(&app_handle, url);
+            }
+
+            // 定时备份到 S3 兼容存储（未启用时循环内直接跳过）
+            backup::start_scheduler(app_handle.clone());
+            offline_queue::start(app_handle.clone());
+            job_queue::start(app_handle.clone());
+
+            // 本地 HTTP API / WebSocket 事件流（可选，顺序启动以免并发生成 token）
+            if cfg.local_api.enabled || cfg.local_api.websocket_enabled {
+                let app_handle_for_api = app_handle.clone();
+                let (api_enabled, ws_enabled) = (cfg.local_api.enabled, cfg.local_api.websocket_enabled);
+                tauri::async_runtime::spawn(async move {
+                    if api_enabled {
+                        if let Err(_e) = local_api::start(app_handle_for_api.clone()).await {
+                            #[cfg(debug_assertions)]
+                            eprintln!("Failed to start local API: {:#}", _e);
+                        }
+                    }
+                    if ws_enabled {
+                        if let Err(_e) = event_stream::start(app_handle_for_api).await {
+                            #[cfg(debug_assertions)]
+                            eprintln!("Failed to start WebSocket event stream: {:#}", _e);
+                        }
+                    }
+                });
+            }
+
             // 监听关闭时保存窗口位置与尺寸
             if let Some(win) = app.get_window("main") {
                 let app_handle_clone = app_handle.clone();
@@ -1193,31 +825,141 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             test_connection,
             open_config_dir,
+            get_data_dir_info,
             recognize_from_screenshot,
             recognize_from_file,
             recognize_from_clipboard,
             recognize_from_image_base64,
             get_history,
+            get_last_item,
             save_to_history,
             delete_history_item,
-            update_favorite_status,
-            update_history_title,
+            set_item_source,
+            update_history_item,
             get_config,
             save_config,
-            register_global_shortcut,
+            reset_config_section,
+            shortcuts::register_global_shortcut,
+            shortcuts::get_shortcuts,
+            text_recognition::recognize_selected_text,
             get_confidence_score,
             copy_image_to_clipboard,
             read_image_as_data_url,
             get_default_prompts,
             get_full_prompts_with_language,
+            get_supported_languages,
+            prompt_library::list_prompt_presets,
+            prompt_library::create_prompt_preset,
+            prompt_library::update_prompt_preset,
+            prompt_library::delete_prompt_preset,
+            ab_testing::run_prompt_ab_test,
+            ab_testing::list_ab_tests,
+            ab_testing::set_ab_test_winner,
+            ab_testing::get_ab_test_stats,
+            prompt_history::list_prompt_revisions,
+            prompt_history::restore_prompt_revision,
+            bulk_ops::reanalyze_history_items,
+            bulk_ops::reverify_history_items,
+            bulk_ops::regenerate_untitled_items,
+            duplicates::merge_duplicate,
+            duplicates::dismiss_duplicate,
+            result_cache::clear_result_cache,
+            image_storage::recompress_history_images,
+            image_storage::dedupe_history_images,
+            symbol_boxes::crop_symbol,
+            fixes::apply_fix,
+            latex_lint::lint_latex,
+            srs::review_item,
+            srs::get_study_queue,
+            srs::reset_item_schedule,
             get_prompt_parts,
             retry_analysis_phase,
             retry_verification_phase,
+            capture::list_displays,
             capture::open_overlays_for_all_displays,
             capture::complete_capture,
+            capture::get_magnifier_pixels,
             capture::close_all_overlays,
-            capture::start_recognition_from_region_capture
+            capture::start_recognition_from_region_capture,
+            backup::run_backup_now,
+            backup::list_backups,
+            backup::restore_backup,
+            history_merge::import_history,
+            history_merge::get_history_conflicts,
+            history_merge::resolve_history_conflict,
+            export::export_espanso,
+            export::export_braille,
+            braille::latex_to_braille,
+            spoken::generate_spoken_description,
+            export::export_spoken_descriptions,
+            collections::list_collections,
+            collections::create_collection,
+            collections::update_collection,
+            collections::delete_collection,
+            collections::get_collection_numbering,
+            collections::move_items_to_collection,
+            export::export_collection_latex,
+            export::export_notebook,
+            export::export_beamer,
+            export::export_favorites_catalogue,
+            export_templates::list_export_templates,
+            export_templates::create_export_template,
+            export_templates::update_export_template,
+            export_templates::delete_export_template,
+            export_templates::preview_export_template,
+            export_templates::export_with_template,
+            bundle::export_bundle,
+            bundle::import_bundle,
+            updates::check_for_updates,
+            crash_report::get_crash_reports,
+            crash_report::dismiss_crash_report,
+            usage_stats::get_usage_stats,
+            usage_stats::reset_usage_stats,
+            benchmark::run_benchmark,
+            history_changes::get_history_changes,
+            pipeline::skip_verification,
+            pipeline::cancel_recognition,
+            llm_log::get_llm_logs,
+            llm_log::clear_llm_logs,
+            llm_cache::clear_llm_cache,
+            batch::recognize_files,
+            job_queue::enqueue_job,
+            job_queue::list_jobs,
+            job_queue::cancel_job,
+            job_queue::retry_job,
+            pdf_import::get_pdf_page_image,
+            offline_queue::get_offline_queue,
+            offline_queue::process_offline_queue,
+            offline_queue::remove_offline_capture,
+            api_keys::get_api_key_usage,
+            failover::get_failover_status,
+            cost_estimate::estimate_cost,
+            bulk_edit::set_items_favorite,
+            bulk_edit::tag_items,
+            bulk_edit::untag_items,
+            config_transfer::export_config,
+            config_transfer::import_config,
+            config_validation::validate_config,
+            rerun::rerun_item,
+            onboarding::run_onboarding_check,
+            plugins::list_plugins,
+            plugins::get_plugins_dir,
+            local_api::restart_local_api,
+            local_api::get_local_api_status,
+            event_stream::restart_event_stream,
+            event_stream::get_event_stream_status,
+            deep_link::take_pending_deep_link,
+            deep_link::register_deep_link_scheme
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                // 退出前写入尚未落盘的历史修改
+                if let Err(_e) = fs_manager::flush_history(app_handle) {
+                    #[cfg(debug_assertions)]
+                    eprintln!("Failed to flush history on exit: {}", _e);
+                }
+            }
+        });
 }
@@ -0,0 +1,146 @@
+// 离线模拟服务商（provider: "mock"）：不联网、不需要密钥，按固定数据返回 LaTeX、分析与核查结果，
+// 用于在没有密钥或网络时调试识别流程、进度事件与历史保存。数据读取自 app 数据目录下 mock_llm/ 中的
+// latex.json、analysis.json、verification.json、structured_verification.json、symbol_boxes.json、smiles.json
+// （与模型的 JSON 输出格式相同），
+// 文件缺失或无法解析时使用内置数据。
+
+use crate::data_models::{Analysis, SymbolBox, Verification, VerificationResult};
+use crate::fs_manager;
+use crate::llm_api::LlmClient;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::sync::mpsc::UnboundedSender;
+
+const FIXTURE_DIRNAME: &str = "mock_llm";
+const DEFAULT_LATEX: &str = "E = mc^2";
+/// 模拟流式输出时每段的字符数与间隔
+const STREAM_CHUNK_CHARS: usize = 4;
+const STREAM_CHUNK_DELAY: Duration = Duration::from_millis(30);
+
+static FIXTURE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Deserialize)]
+struct LatexFixture {
+    latex: String,
+}
+
+#[derive(Deserialize)]
+struct AnalysisFixture {
+    title: String,
+    analysis: Analysis,
+}
+
+#[derive(Deserialize)]
+struct SmilesFixture {
+    smiles: Vec<String>,
+}
+
+/// Remembers the fixture directory so mock clients can be created without an AppHandle
+pub fn install(app_handle: &AppHandle) {
+    if let Ok(dir) = fs_manager::app_data_dir(app_handle) {
+        let _ = FIXTURE_DIR.set(dir.join(FIXTURE_DIRNAME));
+    }
+}
+
+/// Parses fixture `name`; None when it is missing or invalid
+fn fixture<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let bytes = std::fs::read(FIXTURE_DIR.get()?.join(name)).ok()?;
+    match serde_json::from_slice(&bytes) {
+        Ok(value) => Some(value),
+        Err(_e) => {
+            #[cfg(debug_assertions)]
+            eprintln!("[LLM][mock] Ignoring invalid fixture {}: {}", name, _e);
+            None
+        }
+    }
+}
+
+/// Offline client answering every request with fixture data
+#[derive(Debug, Default)]
+pub struct MockClient;
+
+impl MockClient {
+    fn latex() -> String {
+        fixture::<LatexFixture>("latex.json").map(|f| f.latex).unwrap_or_else(|| DEFAULT_LATEX.to_string())
+    }
+
+    fn verification_result() -> VerificationResult {
+        fixture("verification.json")
+            .unwrap_or_else(|| VerificationResult { confidence_score: 100, verification_report: "mock".to_string() })
+    }
+}
+
+#[async_trait]
+impl LlmClient for MockClient {
+    async fn get_verification_result(&self, _prompt: &str, _latex: &str) -> Result<VerificationResult, anyhow::Error> {
+        Ok(Self::verification_result())
+    }
+
+    async fn verify_latex_against_image(&self, _prompt: &str, _latex: &str, _image_base64: &str) -> Result<Verification, anyhow::Error> {
+        Ok(fixture("structured_verification.json")
+            .unwrap_or_else(|| Verification { status: "ok".to_string(), issues: Vec::new(), coverage: None }))
+    }
+
+    async fn extract_latex(&self, _prompt: &str, _image_base64: &str) -> Result<String, anyhow::Error> {
+        Ok(Self::latex())
+    }
+
+    /// Streams the fixture as a `{"latex": ...}` response a few characters at a time
+    async fn extract_latex_streaming(&self, _prompt: &str, _image_base64: &str, partial: UnboundedSender<String>) -> Result<String, anyhow::Error> {
+        let latex = Self::latex();
+        let response = serde_json::json!({ "latex": &latex }).to_string();
+        let chars: Vec<char> = response.chars().collect();
+        for end in (STREAM_CHUNK_CHARS..chars.len()).step_by(STREAM_CHUNK_CHARS).chain([chars.len()]) {
+            let _ = partial.send(chars[..end].iter().collect());
+            tokio::time::sleep(STREAM_CHUNK_DELAY).await;
+        }
+        Ok(latex)
+    }
+
+    async fn detect_language(&self, _prompt: &str, _image_base64: &str) -> Result<String, anyhow::Error> {
+        Ok("en".to_string())
+    }
+
+    async fn convert_text_to_latex(&self, _prompt: &str, _text: &str) -> Result<String, anyhow::Error> {
+        Ok(Self::latex())
+    }
+
+    async fn locate_symbols(&self, _latex: &str, _image_base64: &str) -> Result<Vec<SymbolBox>, anyhow::Error> {
+        Ok(fixture("symbol_boxes.json").unwrap_or_default())
+    }
+
+    async fn extract_smiles(&self, _latex: &str, _image_base64: &str) -> Result<Vec<String>, anyhow::Error> {
+        Ok(fixture::<SmilesFixture>("smiles.json").map(|f| f.smiles).unwrap_or_default())
+    }
+
+    async fn fix_latex(&self, _prompt: &str, latex: &str) -> Result<String, anyhow::Error> {
+        Ok(latex.to_string())
+    }
+
+    async fn generate_analysis(&self, _prompt: &str, _image_base64: &str) -> Result<(String, Analysis), anyhow::Error> {
+        Ok(fixture::<AnalysisFixture>("analysis.json").map(|f| (f.title, f.analysis)).unwrap_or_else(|| {
+            (
+                "Mass-energy equivalence".to_string(),
+                Analysis { summary: "mock".to_string(), variables: Vec::new(), terms: Vec::new(), suggestions: Vec::new() },
+            )
+        }))
+    }
+
+    async fn get_verification_result_with_image(
+        &self,
+        _prompt: &str,
+        _latex: &str,
+        _image_base64: &str,
+    ) -> Result<VerificationResult, anyhow::Error> {
+        Ok(Self::verification_result())
+    }
+
+    async fn generate_content(&self, _prompt: &str) -> Result<String, anyhow::Error> {
+        Ok("mock".to_string())
+    }
+}
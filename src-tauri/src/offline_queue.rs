@@ -0,0 +1,233 @@
+// 离线队列：无法连接 API 时仍接受截图——图片与待识别记录保存在本地（offline_queue.json + offline_queue/<id>.png），
+// 以“排队中”展示；后台定时探测连接，恢复后按提交顺序自动识别，也可以通过 process_offline_queue 手动处理。
+// 因其他原因失败（如提示词未设置）的记录保留在队列中并记下错误，自动处理重试有限次数，手动处理总会重试。
+
+use crate::data_models::{CaptureScale, RecognitionMode};
+use crate::pipeline::{self, CaptureImage, RecognitionError, RecognitionOptions};
+use crate::{event_stream, fs_manager, llm_api};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+const QUEUE_FILENAME: &str = "offline_queue.json";
+const QUEUE_DIRNAME: &str = "offline_queue";
+/// 队列非空时探测连接的间隔
+const RETRY_INTERVAL_SECS: u64 = 60;
+const PROBE_TIMEOUT_SECS: u64 = 5;
+/// 自动处理时，非网络原因失败的记录最多重试的次数
+const MAX_AUTO_ATTEMPTS: u32 = 3;
+
+/// 串行化 offline_queue.json 的读改写
+static QUEUE_LOCK: Mutex<()> = Mutex::new(());
+/// 同一时间只运行一次队列处理
+static PROCESSING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingCapture {
+    pub id: String,
+    /// 截图来源（screenshot、file、clipboard 等）
+    pub source: String,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub preset_id: Option<String>,
+    /// 手写模式
+    #[serde(default)]
+    pub handwriting: bool,
+    #[serde(default)]
+    pub mode: RecognitionMode,
+    pub queued_at: String,
+    /// 排队后已尝试识别的次数
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub capture_scale: Option<CaptureScale>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineQueueSummary {
+    pub recognized: usize,
+    pub failed: usize,
+    pub remaining: usize,
+    /// API 仍无法连接，处理已中止
+    pub still_offline: bool,
+}
+
+fn image_path(app_handle: &AppHandle, id: &str) -> Result<PathBuf> {
+    Ok(fs_manager::app_data_dir(app_handle)?.join(QUEUE_DIRNAME).join(format!("{}.png", id)))
+}
+
+fn read_queue(app_handle: &AppHandle) -> Result<Vec<PendingCapture>> {
+    let path = fs_manager::get_data_file_path(app_handle, QUEUE_FILENAME)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).context("Failed to parse offline_queue.json"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read offline_queue.json")),
+    }
+}
+
+fn write_queue(app_handle: &AppHandle, queue: &[PendingCapture]) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, QUEUE_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec_pretty(queue)?).context("Failed to write offline_queue.json")?;
+    event_stream::publish("offline_queue_changed", &queue);
+    let _ = app_handle.emit_all("offline_queue_changed", queue.to_vec());
+    Ok(())
+}
+
+/// Applies `change` to the stored queue under the queue lock
+fn update_queue<T>(app_handle: &AppHandle, change: impl FnOnce(&mut Vec<PendingCapture>) -> T) -> Result<T> {
+    let _guard = QUEUE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut queue = read_queue(app_handle)?;
+    let result = change(&mut queue);
+    write_queue(app_handle, &queue)?;
+    Ok(result)
+}
+
+/// Saves a capture that could not be sent and adds it to the end of the queue
+pub fn enqueue(app_handle: &AppHandle, image: &CaptureImage, source: &str, options: &RecognitionOptions, reason: &str) -> Result<PendingCapture> {
+    let pending = PendingCapture {
+        id: Uuid::new_v4().to_string(),
+        source: source.to_string(),
+        language: options.language.clone(),
+        preset_id: options.preset_id.clone(),
+        handwriting: options.handwriting,
+        mode: options.mode,
+        queued_at: chrono::Utc::now().to_rfc3339(),
+        attempts: 0,
+        last_error: Some(reason.to_string()),
+        capture_scale: image.capture_scale().cloned(),
+    };
+    let path = image_path(app_handle, &pending.id)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("Failed to create offline queue directory")?;
+    }
+    std::fs::write(&path, image.bytes()).context("Failed to save queued capture")?;
+    update_queue(app_handle, |queue| queue.push(pending.clone()))?;
+    Ok(pending)
+}
+
+fn remove(app_handle: &AppHandle, id: &str) -> Result<()> {
+    update_queue(app_handle, |queue| queue.retain(|p| p.id != id))?;
+    if let Ok(path) = image_path(app_handle, id) {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(())
+}
+
+/// Whether the configured API endpoint answers at all (any HTTP status counts)
+async fn is_reachable(app_handle: &AppHandle) -> bool {
+    let Ok(config) = fs_manager::read_config(app_handle) else { return false };
+    let builder = reqwest::Client::builder().timeout(Duration::from_secs(PROBE_TIMEOUT_SECS));
+    let Ok(client) = llm_api::with_proxy(builder, &config.proxy).and_then(|b| Ok(b.build()?)) else { return false };
+    client.get(&config.api_base_url).send().await.is_ok()
+}
+
+/// Recognizes queued captures in order, stopping as soon as the API turns out to be unreachable.
+/// Automatic runs skip captures that already failed `MAX_AUTO_ATTEMPTS` times for other reasons.
+async fn process(app_handle: &AppHandle, automatic: bool) -> Result<OfflineQueueSummary> {
+    let _guard = PROCESSING.lock().await;
+    let mut summary = OfflineQueueSummary::default();
+    for pending in read_queue(app_handle)? {
+        if automatic && pending.attempts >= MAX_AUTO_ATTEMPTS {
+            continue;
+        }
+        let bytes = match image_path(app_handle, &pending.id).and_then(|p| std::fs::read(p).map_err(anyhow::Error::from)) {
+            Ok(bytes) => bytes,
+            Err(_e) => {
+                // 图片已丢失，记录无法再处理
+                #[cfg(debug_assertions)]
+                eprintln!("Offline queue: image of {} missing: {}", pending.id, _e);
+                remove(app_handle, &pending.id)?;
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let options = RecognitionOptions {
+            language: pending.language.clone(),
+            preset_id: pending.preset_id.clone(),
+            handwriting: pending.handwriting,
+            mode: pending.mode,
+            ..Default::default()
+        };
+        let result = pipeline::recognize_once(app_handle, &CaptureImage::from_bytes(bytes).with_capture_scale(pending.capture_scale.clone()), &pending.source, &options).await;
+        let error = match result {
+            Ok(_) => {
+                remove(app_handle, &pending.id)?;
+                summary.recognized += 1;
+                continue;
+            }
+            Err(RecognitionError::Unreachable(e)) => {
+                summary.still_offline = true;
+                e
+            }
+            Err(RecognitionError::Failed(e)) => {
+                summary.failed += 1;
+                e
+            }
+            Err(RecognitionError::Stopped(e)) => {
+                summary.failed += 1;
+                e.to_string()
+            }
+            // 用户取消：保留在队列中，下次再试
+            Err(RecognitionError::Cancelled) => continue,
+        };
+        update_queue(app_handle, |queue| {
+            if let Some(p) = queue.iter_mut().find(|p| p.id == pending.id) {
+                p.attempts += 1;
+                p.last_error = Some(error);
+            }
+        })?;
+        if summary.still_offline {
+            break;
+        }
+    }
+    summary.remaining = read_queue(app_handle)?.len();
+    Ok(summary)
+}
+
+/// Background task: while captures are queued, probes the API periodically and processes the queue once it answers
+pub fn start(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(RETRY_INTERVAL_SECS)).await;
+            let waiting = read_queue(&app_handle)
+                .map(|queue| queue.iter().any(|p| p.attempts < MAX_AUTO_ATTEMPTS))
+                .unwrap_or(false);
+            if !waiting || !is_reachable(&app_handle).await {
+                continue;
+            }
+            if let Err(_e) = process(&app_handle, true).await {
+                #[cfg(debug_assertions)]
+                eprintln!("Offline queue processing failed: {:#}", _e);
+            }
+        }
+    });
+}
+
+// --- Tauri commands ---
+
+/// Captures waiting for the API, oldest first
+#[tauri::command]
+pub fn get_offline_queue(app_handle: AppHandle) -> Result<Vec<PendingCapture>, String> {
+    read_queue(&app_handle).map_err(|e| e.to_string())
+}
+
+/// Recognizes all queued captures now (including ones that failed before)
+#[tauri::command]
+pub async fn process_offline_queue(app_handle: AppHandle) -> Result<OfflineQueueSummary, String> {
+    process(&app_handle, false).await.map_err(|e| format!("{:#}", e))
+}
+
+/// Discards a queued capture and its image
+#[tauri::command]
+pub fn remove_offline_capture(app_handle: AppHandle, id: String) -> Result<(), String> {
+    remove(&app_handle, &id).map_err(|e| e.to_string())
+}
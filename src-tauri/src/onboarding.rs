@@ -0,0 +1,181 @@
+// 首次使用检查：依次校验设置、API Key（发送一次最小请求）、模型是否接受图片输入，
+// 并对内置的示例公式图片（a² + b² = c²）跑一遍完整识别流水线，逐步返回通过/失败及原因。
+// 示例识别的结果不写入历史、不触发插件与外部命令。
+
+use crate::data_models::{Config, HistoryItem, RecognitionMode};
+use crate::llm_api::{self, LlmClient, Provider};
+use crate::pipeline::{self, CaptureImage, Engine, EventSink, PipelineHooks, RecognitionProgressPayload, ResultStore};
+use crate::{config_validation, fs_manager, prompts};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+/// 内置示例图片及其期望的识别结果（去掉空白、花括号与定界符后比较）
+const SAMPLE_IMAGE: &[u8] = include_bytes!("../assets/onboarding_sample.png");
+const SAMPLE_EXPECTED: &str = "a^2+b^2=c^2";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    /// 可以使用，但有需要注意的地方
+    Warning,
+    Failed,
+    /// 前置步骤失败，未执行
+    Skipped,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingStep {
+    /// config、api_key、vision 或 pipeline
+    pub step: String,
+    pub status: StepStatus,
+    pub message: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingReport {
+    /// 没有失败的步骤
+    pub passed: bool,
+    pub steps: Vec<OnboardingStep>,
+}
+
+/// 示例识别不保存图片与条目
+struct DiscardStore;
+
+#[async_trait]
+impl ResultStore for DiscardStore {
+    async fn save_image(&self, _image_bytes: &[u8]) -> anyhow::Result<String> {
+        Ok(String::new())
+    }
+
+    async fn insert_item(&self, item: HistoryItem, _image_bytes: &[u8]) -> anyhow::Result<HistoryItem> {
+        Ok(item)
+    }
+}
+
+/// 不广播识别进度，避免主窗口把示例当作真实识别显示
+struct SilentEvents;
+
+impl EventSink for SilentEvents {
+    fn progress(&self, _payload: RecognitionProgressPayload) {}
+    fn completed(&self, _item: &HistoryItem) {}
+}
+
+/// 不执行插件、自动复制与外部命令
+struct NoHooks;
+
+impl PipelineHooks for NoHooks {}
+
+fn step(step: &str, status: StepStatus, message: impl Into<String>, duration_ms: u64) -> OnboardingStep {
+    OnboardingStep { step: step.to_string(), status, message: message.into(), duration_ms }
+}
+
+/// Describes a request error, pointing at the likely cause
+fn describe_error(config: &Config, error: &anyhow::Error) -> String {
+    let message = format!("{:#}", error);
+    if llm_api::is_unreachable(error) {
+        format!("Cannot reach {}: {}", config.api_base_url, message)
+    } else if ["401", "403", "API_KEY_INVALID", "PERMISSION_DENIED"].iter().any(|code| message.contains(code)) {
+        format!("The API key was rejected: {}", message)
+    } else if message.contains("404") {
+        format!("Model '{}' was not found at {}: {}", config.default_engine, config.api_base_url, message)
+    } else {
+        message
+    }
+}
+
+/// LaTeX reduced to its symbols, for comparing against the expected sample result
+fn normalize_latex(latex: &str) -> String {
+    let latex = latex.replace("\\[", "").replace("\\]", "").replace("\\left", "").replace("\\right", "");
+    latex.chars().filter(|c| !c.is_whitespace() && !matches!(c, '{' | '}' | '$')).collect()
+}
+
+fn check_config(config: &Config) -> OnboardingStep {
+    let validation = config_validation::validate(config);
+    if !validation.is_valid() {
+        return step("config", StepStatus::Failed, validation.error_message(), 0);
+    }
+    match validation.warnings.first() {
+        Some(warning) => step("config", StepStatus::Warning, format!("{}: {}", warning.field, warning.message), 0),
+        None => step("config", StepStatus::Passed, "Settings are valid", 0),
+    }
+}
+
+async fn check_api_key(config: &Config, client: &dyn LlmClient) -> OnboardingStep {
+    if config.api_keys().is_empty() && Provider::from_name(&config.provider).requires_api_key() {
+        return step("api_key", StepStatus::Failed, "No API key is set", 0);
+    }
+    let (result, ms) = pipeline::timed(client.generate_content("ping")).await;
+    match result {
+        Ok(_) => step("api_key", StepStatus::Passed, format!("{} accepted the API key", config.api_base_url), ms),
+        Err(e) => step("api_key", StepStatus::Failed, describe_error(config, &e), ms),
+    }
+}
+
+async fn check_vision(config: &Config, client: &dyn LlmClient, image: &CaptureImage) -> OnboardingStep {
+    let prompt = prompts::get_language_detection_prompt();
+    let (result, ms) = pipeline::timed(client.detect_language(&prompt, image.base64())).await;
+    match result {
+        Ok(_) => step("vision", StepStatus::Passed, format!("Model '{}' accepts images", config.default_engine), ms),
+        Err(e) => step(
+            "vision",
+            StepStatus::Failed,
+            format!("Model '{}' did not accept an image: {}", config.default_engine, describe_error(config, &e)),
+            ms,
+        ),
+    }
+}
+
+async fn check_pipeline(config: &Config, client: Arc<dyn LlmClient>, image: &CaptureImage) -> OnboardingStep {
+    let engine = Engine { client, store: &DiscardStore, events: &SilentEvents, hooks: &NoHooks };
+    let (result, ms) = pipeline::timed(engine.recognize(Uuid::new_v4().to_string(), config, image, None, RecognitionMode::Math)).await;
+    match result {
+        Ok(item) if normalize_latex(&item.latex) == SAMPLE_EXPECTED => step(
+            "pipeline",
+            StepStatus::Passed,
+            format!("Recognized the sample formula as {} (confidence {})", item.latex, item.confidence_score),
+            ms,
+        ),
+        Ok(item) => step(
+            "pipeline",
+            StepStatus::Warning,
+            format!("The pipeline works, but the sample was recognized as {} instead of a^2 + b^2 = c^2", item.latex),
+            ms,
+        ),
+        Err(e) => step("pipeline", StepStatus::Failed, e.to_string(), ms),
+    }
+}
+
+// --- Tauri commands ---
+
+/// Checks the setup step by step: settings, API key, image input and a full recognition of a
+/// bundled sample formula. Steps after a failure are skipped.
+#[tauri::command]
+pub async fn run_onboarding_check(app_handle: AppHandle) -> Result<OnboardingReport, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
+    let image = CaptureImage::from_bytes(SAMPLE_IMAGE.to_vec());
+
+    let mut steps = vec![check_config(&config)];
+    if steps[0].status != StepStatus::Failed {
+        steps.push(check_api_key(&config, client.as_ref()).await);
+    }
+    if steps.last().is_some_and(|s| s.status != StepStatus::Failed) {
+        steps.push(check_vision(&config, client.as_ref(), &image).await);
+    }
+    if steps.last().is_some_and(|s| s.status != StepStatus::Failed) {
+        steps.push(check_pipeline(&config, client, &image).await);
+    }
+    for name in ["config", "api_key", "vision", "pipeline"].into_iter().skip(steps.len()) {
+        steps.push(step(name, StepStatus::Skipped, "Skipped because an earlier step failed", 0));
+    }
+
+    let passed = steps.iter().all(|s| s.status != StepStatus::Failed);
+    Ok(OnboardingReport { passed, steps })
+}
@@ -0,0 +1,92 @@
+// PDF 导入：用 poppler 的命令行工具（pdfinfo 读取页数，pdftoppm 渲染单页为 PNG）把 PDF 页面转成图片，
+// 前端在页面上框选区域（或整页）后，经 recognize_from_image_base64 走普通的识别流程。
+// 工具默认从 PATH 中查找；Windows 等未安装到 PATH 时可在设置中指定 poppler 的 bin 目录（popplerPath）。
+
+use crate::fs_manager;
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::process::Command;
+
+/// 默认渲染分辨率：公式中的上下标在 200 DPI 下仍清晰，页面图片也不会过大
+const DEFAULT_DPI: u32 = 200;
+const MIN_DPI: u32 = 72;
+const MAX_DPI: u32 = 600;
+const TOOL_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfPageImage {
+    /// PNG 图片的 base64
+    pub image_base64: String,
+    pub width: u32,
+    pub height: u32,
+    /// 页码（从 1 开始）
+    pub page: u32,
+    pub page_count: u32,
+    pub dpi: u32,
+}
+
+/// Path of poppler tool `name`: inside the configured directory, or looked up on PATH
+fn tool_path(poppler_path: &str, name: &str) -> PathBuf {
+    let dir = poppler_path.trim();
+    if dir.is_empty() {
+        return PathBuf::from(name);
+    }
+    let file = if cfg!(target_os = "windows") { format!("{}.exe", name) } else { name.to_string() };
+    PathBuf::from(dir).join(file)
+}
+
+/// Runs a poppler tool and returns its standard output
+async fn run_tool(poppler_path: &str, name: &str, args: &[&str]) -> Result<Vec<u8>, String> {
+    let mut cmd = Command::new(tool_path(poppler_path, name));
+    cmd.args(args).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).kill_on_drop(true);
+    let child = cmd.spawn().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            format!("{} was not found. Install poppler (poppler-utils) or set its bin directory in the settings.", name)
+        } else {
+            format!("Failed to start {}: {}", name, e)
+        }
+    })?;
+    let output = tokio::time::timeout(TOOL_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| format!("{} timed out after {}s", name, TOOL_TIMEOUT.as_secs()))?
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("{} exited with {}: {}", name, output.status, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(output.stdout)
+}
+
+/// Number of pages, from the "Pages:" line of pdfinfo
+async fn page_count(poppler_path: &str, path: &str) -> Result<u32, String> {
+    let info = run_tool(poppler_path, "pdfinfo", &[path]).await?;
+    String::from_utf8_lossy(&info)
+        .lines()
+        .find_map(|line| line.strip_prefix("Pages:").and_then(|count| count.trim().parse().ok()))
+        .ok_or_else(|| "Could not read the page count of the PDF".to_string())
+}
+
+// --- Tauri commands ---
+
+/// Renders page `page` (1-based) of a PDF to PNG at `dpi` (default 200), along with the page count
+#[tauri::command]
+pub async fn get_pdf_page_image(app_handle: AppHandle, path: String, page: u32, dpi: Option<u32>) -> Result<PdfPageImage, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let poppler_path = config.poppler_path.as_str();
+    let page_count = page_count(poppler_path, &path).await?;
+    if page == 0 || page > page_count {
+        return Err(format!("Page {} is out of range (the PDF has {} pages)", page, page_count));
+    }
+    let dpi = dpi.unwrap_or(DEFAULT_DPI).clamp(MIN_DPI, MAX_DPI);
+    let (page_arg, dpi_arg) = (page.to_string(), dpi.to_string());
+    // 不指定输出文件名时，-singlefile 的 PNG 写到标准输出
+    let png = run_tool(poppler_path, "pdftoppm", &["-f", &page_arg, "-l", &page_arg, "-r", &dpi_arg, "-png", "-singlefile", &path]).await?;
+    let (width, height) = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+        .map(|img| (img.width(), img.height()))
+        .map_err(|e| format!("pdftoppm returned an invalid image: {}", e))?;
+    Ok(PdfPageImage { image_base64: general_purpose::STANDARD.encode(&png), width, height, page, page_count, dpi })
+}
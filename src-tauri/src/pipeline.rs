@@ -0,0 +1,1140 @@
+// 识别流水线：截图、文件、剪贴板、base64 四个识别命令共用的编排逻辑。
+// 命令只负责取得 PNG 图片，其余步骤（结果缓存、输出语言、三阶段模型调用、进度事件、保存历史）都在此完成：
+// LaTeX 与分析并行发出，核查在 LaTeX 完成后发出（输入图片 + LaTeX）。
+// 编排本身（Engine）不依赖 AppHandle：模型客户端、结果存储、事件与钩子均以 trait 注入，
+// 应用内的实现见文件末尾，也可以换成内存实现，在不启动 Tauri 的情况下驱动整条流水线。
+
+use crate::data_models::{self, Analysis, CaptureScale, Config, HistoryItem, PromptsUsed, RecognitionMode, StageTimings, VerificationResult};
+use crate::llm_api::{self, ApiClient, LlmClient, Provider};
+use crate::{
+    chemistry, clipboard_output, command_hook, crash_report, duplicates, ensemble, event_stream, failover, fs_manager, handwriting, image_storage, language_detect, latex_lint,
+    offline_queue, plugins, prompt_library, prompts, result_cache, symbol_boxes, usage_stats,
+};
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+#[cfg(debug_assertions)]
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+use tokio::sync::oneshot;
+use tokio::task::AbortHandle;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+/// 待识别的 PNG 图片：原始字节（用于缓存键、去重与保存）及其 base64 编码（用于模型请求）
+pub struct CaptureImage {
+    bytes: Vec<u8>,
+    base64: Arc<str>,
+    /// 图片的实际格式（PNG、JPEG 或 WebP），原样发送给模型
+    mime: &'static str,
+    /// 屏幕截图的来源显示器缩放信息
+    capture_scale: Option<CaptureScale>,
+}
+
+impl CaptureImage {
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        let base64 = Arc::from(general_purpose::STANDARD.encode(&bytes));
+        let mime = image_storage::mime_type(&bytes);
+        CaptureImage { bytes, base64, mime, capture_scale: None }
+    }
+
+    /// Reads an image file. PNG, JPEG and WebP are sent as-is; other formats are converted to PNG.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let image_data = std::fs::read(path).map_err(|e| e.to_string())?;
+        // 模型可直接接受的格式（PNG/JPEG/WebP）原样发送，避免照片转 PNG 后体积成倍增大；其他格式转换为 PNG
+        if matches!(
+            image::guess_format(&image_data),
+            Ok(image::ImageFormat::Png | image::ImageFormat::Jpeg | image::ImageFormat::WebP)
+        ) {
+            return Ok(Self::from_bytes(image_data));
+        }
+        let dyn_img = image::load_from_memory(&image_data).map_err(|e| e.to_string())?;
+        let mut png_bytes: Vec<u8> = Vec::new();
+        dyn_img
+            .write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+            .map_err(|e| e.to_string())?;
+        Ok(Self::from_bytes(png_bytes))
+    }
+
+    /// Uses an already base64-encoded image as-is, decoding it once for the raw bytes
+    pub fn from_base64(encoded: String) -> Result<Self, String> {
+        let bytes = general_purpose::STANDARD
+            .decode(&encoded)
+            .map_err(|e| format!("Failed to decode base64 image: {}", e))?;
+        let mime = image_storage::mime_type(&bytes);
+        Ok(CaptureImage { bytes, base64: Arc::from(encoded), mime, capture_scale: None })
+    }
+
+    /// Attaches the scale of the display the image was captured from
+    pub fn with_capture_scale(mut self, capture_scale: Option<CaptureScale>) -> Self {
+        self.capture_scale = capture_scale;
+        self
+    }
+
+    /// Sends `png` to the model in place of this image; the original bytes are still the ones saved to history
+    pub fn with_model_image(&self, png: &[u8]) -> Self {
+        CaptureImage {
+            bytes: self.bytes.clone(),
+            base64: Arc::from(general_purpose::STANDARD.encode(png)),
+            mime: "image/png",
+            capture_scale: self.capture_scale.clone(),
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn base64(&self) -> &str {
+        &self.base64
+    }
+
+    pub fn capture_scale(&self) -> Option<&CaptureScale> {
+        self.capture_scale.as_ref()
+    }
+}
+
+/// 识别失败的原因：无法连接 API 时可以先把截图放入离线队列，稍后再识别
+#[derive(Debug, Clone)]
+pub enum RecognitionError {
+    /// 请求没有到达 API（无网络、DNS 失败、连接超时）
+    Unreachable(String),
+    Failed(String),
+    /// 模型没有给出公式：被安全过滤拦截或输出达到上限
+    Stopped(llm_api::FinishReasonError),
+    /// 被 cancel_recognition 取消，未保存任何条目
+    Cancelled,
+}
+
+impl std::fmt::Display for RecognitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecognitionError::Unreachable(message) | RecognitionError::Failed(message) => f.write_str(message),
+            RecognitionError::Stopped(error) => write!(f, "{}", error),
+            RecognitionError::Cancelled => f.write_str("Recognition cancelled"),
+        }
+    }
+}
+
+impl From<String> for RecognitionError {
+    fn from(message: String) -> Self {
+        RecognitionError::Failed(message)
+    }
+}
+
+impl From<RecognitionError> for String {
+    fn from(error: RecognitionError) -> Self {
+        error.to_string()
+    }
+}
+
+/// 识别命令的可选参数
+#[derive(Debug, Clone, Default)]
+pub struct RecognitionOptions {
+    /// 本次请求指定的输出语言
+    pub language: Option<String>,
+    /// 本次请求使用的提示词预设
+    pub preset_id: Option<String>,
+    /// 忽略本地结果缓存，强制重新识别
+    pub force_refresh: bool,
+    /// 预先指定的识别 id（任务队列使用任务 id），为空时生成新的 id
+    pub id: Option<String>,
+    /// 手写模式：使用手写提示词并预处理发给模型的图片
+    pub handwriting: bool,
+    /// 识别模式（数学或化学）
+    pub mode: RecognitionMode,
+    /// 由快捷键发起：开启自动粘贴时把本次结果粘贴到前台应用
+    pub auto_paste: bool,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct RecognitionProgressPayload {
+    pub id: String,
+    pub stage: String, // "queued" | "started" | "retrying" | "latex" | "analysis" | "confidence" | "failed" | "cancelled"
+    pub latex: Option<String>,
+    pub title: Option<String>,
+    pub analysis: Option<data_models::Analysis>,
+    pub confidence_score: Option<u8>,
+    pub created_at: Option<String>,
+    pub original_image: Option<String>,
+    pub model_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<data_models::Verification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>, // "default" | "custom" | "full"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification_report: Option<String>,
+    /// 距本次识别开始的耗时（毫秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+    /// 排队中：请求在并发上限处的等待位置（1 表示下一个获得名额）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    /// 排队中：排在前面、同样在等待名额的请求数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items_ahead: Option<usize>,
+    /// 排队中为预计等到名额的时间，运行中为预计还需多久得到本条结果（毫秒，按最近的平均耗时粗略估算）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta_ms: Option<u64>,
+    /// stage 为 "retrying" 时：被限流或服务暂时不可用，等待后重试
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<llm_api::RetryNotice>,
+    /// stage 为 "failed" 时：模型停止的原因（安全拦截、输出达到上限等）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<llm_api::FinishReasonError>,
+}
+
+pub fn emit_progress(app_handle: &AppHandle, payload: RecognitionProgressPayload) {
+    crash_report::breadcrumb(format!("recognition_progress stage={}", payload.stage));
+    event_stream::publish("recognition_progress", &payload);
+    let _ = app_handle.emit_all("recognition_progress", payload);
+}
+
+/// 流式输出中的部分结果，在对应阶段的 recognition_progress 之前陆续发出
+#[derive(Serialize, Clone)]
+pub struct RecognitionStreamPayload {
+    pub id: String,
+    pub stage: String, // "latex"
+    /// 模型到目前为止输出的原始文本
+    pub text: String,
+    /// 从已输出的 JSON 中解析出的部分 LaTeX
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latex: Option<String>,
+}
+
+pub fn emit_stream(app_handle: &AppHandle, payload: RecognitionStreamPayload) {
+    event_stream::publish("recognition_stream", &payload);
+    let _ = app_handle.emit_all("recognition_stream", payload);
+}
+
+/// 识别完成（已写入历史）后广播最终条目
+pub fn emit_completed(app_handle: &AppHandle, item: &HistoryItem) {
+    crash_report::breadcrumb(format!("recognition_completed id={}", item.id));
+    event_stream::publish("recognition_completed", item);
+    let _ = app_handle.emit_all("recognition_completed", item.clone());
+}
+
+/// Milliseconds elapsed since `started`
+pub fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
+
+/// Awaits one pipeline stage and returns its result with the stage's own duration in milliseconds
+pub async fn timed<T>(stage: impl std::future::Future<Output = T>) -> (T, u64) {
+    let started = Instant::now();
+    let result = stage.await;
+    (result, elapsed_ms(started))
+}
+
+pub fn default_title_for_lang(language: &str) -> String {
+    if language == "zh-CN" { "未命名公式".to_string() } else { "Untitled formula".to_string() }
+}
+
+/// An item with only LaTeX (quick capture, text recognition): placeholder title, no analysis or verification.
+/// Callers fill in the image, notes and other source-specific fields.
+pub(crate) fn latex_only_item(config: &Config, id: String, latex: String, latex_prompt: String, latex_ms: u64, started: Instant, client: &dyn LlmClient) -> HistoryItem {
+    HistoryItem {
+        id,
+        latex,
+        title: default_title_for_lang(&config.language),
+        analysis: Analysis {
+            summary: String::new(),
+            variables: Vec::new(),
+            terms: Vec::new(),
+            suggestions: Vec::new(),
+        },
+        is_favorite: false,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        confidence_score: 0,
+        original_image: String::new(),
+        model_name: Some(config.default_engine.clone()),
+        verification: None,
+        verification_report: None,
+        notes: None,
+        prompts_used: Some(PromptsUsed {
+            prompts_version: config.prompts_version,
+            latex: latex_prompt,
+            analysis: String::new(),
+            verification: String::new(),
+        }),
+        perceptual_hash: None,
+        duplicate_of: None,
+        symbol_boxes: None,
+        latex_revisions: None,
+        srs: None,
+        spoken_description: None,
+        source: None,
+        stage_timings: Some(StageTimings { latex_ms: Some(latex_ms), total_ms: Some(elapsed_ms(started)), ..Default::default() }),
+        backend: Some(failover::current_backend().to_string()),
+        tags: None,
+        capture_scale: None,
+        rerun_of: None,
+        token_usage: Some(client.token_usage()).filter(|usage| usage.requests > 0),
+        mode: RecognitionMode::Math,
+        smiles: None,
+    }
+}
+
+pub fn default_summary_for_lang(language: &str) -> String {
+    if language == "zh-CN" { "分析暂不可用，请稍后重试。".to_string() } else { "Analysis is temporarily unavailable. Please try again.".to_string() }
+}
+
+fn determine_prompt_version(config: &Config) -> String {
+    // 存在按阶段的自定义覆盖（含本次请求选择的预设）时为 custom，否则为设置中保存的完整提示词
+    if !config.custom_prompts.is_empty() {
+        return "custom".to_string();
+    }
+    "full".to_string()
+}
+
+// --- 识别耗时 ---
+// 各条识别并发运行，只受 maxConcurrentRequests 限制；请求在并发上限处等待名额时，
+// 由请求限流器广播 "queued" 进度，附带排队位置与预计等待时间。
+
+/// 尚无历史数据时假定的单条识别耗时
+const DEFAULT_RECOGNITION_MS: u64 = 10_000;
+
+/// 最近识别耗时的指数滑动平均（毫秒），0 表示尚无数据
+static AVERAGE_RECOGNITION_MS: AtomicU64 = AtomicU64::new(0);
+
+fn average_recognition_ms() -> u64 {
+    match AVERAGE_RECOGNITION_MS.load(Ordering::Relaxed) {
+        0 => DEFAULT_RECOGNITION_MS,
+        average => average,
+    }
+}
+
+fn record_duration(total_ms: u64) {
+    let average = AVERAGE_RECOGNITION_MS.load(Ordering::Relaxed);
+    let updated = if average == 0 { total_ms } else { (average * 4 + total_ms) / 5 };
+    AVERAGE_RECOGNITION_MS.store(updated.max(1), Ordering::Relaxed);
+}
+
+/// Rough time left for a running recognition started at `started`
+fn estimated_remaining_ms(started: Instant) -> u64 {
+    average_recognition_ms().saturating_sub(elapsed_ms(started))
+}
+
+fn failed_verification() -> VerificationResult {
+    VerificationResult { confidence_score: 0, verification_report: "验证失败".to_string() }
+}
+
+// --- 跳过核查 ---
+
+/// 进行中的核查阶段：识别 id -> 可中止句柄
+static VERIFICATION_TASKS: OnceLock<Mutex<HashMap<String, AbortHandle>>> = OnceLock::new();
+
+fn verification_tasks() -> std::sync::MutexGuard<'static, HashMap<String, AbortHandle>> {
+    VERIFICATION_TASKS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Makes a recognition's verification cancellable until the registration is dropped
+struct VerificationRegistration(String);
+
+impl VerificationRegistration {
+    fn new(id: &str, handle: AbortHandle) -> Self {
+        verification_tasks().insert(id.to_string(), handle);
+        VerificationRegistration(id.to_string())
+    }
+}
+
+impl Drop for VerificationRegistration {
+    fn drop(&mut self) {
+        verification_tasks().remove(&self.0);
+    }
+}
+
+// --- 取消识别 ---
+
+/// 进行中（含排队）的识别：识别 id -> 取消信号
+static RECOGNITION_TASKS: OnceLock<Mutex<HashMap<String, oneshot::Sender<()>>>> = OnceLock::new();
+
+fn recognition_tasks() -> std::sync::MutexGuard<'static, HashMap<String, oneshot::Sender<()>>> {
+    RECOGNITION_TASKS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Makes a recognition cancellable until the registration is dropped
+struct RecognitionRegistration(String);
+
+impl RecognitionRegistration {
+    fn new(id: &str) -> (Self, oneshot::Receiver<()>) {
+        let (tx, rx) = oneshot::channel();
+        recognition_tasks().insert(id.to_string(), tx);
+        (RecognitionRegistration(id.to_string()), rx)
+    }
+}
+
+impl Drop for RecognitionRegistration {
+    fn drop(&mut self) {
+        recognition_tasks().remove(&self.0);
+    }
+}
+
+/// Aborts a spawned API request when the recognition that started it is dropped (cancelled)
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn skipped_verification() -> VerificationResult {
+    VerificationResult { confidence_score: 0, verification_report: "已跳过核查".to_string() }
+}
+
+/// Marker stored on items whose verification was skipped by the user
+fn skipped_verification_marker() -> data_models::Verification {
+    data_models::Verification { status: "skipped".to_string(), issues: Vec::new(), coverage: None }
+}
+
+/// 识别进度与完成事件的接收方
+pub trait EventSink: Send + Sync {
+    fn progress(&self, payload: RecognitionProgressPayload);
+    fn completed(&self, item: &HistoryItem);
+    /// Partial model output while a stage streams; ignored by default
+    fn stream(&self, _payload: RecognitionStreamPayload) {}
+}
+
+/// 识别结果的持久化：保存原图、写入历史
+#[async_trait]
+pub trait ResultStore: Send + Sync {
+    /// Stores the image and returns the path to record on the item
+    async fn save_image(&self, image_bytes: &[u8]) -> anyhow::Result<String>;
+    /// Inserts a finished item at the top of the history and returns it as saved
+    async fn insert_item(&self, item: HistoryItem, image_bytes: &[u8]) -> anyhow::Result<HistoryItem>;
+}
+
+/// 流水线的扩展点（插件、自动复制、外部命令钩子）；默认不做任何处理
+#[async_trait]
+pub trait PipelineHooks: Send + Sync {
+    /// Post-processes the extracted LaTeX before any other stage sees it
+    async fn after_latex(&self, _id: &str, latex: String) -> String {
+        latex
+    }
+
+    /// Last chance to modify the item before it is saved
+    async fn before_save(&self, item: HistoryItem) -> HistoryItem {
+        item
+    }
+}
+
+/// 识别引擎：不依赖 AppHandle，模型调用、存储与事件均通过注入的实现完成
+pub struct Engine<'a> {
+    pub client: Arc<dyn LlmClient>,
+    pub store: &'a dyn ResultStore,
+    pub events: &'a dyn EventSink,
+    pub hooks: &'a dyn PipelineHooks,
+}
+
+impl Engine<'_> {
+    /// Runs the LaTeX, analysis and verification stages on the image, emits progress
+    /// events and saves the finished item. Only a LaTeX failure aborts; the other stages fall back to defaults.
+    pub async fn recognize(
+        &self,
+        id: String,
+        config: &Config,
+        image: &CaptureImage,
+        language: Option<&str>,
+        mode: RecognitionMode,
+    ) -> Result<HistoryItem, RecognitionError> {
+        // 化学模式改用化学提示词集
+        let chemistry_config;
+        let config = match mode {
+            RecognitionMode::Chemistry => {
+                chemistry_config = chemistry::with_prompts(config);
+                &chemistry_config
+            }
+            RecognitionMode::Math => config,
+        };
+        let CaptureImage { bytes: image_bytes, base64: base64_image, mime, capture_scale } = image;
+        let created_at = chrono::Utc::now().to_rfc3339();
+        let started = Instant::now();
+        let model_name = Some(config.default_engine.clone());
+        let client = &self.client;
+
+        // 输出语言：本次请求指定 > 根据图片内容自动识别 > 全局设置
+        let output_language = language_detect::resolve_output_language(config, language, client.as_ref(), base64_image).await;
+
+        // 运行期仅使用用户在前端保存的提示词；若为空则直接报错，提示用户去设置页恢复默认或保存
+        if config.stage_prompt(prompts::PromptType::LaTeX).trim().is_empty() {
+            return Err("LaTeX 提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string().into());
+        }
+        if config.stage_prompt(prompts::PromptType::Analysis).trim().is_empty() {
+            return Err("分析提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string().into());
+        }
+        if config.stage_prompt(prompts::PromptType::Verification).trim().is_empty() {
+            return Err("核查提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string().into());
+        }
+
+        let latex_prompt = format!(
+            "{}{}",
+            config.stage_prompt(prompts::PromptType::LaTeX),
+            prompts::format_rule_for_latex(&config.default_latex_format)
+        );
+        let analysis_prompt = format!(
+            "{}\n\n{}",
+            config.stage_prompt(prompts::PromptType::Analysis),
+            prompts::PromptManager::get_language_constraint_for(prompts::PromptType::Analysis, &output_language)
+        );
+        let verification_prompt = prompts::get_verification_prompt(config.stage_prompt(prompts::PromptType::Verification), &output_language);
+
+        // 第1次和第2次调用同时发出（都只输入图片）；LaTeX 以流式返回，边接收边推送
+        let (partial_tx, mut partial_rx) = tokio::sync::mpsc::unbounded_channel();
+        let latex_task = {
+            let (c, prompt, img) = (client.clone(), latex_prompt.clone(), base64_image.clone());
+            tokio::spawn(timed(async move { c.extract_latex_streaming(&prompt, &img, partial_tx).await }))
+        };
+        let analysis_task = {
+            let (c, prompt, img) = (client.clone(), analysis_prompt.clone(), base64_image.clone());
+            tokio::spawn(timed(async move { c.generate_analysis(&prompt, &img).await }))
+        };
+
+        let _latex_guard = AbortOnDrop(latex_task.abort_handle());
+        // 集成模式：其他模型同时识别 LaTeX
+        let ensemble_tasks = ensemble::spawn_members(config, &latex_prompt, base64_image);
+        let _ensemble_guards: Vec<AbortOnDrop> = ensemble_tasks.iter().map(|(_, task)| AbortOnDrop(task.abort_handle())).collect();
+        let _analysis_guard = AbortOnDrop(analysis_task.abort_handle());
+
+        // 转发部分输出，LaTeX 调用结束时发送端被释放，循环随之退出
+        while let Some(text) = partial_rx.recv().await {
+            let latex = llm_api::partial_latex(&text);
+            self.events.stream(RecognitionStreamPayload { id: id.clone(), stage: "latex".into(), text, latex });
+        }
+
+        // 等待第1次调用（LaTeX识别）完成
+        let (latex, latex_ms) = match latex_task.await {
+            Ok((Ok(latex), ms)) => (latex, ms),
+            Ok((Err(e), _)) if llm_api::is_unreachable(&e) => return Err(RecognitionError::Unreachable(e.to_string())),
+            Ok((Err(e), _)) => match llm_api::finish_reason_error(&e) {
+                Some(stopped) => return Err(RecognitionError::Stopped(stopped.clone())),
+                None => return Err(e.to_string().into()),
+            },
+            Err(e) => return Err(format!("LaTeX task failed: {}", e).into()),
+        };
+        let consensus = ensemble::resolve(&config.default_engine, latex.clone(), ensemble_tasks).await;
+        let latex = consensus.as_ref().map_or(latex, |c| c.latex.clone());
+        let latex = self.hooks.after_latex(&id, latex).await;
+        let backend = failover::current_backend();
+        #[cfg(debug_assertions)]
+        eprintln!("[LLM][Result][latex][{}] {}", id, json!({ "latex": &latex }));
+        let prompt_version = determine_prompt_version(config);
+        self.events.progress(RecognitionProgressPayload {
+            id: id.clone(), stage: "latex".into(), latex: Some(latex.clone()),
+            title: None, analysis: None, confidence_score: None,
+            created_at: Some(created_at.clone()),
+            original_image: Some(format!("data:{};base64,{}", mime, base64_image)),
+            model_name: model_name.clone(),
+            verification: None,
+            prompt_version: Some(prompt_version.clone()),
+            verification_report: None,
+            elapsed_ms: Some(elapsed_ms(started)),
+            eta_ms: Some(estimated_remaining_ms(started)),
+            ..Default::default()
+        });
+
+        // 第3次调用：在第1次完成后发出（输入图片+LaTeX）；结构化核查带有问题区域与 LaTeX 片段位置
+        let verification_task = {
+            let (c, prompt, img, latex) = (client.clone(), verification_prompt.clone(), base64_image.clone(), latex.clone());
+            tokio::spawn(timed(async move {
+                match c.verify_latex_against_image(&prompt, &latex, &img).await {
+                    Ok(v) => (crate::compute_verification_result_from_struct(&v), Some(v)),
+                    Err(_e) => {
+                        #[cfg(debug_assertions)]
+                        eprintln!("Verification failed: {:#}", _e);
+                        (failed_verification(), None)
+                    }
+                }
+            }))
+        };
+        // 在结果返回前可通过 skip_verification 取消本阶段
+        let _verification_registration = VerificationRegistration::new(&id, verification_task.abort_handle());
+        let _verification_guard = AbortOnDrop(verification_task.abort_handle());
+
+        // 可选：逐符号位置框，与分析/核查并行
+        let symbol_task = symbol_boxes::spawn(config, client, &latex, base64_image);
+        let _symbol_guard = symbol_task.as_ref().map(|task| AbortOnDrop(task.abort_handle()));
+        let smiles_task = if mode == RecognitionMode::Chemistry { chemistry::spawn(config, client, &latex, base64_image) } else { None };
+        let _smiles_guard = smiles_task.as_ref().map(|task| AbortOnDrop(task.abort_handle()));
+        // 等待第2次调用（分析）结果
+        let (analysis_result, analysis_ms) = match analysis_task.await { Ok((result, ms)) => (Some(result), Some(ms)), Err(_) => (None, None) };
+        let (title, analysis) = match analysis_result {
+            Some(Ok(v)) => v,
+            _ => (
+                default_title_for_lang(&output_language),
+                Analysis { summary: default_summary_for_lang(&output_language), variables: Vec::new(), terms: Vec::new(), suggestions: Vec::new() },
+            ),
+        };
+        #[cfg(debug_assertions)]
+        eprintln!("[LLM][Result][analysis][{}] {}", id, json!({ "title": &title, "analysis": &analysis }));
+        self.events.progress(RecognitionProgressPayload {
+            id: id.clone(), stage: "analysis".into(), latex: None,
+            title: Some(title.clone()), analysis: Some(analysis.clone()), confidence_score: None,
+            created_at: None, original_image: None, model_name: model_name.clone(),
+            verification: None,
+            prompt_version: Some(prompt_version.clone()),
+            verification_report: None,
+            elapsed_ms: Some(elapsed_ms(started)),
+            eta_ms: Some(estimated_remaining_ms(started)),
+            ..Default::default()
+        });
+
+        // 等待第3次调用（验证）结果
+        let ((verification_result, verification), verification_ms) = match verification_task.await {
+            Ok((result, ms)) => (result, Some(ms)),
+            Err(e) if e.is_cancelled() => ((skipped_verification(), Some(skipped_verification_marker())), None),
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("Verification task failed: {}", _e);
+                ((failed_verification(), None), None)
+            }
+        };
+        // 各模型结果不一致时在核查报告中列出
+        let mut verification_result = verification_result;
+        if let Some(note) = consensus.as_ref().and_then(|c| c.disagreement_report(&output_language)) {
+            verification_result.verification_report = format!("{}\n\n{}", verification_result.verification_report, note);
+        }
+        #[cfg(debug_assertions)]
+        eprintln!(
+            "[LLM][Result][confidence+verify][{}] {}",
+            id,
+            json!({ "confidence_score": verification_result.confidence_score, "verification_report": &verification_result.verification_report, "verification": &verification })
+        );
+        self.events.progress(RecognitionProgressPayload {
+            id: id.clone(), stage: "confidence".into(), latex: None,
+            title: None, analysis: None, confidence_score: Some(verification_result.confidence_score),
+            created_at: None, original_image: None, model_name: model_name.clone(),
+            verification: verification.clone(),
+            prompt_version: Some(prompt_version.clone()),
+            verification_report: Some(verification_result.verification_report.clone()),
+            elapsed_ms: Some(elapsed_ms(started)),
+            eta_ms: Some(estimated_remaining_ms(started)),
+            ..Default::default()
+        });
+
+        // 本地规则检查的建议追加到分析结果中
+        let analysis = latex_lint::append(analysis, &latex, &output_language);
+        let symbol_boxes = symbol_boxes::collect(symbol_task).await;
+        let smiles = chemistry::collect(smiles_task).await;
+        let original_image = self.store.save_image(image_bytes).await.map_err(|e| e.to_string())?;
+        let history_item = HistoryItem {
+            id,
+            latex,
+            title,
+            analysis,
+            is_favorite: false,
+            created_at,
+            confidence_score: verification_result.confidence_score,
+            original_image,
+            model_name,
+            verification,
+            verification_report: Some(verification_result.verification_report),
+            notes: None,
+            prompts_used: Some(PromptsUsed {
+                prompts_version: config.prompts_version,
+                latex: latex_prompt,
+                analysis: analysis_prompt,
+                verification: verification_prompt,
+            }),
+            perceptual_hash: None,
+            duplicate_of: None,
+            symbol_boxes,
+            latex_revisions: None,
+            srs: None,
+            spoken_description: None,
+            source: None,
+            stage_timings: Some(StageTimings { latex_ms: Some(latex_ms), analysis_ms, verification_ms, total_ms: Some(elapsed_ms(started)) }),
+            backend: Some(backend.to_string()),
+            tags: None,
+            capture_scale: capture_scale.clone(),
+            rerun_of: None,
+            token_usage: Some(client.token_usage()).filter(|usage| usage.requests > 0),
+            mode,
+            smiles,
+        };
+
+        let history_item = self.hooks.before_save(history_item).await;
+        let history_item = self.store.insert_item(history_item, image_bytes).await.map_err(|e| e.to_string())?;
+        record_duration(elapsed_ms(started));
+        self.events.completed(&history_item);
+        Ok(history_item)
+    }
+}
+
+// --- 应用内的实现 ---
+
+/// 通过 Tauri 事件和本地事件流广播
+struct AppEvents<'a>(&'a AppHandle);
+
+impl EventSink for AppEvents<'_> {
+    fn progress(&self, payload: RecognitionProgressPayload) {
+        emit_progress(self.0, payload);
+    }
+
+    fn completed(&self, item: &HistoryItem) {
+        emit_completed(self.0, item);
+    }
+
+    fn stream(&self, payload: RecognitionStreamPayload) {
+        emit_stream(self.0, payload);
+    }
+}
+
+/// 图片按内容哈希保存到图片目录，条目写入 history.json
+struct AppStore<'a> {
+    app_handle: &'a AppHandle,
+    config: &'a Config,
+}
+
+#[async_trait]
+impl ResultStore for AppStore<'_> {
+    async fn save_image(&self, image_bytes: &[u8]) -> anyhow::Result<String> {
+        // 相同图片只存一份，条目中只记录相对路径
+        let path = fs_manager::save_image_to_pictures_async(self.app_handle, &self.config.image_storage, image_bytes).await?;
+        Ok(fs_manager::to_stored_image_path(self.app_handle, &path))
+    }
+
+    async fn insert_item(&self, item: HistoryItem, image_bytes: &[u8]) -> anyhow::Result<HistoryItem> {
+        insert_into_history(self.app_handle, self.config, item, image_bytes).await
+    }
+}
+
+/// Adds a new item to the front of the history under [`HISTORY_INSERT`], marking near-duplicate
+/// captures first (pass an empty `image_bytes` for items without an image)
+pub(crate) async fn insert_into_history(app_handle: &AppHandle, config: &Config, item: HistoryItem, image_bytes: &[u8]) -> anyhow::Result<HistoryItem> {
+    // 批量识别的多条结果可能同时保存，读取与写回之间不能穿插其他插入
+    let _insert = HISTORY_INSERT.lock().await;
+    // 持久化保存历史，防止前端页面切换导致结果丢失
+    let mut history = fs_manager::read_history_async(app_handle).await?;
+    let item = duplicates::mark(app_handle, config, item, image_bytes, &history);
+    history.insert(0, item.clone());
+    fs_manager::write_history_async(app_handle, history).await?;
+    Ok(item)
+}
+
+/// 串行化新条目的插入（读取历史 → 插入 → 写回）；改写整个历史的批量操作也需持有
+pub(crate) static HISTORY_INSERT: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Reads the history, applies `change` and writes it back, all under [`HISTORY_INSERT`] so that
+/// items inserted meanwhile are not lost. Nothing is written when `change` fails.
+pub(crate) async fn update_history<T>(app_handle: &AppHandle, change: impl FnOnce(&mut Vec<HistoryItem>) -> Result<T, String>) -> Result<T, String> {
+    let _insert = HISTORY_INSERT.lock().await;
+    let mut history = fs_manager::read_history_async(app_handle).await.map_err(|e| e.to_string())?;
+    let result = change(&mut history)?;
+    fs_manager::write_history_async(app_handle, history).await.map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+/// 插件钩子、LaTeX 自动复制与外部命令钩子
+struct AppHooks<'a> {
+    app_handle: &'a AppHandle,
+    config: &'a Config,
+}
+
+#[async_trait]
+impl PipelineHooks for AppHooks<'_> {
+    async fn after_latex(&self, id: &str, latex: String) -> String {
+        let latex = plugins::apply_post_extraction(self.app_handle, self.config, id, latex).await;
+        clipboard_output::auto_copy_latex(self.app_handle, self.config, id, &latex);
+        latex
+    }
+
+    async fn before_save(&self, item: HistoryItem) -> HistoryItem {
+        let item = plugins::apply_pre_save(self.app_handle, self.config, item).await;
+        command_hook::run(self.app_handle, self.config, item).await
+    }
+}
+
+/// Runs the full recognition pipeline on a PNG image and saves the result to the history.
+/// `source` names the command that acquired the image (for usage statistics).
+/// When the API cannot be reached the capture is put in the offline queue instead.
+pub async fn run(app_handle: &AppHandle, image: CaptureImage, source: &str, options: RecognitionOptions) -> Result<HistoryItem, String> {
+    recognize_or_queue(app_handle, &image, source, &options).await.map_err(String::from)
+}
+
+/// Like [`run`], but keeps the kind of error (an unreachable API is reported as `Failed`
+/// once the capture is in the offline queue)
+pub async fn recognize_or_queue(
+    app_handle: &AppHandle,
+    image: &CaptureImage,
+    source: &str,
+    options: &RecognitionOptions,
+) -> Result<HistoryItem, RecognitionError> {
+    match recognize_once(app_handle, image, source, options).await {
+        Err(RecognitionError::Unreachable(reason)) => {
+            let pending = offline_queue::enqueue(app_handle, image, source, options, &reason).map_err(|e| e.to_string())?;
+            Err(format!("The API is unreachable ({}). The capture was queued as {} and will be recognized once the connection is back.", reason, pending.id).into())
+        }
+        result => result,
+    }
+}
+
+/// One recognition attempt without offline queueing (used by the offline queue itself)
+pub async fn recognize_once(
+    app_handle: &AppHandle,
+    image: &CaptureImage,
+    source: &str,
+    options: &RecognitionOptions,
+) -> Result<HistoryItem, RecognitionError> {
+    let config = fs_manager::read_config(app_handle).map_err(|e| e.to_string())?;
+    let mut config = prompt_library::apply_preset(app_handle, config, options.preset_id.as_deref())?;
+    // 强制重新识别时同样跳过模型响应缓存
+    config.llm_cache_enabled &= !options.force_refresh;
+
+    // 手写模式：数学模式下未自定义 LaTeX 提示词时改用手写提示词
+    let prepared;
+    let image = if options.handwriting {
+        if options.mode == RecognitionMode::Math && config.custom_prompts.latex.trim().is_empty() {
+            config.custom_prompts.latex = prompts::get_handwriting_latex_prompt();
+        }
+        match handwriting::preprocess(&image.bytes, &config.handwriting) {
+            Ok(Some(png)) => {
+                prepared = image.with_model_image(&png);
+                &prepared
+            }
+            Ok(None) => image,
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("[handwriting] preprocessing failed, sending the original image: {:#}", _e);
+                image
+            }
+        }
+    } else {
+        image
+    };
+
+    // 同一张图片以相同设置（模型、预设、语言、模式）识别过时直接复用，不再调用 API
+    let cache_key = result_cache::request_key(&image.bytes, &config, options);
+    let store = AppStore { app_handle, config: &config };
+    if let Some(hit) = result_cache::lookup(app_handle, &config, &cache_key, options.force_refresh) {
+        return result_cache::replay(app_handle, &config, &store, hit, &image.bytes, image.capture_scale.clone(), options)
+            .await
+            .map_err(RecognitionError::from);
+    }
+
+    let history_item = recognize_with_store(app_handle, &config, image, options, &store).await?;
+    result_cache::store(app_handle, &config, &cache_key, &history_item);
+    usage_stats::record(app_handle, &config, source, &history_item);
+
+    Ok(history_item)
+}
+
+/// Runs the engine with the app's events and hooks but the given store. Requests waiting at the
+/// concurrency limit are reported as "queued". No result cache and no offline queueing.
+pub(crate) async fn recognize_with_store(
+    app_handle: &AppHandle,
+    config: &Config,
+    image: &CaptureImage,
+    options: &RecognitionOptions,
+    store: &dyn ResultStore,
+) -> Result<HistoryItem, RecognitionError> {
+    let id = options.id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    if options.auto_paste {
+        clipboard_output::arm_auto_paste(config, &id);
+    }
+    let llm_config = config.to_llm_config();
+    let client: Arc<dyn LlmClient> = if llm_config.provider == Provider::Mock {
+        llm_api::create_client(llm_config).map_err(|e| RecognitionError::Failed(format!("{:#}", e)))?
+    } else {
+        let retry_handle = app_handle.clone();
+        let retry_id = id.clone();
+        let queue_handle = app_handle.clone();
+        let queue_id = id.clone();
+        Arc::new(
+            ApiClient::new(llm_config)
+                .map_err(|e| RecognitionError::Failed(format!("{:#}", e)))?
+                .with_retry_listener(Arc::new(move |notice| {
+                    emit_progress(
+                        &retry_handle,
+                        RecognitionProgressPayload {
+                            id: retry_id.clone(),
+                            stage: "retrying".to_string(),
+                            retry: Some(notice),
+                            ..Default::default()
+                        },
+                    );
+                }))
+                .with_queue_listener(Arc::new(move |notice| {
+                    emit_progress(
+                        &queue_handle,
+                        RecognitionProgressPayload {
+                            id: queue_id.clone(),
+                            stage: "queued".to_string(),
+                            queue_position: Some(notice.requests_ahead + 1),
+                            items_ahead: Some(notice.requests_ahead),
+                            eta_ms: Some(notice.eta_ms),
+                            ..Default::default()
+                        },
+                    );
+                })),
+        )
+    };
+    let engine = Engine {
+        client,
+        store,
+        events: &AppEvents(app_handle),
+        hooks: &AppHooks { app_handle, config },
+    };
+    let (_registration, cancelled) = RecognitionRegistration::new(&id);
+    let recognition = async {
+        engine.events.progress(RecognitionProgressPayload { id: id.clone(), stage: "started".into(), ..Default::default() });
+        engine.recognize(id.clone(), config, image, options.language.as_deref(), options.mode).await
+    };
+    // 取消时丢弃识别 future：已发出的请求随之中止，条目不会写入历史
+    let result = tokio::select! {
+        result = recognition => result,
+        _ = cancelled => {
+            engine.events.progress(RecognitionProgressPayload { id: id.clone(), stage: "cancelled".into(), ..Default::default() });
+            Err(RecognitionError::Cancelled)
+        }
+    };
+    if let Err(RecognitionError::Stopped(stopped)) = &result {
+        engine.events.progress(RecognitionProgressPayload {
+            id: id.clone(),
+            stage: "failed".into(),
+            failure: Some(stopped.clone()),
+            ..Default::default()
+        });
+    }
+    failover::notify_change(app_handle);
+    result
+}
+
+// --- Tauri commands ---
+
+/// Cancels recognition `id` (queued or running): pending API requests are aborted, a final
+/// "cancelled" progress event is emitted and nothing is saved. Returns false if it already finished.
+#[tauri::command]
+pub fn cancel_recognition(id: String) -> bool {
+    match recognition_tasks().remove(&id) {
+        Some(cancel) => cancel.send(()).is_ok(),
+        None => false,
+    }
+}
+
+/// Cancels the in-flight verification of recognition `id`; the LaTeX and analysis are kept and the
+/// item is saved with a "skipped" verification. Returns false if the verification already finished.
+#[tauri::command]
+pub fn skip_verification(id: String) -> bool {
+    match verification_tasks().remove(&id) {
+        Some(handle) => {
+            handle.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_models::{SymbolBox, Verification};
+    use crate::mock_llm::MockClient;
+    use tokio::sync::mpsc::UnboundedSender;
+    use tokio::sync::Notify;
+
+    /// 内存中的历史；`fail` 为真时写入历史失败
+    #[derive(Default)]
+    struct MemoryStore {
+        items: Mutex<Vec<HistoryItem>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl ResultStore for MemoryStore {
+        async fn save_image(&self, _image_bytes: &[u8]) -> anyhow::Result<String> {
+            Ok("pictures/test.png".to_string())
+        }
+
+        async fn insert_item(&self, item: HistoryItem, _image_bytes: &[u8]) -> anyhow::Result<HistoryItem> {
+            if self.fail {
+                anyhow::bail!("disk full");
+            }
+            self.items.lock().unwrap().insert(0, item.clone());
+            Ok(item)
+        }
+    }
+
+    /// 记录收到的进度阶段、流式输出的部分 LaTeX 与完成的条目
+    #[derive(Default)]
+    struct RecordingEvents {
+        stages: Mutex<Vec<String>>,
+        streamed: Mutex<Vec<String>>,
+        completed: Mutex<Vec<String>>,
+    }
+
+    impl EventSink for RecordingEvents {
+        fn progress(&self, payload: RecognitionProgressPayload) {
+            self.stages.lock().unwrap().push(payload.stage);
+        }
+
+        fn completed(&self, item: &HistoryItem) {
+            self.completed.lock().unwrap().push(item.id.clone());
+        }
+
+        fn stream(&self, payload: RecognitionStreamPayload) {
+            self.streamed.lock().unwrap().extend(payload.latex);
+        }
+    }
+
+    struct NoHooks;
+
+    impl PipelineHooks for NoHooks {}
+
+    /// 核查请求一直挂起（开始时通知 `verifying`），其余请求交给模拟服务商
+    struct StalledVerification {
+        mock: MockClient,
+        verifying: Arc<Notify>,
+    }
+
+    #[async_trait]
+    impl LlmClient for StalledVerification {
+        async fn get_verification_result(&self, prompt: &str, latex: &str) -> anyhow::Result<VerificationResult> {
+            self.mock.get_verification_result(prompt, latex).await
+        }
+
+        async fn verify_latex_against_image(&self, _prompt: &str, _latex: &str, _image_base64: &str) -> anyhow::Result<Verification> {
+            self.verifying.notify_one();
+            std::future::pending().await
+        }
+
+        async fn extract_latex(&self, prompt: &str, image_base64: &str) -> anyhow::Result<String> {
+            self.mock.extract_latex(prompt, image_base64).await
+        }
+
+        async fn extract_latex_streaming(&self, prompt: &str, image_base64: &str, partial: UnboundedSender<String>) -> anyhow::Result<String> {
+            self.mock.extract_latex_streaming(prompt, image_base64, partial).await
+        }
+
+        async fn detect_language(&self, prompt: &str, image_base64: &str) -> anyhow::Result<String> {
+            self.mock.detect_language(prompt, image_base64).await
+        }
+
+        async fn convert_text_to_latex(&self, prompt: &str, text: &str) -> anyhow::Result<String> {
+            self.mock.convert_text_to_latex(prompt, text).await
+        }
+
+        async fn locate_symbols(&self, latex: &str, image_base64: &str) -> anyhow::Result<Vec<SymbolBox>> {
+            self.mock.locate_symbols(latex, image_base64).await
+        }
+
+        async fn extract_smiles(&self, latex: &str, image_base64: &str) -> anyhow::Result<Vec<String>> {
+            self.mock.extract_smiles(latex, image_base64).await
+        }
+
+        async fn fix_latex(&self, prompt: &str, latex: &str) -> anyhow::Result<String> {
+            self.mock.fix_latex(prompt, latex).await
+        }
+
+        async fn generate_analysis(&self, prompt: &str, image_base64: &str) -> anyhow::Result<(String, Analysis)> {
+            self.mock.generate_analysis(prompt, image_base64).await
+        }
+
+        async fn get_verification_result_with_image(&self, prompt: &str, latex: &str, image_base64: &str) -> anyhow::Result<VerificationResult> {
+            self.mock.get_verification_result_with_image(prompt, latex, image_base64).await
+        }
+
+        async fn generate_content(&self, prompt: &str) -> anyhow::Result<String> {
+            self.mock.generate_content(prompt).await
+        }
+    }
+
+    fn test_image() -> CaptureImage {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(4, 4)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        CaptureImage::from_bytes(png)
+    }
+
+    /// The client the app creates for provider "mock"
+    fn mock_provider() -> Arc<dyn LlmClient> {
+        let config = Config { provider: "mock".to_string(), ..Default::default() };
+        llm_api::create_client(config.to_llm_config()).unwrap()
+    }
+
+    fn stalled_client() -> (Arc<dyn LlmClient>, Arc<Notify>) {
+        let verifying = Arc::new(Notify::new());
+        (Arc::new(StalledVerification { mock: MockClient, verifying: verifying.clone() }), verifying)
+    }
+
+    #[tokio::test]
+    async fn saves_the_mock_result() {
+        let (store, events) = (MemoryStore::default(), RecordingEvents::default());
+        let engine = Engine { client: mock_provider(), store: &store, events: &events, hooks: &NoHooks };
+
+        let item = engine
+            .recognize("engine-success".to_string(), &Config::default(), &test_image(), None, RecognitionMode::Math)
+            .await
+            .unwrap();
+
+        assert_eq!(item.latex, "E = mc^2");
+        assert_eq!(item.title, "Mass-energy equivalence");
+        assert_eq!(item.original_image, "pictures/test.png");
+        assert_eq!(item.verification.as_ref().map(|v| v.status.as_str()), Some("ok"));
+        assert_eq!(*events.stages.lock().unwrap(), ["latex", "analysis", "confidence"]);
+        // 模拟服务商分段输出，部分 LaTeX 逐步增长到最终结果
+        assert_eq!(events.streamed.lock().unwrap().last().map(String::as_str), Some("E = mc^2"));
+        assert_eq!(*events.completed.lock().unwrap(), ["engine-success"]);
+        assert_eq!(store.items.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn skipped_verification_still_saves() {
+        let (client, verifying) = stalled_client();
+        let (store, events) = (MemoryStore::default(), RecordingEvents::default());
+        let engine = Engine { client, store: &store, events: &events, hooks: &NoHooks };
+
+        let skip = async {
+            verifying.notified().await;
+            assert!(skip_verification("engine-skip".to_string()));
+        };
+        let (result, ()) = tokio::join!(
+            engine.recognize("engine-skip".to_string(), &Config::default(), &test_image(), None, RecognitionMode::Math),
+            skip
+        );
+
+        let item = result.unwrap();
+        assert_eq!(item.latex, "E = mc^2");
+        assert_eq!(item.verification.as_ref().map(|v| v.status.as_str()), Some("skipped"));
+        assert_eq!(item.confidence_score, 0);
+        assert_eq!(store.items.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn cancelled_recognition_saves_nothing() {
+        let (client, verifying) = stalled_client();
+        let (store, events) = (MemoryStore::default(), RecordingEvents::default());
+        let engine = Engine { client, store: &store, events: &events, hooks: &NoHooks };
+
+        // 与 recognize_with_store 一样，取消即丢弃识别 future
+        tokio::select! {
+            _ = engine.recognize("engine-cancel".to_string(), &Config::default(), &test_image(), None, RecognitionMode::Math) => {
+                panic!("recognition finished while verification was pending")
+            }
+            _ = verifying.notified() => {}
+        }
+
+        assert!(store.items.lock().unwrap().is_empty());
+        assert!(events.completed.lock().unwrap().is_empty());
+        // 核查任务的登记随识别一起移除
+        assert!(!skip_verification("engine-cancel".to_string()));
+    }
+
+    #[tokio::test]
+    async fn store_failure_fails_the_recognition() {
+        let store = MemoryStore { fail: true, ..Default::default() };
+        let events = RecordingEvents::default();
+        let engine = Engine { client: mock_provider(), store: &store, events: &events, hooks: &NoHooks };
+
+        let result = engine
+            .recognize("engine-store-failure".to_string(), &Config::default(), &test_image(), None, RecognitionMode::Math)
+            .await;
+
+        assert!(matches!(result, Err(RecognitionError::Failed(message)) if message.contains("disk full")));
+        assert!(events.completed.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,270 @@
+// WASM 插件：从 {数据目录}/plugins/*.wasm 加载，在识别流水线的固定节点调用导出的钩子函数，
+// 让高级用户无需 fork 即可做自定义后处理。
+//
+// 插件 ABI（全部可选，缺少的钩子会被跳过）：
+//   memory                              导出的线性内存
+//   alloc(len: i32) -> i32              分配 len 字节并返回偏移
+//   post_extraction(ptr, len) -> i64    输入 {"id","latex"}，输出同结构 JSON
+//   pre_save(ptr, len) -> i64           输入 HistoryItem JSON，输出修改后的 HistoryItem JSON
+// 钩子返回 (ptr << 32) | len 指向 UTF-8 JSON 输出；返回 0 表示不做修改。
+// 编译后的模块按文件缓存（文件修改后重新编译），钩子在阻塞线程池中执行，不占用异步运行时。
+// 每次调用限制指令燃料与线性内存大小，失控的插件只会让本次钩子失败。
+
+use crate::data_models::{Config, HistoryItem};
+use crate::fs_manager;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use tauri::AppHandle;
+use wasmi::{Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+const PLUGINS_DIRNAME: &str = "plugins";
+/// 每次钩子调用的指令燃料上限，防止插件死循环卡住识别流程
+const FUEL_PER_CALL: u64 = 50_000_000;
+/// 每个插件实例线性内存的上限（1024 页），超出时 memory.grow 失败，防止插件耗尽应用内存
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+static ENGINE: OnceLock<Engine> = OnceLock::new();
+/// 已编译的插件模块：路径 -> (编译时的文件修改时间, 模块)
+static MODULES: Mutex<Option<HashMap<PathBuf, (Option<SystemTime>, Arc<Module>)>>> = Mutex::new(None);
+
+#[derive(Clone, Copy, Debug)]
+enum Hook {
+    PostExtraction,
+    PreSave,
+}
+
+impl Hook {
+    fn export_name(self) -> &'static str {
+        match self {
+            Hook::PostExtraction => "post_extraction",
+            Hook::PreSave => "pre_save",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExtractionPayload {
+    id: String,
+    latex: String,
+}
+
+#[derive(Serialize)]
+pub struct PluginInfo {
+    name: String,
+    path: String,
+    hooks: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn plugins_dir(app_handle: &AppHandle) -> Result<PathBuf> {
+    let dir = fs_manager::app_data_dir(app_handle)?.join(PLUGINS_DIRNAME);
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create plugins directory at {:?}", dir))?;
+    }
+    Ok(dir)
+}
+
+fn plugin_paths(app_handle: &AppHandle) -> Vec<PathBuf> {
+    let Ok(dir) = plugins_dir(app_handle) else { return Vec::new() };
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().map(|e| e.eq_ignore_ascii_case("wasm")).unwrap_or(false))
+                .collect()
+        })
+        .unwrap_or_default();
+    // 按文件名顺序执行，便于用户通过 01_/02_ 前缀控制先后
+    paths.sort();
+    paths
+}
+
+fn load_module(engine: &Engine, path: &Path) -> Result<Module> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read plugin {:?}", path))?;
+    Module::new(engine, &bytes[..]).map_err(|e| anyhow!("Invalid WASM module {:?}: {}", path, e))
+}
+
+/// The engine shared by all cached modules (a module can only run on the engine that compiled it)
+fn engine() -> &'static Engine {
+    ENGINE.get_or_init(|| {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        Engine::new(&config)
+    })
+}
+
+/// Returns the compiled module for `path`, recompiling only when the file changed
+fn cached_module(path: &Path) -> Result<Arc<Module>> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut modules = MODULES.lock().unwrap_or_else(|e| e.into_inner());
+    let modules = modules.get_or_insert_with(HashMap::new);
+    if let Some((compiled_at, module)) = modules.get(path) {
+        if *compiled_at == modified && modified.is_some() {
+            return Ok(module.clone());
+        }
+    }
+    let module = Arc::new(load_module(engine(), path)?);
+    modules.insert(path.to_path_buf(), (modified, module.clone()));
+    Ok(module)
+}
+
+/// Runs one hook of one plugin. Returns Ok(None) if the plugin does not export the hook
+/// or asked for no change.
+fn run_hook(path: &Path, hook: Hook, input: &str) -> Result<Option<String>> {
+    let engine = engine();
+    let module = cached_module(path)?;
+    if module.exports().all(|e| e.name() != hook.export_name()) {
+        return Ok(None);
+    }
+
+    let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY_BYTES).build();
+    let mut store = Store::new(engine, limits);
+    store.limiter(|limits| limits);
+    store.add_fuel(FUEL_PER_CALL).map_err(|e| anyhow!("{}", e))?;
+    let linker = <Linker<StoreLimits>>::new(engine);
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .and_then(|pre| pre.start(&mut store))
+        .map_err(|e| anyhow!("Failed to instantiate plugin: {}", e))?;
+
+    let memory = instance
+        .get_memory(&store, "memory")
+        .ok_or_else(|| anyhow!("Plugin does not export 'memory'"))?;
+    let alloc = instance
+        .get_typed_func::<i32, i32>(&store, "alloc")
+        .map_err(|e| anyhow!("Plugin does not export 'alloc(i32) -> i32': {}", e))?;
+    let func = instance
+        .get_typed_func::<(i32, i32), i64>(&store, hook.export_name())
+        .map_err(|e| anyhow!("Hook '{}' has an unexpected signature: {}", hook.export_name(), e))?;
+
+    let input_len = i32::try_from(input.len()).context("Plugin input too large")?;
+    let ptr = alloc.call(&mut store, input_len).map_err(|e| anyhow!("alloc trapped: {}", e))?;
+    memory
+        .write(&mut store, ptr as u32 as usize, input.as_bytes())
+        .map_err(|e| anyhow!("Failed to write plugin input: {}", e))?;
+
+    let packed = func
+        .call(&mut store, (ptr, input_len))
+        .map_err(|e| anyhow!("Hook '{}' trapped: {}", hook.export_name(), e))?;
+    if packed == 0 {
+        return Ok(None);
+    }
+    let out_ptr = ((packed as u64) >> 32) as usize;
+    let out_len = ((packed as u64) & 0xFFFF_FFFF) as usize;
+    let mut buffer = vec![0u8; out_len];
+    memory
+        .read(&store, out_ptr, &mut buffer)
+        .map_err(|e| anyhow!("Failed to read plugin output: {}", e))?;
+    String::from_utf8(buffer).map(Some).context("Plugin output is not valid UTF-8")
+}
+
+/// Lets every plugin transform the freshly extracted LaTeX. Failing plugins are skipped.
+pub async fn apply_post_extraction(app_handle: &AppHandle, config: &Config, id: &str, latex: String) -> String {
+    if !config.plugins_enabled {
+        return latex;
+    }
+    let paths = plugin_paths(app_handle);
+    if paths.is_empty() {
+        return latex;
+    }
+    let (id, original) = (id.to_string(), latex.clone());
+    tokio::task::spawn_blocking(move || post_extraction_chain(&paths, &id, latex))
+        .await
+        .unwrap_or(original)
+}
+
+fn post_extraction_chain(paths: &[PathBuf], id: &str, latex: String) -> String {
+    let mut current = latex;
+    for path in paths {
+        let input = match serde_json::to_string(&ExtractionPayload { id: id.to_string(), latex: current.clone() }) {
+            Ok(s) => s,
+            Err(_) => break,
+        };
+        match run_hook(path, Hook::PostExtraction, &input)
+            .and_then(|out| out.map(|s| serde_json::from_str::<ExtractionPayload>(&s).context("Invalid post_extraction output")).transpose())
+        {
+            Ok(Some(payload)) => current = payload.latex,
+            Ok(None) => {}
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("[Plugin] {:?} post_extraction failed: {:#}", path, _e);
+            }
+        }
+    }
+    current
+}
+
+/// Lets every plugin transform the history item right before it is persisted.
+/// The id is kept stable regardless of what the plugin returns.
+pub async fn apply_pre_save(app_handle: &AppHandle, config: &Config, item: HistoryItem) -> HistoryItem {
+    if !config.plugins_enabled {
+        return item;
+    }
+    let paths = plugin_paths(app_handle);
+    if paths.is_empty() {
+        return item;
+    }
+    let original = item.clone();
+    tokio::task::spawn_blocking(move || pre_save_chain(&paths, item))
+        .await
+        .unwrap_or(original)
+}
+
+fn pre_save_chain(paths: &[PathBuf], item: HistoryItem) -> HistoryItem {
+    let mut current = item;
+    for path in paths {
+        let Ok(input) = serde_json::to_string(&current) else { break };
+        match run_hook(path, Hook::PreSave, &input)
+            .and_then(|out| out.map(|s| serde_json::from_str::<HistoryItem>(&s).context("Invalid pre_save output")).transpose())
+        {
+            Ok(Some(mut updated)) => {
+                updated.id = current.id.clone();
+                current = updated;
+            }
+            Ok(None) => {}
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("[Plugin] {:?} pre_save failed: {:#}", path, _e);
+            }
+        }
+    }
+    current
+}
+
+// --- Tauri commands ---
+
+#[tauri::command]
+pub fn list_plugins(app_handle: AppHandle) -> Vec<PluginInfo> {
+    plugin_paths(&app_handle)
+        .into_iter()
+        .map(|path| {
+            let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let (hooks, error) = match cached_module(&path) {
+                Ok(module) => {
+                    let hooks = [Hook::PostExtraction, Hook::PreSave]
+                        .iter()
+                        .map(|h| h.export_name())
+                        .filter(|name| module.exports().any(|e| e.name() == *name))
+                        .map(|s| s.to_string())
+                        .collect();
+                    (hooks, None)
+                }
+                Err(e) => (Vec::new(), Some(format!("{:#}", e))),
+            };
+            PluginInfo { name, path: path.to_string_lossy().to_string(), hooks, error }
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_plugins_dir(app_handle: AppHandle) -> Result<String, String> {
+    plugins_dir(&app_handle)
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,108 @@
+// 提示词修订历史：内置提示词版本升级（migrate_prompts）覆盖用户提示词前，
+// 先把旧文本保存到 prompt_history.json，之后可查看并恢复任一历史修订。
+
+use crate::data_models::Config;
+use crate::fs_manager;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use uuid::Uuid;
+
+const PROMPT_HISTORY_FILENAME: &str = "prompt_history.json";
+const MAX_REVISIONS: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptRevision {
+    pub id: String,
+    pub created_at: String,
+    /// 保存原因：migration（版本升级覆盖前）或 restore（恢复旧修订前）
+    pub reason: String,
+    pub prompts_version: u32,
+    #[serde(default)]
+    pub latex_prompt: String,
+    #[serde(default)]
+    pub analysis_prompt: String,
+    #[serde(default)]
+    pub verification_prompt: String,
+}
+
+impl PromptRevision {
+    fn same_prompts(&self, config: &Config) -> bool {
+        self.latex_prompt == config.latex_prompt
+            && self.analysis_prompt == config.analysis_prompt
+            && self.verification_prompt == config.verification_prompt
+    }
+}
+
+fn read_revisions(app_handle: &AppHandle) -> Result<Vec<PromptRevision>> {
+    let path = fs_manager::get_data_file_path(app_handle, PROMPT_HISTORY_FILENAME)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).context("Failed to parse prompt_history.json"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read prompt_history.json")),
+    }
+}
+
+fn write_revisions(app_handle: &AppHandle, revisions: &[PromptRevision]) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, PROMPT_HISTORY_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec_pretty(revisions)?).context("Failed to write prompt_history.json")
+}
+
+/// Saves the prompts of `config` as a new revision.
+/// Empty prompt sets and duplicates of the latest revision are skipped.
+pub fn record_revision(app_handle: &AppHandle, config: &Config, reason: &str) -> Result<()> {
+    if config.latex_prompt.trim().is_empty()
+        && config.analysis_prompt.trim().is_empty()
+        && config.verification_prompt.trim().is_empty()
+    {
+        return Ok(());
+    }
+    let mut revisions = read_revisions(app_handle)?;
+    if revisions.first().is_some_and(|r| r.same_prompts(config)) {
+        return Ok(());
+    }
+    revisions.insert(
+        0,
+        PromptRevision {
+            id: Uuid::new_v4().to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            reason: reason.to_string(),
+            prompts_version: config.prompts_version,
+            latex_prompt: config.latex_prompt.clone(),
+            analysis_prompt: config.analysis_prompt.clone(),
+            verification_prompt: config.verification_prompt.clone(),
+        },
+    );
+    revisions.truncate(MAX_REVISIONS);
+    write_revisions(app_handle, &revisions)
+}
+
+// --- Tauri commands ---
+
+/// Lists saved prompt revisions, newest first
+#[tauri::command]
+pub fn list_prompt_revisions(app_handle: AppHandle) -> Result<Vec<PromptRevision>, String> {
+    read_revisions(&app_handle).map_err(|e| e.to_string())
+}
+
+/// Restores the prompts of a revision into the config.
+/// The current prompts are saved as a revision first so the restore can be undone.
+#[tauri::command]
+pub fn restore_prompt_revision(app_handle: AppHandle, id: String) -> Result<Config, String> {
+    let revision = read_revisions(&app_handle)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|r| r.id == id)
+        .ok_or_else(|| format!("Prompt revision '{}' not found", id))?;
+    let mut config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    if revision.same_prompts(&config) {
+        return Ok(config);
+    }
+    record_revision(&app_handle, &config, "restore").map_err(|e| e.to_string())?;
+    config.latex_prompt = revision.latex_prompt;
+    config.analysis_prompt = revision.analysis_prompt;
+    config.verification_prompt = revision.verification_prompt;
+    fs_manager::write_config(&app_handle, &config).map_err(|e| e.to_string())?;
+    Ok(config)
+}
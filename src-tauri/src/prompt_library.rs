@@ -0,0 +1,145 @@
+// 提示词库：命名的提示词预设（如“手写体”“清晰截图”“物理公式”），保存在 prompt_presets.json，
+// 识别时可通过 presetId 选择；预设中留空的字段沿用 Config 中的提示词（或按阶段自定义覆盖）。
+
+use crate::data_models::Config;
+use crate::fs_manager;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use uuid::Uuid;
+
+const PRESETS_FILENAME: &str = "prompt_presets.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptPreset {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub latex_prompt: String,
+    #[serde(default)]
+    pub analysis_prompt: String,
+    #[serde(default)]
+    pub verification_prompt: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Fields accepted when creating or updating a preset
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptPresetInput {
+    pub name: String,
+    #[serde(default)]
+    pub latex_prompt: String,
+    #[serde(default)]
+    pub analysis_prompt: String,
+    #[serde(default)]
+    pub verification_prompt: String,
+}
+
+fn read_presets(app_handle: &AppHandle) -> Result<Vec<PromptPreset>> {
+    let path = fs_manager::get_data_file_path(app_handle, PRESETS_FILENAME)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).context("Failed to parse prompt_presets.json"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read prompt_presets.json")),
+    }
+}
+
+fn write_presets(app_handle: &AppHandle, presets: &[PromptPreset]) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, PRESETS_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec_pretty(presets)?).context("Failed to write prompt_presets.json")
+}
+
+fn validate_name(presets: &[PromptPreset], name: &str, except_id: Option<&str>) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Preset name must not be empty".to_string());
+    }
+    if presets
+        .iter()
+        .any(|p| Some(p.id.as_str()) != except_id && p.name.eq_ignore_ascii_case(name))
+    {
+        return Err(format!("A preset named '{}' already exists", name));
+    }
+    Ok(name.to_string())
+}
+
+/// Returns the config with the preset's non-empty prompts applied as per-stage overrides
+/// (unchanged when no preset is given), so a preset takes precedence over configured overrides
+pub fn apply_preset(app_handle: &AppHandle, mut config: Config, preset_id: Option<&str>) -> Result<Config, String> {
+    let Some(preset_id) = preset_id.filter(|id| !id.is_empty()) else {
+        return Ok(config);
+    };
+    let preset = read_presets(app_handle)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|p| p.id == preset_id)
+        .ok_or_else(|| format!("Prompt preset '{}' not found", preset_id))?;
+    if !preset.latex_prompt.trim().is_empty() {
+        config.custom_prompts.latex = preset.latex_prompt;
+    }
+    if !preset.analysis_prompt.trim().is_empty() {
+        config.custom_prompts.analysis = preset.analysis_prompt;
+    }
+    if !preset.verification_prompt.trim().is_empty() {
+        config.custom_prompts.verification = preset.verification_prompt;
+    }
+    Ok(config)
+}
+
+// --- Tauri commands ---
+
+#[tauri::command]
+pub fn list_prompt_presets(app_handle: AppHandle) -> Result<Vec<PromptPreset>, String> {
+    read_presets(&app_handle).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_prompt_preset(app_handle: AppHandle, preset: PromptPresetInput) -> Result<PromptPreset, String> {
+    let mut presets = read_presets(&app_handle).map_err(|e| e.to_string())?;
+    let name = validate_name(&presets, &preset.name, None)?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let created = PromptPreset {
+        id: Uuid::new_v4().to_string(),
+        name,
+        latex_prompt: preset.latex_prompt,
+        analysis_prompt: preset.analysis_prompt,
+        verification_prompt: preset.verification_prompt,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    presets.push(created.clone());
+    write_presets(&app_handle, &presets).map_err(|e| e.to_string())?;
+    Ok(created)
+}
+
+#[tauri::command]
+pub fn update_prompt_preset(app_handle: AppHandle, id: String, preset: PromptPresetInput) -> Result<PromptPreset, String> {
+    let mut presets = read_presets(&app_handle).map_err(|e| e.to_string())?;
+    let name = validate_name(&presets, &preset.name, Some(&id))?;
+    let existing = presets
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Prompt preset '{}' not found", id))?;
+    existing.name = name;
+    existing.latex_prompt = preset.latex_prompt;
+    existing.analysis_prompt = preset.analysis_prompt;
+    existing.verification_prompt = preset.verification_prompt;
+    existing.updated_at = chrono::Utc::now().to_rfc3339();
+    let updated = existing.clone();
+    write_presets(&app_handle, &presets).map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn delete_prompt_preset(app_handle: AppHandle, id: String) -> Result<(), String> {
+    let mut presets = read_presets(&app_handle).map_err(|e| e.to_string())?;
+    let before = presets.len();
+    presets.retain(|p| p.id != id);
+    if presets.len() == before {
+        return Err(format!("Prompt preset '{}' not found", id));
+    }
+    write_presets(&app_handle, &presets).map_err(|e| e.to_string())
+}
@@ -9,22 +9,78 @@ pub enum PromptType {
     Verification, // 原置信度评分，现在改为验证（包含置信度和核查报告）
 }
 
-/// 语言类型
+/// 输出语言（任意 BCP-47 标签，如 zh-CN、en、ja、de、pt-BR）
 #[derive(Debug, Clone)]
-pub enum Language {
-    Chinese,
-    English,
+pub struct Language {
+    code: String,
+}
+
+/// 常用语言：BCP-47 主标签 → 英文名称（写入提示词中）
+const KNOWN_LANGUAGES: [(&str, &str); 24] = [
+    ("en", "English"),
+    ("zh", "Simplified Chinese"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("de", "German"),
+    ("fr", "French"),
+    ("es", "Spanish"),
+    ("pt", "Portuguese"),
+    ("it", "Italian"),
+    ("ru", "Russian"),
+    ("uk", "Ukrainian"),
+    ("pl", "Polish"),
+    ("nl", "Dutch"),
+    ("sv", "Swedish"),
+    ("tr", "Turkish"),
+    ("ar", "Arabic"),
+    ("he", "Hebrew"),
+    ("fa", "Persian"),
+    ("hi", "Hindi"),
+    ("bn", "Bengali"),
+    ("vi", "Vietnamese"),
+    ("th", "Thai"),
+    ("id", "Indonesian"),
+    ("el", "Greek"),
+];
+
+impl Language {
+    /// 用于提示词的语言名称；未知标签直接以标签描述
+    pub fn display_name(&self) -> String {
+        let lower = self.code.to_lowercase();
+        let mut subtags = lower.split('-');
+        let primary = subtags.next().unwrap_or("");
+        let rest: Vec<&str> = subtags.collect();
+        match primary {
+            "zh" if rest.iter().any(|t| matches!(*t, "hant" | "tw" | "hk" | "mo")) => "Traditional Chinese".to_string(),
+            "pt" if rest.contains(&"br") => "Brazilian Portuguese".to_string(),
+            _ => KNOWN_LANGUAGES
+                .iter()
+                .find(|(code, _)| *code == primary)
+                .map(|(_, name)| name.to_string())
+                .unwrap_or_else(|| format!("the language identified by the BCP-47 tag \"{}\"", self.code)),
+        }
+    }
 }
 
 impl From<&str> for Language {
     fn from(lang: &str) -> Self {
-        match lang {
-            "zh-CN" => Language::Chinese,
-            _ => Language::English,
-        }
+        let code = lang.trim().replace('_', "-");
+        Language { code: if code.is_empty() { "en".to_string() } else { code } }
     }
 }
 
+/// 返回内置的常用语言列表（标签, 名称），供设置页选择
+pub fn known_languages() -> Vec<(String, String)> {
+    let mut list: Vec<(String, String)> = KNOWN_LANGUAGES
+        .iter()
+        .map(|(code, name)| (code.to_string(), name.to_string()))
+        .collect();
+    // 中文使用带地区的标签，与前端界面语言保持一致
+    list[1].0 = "zh-CN".to_string();
+    list.insert(2, ("zh-TW".to_string(), "Traditional Chinese".to_string()));
+    list
+}
+
 /// 提示词管理器
 pub struct PromptManager;
 
@@ -38,13 +94,6 @@ impl PromptManager {
         }
     }
 
-    /// 获取完整提示词（含语言约束）
-    pub fn get_full_prompt(prompt_type: PromptType, language: Language) -> String {
-        let base = Self::get_base_prompt(prompt_type.clone());
-        let constraint = Self::get_language_constraint(prompt_type, language);
-        format!("{}\n\n{}", base, constraint)
-    }
-
     /// 获取语言约束
     fn get_language_constraint(prompt_type: PromptType, language: Language) -> String {
         match prompt_type {
@@ -108,24 +157,15 @@ Be precise and objective in your assessment. No Markdown formatting, no code fen
     // === 语言约束定义 ===
 
     fn latex_language_constraint(language: Language) -> String {
-        match language {
-            Language::Chinese => "Important: Use Simplified Chinese for any error messages or explanations if needed. Keep JSON keys in English.",
-            Language::English => "Important: Use English for any error messages or explanations if needed. Keep JSON keys in English.",
-        }.to_string()
+        format!("Important: Use {} for any error messages or explanations if needed. Keep JSON keys in English.", language.display_name())
     }
 
     fn analysis_language_constraint(language: Language) -> String {
-        match language {
-            Language::Chinese => "Important: Use Simplified Chinese for the values of 'title', 'analysis.summary', 'analysis.variables[*].description', 'analysis.terms[*].description', and 'analysis.suggestions[*].message'. Keep JSON keys in English.",
-            Language::English => "Important: Use English for the values of 'title', 'analysis.summary', 'analysis.variables[*].description', 'analysis.terms[*].description', and 'analysis.suggestions[*].message'. Keep JSON keys in English.",
-        }.to_string()
+        format!("Important: Use {} for the values of 'title', 'analysis.summary', 'analysis.variables[*].description', 'analysis.terms[*].description', and 'analysis.suggestions[*].message'. Keep JSON keys in English.", language.display_name())
     }
 
     fn verification_language_constraint(language: Language) -> String {
-        match language {
-            Language::Chinese => "Important: Use Simplified Chinese for the 'verification_report' content. Keep JSON keys in English.",
-            Language::English => "Important: Use English for the 'verification_report' content. Keep JSON keys in English.",
-        }.to_string()
+        format!("Important: Use {} for the 'verification_report' content. Keep JSON keys in English.", language.display_name())
     }
 
     /// 对外暴露：获取指定提示类型与语言的语言约束文案
@@ -137,14 +177,152 @@ Be precise and objective in your assessment. No Markdown formatting, no code fen
 
 // === 便捷函数 ===
 
-/// 获取分析提示词
-pub fn get_analysis_prompt(language: &str) -> String {
-    PromptManager::get_full_prompt(PromptType::Analysis, Language::from(language))
+/// 纯文本伪公式（如 x^2/(2*sigma^2)）转 LaTeX 的提示词
+pub fn get_text_to_latex_prompt() -> String {
+    "You are an expert in LaTeX. Task: Convert the given plain-text mathematical expression (calculator/programming style, e.g. x^2/(2*sigma^2), sqrt(a+b), sum_{i=1}^n x_i) into proper, idiomatic LaTeX.
+
+Rules:
+1) Preserve the mathematical meaning exactly. Do not simplify, expand, or reorder terms.
+2) Convert spelled-out Greek letters and functions to LaTeX commands (sigma -> \\sigma, sqrt(x) -> \\sqrt{x}, exp, log, sin, ...).
+3) Turn a/b divisions with clear numerator/denominator into \\frac{a}{b}; use ^{} and _{} with proper grouping; remove multiplication signs that are implicit in math notation, but keep \\cdot where it aids readability.
+4) If the input contains surrounding prose, convert only the mathematical part.
+
+Output only a strict JSON object: {\"latex\": \"...\"}. No Markdown, no comments, no extra text. Ensure JSON validity: escape every backslash in LaTeX for JSON (e.g., \\\\frac).".to_string()
+}
+
+/// 手写模式的 LaTeX 识别提示词（替代设置中保存的 LaTeX 提示词，自定义覆盖仍优先）
+pub fn get_handwriting_latex_prompt() -> String {
+    "You are an expert in reading handwritten mathematics. Task: Given a photo or scan of a handwritten formula, transcribe it into LaTeX exactly as written.
+
+Transcribe, do not solve: never correct the math, never simplify, never complete unfinished expressions. Preserve the order of terms and the written structure (fractions, exponents, indices, matrices, aligned lines).
+
+Reading handwriting:
+1) Ambiguous glyphs: decide between look-alike symbols from the surrounding mathematical context, e.g. 1 / l / | / ), 0 / O / o, 2 / z / Z, 5 / S / s, 9 / g / q, u / v / \\nu, x / \\times, t / +, a / \\alpha, n / \\eta, B / \\beta, \\epsilon / \\in, \\phi / \\emptyset. Prefer the reading that gives a well-formed expression in the same notation as the rest of the formula.
+2) Structure: a symbol written noticeably higher and smaller is an exponent, lower and smaller is a subscript. A long horizontal stroke with content above and below is a fraction bar, a short one between terms is a minus sign. Text written above/below \\sum, \\int, \\lim and similar operators are limits.
+3) Notation: underlined or arrowed letters are vectors (\\underline{v}, \\vec{v}); double-struck letters are blackboard bold (\\mathbb{R}). Keep the writer's choice; do not convert between conventions.
+4) Ignore crossed-out or scribbled-over strokes, ruled or grid lines of the paper, margin notes, arrows pointing at the formula, and shadows or smudges.
+5) Command integrity: always output LaTeX commands with their backslashes and keep braces balanced.
+
+Output only a strict JSON object: {\"latex\": \"...\"}. No Markdown, no comments, no extra text. Ensure JSON validity: escape every backslash in LaTeX for JSON (e.g., \\\\frac).".to_string()
+}
+
+/// 化学模式的提示词：LaTeX 阶段以 mhchem 转写化学式/反应式，分析与核查针对物质和反应，输出格式与数学模式相同
+pub fn get_chemistry_prompt(prompt_type: PromptType) -> String {
+    match prompt_type {
+        PromptType::LaTeX => "You are an expert in chemical notation and LaTeX. Task: Given an image of a chemical formula, equation or reaction scheme, transcribe it EXACTLY as shown using the mhchem package.
+
+Rules:
+1) Wrap every chemical formula or reaction in \\ce{...} (e.g., \\ce{2H2 + O2 -> 2H2O}, \\ce{SO4^2-}, \\ce{[Cu(NH3)4]^2+}). Use mhchem syntax inside \\ce: plain digits for stoichiometric indices, ^ for charges and isotopes, and \\pu{...} for quantities with units (e.g., \\pu{25 °C}, \\pu{1.0 mol L-1}).
+2) Arrows: ->, <-, <->, <=>, <=>> and <<=> as drawn. Reagents and conditions written above/below an arrow go into ->[above][below].
+3) States and symbols: keep (s), (l), (g), (aq), precipitate/gas arrows (v, ^), charges, oxidation states, radicals and dots in hydrates (\\ce{CuSO4.5H2O}) exactly as written.
+4) Never balance, correct, complete or simplify the reaction; do not add states, conditions or charges that are not in the image.
+5) Structural drawings (skeletal formulas) cannot be written with mhchem: transcribe their condensed formula if it is printed, otherwise the molecular formula you can read off the drawing.
+6) Mathematical parts around the chemistry (e.g., equilibrium constant expressions) stay ordinary LaTeX, with species inside \\ce{...}.
+7) Ignore non-formula artifacts (UI chrome, captions, reference tags like [1]).
+
+Output only a strict JSON object: {\"latex\": \"...\"}. No Markdown, no comments, no extra text. Ensure JSON validity: escape every backslash in LaTeX for JSON (e.g., \\\\ce).".to_string(),
+        PromptType::Analysis => "You are an expert chemist. Based on the provided image of a chemical formula or reaction (DO NOT change it), produce a structured analysis JSON with the following fields only: {\"title\": \"...\", \"analysis\": {\"summary\": \"...\", \"variables\": [{\"symbol\": \"...\", \"description\": \"...\", \"unit\": \"?\"}], \"terms\": [{\"name\": \"...\", \"description\": \"...\"}], \"suggestions\": [{\"type\": \"error|warning|info\", \"message\": \"...\"}]}}.
+
+Instructions:
+1) Title: the name of the compound or reaction (e.g., \"Combustion of methane\").
+2) Variables: one entry per chemical species, with its formula as 'symbol', its name (IUPAC or common) as 'description', and its molar mass in g/mol as 'unit' when it can be computed (otherwise \"?\"). Physical quantities that appear (temperature, concentration, ΔH) are listed the same way with their units.
+3) Terms: the roles in the reaction (reactants, products, catalysts, conditions, reaction type such as redox, acid-base, precipitation) with a one-sentence explanation each.
+4) Suggestions (three levels):
+   - error: an unbalanced equation (atoms or charge), impossible formulas or charges, or evident transcription mistakes.
+   - warning: missing states, ambiguous arrows or notation that differs from IUPAC recommendations.
+   - info: useful context (e.g., safety notes, related reactions, how to balance).
+5) References: Do NOT add references/citations/links anywhere.
+6) Output must be a strict JSON object with the exact schema above. No Markdown, no code fences, no extra commentary.".to_string(),
+        PromptType::Verification => "You are a meticulous verification expert. Your task is to carefully compare the provided mhchem LaTeX code against the original image of a chemical formula or reaction and provide both a confidence score and a detailed verification report.
+
+Task: Analyze how accurately the LaTeX code represents the original image by examining:
+1) Species: Are all element symbols, subscripts (atom counts) and stoichiometric coefficients correct?
+2) Charges and states: Do charges, oxidation states, isotopes and (s)/(l)/(g)/(aq) match exactly?
+3) Arrows: Is the arrow type (reaction, equilibrium, resonance) and the text above/below it correct?
+4) Completeness: Are there any missing or extra species, conditions or terms?
+5) Treat a changed atom count, coefficient or charge as a meaning-changing error, even when the result is still a valid formula.
+
+Output a strict JSON object with this exact schema:
+{
+  \"confidence_score\": 0-100,
+  \"verification_report\": \"A concise but thorough report detailing any discrepancies found between the LaTeX and the original image. If perfect match, state 'LaTeX accurately represents the original formula.' If issues found, describe specific problems like 'Wrong coefficient for O2' or 'Missing charge on sulfate'.\"
+}
+
+Be precise and objective in your assessment. No Markdown formatting, no code fences, no extra commentary.".to_string(),
+    }
+}
+
+/// 根据化学式（mhchem LaTeX）与原图给出各物质 SMILES 的提示词
+pub fn get_smiles_prompt(latex: &str) -> String {
+    format!(
+        "You are an expert in cheminformatics. Give the SMILES of every distinct chemical species in the formula or reaction below (use the image to resolve structural details such as isomers or drawn structures).
+
+Rules:
+1) One canonical SMILES per species, in the order the species appear; skip duplicates.
+2) Ions keep their charges (e.g., [NH4+], [O-]S(=O)(=O)[O-]); hydrates and salts are written as dot-separated components.
+3) Omit species whose structure cannot be determined (e.g., generic R groups or polymers) instead of guessing.
+
+Output only a strict JSON object: {{\"smiles\": [\"...\"]}}. No Markdown, no comments, no extra text.
+Formula:
+{}",
+        latex
+    )
+}
+
+/// 按核查问题修正已有 LaTeX 的提示词
+pub fn get_fix_latex_prompt(issue_message: &str, fragment: Option<&str>, suggested_fix: Option<&str>) -> String {
+    let mut prompt = format!(
+        "You are an expert in LaTeX. Task: apply exactly one correction to the LaTeX below and change nothing else (keep formatting, spacing and environments as they are).
+
+Problem to fix: {}",
+        issue_message
+    );
+    if let Some(fragment) = fragment {
+        prompt.push_str(&format!("\nOffending fragment: {}", fragment));
+    }
+    if let Some(fix) = suggested_fix {
+        prompt.push_str(&format!("\nSuggested correction: {}", fix));
+    }
+    prompt.push_str("\n\nOutput only a strict JSON object with the complete corrected formula: {\"latex\": \"...\"}. No Markdown, no comments, no extra text. Ensure JSON validity: escape every backslash in LaTeX for JSON (e.g., \\\\frac).");
+    prompt
+}
+
+/// 公式朗读文本（屏幕阅读器/TTS）的提示词
+pub fn get_spoken_description_prompt(language: &str) -> String {
+    format!(
+        "You write spoken readings of mathematical formulas for screen readers and text-to-speech. Read the LaTeX below aloud as a teacher would, e.g. \"x equals the fraction with numerator negative b plus or minus the square root of b squared minus 4 a c, and denominator 2 a\".
+
+Rules:
+1) Use {} only. Spell out every symbol, operator and structure; never output LaTeX commands, Markdown or symbols such as ^, _, \\.
+2) Make grouping unambiguous (\"the fraction … over …\", \"the quantity … end quantity\", \"end root\").
+3) Read variable names letter by letter and Greek letters by name.
+4) Output only the reading as a single plain-text paragraph.",
+        Language::from(language).display_name()
+    )
 }
 
-/// 获取验证提示词（原置信度评分）
-pub fn get_verification_prompt(language: &str) -> String {
-    PromptManager::get_full_prompt(PromptType::Verification, Language::from(language))
+/// 根据截图识别文档语言（公式周围文字）的提示词
+pub fn get_language_detection_prompt() -> String {
+    "Identify the natural language of the document this image was captured from, based on any words, labels or surrounding text visible in it (ignore the mathematical symbols themselves). Output only a strict JSON object: {\"language\": \"<BCP-47 tag>\"}, e.g. {\"language\": \"de\"} or {\"language\": \"zh-CN\"}. If the image contains no natural-language text, output {\"language\": \"und\"}. No Markdown, no extra text.".to_string()
+}
+
+/// 核查阶段实际发送的提示词：设置中的核查提示词（含预设、自定义与化学模式）+ 语言约束 + 结构化输出要求。
+/// 结构化结果带有问题区域与 LaTeX 片段，置信度与报告由其计算得出。
+pub fn get_verification_prompt(stage_prompt: &str, language: &str) -> String {
+    format!(
+        "{}\n\n{}\n\n{}",
+        stage_prompt,
+        PromptManager::get_language_constraint_for(PromptType::Verification, language),
+        verification_schema_addendum(language)
+    )
+}
+
+/// 结构化核查的输出格式说明（问题类别、区域、LaTeX 片段与修改建议）
+fn verification_schema_addendum(language: &str) -> String {
+    format!(
+        "Output format: Regardless of any output format described above, do NOT fix the LaTeX; only point out mismatches. Return a strict JSON: {{\n  \"status\": \"error|warning|ok\",\n  \"issues\": [{{\"category\": \"missing_term|extra_term|symbol_mismatch|notation_mismatch|layout_mismatch|other\", \"message\": \"...\", \"region\": {{\"x\": 0.0, \"y\": 0.0, \"width\": 0.0, \"height\": 0.0}}, \"latex_fragment\": \"...\", \"suggested_fix\": \"...\"}}],\n  \"coverage\": {{\"symbols_matched\": n, \"symbols_total\": n, \"terms_matched\": n, \"terms_total\": n}}\n}}.\nRules:\n- status=error if ANY mismatch that changes math meaning (missing/extra term, wrong symbol, wrong power/subscript, different operator).\n- status=warning for layout/formatting-only differences (line breaks, spacing) that do not change math.\n- status=ok only if visually and semantically equivalent.\n- For each issue, 'region' is the approximate bounding box of the mismatched part in the image, as fractions of the image width/height (0-1, origin top-left). Use null if it cannot be located.\n- 'latex_fragment' is the offending part of the LaTeX, copied verbatim (shortest unique substring). Use null for missing terms that have no counterpart in the LaTeX.\n- 'suggested_fix' is the corrected LaTeX that should replace 'latex_fragment' (for missing terms: the LaTeX to insert). Use null if no concrete correction can be given.\nOutput language: {} for 'issues[*].message'. Keys remain English.",
+        Language::from(language).display_name()
+    )
 }
 
 /// 获取所有基础提示词（用于设置页面显示）
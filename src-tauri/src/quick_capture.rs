@@ -0,0 +1,75 @@
+// 静默快速截图：区域截图后仅执行 LaTeX 提取，结果直接写入剪贴板并弹出通知，
+// 整个过程不把主窗口带到前台。条目仍会写入历史（标题/摘要使用默认占位）。
+
+use crate::data_models::{CaptureScale, HistoryItem, RecognitionMode};
+use crate::llm_api;
+use crate::{chemistry, clipboard_output, command_hook, fs_manager, handwriting, pipeline, plugins, prompts};
+use base64::{engine::general_purpose, Engine as _};
+use tauri::AppHandle;
+use uuid::Uuid;
+
+pub async fn run(
+    app_handle: AppHandle,
+    image_path: String,
+    capture_scale: Option<CaptureScale>,
+    handwriting: bool,
+    mode: RecognitionMode,
+    auto_paste: bool,
+) -> Result<HistoryItem, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let mut config = if mode == RecognitionMode::Chemistry { chemistry::with_prompts(&config) } else { config };
+    if handwriting && mode == RecognitionMode::Math && config.custom_prompts.latex.trim().is_empty() {
+        config.custom_prompts.latex = prompts::get_handwriting_latex_prompt();
+    }
+    if config.stage_prompt(prompts::PromptType::LaTeX).trim().is_empty() {
+        return Err("LaTeX 提示词未设置。请在设置中填写或点击‘恢复默认提示词’后重试。".to_string());
+    }
+
+    // 读取截图、重新编码为 PNG 与手写预处理都在阻塞线程池中完成
+    let (png_bytes, model_image) = {
+        let handwriting_settings = config.handwriting.clone();
+        fs_manager::run_blocking(move || {
+            let image_data = std::fs::read(&image_path)?;
+            let dyn_img = image::load_from_memory(&image_data)?;
+            let mut png_bytes: Vec<u8> = Vec::new();
+            dyn_img.write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)?;
+            // 手写模式下发给模型预处理后的图片，历史中保存原图
+            let model_image = if handwriting { handwriting::preprocess(&png_bytes, &handwriting_settings).ok().flatten() } else { None };
+            Ok((png_bytes, model_image))
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    };
+    let base64_image = general_purpose::STANDARD.encode(model_image.as_deref().unwrap_or(&png_bytes));
+
+    let id = Uuid::new_v4().to_string();
+    let created_at = chrono::Utc::now();
+    let started = std::time::Instant::now();
+    let latex_prompt = format!(
+        "{}{}",
+        config.stage_prompt(prompts::PromptType::LaTeX),
+        prompts::format_rule_for_latex(&config.default_latex_format)
+    );
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
+    let (latex, latex_ms) = pipeline::timed(client.extract_latex(&latex_prompt, &base64_image)).await;
+    let latex = latex.map_err(|e| e.to_string())?;
+    let latex = plugins::apply_post_extraction(&app_handle, &config, &id, latex).await;
+    if auto_paste {
+        clipboard_output::arm_auto_paste(&config, &id);
+    }
+    clipboard_output::deliver_latex(&app_handle, &config, &id, &latex, true);
+
+    let img_path = fs_manager::save_image_to_pictures_async(&app_handle, &config.image_storage, &png_bytes).await.map_err(|e| e.to_string())?;
+    let mut history_item = pipeline::latex_only_item(&config, id, latex, latex_prompt, latex_ms, started, client.as_ref());
+    history_item.created_at = created_at.to_rfc3339();
+    history_item.original_image = fs_manager::to_stored_image_path(&app_handle, &img_path);
+    history_item.capture_scale = capture_scale;
+    history_item.mode = mode;
+    let history_item = plugins::apply_pre_save(&app_handle, &config, history_item).await;
+    let history_item = command_hook::run(&app_handle, &config, history_item).await;
+
+    let history_item = pipeline::insert_into_history(&app_handle, &config, history_item, &png_bytes).await.map_err(|e| e.to_string())?;
+    pipeline::emit_completed(&app_handle, &history_item);
+    crate::usage_stats::record(&app_handle, &config, "quick_capture", &history_item);
+    Ok(history_item)
+}
@@ -0,0 +1,126 @@
+// 用其他模型/提示词预设重新识别历史条目：把保存的原图重新送入完整流水线。
+// 结果可作为原条目的新版本（旧 LaTeX 记入 latexRevisions），也可另存为关联的新条目（rerunOf 指向原条目）便于对比。
+
+use crate::data_models::HistoryItem;
+use crate::pipeline::{self, CaptureImage, RecognitionOptions, ResultStore};
+use crate::{bulk_ops, fixes, fs_manager, prompt_library};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RerunMode {
+    /// 替换原条目的识别结果，旧 LaTeX 记为修订版本
+    Revision,
+    /// 另存为新条目，通过 rerun_of 关联原条目
+    #[default]
+    Sibling,
+}
+
+/// 重新识别结果的保存方式：原图已保存过，直接沿用原条目的图片路径
+struct RerunStore<'a> {
+    app_handle: &'a AppHandle,
+    original: &'a HistoryItem,
+    mode: RerunMode,
+    reason: String,
+}
+
+/// Copies the recognition results of `rerun` onto `item`, keeping the user's data
+/// (favorite, tags, notes, source, review state)
+fn apply_results(item: &mut HistoryItem, rerun: HistoryItem, reason: String) {
+    fixes::push_revision(item, reason);
+    item.latex = rerun.latex;
+    item.title = rerun.title;
+    item.analysis = rerun.analysis;
+    item.confidence_score = rerun.confidence_score;
+    item.model_name = rerun.model_name;
+    item.verification = rerun.verification;
+    item.verification_report = rerun.verification_report;
+    item.prompts_used = rerun.prompts_used;
+    item.symbol_boxes = rerun.symbol_boxes;
+    item.stage_timings = rerun.stage_timings;
+    item.backend = rerun.backend;
+    item.token_usage = rerun.token_usage;
+    item.mode = rerun.mode;
+    item.smiles = rerun.smiles;
+    // 朗读描述基于旧 LaTeX，已过期
+    item.spoken_description = None;
+}
+
+#[async_trait]
+impl ResultStore for RerunStore<'_> {
+    async fn save_image(&self, _image_bytes: &[u8]) -> anyhow::Result<String> {
+        Ok(self.original.original_image.clone())
+    }
+
+    async fn insert_item(&self, item: HistoryItem, _image_bytes: &[u8]) -> anyhow::Result<HistoryItem> {
+        // 与新识别结果的插入共用同一把锁，避免两次读写交错丢失条目
+        let _insert = pipeline::HISTORY_INSERT.lock().await;
+        let mut history = fs_manager::read_history_async(self.app_handle).await?;
+        let saved = match self.mode {
+            RerunMode::Sibling => {
+                // 与原条目同图，不标记为重复
+                let item = HistoryItem { rerun_of: Some(self.original.id.clone()), duplicate_of: None, ..item };
+                history.insert(0, item.clone());
+                item
+            }
+            RerunMode::Revision => {
+                let slot = history
+                    .iter_mut()
+                    .find(|i| i.id == self.original.id)
+                    .ok_or_else(|| anyhow::anyhow!("Item with ID '{}' not found", self.original.id))?;
+                apply_results(slot, item, self.reason.clone());
+                slot.clone()
+            }
+        };
+        fs_manager::write_history_async(self.app_handle, history).await?;
+        Ok(saved)
+    }
+}
+
+// --- Tauri commands ---
+
+/// Re-recognizes the stored image of item `id` with another model and/or prompt preset.
+/// `mode` "sibling" (default) saves a new item linked via `rerun_of`; "revision" replaces the
+/// item's results and keeps the previous LaTeX as a revision.
+#[tauri::command]
+pub async fn rerun_item(
+    app_handle: AppHandle,
+    id: String,
+    model: Option<String>,
+    preset: Option<String>,
+    mode: Option<RerunMode>,
+) -> Result<HistoryItem, String> {
+    let history = fs_manager::read_history_async(&app_handle).await.map_err(|e| e.to_string())?;
+    let original = history
+        .into_iter()
+        .find(|i| i.id == id)
+        .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+    let image = bulk_ops::load_item_image(&app_handle, &original)?
+        .ok_or_else(|| "This item has no stored image to re-recognize".to_string())?;
+    let image = CaptureImage::from_base64(image)?.with_capture_scale(original.capture_scale.clone());
+
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let mut config = prompt_library::apply_preset(&app_handle, config, preset.as_deref())?;
+    if let Some(model) = model.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+        config.default_engine = model.to_string();
+    }
+
+    let mode = mode.unwrap_or_default();
+    let reason = match preset.as_deref().filter(|p| !p.trim().is_empty()) {
+        Some(preset) => format!("rerun: {} ({})", config.default_engine, preset),
+        None => format!("rerun: {}", config.default_engine),
+    };
+    let store = RerunStore { app_handle: &app_handle, original: &original, mode, reason };
+    // 替换原条目时沿用其 id，进度事件直接对应到原条目
+    let run_id = match mode {
+        RerunMode::Revision => original.id.clone(),
+        RerunMode::Sibling => Uuid::new_v4().to_string(),
+    };
+    let options = RecognitionOptions { id: Some(run_id), mode: original.mode, ..Default::default() };
+    pipeline::recognize_with_store(&app_handle, &config, &image, &options, &store)
+        .await
+        .map_err(String::from)
+}
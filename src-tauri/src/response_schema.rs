@@ -0,0 +1,130 @@
+// 结构化输出的响应模式：开启 structuredOutput 时随 LaTeX、分析与核查请求发送（Gemini 的 responseSchema，
+// Ollama 的 format），让模型直接返回可解析的 JSON，减少宽松解析兜底。
+// 模式按 Gemini 的 OpenAPI 子集书写（类型大写、nullable），发给 Ollama 前转换为标准 JSON Schema。
+
+use serde_json::{json, Value};
+
+fn string() -> Value {
+    json!({ "type": "STRING" })
+}
+
+fn nullable_string() -> Value {
+    json!({ "type": "STRING", "nullable": true })
+}
+
+fn object(properties: Value, required: &[&str]) -> Value {
+    json!({ "type": "OBJECT", "properties": properties, "required": required })
+}
+
+fn array_of(items: Value) -> Value {
+    json!({ "type": "ARRAY", "items": items })
+}
+
+/// {"latex": "..."}, used by LaTeX extraction, text conversion and fixes
+pub fn latex() -> Value {
+    object(json!({ "latex": string() }), &["latex"])
+}
+
+/// {"title": "...", "analysis": {...}} matching `AnalysisOnlyContent`
+pub fn analysis() -> Value {
+    let variable = object(json!({ "symbol": string(), "description": string(), "unit": nullable_string() }), &["symbol", "description"]);
+    let term = object(json!({ "name": string(), "description": string() }), &["name", "description"]);
+    let suggestion = object(json!({ "type": string(), "message": string() }), &["type", "message"]);
+    let analysis = object(
+        json!({
+            "summary": string(),
+            "variables": array_of(variable),
+            "terms": array_of(term),
+            "suggestions": array_of(suggestion),
+        }),
+        &["summary", "variables", "terms", "suggestions"],
+    );
+    object(json!({ "title": string(), "analysis": analysis }), &["title", "analysis"])
+}
+
+/// {"confidence_score": n, "verification_report": "..."}
+pub fn verification_result() -> Value {
+    object(
+        json!({
+            "confidence_score": { "type": "INTEGER" },
+            "verification_report": string(),
+        }),
+        &["confidence_score", "verification_report"],
+    )
+}
+
+/// Structured verification (status, located issues, coverage) matching `Verification`
+pub fn verification() -> Value {
+    let number = json!({ "type": "NUMBER" });
+    let integer = json!({ "type": "INTEGER" });
+    let mut region = object(
+        json!({ "x": number.clone(), "y": number.clone(), "width": number.clone(), "height": number }),
+        &["x", "y", "width", "height"],
+    );
+    region["nullable"] = json!(true);
+    let category = json!({
+        "type": "STRING",
+        "enum": ["missing_term", "extra_term", "symbol_mismatch", "notation_mismatch", "layout_mismatch", "other"],
+    });
+    let issue = object(
+        json!({
+            "category": category,
+            "message": string(),
+            "region": region,
+            "latex_fragment": nullable_string(),
+            "suggested_fix": nullable_string(),
+        }),
+        &["category", "message"],
+    );
+    let mut coverage = object(
+        json!({
+            "symbols_matched": integer.clone(),
+            "symbols_total": integer.clone(),
+            "terms_matched": integer.clone(),
+            "terms_total": integer,
+        }),
+        &["symbols_matched", "symbols_total", "terms_matched", "terms_total"],
+    );
+    coverage["nullable"] = json!(true);
+    object(
+        json!({
+            "status": { "type": "STRING", "enum": ["error", "warning", "ok"] },
+            "issues": array_of(issue),
+            "coverage": coverage,
+        }),
+        &["status", "issues"],
+    )
+}
+
+/// Converts a Gemini schema to standard JSON Schema: lowercase types, `nullable` as a null type
+pub fn to_json_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(map) => {
+            let nullable = map.get("nullable").and_then(Value::as_bool).unwrap_or(false);
+            let mut converted = serde_json::Map::new();
+            for (key, value) in map {
+                match key.as_str() {
+                    "nullable" => {}
+                    // 属性名可能与关键字同名（如 suggestions 的 "type"），逐个转换属性的模式
+                    "properties" => {
+                        let properties = value
+                            .as_object()
+                            .map(|props| props.iter().map(|(name, prop)| (name.clone(), to_json_schema(prop))).collect())
+                            .unwrap_or_default();
+                        converted.insert(key.clone(), Value::Object(properties));
+                    }
+                    "type" => {
+                        let ty = json!(value.as_str().unwrap_or_default().to_ascii_lowercase());
+                        converted.insert(key.clone(), if nullable { json!([ty, "null"]) } else { ty });
+                    }
+                    _ => {
+                        converted.insert(key.clone(), to_json_schema(value));
+                    }
+                }
+            }
+            Value::Object(converted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(to_json_schema).collect()),
+        other => other.clone(),
+    }
+}
@@ -0,0 +1,195 @@
+// 识别结果缓存：以图片内容与识别设置（模型、提示词、语言、模式）的 SHA-256 为键保存上一次的识别结果（result_cache.json），
+// 重复截取同一区域时直接复用 LaTeX/分析/核查结果，不再调用 API；请求可指定 forceRefresh 跳过缓存。
+
+use crate::data_models::{Analysis, CaptureScale, Config, HistoryItem, PromptsUsed, RecognitionMode, SymbolBox, Verification};
+use crate::pipeline::{RecognitionOptions, ResultStore};
+use crate::prompts::PromptType;
+use crate::{clipboard_output, command_hook, fs_manager, latex_lint, plugins};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+const RESULT_CACHE_FILENAME: &str = "result_cache.json";
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedResult {
+    pub latex: String,
+    pub title: String,
+    pub analysis: Analysis,
+    pub confidence_score: u8,
+    #[serde(default)]
+    pub verification: Option<Verification>,
+    #[serde(default)]
+    pub verification_report: Option<String>,
+    #[serde(default)]
+    pub model_name: Option<String>,
+    #[serde(default)]
+    pub prompts_used: Option<PromptsUsed>,
+    #[serde(default)]
+    pub symbol_boxes: Option<Vec<SymbolBox>>,
+    #[serde(default)]
+    pub mode: RecognitionMode,
+    #[serde(default)]
+    pub smiles: Option<Vec<String>>,
+    pub cached_at: String,
+}
+
+fn read_cache(app_handle: &AppHandle) -> Result<HashMap<String, CachedResult>> {
+    let path = fs_manager::get_data_file_path(app_handle, RESULT_CACHE_FILENAME)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).context("Failed to parse result_cache.json"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read result_cache.json")),
+    }
+}
+
+fn write_cache(app_handle: &AppHandle, cache: &HashMap<String, CachedResult>) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, RESULT_CACHE_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec(cache)?).context("Failed to write result_cache.json")
+}
+
+/// Hex SHA-256 of the image bytes
+pub fn image_key(image_bytes: &[u8]) -> String {
+    Sha256::digest(image_bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Cache key: hex SHA-256 of the image together with everything that changes the result —
+/// model, stage prompts (after the preset and handwriting prompt are applied), output format,
+/// output language and recognition mode
+pub fn request_key(image_bytes: &[u8], config: &Config, options: &RecognitionOptions) -> String {
+    let mut hasher = Sha256::new();
+    let mut field = |value: &str| {
+        hasher.update((value.len() as u64).to_le_bytes());
+        hasher.update(value.as_bytes());
+    };
+    field(&image_key(image_bytes));
+    field(&config.default_engine);
+    field(config.stage_prompt(PromptType::LaTeX));
+    field(config.stage_prompt(PromptType::Analysis));
+    field(config.stage_prompt(PromptType::Verification));
+    field(&config.default_latex_format);
+    field(options.language.as_deref().unwrap_or(&config.language));
+    field(if options.language.is_none() && config.auto_detect_language { "auto" } else { "" });
+    field(&format!("{:?}", options.mode));
+    field(if options.handwriting { "handwriting" } else { "" });
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn lookup(app_handle: &AppHandle, config: &Config, key: &str, force_refresh: bool) -> Option<CachedResult> {
+    if !config.result_cache_enabled || force_refresh {
+        return None;
+    }
+    read_cache(app_handle).ok()?.remove(key)
+}
+
+/// Stores a finished recognition; results whose verification failed are not cached
+pub fn store(app_handle: &AppHandle, config: &Config, key: &str, item: &HistoryItem) {
+    if !config.result_cache_enabled || item.latex.trim().is_empty() || item.confidence_score == 0 {
+        return;
+    }
+    let result = (|| -> Result<()> {
+        let mut cache = read_cache(app_handle)?;
+        cache.insert(
+            key.to_string(),
+            CachedResult {
+                latex: item.latex.clone(),
+                title: item.title.clone(),
+                analysis: item.analysis.clone(),
+                confidence_score: item.confidence_score,
+                verification: item.verification.clone(),
+                verification_report: item.verification_report.clone(),
+                model_name: item.model_name.clone(),
+                prompts_used: item.prompts_used.clone(),
+                symbol_boxes: item.symbol_boxes.clone(),
+                mode: item.mode,
+                smiles: item.smiles.clone(),
+                cached_at: chrono::Utc::now().to_rfc3339(),
+            },
+        );
+        if cache.len() > MAX_ENTRIES {
+            let mut by_age: Vec<(String, String)> = cache.iter().map(|(k, v)| (v.cached_at.clone(), k.clone())).collect();
+            by_age.sort();
+            for (_, k) in by_age.into_iter().take(cache.len() - MAX_ENTRIES) {
+                cache.remove(&k);
+            }
+        }
+        write_cache(app_handle, &cache)
+    })();
+    if let Err(_e) = result {
+        #[cfg(debug_assertions)]
+        eprintln!("Failed to update result cache: {}", _e);
+    }
+}
+
+/// Creates a new history item from a cache hit without calling the API and saves it through `store`
+pub async fn replay(
+    app_handle: &AppHandle,
+    config: &Config,
+    store: &dyn ResultStore,
+    hit: CachedResult,
+    png_bytes: &[u8],
+    capture_scale: Option<CaptureScale>,
+    options: &RecognitionOptions,
+) -> Result<HistoryItem, String> {
+    let id = options.id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let created_at = chrono::Utc::now();
+    if options.auto_paste {
+        clipboard_output::arm_auto_paste(config, &id);
+    }
+    let latex = plugins::apply_post_extraction(app_handle, config, &id, hit.latex).await;
+    clipboard_output::auto_copy_latex(app_handle, config, &id, &latex);
+
+    let language = options.language.as_deref().unwrap_or(&config.language);
+    let analysis = latex_lint::append(hit.analysis, &latex, language);
+
+    let original_image = store.save_image(png_bytes).await.map_err(|e| e.to_string())?;
+    let history_item = HistoryItem {
+        id,
+        latex,
+        title: hit.title,
+        analysis,
+        is_favorite: false,
+        created_at: created_at.to_rfc3339(),
+        confidence_score: hit.confidence_score,
+        original_image,
+        model_name: hit.model_name,
+        verification: hit.verification,
+        verification_report: hit.verification_report,
+        notes: None,
+        prompts_used: hit.prompts_used,
+        perceptual_hash: None,
+        duplicate_of: None,
+        symbol_boxes: hit.symbol_boxes,
+        latex_revisions: None,
+        srs: None,
+        spoken_description: None,
+        source: None,
+        // 命中缓存，没有调用模型
+        stage_timings: None,
+        backend: None,
+        tags: None,
+        capture_scale,
+        rerun_of: None,
+        token_usage: None,
+        mode: hit.mode,
+        smiles: hit.smiles,
+    };
+    let history_item = plugins::apply_pre_save(app_handle, config, history_item).await;
+    let history_item = command_hook::run(app_handle, config, history_item).await;
+
+    let history_item = store.insert_item(history_item, png_bytes).await.map_err(|e| e.to_string())?;
+    crate::pipeline::emit_completed(app_handle, &history_item);
+    Ok(history_item)
+}
+
+// --- Tauri commands ---
+
+#[tauri::command]
+pub fn clear_result_cache(app_handle: AppHandle) -> Result<(), String> {
+    write_cache(&app_handle, &HashMap::new()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,198 @@
+// 全局快捷键：Config.shortcuts 中的每个动作可单独绑定，启动时统一注册
+
+use crate::data_models::{Config, ShortcutsConfig};
+use crate::pipeline::RecognitionOptions;
+use crate::{capture, fs_manager, text_recognition};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, GlobalShortcutManager, Manager};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ShortcutAction {
+    CaptureRegion,
+    CaptureFullScreen,
+    RecognizeClipboard,
+    ToggleWindow,
+    RepeatLastCapture,
+    QuickCapture,
+    RecognizeSelection,
+    RecallLastResult,
+}
+
+pub(crate) const ALL_ACTIONS: [ShortcutAction; 8] = [
+    ShortcutAction::CaptureRegion,
+    ShortcutAction::CaptureFullScreen,
+    ShortcutAction::RecognizeClipboard,
+    ShortcutAction::ToggleWindow,
+    ShortcutAction::RepeatLastCapture,
+    ShortcutAction::QuickCapture,
+    ShortcutAction::RecognizeSelection,
+    ShortcutAction::RecallLastResult,
+];
+
+impl ShortcutAction {
+    pub(crate) fn binding(self, shortcuts: &ShortcutsConfig) -> &str {
+        match self {
+            ShortcutAction::CaptureRegion => &shortcuts.capture_region,
+            ShortcutAction::CaptureFullScreen => &shortcuts.capture_full_screen,
+            ShortcutAction::RecognizeClipboard => &shortcuts.recognize_clipboard,
+            ShortcutAction::ToggleWindow => &shortcuts.toggle_window,
+            ShortcutAction::RepeatLastCapture => &shortcuts.repeat_last_capture,
+            ShortcutAction::QuickCapture => &shortcuts.quick_capture,
+            ShortcutAction::RecognizeSelection => &shortcuts.recognize_selection,
+            ShortcutAction::RecallLastResult => &shortcuts.recall_last_result,
+        }
+    }
+
+    /// The action's field name in `Config.shortcuts` (e.g. "captureRegion")
+    pub(crate) fn name(self) -> String {
+        serde_json::to_value(self).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+    }
+
+    fn binding_mut(self, shortcuts: &mut ShortcutsConfig) -> &mut String {
+        match self {
+            ShortcutAction::CaptureRegion => &mut shortcuts.capture_region,
+            ShortcutAction::CaptureFullScreen => &mut shortcuts.capture_full_screen,
+            ShortcutAction::RecognizeClipboard => &mut shortcuts.recognize_clipboard,
+            ShortcutAction::ToggleWindow => &mut shortcuts.toggle_window,
+            ShortcutAction::RepeatLastCapture => &mut shortcuts.repeat_last_capture,
+            ShortcutAction::QuickCapture => &mut shortcuts.quick_capture,
+            ShortcutAction::RecognizeSelection => &mut shortcuts.recognize_selection,
+            ShortcutAction::RecallLastResult => &mut shortcuts.recall_last_result,
+        }
+    }
+}
+
+/// Accelerators compare case-insensitively and ignoring spaces
+fn normalized(accelerator: &str) -> String {
+    accelerator.to_lowercase().replace(' ', "")
+}
+
+/// Every action bound to the same accelerator as an earlier action, paired with that earlier action
+pub(crate) fn conflicts(shortcuts: &ShortcutsConfig) -> Vec<(ShortcutAction, ShortcutAction)> {
+    let mut seen: Vec<(String, ShortcutAction)> = Vec::new();
+    let mut conflicts = Vec::new();
+    for action in ALL_ACTIONS {
+        let accelerator = normalized(action.binding(shortcuts));
+        if accelerator.is_empty() {
+            continue;
+        }
+        match seen.iter().find(|(a, _)| *a == accelerator) {
+            Some((_, other)) => conflicts.push((action, *other)),
+            None => seen.push((accelerator, action)),
+        }
+    }
+    conflicts
+}
+
+fn conflict_message(shortcuts: &ShortcutsConfig, action: ShortcutAction, other: ShortcutAction) -> String {
+    format!("'{}' for {} is already bound to {}", action.binding(shortcuts).trim(), action.name(), other.name())
+}
+
+fn toggle_main_window(app_handle: &AppHandle) {
+    if let Some(win) = app_handle.get_window("main") {
+        if win.is_visible().unwrap_or(false) {
+            let _ = win.hide();
+        } else {
+            let _ = win.unminimize();
+            let _ = win.show();
+            let _ = win.set_focus();
+        }
+    }
+}
+
+fn trigger(app_handle: AppHandle, action: ShortcutAction) {
+    if action == ShortcutAction::ToggleWindow {
+        toggle_main_window(&app_handle);
+        return;
+    }
+    let _ = app_handle.emit_all("shortcut-triggered", action);
+
+    // 快捷键发起的识别在开启自动粘贴时把结果粘贴到前台应用
+    tauri::async_runtime::spawn(async move {
+        let options = || RecognitionOptions { auto_paste: true, ..Default::default() };
+        let result = match action {
+            ShortcutAction::CaptureRegion => capture::open_overlays(app_handle, false, true).await,
+            ShortcutAction::QuickCapture => capture::open_overlays(app_handle, true, true).await,
+            ShortcutAction::CaptureFullScreen => crate::screenshot_with_options(app_handle, None, None, options()).await.map(|_| ()),
+            ShortcutAction::RecognizeClipboard => crate::clipboard_with_options(app_handle, options()).await.map(|_| ()),
+            ShortcutAction::RepeatLastCapture => capture::repeat_last_capture(app_handle, true).await,
+            ShortcutAction::RecognizeSelection => text_recognition::recognize_selection(app_handle).await,
+            ShortcutAction::RecallLastResult => match crate::recall_last_item(app_handle, true, true) {
+                Ok(Some(_)) => Ok(()),
+                Ok(None) => Err("The history is empty".to_string()),
+                Err(e) => Err(e),
+            },
+            ShortcutAction::ToggleWindow => Ok(()),
+        };
+        if let Err(_e) = result {
+            #[cfg(debug_assertions)]
+            eprintln!("Shortcut action {:?} failed: {}", action, _e);
+        }
+    });
+}
+
+/// Unregisters everything and registers all bound actions from the config.
+/// Returns the actions that failed to register along with the error; an action sharing
+/// its accelerator with an earlier one is not registered and its error names that action.
+pub fn register_all(app_handle: &AppHandle, config: &Config) -> Vec<(ShortcutAction, String)> {
+    let mut manager = app_handle.global_shortcut_manager();
+    let _ = manager.unregister_all();
+
+    let conflicts = conflicts(&config.shortcuts);
+    let mut failures = Vec::new();
+    for action in ALL_ACTIONS {
+        let accelerator = action.binding(&config.shortcuts).trim().to_string();
+        if accelerator.is_empty() {
+            continue;
+        }
+        if let Some((_, other)) = conflicts.iter().find(|(a, _)| *a == action) {
+            failures.push((action, conflict_message(&config.shortcuts, action, *other)));
+            continue;
+        }
+        let app = app_handle.clone();
+        if let Err(e) = manager.register(&accelerator, move || trigger(app.clone(), action)) {
+            failures.push((action, e.to_string()));
+        }
+    }
+    failures
+}
+
+// --- Tauri commands ---
+
+/// Rebinds one action (capture region when omitted), persists the map and re-registers all shortcuts.
+/// Fails without changing anything when two configured actions would share an accelerator;
+/// the previous binding is restored if the new one cannot be registered.
+#[tauri::command]
+pub fn register_global_shortcut(
+    app_handle: AppHandle,
+    shortcut: String,
+    action: Option<ShortcutAction>,
+) -> Result<(), String> {
+    let action = action.unwrap_or(ShortcutAction::CaptureRegion);
+    let mut config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let previous = std::mem::replace(action.binding_mut(&mut config.shortcuts), shortcut.trim().to_string());
+
+    // 检查所有动作；优先报告与本次改绑动作冲突的那一组
+    let conflict = conflicts(&config.shortcuts)
+        .into_iter()
+        .map(|(a, b)| if b == action { (b, a) } else { (a, b) })
+        .min_by_key(|(a, _)| *a != action);
+    if let Some((conflicting, other)) = conflict {
+        return Err(conflict_message(&config.shortcuts, conflicting, other));
+    }
+
+    if let Some((_, err)) = register_all(&app_handle, &config).into_iter().find(|(a, _)| *a == action) {
+        *action.binding_mut(&mut config.shortcuts) = previous;
+        register_all(&app_handle, &config);
+        return Err(err);
+    }
+    fs_manager::write_config(&app_handle, &config).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_shortcuts(app_handle: AppHandle) -> Result<ShortcutsConfig, String> {
+    fs_manager::read_config(&app_handle)
+        .map(|c| c.shortcuts)
+        .map_err(|e| e.to_string())
+}
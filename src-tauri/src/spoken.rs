@@ -0,0 +1,55 @@
+// 公式朗读文本：让模型把 LaTeX 读成自然语言（如 "x equals negative b plus or minus …"），
+// 保存在条目的 spoken_description 上，供屏幕阅读器/TTS 使用，也可随导出一起输出。
+
+use crate::data_models::HistoryItem;
+use crate::llm_api;
+use crate::{fs_manager, pipeline, prompts};
+use tauri::AppHandle;
+
+/// Strips formatting the model may wrap around the reading
+fn clean_reading(text: &str) -> String {
+    text.replace("```", "")
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches('"')
+        .to_string()
+}
+
+// --- Tauri commands ---
+
+/// Generates (or regenerates) the spoken reading of an item and stores it on the item
+#[tauri::command]
+pub async fn generate_spoken_description(app_handle: AppHandle, id: String, language: Option<String>) -> Result<HistoryItem, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let history = fs_manager::read_history_async(&app_handle).await.map_err(|e| e.to_string())?;
+    let latex = history
+        .iter()
+        .find(|i| i.id == id)
+        .map(|i| i.latex.clone())
+        .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+    if latex.trim().is_empty() {
+        return Err("Item has no LaTeX to read".to_string());
+    }
+
+    let language = language.filter(|l| !l.trim().is_empty()).unwrap_or_else(|| config.language.clone());
+    let prompt = format!("{}\n\nLaTeX:\n{}", prompts::get_spoken_description_prompt(&language), latex);
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
+    let reading = clean_reading(&client.generate_content(&prompt).await.map_err(|e| e.to_string())?);
+    if reading.is_empty() {
+        return Err("The model returned an empty reading".to_string());
+    }
+
+    // 重新读取历史再写回，保留等待期间对其他条目的修改
+    pipeline::update_history(&app_handle, |history| {
+        let item = history
+            .iter_mut()
+            .find(|i| i.id == id)
+            .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+        item.spoken_description = Some(reading);
+        Ok(item.clone())
+    })
+    .await
+}
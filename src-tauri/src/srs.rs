@@ -0,0 +1,120 @@
+// 间隔重复复习：把收藏列表当作学习卡组，按 SM-2 算法安排每个条目的下次复习时间。
+// 复习状态保存在条目的 srs 字段上；从未复习过的收藏视为新卡片，随时可复习。
+
+use crate::data_models::{HistoryItem, SrsState};
+use crate::{fs_manager, pipeline};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use tauri::AppHandle;
+
+const INITIAL_EASE: f32 = 2.5;
+const MIN_EASE: f32 = 1.3;
+/// 每次列出的新卡片上限，避免一次性把全部收藏都排进当天
+const DEFAULT_NEW_PER_SESSION: usize = 20;
+
+/// Applies one SM-2 review with `grade` 0-5 (0-2 = forgotten, 3 = hard, 4 = good, 5 = easy)
+pub fn schedule(previous: Option<&SrsState>, grade: u8, now: DateTime<Utc>) -> SrsState {
+    let grade = grade.min(5);
+    let (mut ease, mut repetitions, mut interval_days, mut lapses) = match previous {
+        Some(s) => (s.ease, s.repetitions, s.interval_days, s.lapses),
+        None => (INITIAL_EASE, 0, 0, 0),
+    };
+
+    if grade < 3 {
+        // 忘记：重新开始学习，次日再复习
+        if repetitions > 0 {
+            lapses += 1;
+        }
+        repetitions = 0;
+        interval_days = 1;
+    } else {
+        interval_days = match repetitions {
+            0 => 1,
+            1 => 6,
+            _ => ((interval_days.max(1) as f32) * ease).round() as u32,
+        };
+        repetitions += 1;
+    }
+    let q = grade as f32;
+    ease = (ease + (0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02))).max(MIN_EASE);
+
+    SrsState {
+        due: (now + Duration::days(interval_days as i64)).to_rfc3339(),
+        interval_days,
+        ease,
+        repetitions,
+        lapses,
+        last_reviewed: now.to_rfc3339(),
+    }
+}
+
+fn is_due(item: &HistoryItem, now: DateTime<Utc>) -> bool {
+    match &item.srs {
+        Some(state) => DateTime::parse_from_rfc3339(&state.due).map_or(true, |due| due <= now),
+        None => true,
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StudyQueue {
+    /// 已到期的复习卡片（最早到期的在前）
+    pub due: Vec<HistoryItem>,
+    /// 尚未复习过的收藏
+    pub new: Vec<HistoryItem>,
+    /// 新卡片总数（new 可能被截断）
+    pub new_total: usize,
+}
+
+// --- Tauri commands ---
+
+/// Records a review of `id` with `grade` 0-5 and schedules the next one
+#[tauri::command]
+pub async fn review_item(app_handle: AppHandle, id: String, grade: u8) -> Result<HistoryItem, String> {
+    if grade > 5 {
+        return Err("Grade must be between 0 and 5".to_string());
+    }
+    pipeline::update_history(&app_handle, |history| {
+        let item = history
+            .iter_mut()
+            .find(|i| i.id == id)
+            .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+        item.srs = Some(schedule(item.srs.as_ref(), grade, Utc::now()));
+        Ok(item.clone())
+    })
+    .await
+}
+
+/// Favorites that are due for review, plus up to `newLimit` never-reviewed favorites
+#[tauri::command]
+pub fn get_study_queue(app_handle: AppHandle, new_limit: Option<usize>) -> Result<StudyQueue, String> {
+    let history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
+    let now = Utc::now();
+    let (mut due, mut new): (Vec<HistoryItem>, Vec<HistoryItem>) = history
+        .into_iter()
+        .filter(|i| i.is_favorite && is_due(i, now))
+        .partition(|i| i.srs.is_some());
+    due.sort_by(|a, b| {
+        let key = |i: &HistoryItem| i.srs.as_ref().map(|s| s.due.clone()).unwrap_or_default();
+        key(a).cmp(&key(b))
+    });
+    // 新卡片按加入顺序（最早的先学）
+    new.reverse();
+    let new_total = new.len();
+    new.truncate(new_limit.unwrap_or(DEFAULT_NEW_PER_SESSION));
+    Ok(StudyQueue { due, new, new_total })
+}
+
+/// Clears the review state of an item so it becomes a new card again
+#[tauri::command]
+pub async fn reset_item_schedule(app_handle: AppHandle, id: String) -> Result<(), String> {
+    pipeline::update_history(&app_handle, |history| {
+        let item = history
+            .iter_mut()
+            .find(|i| i.id == id)
+            .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+        item.srs = None;
+        Ok(())
+    })
+    .await
+}
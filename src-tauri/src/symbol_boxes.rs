@@ -0,0 +1,85 @@
+// 逐符号位置框：开启 Config.symbolBoxes 后，在 LaTeX 阶段完成后额外请求模型给出每个符号在原图中的位置，
+// 保存在条目的 symbol_boxes 上，供前端叠加高亮，或裁剪出单个符号以便后续纠错。
+// 该阶段失败（模型不支持、输出无法解析）时静默跳过，不影响识别结果。
+
+use crate::data_models::{Config, SymbolBox};
+use crate::fs_manager;
+use crate::llm_api::LlmClient;
+use base64::{engine::general_purpose, Engine as _};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tauri::AppHandle;
+
+/// 裁剪时在符号框四周额外保留的边距（相对框尺寸）
+const CROP_MARGIN: f32 = 0.15;
+
+/// Starts the symbol-box request in the background when enabled in config
+pub fn spawn(
+    config: &Config,
+    client: &Arc<dyn LlmClient>,
+    latex: &str,
+    image_base64: &Arc<str>,
+) -> Option<JoinHandle<anyhow::Result<Vec<SymbolBox>>>> {
+    if !config.symbol_boxes || latex.trim().is_empty() {
+        return None;
+    }
+    let client = client.clone();
+    let latex = latex.to_string();
+    let image = image_base64.clone();
+    Some(tokio::spawn(async move { client.locate_symbols(&latex, &image).await }))
+}
+
+/// Waits for the symbol-box request; failures yield None
+pub async fn collect(task: Option<JoinHandle<anyhow::Result<Vec<SymbolBox>>>>) -> Option<Vec<SymbolBox>> {
+    match task?.await {
+        Ok(Ok(boxes)) if !boxes.is_empty() => Some(boxes),
+        Ok(Ok(_)) => None,
+        Ok(Err(_e)) => {
+            #[cfg(debug_assertions)]
+            eprintln!("Symbol box extraction failed: {}", _e);
+            None
+        }
+        Err(_e) => {
+            #[cfg(debug_assertions)]
+            eprintln!("Symbol box task failed: {}", _e);
+            None
+        }
+    }
+}
+
+// --- Tauri commands ---
+
+/// Crops a single symbol (with a small margin) out of the item's image; returns a PNG data URL
+#[tauri::command]
+pub fn crop_symbol(app_handle: AppHandle, id: String, index: usize) -> Result<String, String> {
+    let history = fs_manager::read_history(&app_handle).map_err(|e| e.to_string())?;
+    let item = history
+        .iter()
+        .find(|i| i.id == id)
+        .ok_or_else(|| format!("Item with ID '{}' not found", id))?;
+    let symbol = item
+        .symbol_boxes
+        .as_ref()
+        .and_then(|boxes| boxes.get(index))
+        .ok_or_else(|| format!("Item '{}' has no symbol box #{}", id, index))?;
+    if item.original_image.is_empty() {
+        return Err("Item has no stored image".to_string());
+    }
+
+    let bytes = std::fs::read(fs_manager::resolve_image_path(&app_handle, &item.original_image)).map_err(|e| e.to_string())?;
+    let img = image::load_from_memory(&bytes).map_err(|e| e.to_string())?;
+    let (width, height) = (img.width() as f32, img.height() as f32);
+    let r = symbol.region;
+    let (mx, my) = (r.width * CROP_MARGIN, r.height * CROP_MARGIN);
+    let x0 = ((r.x - mx).max(0.0) * width).floor() as u32;
+    let y0 = ((r.y - my).max(0.0) * height).floor() as u32;
+    let x1 = ((r.x + r.width + mx).min(1.0) * width).ceil() as u32;
+    let y1 = ((r.y + r.height + my).min(1.0) * height).ceil() as u32;
+    let cropped = img.crop_imm(x0, y0, (x1 - x0).max(1), (y1 - y0).max(1));
+
+    let mut png_bytes: Vec<u8> = Vec::new();
+    cropped
+        .write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(&png_bytes)))
+}
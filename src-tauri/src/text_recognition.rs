@@ -0,0 +1,101 @@
+// 选中文本识别：把纯文本伪公式（如 x^2/(2*sigma^2)）交给模型转换为规范 LaTeX，
+// 结果保存为不含图片的历史条目（originalImage 为空）。
+// 快捷键触发时先模拟 Ctrl+C 复制前台应用中的选区，再读取剪贴板文本。
+
+use crate::data_models::HistoryItem;
+use crate::llm_api;
+use crate::{clipboard_output, command_hook, fs_manager, pipeline, plugins, prompts};
+use std::time::Duration;
+use tauri::{AppHandle, ClipboardManager};
+use uuid::Uuid;
+
+const MAX_TEXT_CHARS: usize = 2000;
+/// 模拟复制后等待目标应用写入剪贴板的时间
+const COPY_SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+const MATH_WORDS: [&str; 20] = [
+    "sqrt", "sum", "int", "prod", "lim", "log", "ln", "exp", "sin", "cos", "tan", "alpha", "beta", "gamma",
+    "delta", "theta", "lambda", "mu", "sigma", "pi",
+];
+
+/// Heuristic check whether the text looks like a formula rather than prose
+pub fn looks_like_math(text: &str) -> bool {
+    let text = text.trim();
+    if text.is_empty() || text.chars().count() > MAX_TEXT_CHARS {
+        return false;
+    }
+    if text.contains('\\') || text.chars().any(|c| "^_=<>≤≥±∑∫√·×÷".contains(c)) {
+        return true;
+    }
+    let has_operator = text.chars().any(|c| "+-*/()".contains(c));
+    let has_operand = text.chars().any(|c| c.is_ascii_alphanumeric());
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower.split(|c: char| !c.is_ascii_alphabetic()).filter(|w| !w.is_empty()).collect();
+    let has_math_word = words.iter().any(|w| MATH_WORDS.contains(w));
+    // 长段英文句子即便包含括号也视为普通文本
+    let looks_like_prose = words.iter().filter(|w| w.len() > 3 && !MATH_WORDS.contains(w)).count() > 6;
+    has_operand && (has_operator || has_math_word) && !looks_like_prose
+}
+
+pub async fn recognize_text(app_handle: AppHandle, text: String, auto_paste: bool) -> Result<HistoryItem, String> {
+    let text = text.trim().to_string();
+    if !looks_like_math(&text) {
+        return Err("The selected text does not look like a formula".to_string());
+    }
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let id = Uuid::new_v4().to_string();
+    let prompt = format!(
+        "{}{}",
+        prompts::get_text_to_latex_prompt(),
+        prompts::format_rule_for_latex(&config.default_latex_format)
+    );
+    let client = llm_api::create_client(config.to_llm_config()).map_err(|e| format!("{:#}", e))?;
+    let started = std::time::Instant::now();
+    let (latex, latex_ms) = pipeline::timed(client.convert_text_to_latex(&prompt, &text)).await;
+    let latex = latex.map_err(|e| e.to_string())?;
+    let latex = plugins::apply_post_extraction(&app_handle, &config, &id, latex).await;
+    if auto_paste {
+        clipboard_output::arm_auto_paste(&config, &id);
+    }
+    clipboard_output::auto_copy_latex(&app_handle, &config, &id, &latex);
+
+    let mut history_item = pipeline::latex_only_item(&config, id, latex, prompt, latex_ms, started, client.as_ref());
+    history_item.notes = Some(text);
+    let history_item = plugins::apply_pre_save(&app_handle, &config, history_item).await;
+    let history_item = command_hook::run(&app_handle, &config, history_item).await;
+
+    let history_item = pipeline::insert_into_history(&app_handle, &config, history_item, &[]).await.map_err(|e| e.to_string())?;
+    pipeline::emit_completed(&app_handle, &history_item);
+    crate::usage_stats::record(&app_handle, &config, "text", &history_item);
+    Ok(history_item)
+}
+
+/// Copies the selection of the foreground app and recognizes it (shortcut entry point)
+pub async fn recognize_selection(app_handle: AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(clipboard_output::simulate_copy)
+        .await
+        .map_err(|e| e.to_string())??;
+    tokio::time::sleep(COPY_SETTLE_DELAY).await;
+    let text = app_handle
+        .clipboard_manager()
+        .read_text()
+        .map_err(|e| e.to_string())?
+        .unwrap_or_default();
+    recognize_text(app_handle, text, true).await.map(|_| ())
+}
+
+// --- Tauri commands ---
+
+/// Converts the given text (or the clipboard text when omitted) to LaTeX and stores it in history
+#[tauri::command]
+pub async fn recognize_selected_text(app_handle: AppHandle, text: Option<String>) -> Result<HistoryItem, String> {
+    let text = match text {
+        Some(t) => t,
+        None => app_handle
+            .clipboard_manager()
+            .read_text()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Clipboard does not contain text".to_string())?,
+    };
+    recognize_text(app_handle, text, false).await
+}
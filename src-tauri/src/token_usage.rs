@@ -0,0 +1,163 @@
+// Token 用量记录：每次模型请求返回的用量（usageMetadata / usage / eval_count）按本地日期与模型累加到
+// token_usage.json，由 get_usage_stats 汇总为每日、每月合计，并按价格表估算各模型的费用。
+// 只保存计数，不保存请求内容。
+
+use crate::cost_estimate;
+use crate::data_models::TokenUsage;
+use crate::fs_manager;
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::AppHandle;
+
+const USAGE_FILENAME: &str = "token_usage.json";
+
+static USAGE_PATH: OnceLock<PathBuf> = OnceLock::new();
+/// 串行化读改写，避免并发请求互相覆盖计数
+static USAGE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct UsageFile {
+    /// 本地日期（YYYY-MM-DD）-> 模型 -> 用量
+    #[serde(default)]
+    days: BTreeMap<String, BTreeMap<String, TokenUsage>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodTokens {
+    /// YYYY-MM-DD 或 YYYY-MM
+    pub period: String,
+    pub prompt_tokens: u64,
+    pub candidate_tokens: u64,
+    pub requests: u32,
+    /// 只含价格表收录的模型
+    pub estimated_cost_usd: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelTokens {
+    pub model: String,
+    pub prompt_tokens: u64,
+    pub candidate_tokens: u64,
+    pub requests: u32,
+    /// 价格表未收录该模型时为空
+    pub estimated_cost_usd: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsageSummary {
+    pub prompt_tokens: u64,
+    pub candidate_tokens: u64,
+    pub requests: u32,
+    pub estimated_cost_usd: f64,
+    /// 范围内有用量的模型
+    pub models: Vec<ModelTokens>,
+    /// 范围内每天一条（无用量的日期也包含，便于绘图）
+    pub days: Vec<PeriodTokens>,
+    /// 范围内涉及的每个月（只统计范围内的日期）
+    pub months: Vec<PeriodTokens>,
+}
+
+/// Remembers where the usage log lives so API clients can record without an AppHandle
+pub fn install(app_handle: &AppHandle) {
+    if let Ok(path) = fs_manager::get_data_file_path(app_handle, USAGE_FILENAME) {
+        let _ = USAGE_PATH.set(path);
+    }
+}
+
+fn read_usage(path: &PathBuf) -> Result<UsageFile> {
+    match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).context("Failed to parse token_usage.json"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(UsageFile::default()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read token_usage.json")),
+    }
+}
+
+fn write_usage(path: &PathBuf, usage: &UsageFile) -> Result<()> {
+    std::fs::write(path, serde_json::to_vec_pretty(usage)?).context("Failed to write token_usage.json")
+}
+
+/// Adds the usage of one request to today's total for `model`
+pub fn record(model: &str, usage: TokenUsage) {
+    let Some(path) = USAGE_PATH.get() else { return };
+    let _guard = USAGE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let result = read_usage(path).and_then(|mut file| {
+        let day = file.days.entry(Local::now().format("%Y-%m-%d").to_string()).or_default();
+        day.entry(model.to_string()).or_default().add(usage);
+        write_usage(path, &file)
+    });
+    if let Err(_e) = result {
+        #[cfg(debug_assertions)]
+        eprintln!("Failed to record token usage: {}", _e);
+    }
+}
+
+/// Deletes the usage log
+pub fn reset() -> Result<()> {
+    let Some(path) = USAGE_PATH.get() else { return Ok(()) };
+    let _guard = USAGE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    write_usage(path, &UsageFile::default())
+}
+
+fn period(period: String, usage: TokenUsage, cost: f64) -> PeriodTokens {
+    PeriodTokens {
+        period,
+        prompt_tokens: usage.prompt_tokens,
+        candidate_tokens: usage.candidate_tokens,
+        requests: usage.requests,
+        estimated_cost_usd: cost,
+    }
+}
+
+/// Token totals for `from..=to` (local dates) by day, month and model
+pub fn summarize(from: NaiveDate, to: NaiveDate) -> Result<TokenUsageSummary> {
+    let file = match USAGE_PATH.get() {
+        Some(path) => read_usage(path)?,
+        None => UsageFile::default(),
+    };
+    let cost_of = |model: &str, usage: &TokenUsage| cost_estimate::prices_for(model).map(|prices| usage.cost_usd(prices));
+
+    let mut summary = TokenUsageSummary::default();
+    let mut total = TokenUsage::default();
+    let mut models: BTreeMap<String, TokenUsage> = BTreeMap::new();
+    let mut months: BTreeMap<String, (TokenUsage, f64)> = BTreeMap::new();
+    let mut date = from;
+    while date <= to {
+        let key = date.format("%Y-%m-%d").to_string();
+        let (mut day_usage, mut day_cost) = (TokenUsage::default(), 0.0);
+        for (model, usage) in file.days.get(&key).into_iter().flatten() {
+            day_usage.add(*usage);
+            day_cost += cost_of(model, usage).unwrap_or(0.0);
+            models.entry(model.clone()).or_default().add(*usage);
+        }
+        let month = months.entry(date.format("%Y-%m").to_string()).or_default();
+        month.0.add(day_usage);
+        month.1 += day_cost;
+        total.add(day_usage);
+        summary.estimated_cost_usd += day_cost;
+        summary.days.push(period(key, day_usage, day_cost));
+        date = date.succ_opt().context("Date out of range")?;
+    }
+
+    summary.prompt_tokens = total.prompt_tokens;
+    summary.candidate_tokens = total.candidate_tokens;
+    summary.requests = total.requests;
+    summary.months = months.into_iter().map(|(month, (usage, cost))| period(month, usage, cost)).collect();
+    summary.models = models
+        .into_iter()
+        .map(|(model, usage)| ModelTokens {
+            estimated_cost_usd: cost_of(&model, &usage),
+            model,
+            prompt_tokens: usage.prompt_tokens,
+            candidate_tokens: usage.candidate_tokens,
+            requests: usage.requests,
+        })
+        .collect();
+    Ok(summary)
+}
@@ -0,0 +1,198 @@
+// 检查更新：查询 GitHub Releases，按配置的发布渠道（stable 只看正式版，beta 也包含预发布版）
+// 找出比当前版本新的最高版本，返回更新说明与适合本平台的下载地址。
+
+use crate::data_models::UpdateChannel;
+use crate::fs_manager;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::time::Duration;
+use tauri::AppHandle;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/Ryson-32/AI-Formula-Scanner/releases?per_page=30";
+const REQUEST_TIMEOUT_SECONDS: u64 = 15;
+
+#[derive(Deserialize, Debug, Clone)]
+struct GithubRelease {
+    tag_name: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    body: Option<String>,
+    html_url: String,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    published_at: Option<String>,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub channel: UpdateChannel,
+    pub update_available: bool,
+    /// 渠道内最新版本（无可用发布时为空）
+    pub latest_version: Option<String>,
+    pub release_name: Option<String>,
+    /// Markdown 更新说明
+    pub changelog: Option<String>,
+    /// 本平台安装包地址；找不到匹配的安装包时为发布页地址
+    pub download_url: Option<String>,
+    pub release_url: Option<String>,
+    pub published_at: Option<String>,
+    pub prerelease: bool,
+}
+
+/// Version parsed from a tag such as `v1.2.3` or `1.3.0-beta.2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Version {
+    numbers: Vec<u64>,
+    pre: Option<String>,
+}
+
+impl Version {
+    fn parse(tag: &str) -> Option<Version> {
+        let tag = tag.trim().trim_start_matches(['v', 'V']);
+        let (core, pre) = match tag.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (tag, None),
+        };
+        // 构建元数据（+xxx）不参与比较
+        let core = core.split('+').next().unwrap_or(core);
+        let numbers = core.split('.').map(|p| p.parse::<u64>().ok()).collect::<Option<Vec<_>>>()?;
+        if numbers.is_empty() {
+            return None;
+        }
+        Some(Version { numbers, pre })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.numbers.len().max(other.numbers.len());
+        for i in 0..len {
+            let a = self.numbers.get(i).copied().unwrap_or(0);
+            let b = other.numbers.get(i).copied().unwrap_or(0);
+            match a.cmp(&b) {
+                Ordering::Equal => {}
+                unequal => return unequal,
+            }
+        }
+        // 同一版本号下，预发布版低于正式版；预发布标识逐段比较（数字段按数值）
+        match (&self.pre, &other.pre) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => {
+                for (x, y) in a.split('.').zip(b.split('.')) {
+                    let ord = match (x.parse::<u64>(), y.parse::<u64>()) {
+                        (Ok(x), Ok(y)) => x.cmp(&y),
+                        _ => x.cmp(y),
+                    };
+                    if ord != Ordering::Equal {
+                        return ord;
+                    }
+                }
+                a.split('.').count().cmp(&b.split('.').count())
+            }
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Installer asset for the running platform, by file extension preference
+fn platform_asset(assets: &[GithubAsset]) -> Option<&GithubAsset> {
+    let preferred: &[&str] = if cfg!(target_os = "windows") {
+        &[".msi", "-setup.exe", ".exe"]
+    } else if cfg!(target_os = "macos") {
+        &[".dmg", ".app.tar.gz"]
+    } else {
+        &[".appimage", ".deb", ".rpm"]
+    };
+    preferred
+        .iter()
+        .find_map(|ext| assets.iter().find(|a| a.name.to_lowercase().ends_with(ext)))
+}
+
+/// Newest release in the channel, if any
+fn latest_release(releases: Vec<GithubRelease>, channel: UpdateChannel) -> Option<(Version, GithubRelease)> {
+    releases
+        .into_iter()
+        .filter(|r| !r.draft && (channel == UpdateChannel::Beta || !r.prerelease))
+        .filter_map(|r| Version::parse(&r.tag_name).map(|v| (v, r)))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+}
+
+// --- Tauri commands ---
+
+/// Queries GitHub releases for a newer version in the configured (or given) channel
+#[tauri::command]
+pub async fn check_for_updates(app_handle: AppHandle, channel: Option<UpdateChannel>) -> Result<UpdateInfo, String> {
+    let channel = match channel {
+        Some(channel) => channel,
+        None => fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?.update_channel,
+    };
+    let current_version = env!("CARGO_PKG_VERSION").to_string();
+    let current = Version::parse(&current_version).ok_or_else(|| format!("Invalid app version '{}'", current_version))?;
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+        .user_agent(format!("AI-Formula-Scanner/{}", current_version))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .get(RELEASES_URL)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach GitHub: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("GitHub releases request failed with status {}", response.status()));
+    }
+    let releases: Vec<GithubRelease> = response.json().await.map_err(|e| format!("Unexpected GitHub response: {}", e))?;
+
+    let Some((latest, release)) = latest_release(releases, channel) else {
+        return Ok(UpdateInfo {
+            current_version,
+            channel,
+            update_available: false,
+            latest_version: None,
+            release_name: None,
+            changelog: None,
+            download_url: None,
+            release_url: None,
+            published_at: None,
+            prerelease: false,
+        });
+    };
+    let download_url = platform_asset(&release.assets)
+        .map(|a| a.browser_download_url.clone())
+        .unwrap_or_else(|| release.html_url.clone());
+    Ok(UpdateInfo {
+        current_version,
+        channel,
+        update_available: latest > current,
+        latest_version: Some(release.tag_name.trim_start_matches(['v', 'V']).to_string()),
+        release_name: release.name.filter(|n| !n.trim().is_empty()),
+        changelog: release.body.filter(|b| !b.trim().is_empty()),
+        download_url: Some(download_url),
+        release_url: Some(release.html_url),
+        published_at: release.published_at,
+        prerelease: release.prerelease,
+    })
+}
@@ -0,0 +1,176 @@
+// 本地使用统计（需在设置中开启 usageStatsEnabled）：按天记录识别次数、来源（截图/文件/剪贴板等）
+// 与平均耗时，保存在 usage_stats.json，仅供应用内的统计视图使用，不会上传到任何地方。
+// 统计结果同时附带 token_usage 记录的 Token 用量与费用估算（该记录不受开关影响）。
+
+use crate::data_models::{Config, HistoryItem};
+use crate::{fs_manager, token_usage};
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+const STATS_FILENAME: &str = "usage_stats.json";
+/// 未指定范围时的统计天数
+const DEFAULT_RANGE_DAYS: i64 = 30;
+
+/// 串行化读改写，避免并发识别互相覆盖计数
+static STATS_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct DayUsage {
+    captures: u32,
+    /// 来源 -> 次数
+    #[serde(default)]
+    sources: BTreeMap<String, u32>,
+    #[serde(default)]
+    latency_ms_total: u64,
+    #[serde(default)]
+    latency_samples: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct UsageFile {
+    /// 本地日期（YYYY-MM-DD）-> 当天统计
+    #[serde(default)]
+    days: BTreeMap<String, DayUsage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyUsage {
+    pub date: String,
+    pub captures: u32,
+    pub average_latency_ms: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSummary {
+    pub enabled: bool,
+    pub from: String,
+    pub to: String,
+    pub total_captures: u32,
+    pub sources: BTreeMap<String, u32>,
+    pub average_latency_ms: Option<u64>,
+    /// 识别次数最多的一天
+    pub busiest_day: Option<DailyUsage>,
+    /// 范围内每天一条（无识别的日期也包含，便于绘图）
+    pub days: Vec<DailyUsage>,
+    /// 范围内的 Token 用量（按天、月、模型）与估算费用
+    pub tokens: token_usage::TokenUsageSummary,
+}
+
+fn read_stats(app_handle: &AppHandle) -> Result<UsageFile> {
+    let path = fs_manager::get_data_file_path(app_handle, STATS_FILENAME)?;
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).context("Failed to parse usage_stats.json"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(UsageFile::default()),
+        Err(e) => Err(anyhow::Error::new(e).context("Failed to read usage_stats.json")),
+    }
+}
+
+fn write_stats(app_handle: &AppHandle, stats: &UsageFile) -> Result<()> {
+    let path = fs_manager::get_data_file_path(app_handle, STATS_FILENAME)?;
+    std::fs::write(path, serde_json::to_vec_pretty(stats)?).context("Failed to write usage_stats.json")
+}
+
+/// Counts a completed recognition from `source` (no-op unless usage statistics are enabled).
+/// Latency is measured from the item's creation time, which is taken when the pipeline starts.
+pub fn record(app_handle: &AppHandle, config: &Config, source: &str, item: &HistoryItem) {
+    if !config.usage_stats_enabled {
+        return;
+    }
+    let latency_ms = chrono::DateTime::parse_from_rfc3339(&item.created_at)
+        .ok()
+        .map(|start| (chrono::Utc::now() - start.with_timezone(&chrono::Utc)).num_milliseconds())
+        .filter(|ms| *ms >= 0)
+        .map(|ms| ms as u64);
+
+    let _guard = STATS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let result = read_stats(app_handle).and_then(|mut stats| {
+        let day = stats.days.entry(Local::now().format("%Y-%m-%d").to_string()).or_default();
+        day.captures += 1;
+        *day.sources.entry(source.to_string()).or_insert(0) += 1;
+        if let Some(ms) = latency_ms {
+            day.latency_ms_total += ms;
+            day.latency_samples += 1;
+        }
+        write_stats(app_handle, &stats)
+    });
+    if let Err(_e) = result {
+        #[cfg(debug_assertions)]
+        eprintln!("Failed to record usage statistics: {}", _e);
+    }
+}
+
+fn parse_date(value: Option<&str>, fallback: NaiveDate) -> Result<NaiveDate, String> {
+    match value.filter(|v| !v.trim().is_empty()) {
+        Some(v) => NaiveDate::parse_from_str(v.trim(), "%Y-%m-%d").map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", v)),
+        None => Ok(fallback),
+    }
+}
+
+fn average(total: u64, samples: u32) -> Option<u64> {
+    (samples > 0).then(|| total / samples as u64)
+}
+
+// --- Tauri commands ---
+
+/// Usage summary for `from`..=`to` (local dates, YYYY-MM-DD); defaults to the last 30 days
+#[tauri::command]
+pub fn get_usage_stats(app_handle: AppHandle, from: Option<String>, to: Option<String>) -> Result<UsageSummary, String> {
+    let config = fs_manager::read_config(&app_handle).map_err(|e| e.to_string())?;
+    let today = Local::now().date_naive();
+    let to = parse_date(to.as_deref(), today)?;
+    let from = parse_date(from.as_deref(), to - chrono::Duration::days(DEFAULT_RANGE_DAYS - 1))?;
+    if from > to {
+        return Err("The start date must not be after the end date".to_string());
+    }
+    let stats = read_stats(&app_handle).map_err(|e| e.to_string())?;
+
+    let mut days = Vec::new();
+    let mut sources = BTreeMap::new();
+    let (mut total_captures, mut latency_total, mut latency_samples) = (0u32, 0u64, 0u32);
+    let mut date = from;
+    while date <= to {
+        let key = date.format("%Y-%m-%d").to_string();
+        let usage = stats.days.get(&key).cloned().unwrap_or_default();
+        total_captures += usage.captures;
+        latency_total += usage.latency_ms_total;
+        latency_samples += usage.latency_samples;
+        for (source, count) in &usage.sources {
+            *sources.entry(source.clone()).or_insert(0) += count;
+        }
+        days.push(DailyUsage {
+            date: key,
+            captures: usage.captures,
+            average_latency_ms: average(usage.latency_ms_total, usage.latency_samples),
+        });
+        date = date.succ_opt().ok_or("Date out of range")?;
+    }
+    let busiest_day = days.iter().filter(|d| d.captures > 0).max_by_key(|d| d.captures).cloned();
+    let tokens = token_usage::summarize(from, to).map_err(|e| e.to_string())?;
+
+    Ok(UsageSummary {
+        enabled: config.usage_stats_enabled,
+        from: from.format("%Y-%m-%d").to_string(),
+        to: to.format("%Y-%m-%d").to_string(),
+        total_captures,
+        sources,
+        average_latency_ms: average(latency_total, latency_samples),
+        busiest_day,
+        days,
+        tokens,
+    })
+}
+
+/// Deletes all recorded usage statistics, including token usage
+#[tauri::command]
+pub fn reset_usage_stats(app_handle: AppHandle) -> Result<(), String> {
+    let _guard = STATS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    write_stats(&app_handle, &UsageFile::default()).map_err(|e| e.to_string())?;
+    token_usage::reset().map_err(|e| e.to_string())
+}
@@ -37,7 +37,7 @@ const messages = {
     'settings.ai.title': 'AI 配置',
     'settings.ai.desc': '管理 API 和提示词配置。',
       'settings.api.title': 'API 配置',
-      'settings.api.provider': '服务提供商（目前只支持Google）',
+      'settings.api.provider': '服务提供商（Google、OpenAI 兼容接口或本地 Ollama）',
     'settings.api.key': 'API 密钥',
     'settings.api.key.ph': '请输入你的 API 密钥',
     'settings.api.base_url': 'API 基础 URL',
@@ -110,9 +110,20 @@ const messages = {
 
     // Recognition view
     'recognition.region_capture': '截图识别',
+    'recognition.mode': '识别模式',
+    'recognition.mode.math': '数学公式',
+    'recognition.mode.chemistry': '化学式（mhchem）',
     'recognition.import': '导入图片',
     'recognition.processing': '处理中...',
     'recognition.loading': '正在处理，请稍候...',
+    'recognition.batch.progress': '批量识别中：已完成 {completed}/{total}',
+    'recognition.batch.done': '批量识别完成：成功 {succeeded} 个，失败 {failed} 个',
+    'pdf.page': '第 {page} / {count} 页',
+    'pdf.select_hint': '在页面上拖动以框选公式，不框选则识别整页',
+    'pdf.cancel': '取消',
+    'pdf.recognize_page': '识别整页',
+    'pdf.recognize_selection': '识别选区',
+    'recognition.cancel': '取消识别',
     'recognition.error.config_missing': '请先在设置中配置API密钥',
     'recognition.error.failed': '识别失败',
     'recognition.error.failed_code': '识别失败（代码 {code}）',
@@ -121,6 +132,10 @@ const messages = {
     'recognition.error.finish_reason': '识别中断（原因: {reason}）',
     'recognition.finish_reason.stop': '模型结束但未返回文本，请重试或更换模型/降低提示复杂度',
     'recognition.finish_reason.max_tokens': '达到最大输出长度，请增大“最大输出 Token”或更换支持更长输出的模型',
+    'recognition.finish_reason.safety': '内容被模型的安全过滤拦截（{reason}{categories}），可更换图片或在设置中调整安全阈值（safetySettings）',
+    'recognition.retrying.rate_limited': '请求被限流，{seconds} 秒后重试（第 {attempt}/{max} 次）',
+    'recognition.retrying.server_error': '服务暂时不可用，{seconds} 秒后重试（第 {attempt}/{max} 次）',
+    'recognition.retrying.network': '网络错误，{seconds} 秒后重试（第 {attempt}/{max} 次）',
     'recognition.copy_latex': '复制LaTeX',
     'recognition.copy_latex_success': 'LaTeX已复制到剪贴板',
     'recognition.copy_latex_failed': '复制LaTeX失败',
@@ -215,7 +230,7 @@ const messages = {
     'settings.ai.title': 'AI Configuration',
     'settings.ai.desc': 'Manage API settings and prompt configurations.',
       'settings.api.title': 'API Configuration',
-      'settings.api.provider': 'Provider (Google only for now)',
+      'settings.api.provider': 'Provider (Google, OpenAI-compatible or local Ollama)',
     'settings.api.key': 'API Key',
     'settings.api.key.ph': 'Enter your API key',
     'settings.api.base_url': 'API Base URL',
@@ -290,9 +305,20 @@ const messages = {
 
     // Recognition view
     'recognition.region_capture': 'Screenshot Recognition',
+    'recognition.mode': 'Recognition mode',
+    'recognition.mode.math': 'Math',
+    'recognition.mode.chemistry': 'Chemistry (mhchem)',
     'recognition.import': 'Import Image',
     'recognition.processing': 'Processing...',
     'recognition.loading': 'Processing, please wait...',
+    'recognition.batch.progress': 'Recognizing files: {completed}/{total} done',
+    'recognition.batch.done': 'Batch recognition finished: {succeeded} succeeded, {failed} failed',
+    'pdf.page': 'Page {page} of {count}',
+    'pdf.select_hint': 'Drag on the page to select a formula, or recognize the whole page',
+    'pdf.cancel': 'Cancel',
+    'pdf.recognize_page': 'Recognize page',
+    'pdf.recognize_selection': 'Recognize selection',
+    'recognition.cancel': 'Cancel',
     'recognition.error.config_missing': 'Please configure API key in Settings first',
     'recognition.error.failed': 'Recognition failed',
     'recognition.error.failed_code': 'Recognition failed (code {code})',
@@ -301,6 +327,10 @@ const messages = {
     'recognition.error.finish_reason': 'Recognition interrupted (reason: {reason})',
     'recognition.finish_reason.stop': 'Model stopped without returning text. Please retry, switch model, or simplify the prompt',
     'recognition.finish_reason.max_tokens': 'Reached maximum output length. Increase Max Output Tokens or use a model with higher output limits',
+    'recognition.finish_reason.safety': "Blocked by the model's safety filters ({reason}{categories}). Try another image or adjust the safety thresholds (safetySettings) in the settings",
+    'recognition.retrying.rate_limited': 'Rate limited, retrying in {seconds}s (attempt {attempt}/{max})',
+    'recognition.retrying.server_error': 'Service unavailable, retrying in {seconds}s (attempt {attempt}/{max})',
+    'recognition.retrying.network': 'Network error, retrying in {seconds}s (attempt {attempt}/{max})',
     'recognition.copy_latex': 'Copy LaTeX',
     'recognition.copy_latex_success': 'LaTeX copied to clipboard',
     'recognition.copy_latex_failed': 'Failed to copy LaTeX',
@@ -119,10 +119,16 @@ async function loadKaTeX(): Promise<void> {
       script.async = true;
       
       script.onload = () => {
-        engine.isLoaded = true;
-        engine.isLoading = false;
-        console.log('KaTeX loaded successfully');
-        resolve();
+        // mhchem 扩展（化学模式的 \ce{...}）；加载失败不影响普通公式
+        const mhchem = document.createElement('script');
+        mhchem.src = 'https://cdn.jsdelivr.net/npm/katex@0.16.8/dist/contrib/mhchem.min.js';
+        mhchem.onload = mhchem.onerror = () => {
+          engine.isLoaded = true;
+          engine.isLoading = false;
+          console.log('KaTeX loaded successfully');
+          resolve();
+        };
+        document.head.appendChild(mhchem);
       };
       
       script.onerror = (error) => {
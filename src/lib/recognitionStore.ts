@@ -7,6 +7,8 @@ export type RecognitionState = {
   errorMessage: string;
   currentPromptVersion?: string;
   currentImageBase64?: string;
+  // 批量识别进行中：各文件的阶段事件不更新当前结果
+  batchRunning?: boolean;
 };
 
 const initialState: RecognitionState = {
@@ -58,6 +60,9 @@ export const recognitionStore = {
   },
   setCurrentImageBase64(value: string | null): void {
     update((state) => ({ ...state, currentImageBase64: value ?? undefined }));
+  },
+  setBatchRunning(value: boolean): void {
+    update((state) => ({ ...state, batchRunning: value }));
   }
 };
 
@@ -1,8 +1,14 @@
 export interface Config {
   apiKey: string;
+  additionalApiKeys?: string[];
+  keyRotation?: 'roundRobin' | 'onRateLimit';
+  // secondary backend used while the primary one is failing
+  failover?: { enabled: boolean; apiKey: string; apiBaseUrl: string; modelName: string; provider?: string };
   apiBaseUrl: string;
   provider: string;
   defaultEngine: string;
+  // per-stage overrides; empty string falls back to the saved stage prompt
+  customPrompts?: CustomPrompts;
   latexPrompt: string;
   analysisPrompt: string;
   // 前端统一字段：verificationPrompt（兼容旧字段名 confidencePrompt 由后端 serde alias 处理）
@@ -21,6 +27,78 @@ export interface Config {
   windowX?: number | null;
   windowY?: number | null;
   rememberWindowState: boolean;
+  // global shortcuts (empty string = unbound)
+  shortcuts?: ShortcutsConfig;
+  // duplicate detection (perceptual hash, max Hamming distance)
+  duplicateDetection?: boolean;
+  duplicateThreshold?: number;
+  // reuse prior results for identical images (pass forceRefresh to bypass)
+  resultCacheEnabled?: boolean;
+  // history image storage (webp = lossless WebP)
+  imageStorage?: { format: 'png' | 'webp'; pngCompression: 'fast' | 'default' | 'best' };
+  // request per-symbol bounding boxes after LaTeX extraction (extra API call)
+  symbolBoxes?: boolean;
+  // release channel for update checks (beta includes pre-releases)
+  updateChannel?: 'stable' | 'beta';
+  // opt-in local usage statistics (never leaves this machine)
+  usageStatsEnabled?: boolean;
+  // display used for full-screen recognition (null = the one under the cursor)
+  fullScreenDisplay?: number | null;
+  // stitch all displays into one image for full-screen recognition
+  fullScreenAllDisplays?: boolean;
+  // HTTP(S)/SOCKS5 proxy for model API requests; bypass lists hosts reached directly
+  proxy?: { enabled: boolean; url: string; username: string; password: string; bypass: string[] };
+  // ask the model for schema-constrained JSON (turn off for proxies that reject responseSchema)
+  structuredOutput?: boolean;
+  // per-stage sampling parameters (unset topP/topK use the model default, unset maxOutputTokens the global one)
+  generation?: { latex: StageGeneration; analysis: StageGeneration; verification: StageGeneration };
+  // shared limit on parallel model requests (0 = unlimited) and minimum gap between requests
+  maxConcurrentRequests?: number;
+  minRequestIntervalMs?: number;
+  // write every model request/response to the local audit log (get_llm_logs); keys and images are redacted
+  llmAuditLog?: boolean;
+  // reuse cached model responses for identical requests (same image, prompt and model) within the TTL
+  llmCacheEnabled?: boolean;
+  llmCacheTtlHours?: number;
+  // images larger than this (KB) are uploaded via the Gemini Files API instead of sent inline (0 = always inline)
+  filesApiThresholdKb?: number;
+  // corporate LLM gateway: extra headers ({apiKey} is replaced), a JSON envelope with "{{body}}"/"{{model}}"
+  // placeholders, and a JSON Pointer to the provider response inside the gateway reply
+  gateway?: { headers: Record<string, string>; requestEnvelope: string; responsePath: string };
+  // also extract LaTeX with up to two more models and keep the majority result (disagreements go to the verification report)
+  ensemble?: { enabled: boolean; models: string[] };
+  // Gemini safety filter thresholds, e.g. { category: 'HARM_CATEGORY_DANGEROUS_CONTENT', threshold: 'BLOCK_ONLY_HIGH' }
+  safetySettings?: Array<{ category: string; threshold: string }>;
+  // directory containing poppler's pdftoppm/pdfinfo for PDF import (empty = look them up on PATH)
+  popplerPath?: string;
+  // handwriting mode (overlay toggle): preprocessing of the image sent to the model
+  handwriting?: { binarize: boolean; thicken: boolean };
+  // chemistry mode: also ask for the SMILES of each species
+  chemistry?: { smiles: boolean };
+}
+
+export interface StageGeneration {
+  temperature: number;
+  topP?: number | null;
+  topK?: number | null;
+  maxOutputTokens?: number | null;
+}
+
+export interface CustomPrompts {
+  latex: string;
+  analysis: string;
+  verification: string;
+}
+
+export interface ShortcutsConfig {
+  captureRegion: string;
+  captureFullScreen: string;
+  recognizeClipboard: string;
+  toggleWindow: string;
+  repeatLastCapture: string;
+  quickCapture: string;
+  recognizeSelection: string;
+  recallLastResult?: string;
 }
 
 export interface RecognitionResult {
@@ -34,6 +112,8 @@ export interface RecognitionResult {
     suggestions: Array<{
       type: string;
       message: string;
+      // 本地规则检查产生的建议带有规则名（如 frac_missing_argument）
+      rule?: string | null;
     }>;
   };
   is_favorite: boolean;
@@ -43,10 +123,100 @@ export interface RecognitionResult {
   model_name?: string;
   prompt_version?: string;
   verification?: {
-    status: 'error' | 'warning' | 'ok' | string;
-    issues?: Array<{ category: string; message: string }>;
+    status: 'error' | 'warning' | 'ok' | 'skipped' | string;
+    // region：问题在原图中的相对位置（0~1，左上角为原点）
+    // latex_span：latex_fragment 在 LaTeX 中的位置（UTF-16 偏移，与文本框选区一致）
+    issues?: Array<{
+      category: string;
+      message: string;
+      region?: { x: number; y: number; width: number; height: number } | null;
+      latex_fragment?: string | null;
+      latex_span?: { start: number; end: number } | null;
+      // 具体修正，可通过 apply_fix 命令应用
+      suggested_fix?: string | null;
+    }>;
     coverage?: { symbols_matched: number; symbols_total: number; terms_matched: number; terms_total: number };
   };
   // 当结构化 verification 缺失时，后端可能仅提供文字报告作为兜底
   verification_report?: string;
-}
\ No newline at end of file
+  notes?: string;
+  // 逐符号位置框（相对坐标，开启 symbolBoxes 时提供）
+  symbol_boxes?: Array<{ token: string; region: { x: number; y: number; width: number; height: number } }>;
+  // 公式出处（文献标题、页码、URL、DOI）
+  source?: { document_title?: string | null; page?: string | null; url?: string | null; doi?: string | null } | null;
+  // 供屏幕阅读器/TTS 朗读的自然语言描述
+  spoken_description?: string;
+  // 间隔重复复习状态（SM-2；首次复习后才存在）
+  srs?: { due: string; interval_days: number; ease: number; repetitions: number; lapses: number; last_reviewed: string };
+  // 应用修正前的 LaTeX 版本（最新的在最后）
+  latex_revisions?: Array<{ latex: string; created_at: string; reason: string }>;
+  // 生成该结果时实际使用的完整提示词
+  prompts_used?: {
+    prompts_version: number;
+    latex: string;
+    analysis: string;
+    verification: string;
+  };
+  perceptual_hash?: string;
+  // 疑似重复的已有条目 id（possible_duplicate 事件），可合并或忽略
+  duplicate_of?: string;
+  // 各阶段耗时（毫秒）；命中结果缓存的条目没有此字段
+  stage_timings?: { latex_ms?: number | null; analysis_ms?: number | null; verification_ms?: number | null; total_ms?: number | null } | null;
+  // 'failover' when produced by the secondary backend during a primary outage
+  backend?: 'primary' | 'failover' | null;
+  tags?: string[] | null;
+  // source display scale for mapping coordinates on HiDPI screens (screen captures only)
+  capture_scale?: CaptureScale | null;
+  // id of the item this one re-recognizes with another model/preset
+  rerun_of?: string | null;
+  // tokens billed for this recognition's API requests (when the provider reports usage)
+  token_usage?: { prompt_tokens: number; candidate_tokens: number; requests: number } | null;
+  // 'chemistry' for mhchem results of the chemistry mode
+  mode?: 'math' | 'chemistry';
+  // SMILES of each species (chemistry mode with chemistry.smiles)
+  smiles?: string[] | null;
+}
+
+export interface CaptureScale {
+  scale_factor: number;
+  physical_width: number;
+  physical_height: number;
+  logical_width: number;
+  logical_height: number;
+  logical_x: number;
+  logical_y: number;
+  display_index?: number | null;
+}
+// payload.retry of a recognition_progress event with stage "retrying"
+export interface RetryNotice {
+  stage: string;
+  attempt: number;
+  max_retries: number;
+  delay_ms: number;
+  reason: 'rate_limited' | 'server_error' | 'network';
+  status?: number;
+}
+// payload.failure of a recognition_progress event with stage "failed": the model stopped without a formula
+export interface FinishReasonError {
+  stage: string;
+  finish_reason: string;
+  categories: string[];
+}
+// recognition job (list_jobs / jobs_changed); the id is also the recognition id of its progress events
+export interface Job {
+  id: string;
+  source: string;
+  status: 'pending' | 'running' | 'done' | 'failed' | 'cancelled';
+  language?: string | null;
+  presetId?: string | null;
+  forceRefresh: boolean;
+  handwriting?: boolean;
+  mode?: 'math' | 'chemistry';
+  batchId?: string | null;
+  createdAt: string;
+  startedAt?: string | null;
+  finishedAt?: string | null;
+  attempts: number;
+  error?: string | null;
+  itemId?: string | null;
+}